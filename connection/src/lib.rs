@@ -20,7 +20,8 @@ pub use self::{
     thick::{ThickClient, ThickClientAttestationError},
     traits::{
         AttestationError, AttestedConnection, BlockInfo, BlockchainConnection, Connection,
-        RetryableBlockchainConnection, RetryableUserTxConnection, UserTxConnection,
+        ConnectionTimeouts, RetryableBlockchainConnection, RetryableUserTxConnection,
+        UserTxConnection,
     },
 };
 
@@ -2,7 +2,10 @@
 
 //! Common connection manager implementation
 
-use crate::{sync::SyncConnection, traits::Connection};
+use crate::{
+    sync::SyncConnection,
+    traits::{Connection, ConnectionTimeouts},
+};
 use mc_common::{
     logger::{o, Logger},
     ResponderId,
@@ -16,6 +19,9 @@ use std::{
 struct ConnectionManagerInner<C: Connection> {
     /// Map of responder id -> retryable connection.
     id_to_conn: BTreeMap<ResponderId, SyncConnection<C>>,
+
+    /// Timeouts applied to every connection.
+    timeouts: ConnectionTimeouts,
 }
 
 /// A connection manager manages a list of peers it is connected to.
@@ -34,11 +40,17 @@ impl<C: Connection> Clone for ConnectionManager<C> {
 /// A collection of connections
 impl<C: Connection> ConnectionManager<C> {
     pub fn new(conns: Vec<C>, logger: Logger) -> Self {
+        Self::new_with_timeouts(conns, ConnectionTimeouts::default(), logger)
+    }
+
+    /// Create a connection manager whose connections use the given timeouts.
+    pub fn new_with_timeouts(conns: Vec<C>, timeouts: ConnectionTimeouts, logger: Logger) -> Self {
         Self {
             inner: Arc::new(RwLock::new(ConnectionManagerInner {
                 id_to_conn: conns
                     .into_iter()
-                    .map(|conn| {
+                    .map(|mut conn| {
+                        conn.set_timeouts(timeouts);
                         let name = conn.to_string();
                         let responder_id = conn.uri().responder_id().unwrap_or_else(|_| {
                             panic!(
@@ -51,6 +63,7 @@ impl<C: Connection> ConnectionManager<C> {
                         (responder_id, sync_conn)
                     })
                     .collect(),
+                timeouts,
             })),
         }
     }
@@ -79,6 +92,11 @@ impl<C: Connection> ConnectionManager<C> {
         self.read().id_to_conn.get(responder_id).cloned()
     }
 
+    /// Retrieve the timeouts used by the connections.
+    pub fn timeouts(&self) -> ConnectionTimeouts {
+        self.read().timeouts
+    }
+
     /// Retrieve a count of the number connections we're aware of.
     pub fn len(&self) -> usize {
         self.read().id_to_conn.len()
@@ -8,7 +8,7 @@ use crate::{
     error::{Error, Result},
     traits::{
         AttestationError, AttestedConnection, BlockInfo, BlockchainConnection, Connection,
-        UserTxConnection,
+        ConnectionTimeouts, UserTxConnection,
     },
 };
 use aes_gcm::Aes256Gcm;
@@ -47,6 +47,7 @@ use std::{
     ops::Range,
     result::Result as StdResult,
     sync::Arc,
+    time::Duration,
 };
 
 /// Attestation failures a thick client can generate
@@ -134,6 +135,8 @@ pub struct ThickClient<CP: CredentialsProvider> {
     /// A hash map of metadata to set on outbound requests, filled by inbound
     /// `Set-Cookie` metadata
     cookies: CookieJar,
+    /// Deadlines applied to outbound calls.
+    timeouts: ConnectionTimeouts,
 }
 
 impl<CP: CredentialsProvider> ThickClient<CP> {
@@ -163,20 +166,23 @@ impl<CP: CredentialsProvider> ThickClient<CP> {
             enclave_connection: None,
             credentials_provider,
             cookies: CookieJar::default(),
+            timeouts: ConnectionTimeouts::default(),
         })
     }
 
     /// A wrapper for performing an authenticated call. This also takes care to
-    /// properly include cookie information in the request.
+    /// properly include cookie information in the request. The call fails if
+    /// it does not complete within `timeout`.
     fn authenticated_call<
         T,
         E: AuthenticationError + From<Box<dyn CredentialsProviderError + 'static>>,
     >(
         &mut self,
+        timeout: Option<Duration>,
         func: impl FnOnce(&mut Self, CallOption) -> StdResult<T, E>,
     ) -> StdResult<T, E> {
         // Make the actual RPC call.
-        let call_option = self.call_option()?;
+        let call_option = self.call_option(timeout)?;
         let result = func(self, call_option);
 
         // If the call failed due to authentication (credentials) error, reset creds so
@@ -194,13 +200,20 @@ impl<CP: CredentialsProvider> ThickClient<CP> {
         &mut self,
         func: impl FnOnce(&mut Self, CallOption) -> StdResult<T, GrpcError>,
     ) -> StdResult<T, ThickClientAttestationError> {
-        self.authenticated_call(|this, call_option| {
+        let timeout = self.timeouts.request;
+        self.authenticated_call(timeout, |this, call_option| {
             this.attested_call(|this| func(this, call_option))
         })
     }
 
-    fn call_option(&self) -> StdResult<CallOption, Box<dyn CredentialsProviderError + 'static>> {
-        let retval = CallOption::default();
+    fn call_option(
+        &self,
+        timeout: Option<Duration>,
+    ) -> StdResult<CallOption, Box<dyn CredentialsProviderError + 'static>> {
+        let mut retval = CallOption::default();
+        if let Some(timeout) = timeout {
+            retval = retval.timeout(timeout);
+        }
 
         // Create metadata from cookies and credentials
         let mut metadata_builder = self
@@ -230,6 +243,10 @@ impl<CP: CredentialsProvider> Connection for ThickClient<CP> {
     fn uri(&self) -> Self::Uri {
         self.uri.clone()
     }
+
+    fn set_timeouts(&mut self, timeouts: ConnectionTimeouts) {
+        self.timeouts = timeouts;
+    }
 }
 
 impl<CP: CredentialsProvider> AttestedConnection for ThickClient<CP> {
@@ -252,8 +269,9 @@ impl<CP: CredentialsProvider> AttestedConnection for ThickClient<CP> {
         let (initiator, auth_request_output) = initiator.try_next(&mut csprng, init_input)?;
 
         // Do the gRPC Call
+        let timeout = self.timeouts.connect;
         let (header, auth_response_msg, trailer) =
-            self.authenticated_call(|this, call_option| -> StdResult<_, Self::Error> {
+            self.authenticated_call(timeout, |this, call_option| -> StdResult<_, Self::Error> {
                 Ok(this
                     .attested_api_client
                     .auth_full(&auth_request_output.into(), call_option)?)
@@ -17,11 +17,27 @@ use std::{
     time::Duration,
 };
 
+/// Timeouts for the calls made over a connection. A timeout of `None` means
+/// calls wait indefinitely.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ConnectionTimeouts {
+    /// How long to wait for a connection to the peer to be established,
+    /// including attestation.
+    pub connect: Option<Duration>,
+
+    /// How long to wait for the peer to respond to a single request.
+    pub request: Option<Duration>,
+}
+
 /// A base connection trait, applicable to all connections.
 pub trait Connection: Display + Eq + Hash + Ord + PartialEq + PartialOrd + Send + Sync {
     type Uri: ConnectionUri;
 
     fn uri(&self) -> Self::Uri;
+
+    /// Set the timeouts used by subsequent calls. Connections that cannot
+    /// enforce timeouts ignore them.
+    fn set_timeouts(&mut self, _timeouts: ConnectionTimeouts) {}
 }
 
 /// A marker trait used to encapsulate connection-impl-specific attestation
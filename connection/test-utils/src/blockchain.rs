@@ -7,13 +7,16 @@ use mc_connection::{
     Result as ConnectionResult, UserTxConnection,
 };
 use mc_ledger_db::Ledger;
-use mc_transaction_core::{constants::MINIMUM_FEE, tx::Tx, Block, BlockID, BlockIndex};
+use mc_transaction_core::{
+    constants::MINIMUM_FEE, tx::Tx, Block, BlockContents, BlockID, BlockIndex, BLOCK_VERSION,
+};
 use mc_util_uri::{ConnectionUri, ConsensusClientUri};
 use std::{
     cmp::{min, Ordering},
     fmt::{Display, Formatter, Result as FmtResult},
     hash::{Hash, Hasher},
     ops::Range,
+    sync::{Arc, Mutex},
     thread,
     time::Duration,
 };
@@ -31,19 +34,89 @@ pub struct MockBlockchainConnection<L: Ledger + Sync> {
 
     /// Proposed transactions.
     pub proposed_txs: Vec<Tx>,
+
+    /// If set, proposed transactions are appended to the mock ledger as a new
+    /// block at this interval.
+    pub block_interval: Option<Duration>,
+
+    /// Proposed transactions that have not been appended to the mock ledger
+    /// yet. Shared with the block production thread.
+    pending_txs: Arc<Mutex<Vec<Tx>>>,
 }
 
-impl<L: Ledger + Sync> MockBlockchainConnection<L> {
-    pub fn new(uri: ConsensusClientUri, ledger: L, latency_millis: u64) -> Self {
+impl<L: Ledger + Clone + Sync + 'static> MockBlockchainConnection<L> {
+    /// Create a mock connection serving blocks from `ledger`. If
+    /// `block_interval` is set, a background thread appends proposed
+    /// transactions to `ledger` at that interval, until every clone of the
+    /// connection has been dropped.
+    pub fn new(
+        uri: ConsensusClientUri,
+        ledger: L,
+        latency_millis: u64,
+        block_interval: Option<Duration>,
+    ) -> Self {
+        let pending_txs = Arc::new(Mutex::new(Vec::new()));
+
+        if let Some(block_interval) = block_interval {
+            let mut ledger = ledger.clone();
+            let pending_txs = pending_txs.clone();
+            thread::Builder::new()
+                .name("mock_block_production".to_string())
+                .spawn(move || loop {
+                    thread::sleep(block_interval);
+
+                    // Stop once every connection sharing the queue is gone.
+                    if Arc::strong_count(&pending_txs) == 1 {
+                        break;
+                    }
+
+                    let txs: Vec<Tx> = pending_txs
+                        .lock()
+                        .expect("mutex poisoned")
+                        .drain(..)
+                        .collect();
+                    if !txs.is_empty() {
+                        append_txs_to_ledger(&mut ledger, &txs);
+                    }
+                })
+                .expect("failed starting mock block production thread");
+        }
+
         Self {
             uri,
             ledger,
             latency_millis,
             proposed_txs: Vec::new(),
+            block_interval,
+            pending_txs,
         }
     }
 }
 
+/// Append a block holding the key images and outputs of the given
+/// transactions to a ledger.
+fn append_txs_to_ledger<L: Ledger>(ledger: &mut L, txs: &[Tx]) {
+    let key_images = txs.iter().flat_map(|tx| tx.key_images()).collect();
+    let outputs = txs
+        .iter()
+        .flat_map(|tx| tx.prefix.outputs.iter().cloned())
+        .collect();
+    let mut block_contents = BlockContents::new(key_images, outputs);
+    block_contents.sort_outputs();
+
+    let num_blocks = ledger
+        .num_blocks()
+        .expect("failed getting number of blocks");
+    let parent = ledger
+        .get_block(num_blocks - 1)
+        .expect("failed getting parent block");
+    let block =
+        Block::new_with_parent(BLOCK_VERSION, &parent, &Default::default(), &block_contents);
+    ledger
+        .append_block(&block, &block_contents, None)
+        .expect("failed appending block");
+}
+
 impl<L: Ledger + Sync> Display for MockBlockchainConnection<L> {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
         write!(f, "{}", self.uri())
@@ -110,10 +183,14 @@ impl<L: Ledger + Sync> BlockchainConnection for MockBlockchainConnection<L> {
     }
 
     fn fetch_block_height(&mut self) -> ConnectionResult<BlockIndex> {
+        thread::sleep(Duration::from_millis(self.latency_millis));
+
         Ok(self.ledger.num_blocks().unwrap() - 1)
     }
 
     fn fetch_block_info(&mut self) -> ConnectionResult<BlockInfo> {
+        thread::sleep(Duration::from_millis(self.latency_millis));
+
         Ok(BlockInfo {
             block_index: self.ledger.num_blocks().unwrap() - 1,
             minimum_fee: MINIMUM_FEE,
@@ -124,6 +201,12 @@ impl<L: Ledger + Sync> BlockchainConnection for MockBlockchainConnection<L> {
 impl<L: Ledger + Sync> UserTxConnection for MockBlockchainConnection<L> {
     fn propose_tx(&mut self, tx: &Tx) -> ConnectionResult<BlockIndex> {
         self.proposed_txs.push(tx.clone());
+        if self.block_interval.is_some() {
+            self.pending_txs
+                .lock()
+                .expect("mutex poisoned")
+                .push(tx.clone());
+        }
         Ok(self.ledger.num_blocks().unwrap())
     }
 }
@@ -133,13 +216,15 @@ mod tests {
     use super::*;
     use crate::test_client_uri;
     use mc_ledger_db::test_utils::get_mock_ledger;
+    use std::time::Instant;
 
     #[test]
     // Mock peer should return the correct range of blocks.
     fn fetch_blocks() {
         let mock_ledger = get_mock_ledger(25);
         assert_eq!(mock_ledger.lock().blocks_by_block_number.len(), 25);
-        let mut mock_peer = MockBlockchainConnection::new(test_client_uri(123), mock_ledger, 50);
+        let mut mock_peer =
+            MockBlockchainConnection::new(test_client_uri(123), mock_ledger, 50, None);
 
         {
             // Get a subset of the peer's blocks.
@@ -168,4 +253,41 @@ mod tests {
             assert_eq!(blocks.len(), 5)
         }
     }
+
+    #[test]
+    // With a block interval, proposed transactions should only be appended to the
+    // ledger once the interval elapses.
+    fn propose_tx_with_block_interval() {
+        let mock_ledger = get_mock_ledger(5);
+        let block_interval = Duration::from_millis(500);
+
+        let start = Instant::now();
+        let mut mock_peer = MockBlockchainConnection::new(
+            test_client_uri(123),
+            mock_ledger.clone(),
+            0,
+            Some(block_interval),
+        );
+
+        let mut tx = Tx::default();
+        tx.prefix.outputs = mock_ledger.get_block_contents(1).unwrap().outputs;
+        assert_eq!(mock_peer.propose_tx(&tx).unwrap(), 5);
+
+        // Not appended right away.
+        assert_eq!(mock_ledger.num_blocks().unwrap(), 5);
+        assert_eq!(mock_peer.proposed_txs, vec![tx.clone()]);
+
+        // Appended once the interval elapses.
+        while mock_ledger.num_blocks().unwrap() == 5 {
+            assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(start.elapsed() >= block_interval);
+        assert_eq!(mock_ledger.num_blocks().unwrap(), 6);
+        assert_eq!(
+            mock_ledger.get_block_contents(5).unwrap().outputs,
+            tx.prefix.outputs
+        );
+        assert_eq!(mock_peer.fetch_block_height().unwrap(), 5);
+    }
 }
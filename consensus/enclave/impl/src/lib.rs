@@ -634,7 +634,8 @@ mod tests {
         validation::TransactionValidationError,
     };
     use mc_transaction_core_test_utils::{
-        create_ledger, create_transaction, initialize_ledger, AccountKey, ViewKey,
+        create_ledger, create_transaction, create_transaction_with_mismatched_commitment,
+        create_transaction_with_tampered_signature, initialize_ledger, AccountKey, ViewKey,
     };
     use rand_core::SeedableRng;
     use rand_hc::Hc128Rng;
@@ -790,6 +791,73 @@ mod tests {
         );
     }
 
+    #[test_with_logger]
+    // tx_is_well_formed rejects transactions with a tampered signature or a
+    // mismatched amount commitment.
+    fn test_tx_is_well_formed_rejects_flawed_transactions(logger: Logger) {
+        let enclave = SgxConsensusEnclave::new(logger);
+        let mut rng = Hc128Rng::from_seed([77u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let recipient = AccountKey::random(&mut rng);
+
+        let mut ledger = create_ledger();
+        let n_blocks = 3;
+        initialize_ledger(&mut ledger, n_blocks, &sender, &mut rng);
+
+        // Choose a TxOut to spend. Only the TxOut in the last block is unspent.
+        let block_contents = ledger.get_block_contents(n_blocks - 1).unwrap();
+        let tx_out = block_contents.outputs[0].clone();
+
+        let tampered_signature_tx = create_transaction_with_tampered_signature(
+            &mut ledger,
+            &tx_out,
+            &sender,
+            &recipient.default_subaddress(),
+            n_blocks + 1,
+            &mut rng,
+        );
+        let mismatched_commitment_tx = create_transaction_with_mismatched_commitment(
+            &mut ledger,
+            &tx_out,
+            &sender,
+            &recipient.default_subaddress(),
+            n_blocks + 1,
+            &mut rng,
+        );
+
+        for (tx, expected_error) in vec![
+            (
+                tampered_signature_tx,
+                mc_transaction_core::ring_signature::Error::InvalidSignature,
+            ),
+            (
+                mismatched_commitment_tx,
+                mc_transaction_core::ring_signature::Error::RangeProofError,
+            ),
+        ] {
+            let locally_encrypted_tx = LocallyEncryptedTx(
+                enclave
+                    .locally_encrypted_tx_cipher
+                    .lock()
+                    .unwrap()
+                    .encrypt_bytes(&mut rng, mc_util_serial::encode(&tx)),
+            );
+            let highest_indices = tx.get_membership_proof_highest_indices();
+            let proofs = ledger
+                .get_tx_out_proof_of_memberships(&highest_indices)
+                .expect("failed getting proofs");
+            let block_index = ledger.num_blocks().unwrap();
+
+            assert_eq!(
+                enclave.tx_is_well_formed(locally_encrypted_tx, block_index, proofs),
+                Err(Error::MalformedTx(
+                    TransactionValidationError::InvalidTransactionSignature(expected_error)
+                ))
+            );
+        }
+    }
+
     #[test_with_logger]
     // tx_is_well_formed rejects inconsistent root elements.
     fn test_tx_is_well_form_rejects_inconsistent_root_elements(logger: Logger) {
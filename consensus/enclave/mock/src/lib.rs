@@ -260,7 +260,8 @@ impl ConsensusEnclave for ConsensusServiceMockEnclave {
             outputs.extend(tx.prefix.outputs.into_iter());
         }
 
-        let block_contents = BlockContents::new(key_images, outputs);
+        let mut block_contents = BlockContents::new(key_images, outputs);
+        block_contents.sort_outputs();
 
         let block = Block::new_with_parent(
             BLOCK_VERSION,
@@ -21,5 +21,5 @@ pub use self::{
     core_types::{CombineFn, GenericNodeId, Identifier, SlotIndex, ValidityFn, Value},
     msg::{Msg, Topic},
     node::{MockScpNode, Node, ScpNode},
-    quorum_set::{QuorumSet, QuorumSetMember},
+    quorum_set::{QuorumSet, QuorumSetError, QuorumSetMember},
 };
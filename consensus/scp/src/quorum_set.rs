@@ -6,11 +6,12 @@
 //! and depends on.
 use mc_common::{NodeID, ResponderId};
 use mc_crypto_digestible::Digestible;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
     collections::{HashMap, HashSet},
-    fmt::Debug,
+    fmt::{self, Debug, Display},
     hash::{Hash, Hasher},
+    io::Read,
     iter::FromIterator,
 };
 
@@ -33,6 +34,43 @@ pub enum QuorumSetMember<ID: GenericNodeId> {
     InnerSet(QuorumSet<ID>),
 }
 
+/// An error loading a quorum set.
+#[derive(Debug)]
+pub enum QuorumSetError {
+    /// The quorum set could not be parsed.
+    Json(serde_json::Error),
+
+    /// A quorum set, or one of its inner sets, has no members.
+    EmptySet,
+
+    /// A quorum set, or one of its inner sets, has a threshold larger than its
+    /// number of members.
+    ThresholdTooHigh { threshold: u32, num_members: usize },
+}
+
+impl Display for QuorumSetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QuorumSetError::Json(err) => write!(f, "Error parsing quorum set: {}", err),
+            QuorumSetError::EmptySet => write!(f, "Quorum set has no members"),
+            QuorumSetError::ThresholdTooHigh {
+                threshold,
+                num_members,
+            } => write!(
+                f,
+                "Quorum set threshold {} exceeds its {} members",
+                threshold, num_members
+            ),
+        }
+    }
+}
+
+impl From<serde_json::Error> for QuorumSetError {
+    fn from(src: serde_json::Error) -> Self {
+        QuorumSetError::Json(src)
+    }
+}
+
 /// The quorum set defining the trusted set of peers.
 #[derive(Clone, Debug, Ord, PartialOrd, Serialize, Deserialize, Digestible)]
 pub struct QuorumSet<ID: GenericNodeId = NodeID> {
@@ -98,6 +136,45 @@ impl<ID: GenericNodeId> QuorumSet<ID> {
         Self::new(0, vec![])
     }
 
+    /// Read a quorum set from JSON, and validate it.
+    ///
+    /// The JSON representation nests inner sets as members, for example:
+    /// {"threshold":2,"members":[{"type":"Node","args":"node1:443"},
+    /// {"type":"InnerSet","args":{"threshold":1,"members":[...]}}]}
+    pub fn from_json<R: Read>(reader: R) -> Result<Self, QuorumSetError>
+    where
+        ID: DeserializeOwned,
+    {
+        let quorum_set: Self = serde_json::from_reader(reader)?;
+        quorum_set.validate()?;
+        Ok(quorum_set)
+    }
+
+    /// Check that the quorum set and all of its inner sets have members, and a
+    /// threshold no larger than their number of members.
+    ///
+    /// This is stricter than `is_valid`, which accepts empty sets.
+    pub fn validate(&self) -> Result<(), QuorumSetError> {
+        if self.members.is_empty() {
+            return Err(QuorumSetError::EmptySet);
+        }
+
+        if self.threshold as usize > self.members.len() {
+            return Err(QuorumSetError::ThresholdTooHigh {
+                threshold: self.threshold,
+                num_members: self.members.len(),
+            });
+        }
+
+        for member in self.members.iter() {
+            if let QuorumSetMember::InnerSet(qs) = member {
+                qs.validate()?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Check if a quorum set is valid.
     pub fn is_valid(&self) -> bool {
         // Must have at least `threshold` members.
@@ -431,7 +508,7 @@ mod quorum_set_tests {
     use super::*;
     use crate::{core_types::*, msg::*, predicates::*, test_utils::test_node_id};
     use mc_common::ResponderId;
-    use std::collections::hash_map::DefaultHasher;
+    use std::{collections::hash_map::DefaultHasher, str::FromStr};
 
     #[test]
     // quorum sets should sort recursively
@@ -1026,6 +1103,89 @@ mod quorum_set_tests {
         );
     }
 
+    #[test]
+    // A nested quorum set should be read from JSON.
+    fn test_from_json() {
+        let json = r#"{
+            "threshold": 2,
+            "members": [
+                {"type": "Node", "args": "node1.test.mobilecoin.com:443"},
+                {"type": "InnerSet", "args": {
+                    "threshold": 1,
+                    "members": [
+                        {"type": "Node", "args": "node2.test.mobilecoin.com:443"},
+                        {"type": "Node", "args": "node3.test.mobilecoin.com:443"}
+                    ]
+                }}
+            ]
+        }"#;
+
+        let quorum_set = QuorumSet::<ResponderId>::from_json(json.as_bytes()).unwrap();
+        assert_eq!(
+            quorum_set,
+            QuorumSet::new(
+                2,
+                vec![
+                    QuorumSetMember::Node(
+                        ResponderId::from_str("node1.test.mobilecoin.com:443").unwrap()
+                    ),
+                    QuorumSetMember::InnerSet(QuorumSet::new_with_node_ids(
+                        1,
+                        vec![
+                            ResponderId::from_str("node2.test.mobilecoin.com:443").unwrap(),
+                            ResponderId::from_str("node3.test.mobilecoin.com:443").unwrap(),
+                        ],
+                    )),
+                ],
+            )
+        );
+    }
+
+    #[test]
+    // Invalid quorum sets should be rejected when read from JSON.
+    fn test_from_json_rejects_invalid_quorum_sets() {
+        // Not a quorum set.
+        match QuorumSet::<ResponderId>::from_json(r#"{"threshold": 1}"#.as_bytes()) {
+            Err(QuorumSetError::Json(_)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Threshold larger than the number of members.
+        let json = r#"{
+            "threshold": 3,
+            "members": [
+                {"type": "Node", "args": "node1.test.mobilecoin.com:443"},
+                {"type": "Node", "args": "node2.test.mobilecoin.com:443"}
+            ]
+        }"#;
+        match QuorumSet::<ResponderId>::from_json(json.as_bytes()) {
+            Err(QuorumSetError::ThresholdTooHigh {
+                threshold: 3,
+                num_members: 2,
+            }) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Empty set.
+        match QuorumSet::<ResponderId>::from_json(r#"{"threshold": 0, "members": []}"#.as_bytes()) {
+            Err(QuorumSetError::EmptySet) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Empty inner set.
+        let json = r#"{
+            "threshold": 1,
+            "members": [
+                {"type": "Node", "args": "node1.test.mobilecoin.com:443"},
+                {"type": "InnerSet", "args": {"threshold": 0, "members": []}}
+            ]
+        }"#;
+        match QuorumSet::<ResponderId>::from_json(json.as_bytes()) {
+            Err(QuorumSetError::EmptySet) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
     #[test]
     fn test_is_valid() {
         // An empty quorum set is valid.
@@ -75,7 +75,10 @@ fn main() -> Result<(), ConsensusServiceError> {
 
     setup_ledger_dir(&config.origin_block_path, &config.ledger_path);
 
-    let local_ledger = LedgerDB::open(&config.ledger_path).expect("Failed creating LedgerDB");
+    let mut local_ledger = LedgerDB::open(&config.ledger_path).expect("Failed creating LedgerDB");
+    if let Some(fee_view_private_key) = config.fee_view_private_key {
+        local_ledger.set_fee_view_private_key(fee_view_private_key);
+    }
 
     let ias_client = Client::new(&config.ias_api_key).expect("Could not create IAS client");
 
@@ -5,12 +5,15 @@
 use mc_attest_core::ProviderId;
 use mc_common::{HashMap, HashSet, NodeID, ResponderId};
 use mc_consensus_scp::{QuorumSet, QuorumSetMember};
-use mc_crypto_keys::{DistinguishedEncoding, Ed25519Pair, Ed25519Private};
+use mc_crypto_keys::{DistinguishedEncoding, Ed25519Pair, Ed25519Private, RistrettoPrivate};
 use mc_util_uri::{
     AdminUri, ConnectionUri, ConsensusClientUri as ClientUri, ConsensusPeerUri as PeerUri,
 };
 use serde::{Deserialize, Serialize};
-use std::{fmt::Debug, fs, path::PathBuf, str::FromStr, string::String, sync::Arc, time::Duration};
+use std::{
+    convert::TryFrom, fmt::Debug, fs, path::PathBuf, str::FromStr, string::String, sync::Arc,
+    time::Duration,
+};
 use structopt::StructOpt;
 
 #[derive(Clone, Debug, StructOpt)]
@@ -99,6 +102,11 @@ pub struct Config {
     /// Allow extreme (>= 1MOB, <= 0.000_000_01 MOB).
     #[structopt(long)]
     pub allow_any_fee: bool,
+
+    /// Hex-encoded view private key of the fee recipient, used to record the
+    /// fees collected by each block appended to the local ledger.
+    #[structopt(long, parse(try_from_str=parse_ristretto_private_from_hex))]
+    pub fee_view_private_key: Option<RistrettoPrivate>,
 }
 
 /// Decodes an Ed25519 private key.
@@ -114,6 +122,14 @@ fn keypair_from_base64(private_key: &str) -> Result<Arc<Ed25519Pair>, String> {
     Ok(Arc::new(Ed25519Pair::from(secret_key)))
 }
 
+/// Decodes a hex-encoded Ristretto private key.
+fn parse_ristretto_private_from_hex(src: &str) -> Result<RistrettoPrivate, String> {
+    let bytes = hex::decode(src)
+        .map_err(|err| format!("Could not decode private key from hex {:?}", err))?;
+    RistrettoPrivate::try_from(&bytes[..])
+        .map_err(|err| format!("Could not get RistrettoPrivate from bytes {:?}", err))
+}
+
 /// Converts a string containing number of seconds to a Duration object.
 fn parse_duration_in_seconds(src: &str) -> Result<Duration, std::num::ParseIntError> {
     Ok(Duration::from_secs(u64::from_str(src)?))
@@ -456,6 +472,7 @@ mod tests {
             client_auth_token_max_lifetime: Duration::from_secs(60),
             minimum_fee: None,
             allow_any_fee: false,
+            fee_view_private_key: None,
         };
 
         assert_eq!(
@@ -513,6 +530,7 @@ mod tests {
             client_auth_token_max_lifetime: Duration::from_secs(60),
             minimum_fee: None,
             allow_any_fee: false,
+            fee_view_private_key: None,
         };
 
         assert_eq!(
@@ -947,14 +947,18 @@ mod tests {
         let (block, block_contents, _signature) = tx_manager
             .tx_hashes_to_block(&[hash_tx_zero, hash_tx_one], &parent_block)
             .expect("failed assembling block");
-        assert_eq!(
-            client_tx_zero.prefix.outputs[0].public_key,
-            block_contents.outputs[0].public_key
-        );
-        assert_eq!(
-            client_tx_one.prefix.outputs[0].public_key,
-            block_contents.outputs[1].public_key
-        );
+        // The block lists its outputs in canonical order.
+        let mut expected_public_keys = vec![
+            client_tx_zero.prefix.outputs[0].public_key.clone(),
+            client_tx_one.prefix.outputs[0].public_key.clone(),
+        ];
+        expected_public_keys.sort();
+        let public_keys: Vec<_> = block_contents
+            .outputs
+            .iter()
+            .map(|tx_out| tx_out.public_key.clone())
+            .collect();
+        assert_eq!(public_keys, expected_public_keys);
 
         // The ledger was previously initialized with 3 blocks.
         assert_eq!(block.index, 3);
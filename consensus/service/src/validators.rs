@@ -175,6 +175,11 @@ impl<L: Ledger + Sync> TxManagerUntrustedInterfaces for DefaultTxManagerUntruste
 pub mod well_formed_tests {
     use super::*;
     use mc_ledger_db::{Error as LedgerError, MockLedger};
+    use mc_transaction_core_test_utils::{
+        create_ledger, create_transaction_reusing_key_image, initialize_ledger, AccountKey,
+    };
+    use rand_core::SeedableRng;
+    use rand_hc::Hc128Rng;
 
     #[test]
     // `is_well_formed` should accept a well-formed transaction.
@@ -425,6 +430,33 @@ mod is_valid_tests {
         );
     }
 
+    #[test]
+    /// `is_valid` should reject a transaction spending a TxOut that has already
+    /// been spent in the ledger.
+    fn is_valid_rejects_transaction_reusing_key_image() {
+        let mut rng = Hc128Rng::from_seed([1u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let recipient = AccountKey::random(&mut rng);
+
+        let mut ledger = create_ledger();
+        let n_blocks = 3;
+        initialize_ledger(&mut ledger, n_blocks, &sender, &mut rng);
+
+        let tx = create_transaction_reusing_key_image(
+            &mut ledger,
+            &sender,
+            &recipient.default_subaddress(),
+            n_blocks + 1,
+            &mut rng,
+        );
+
+        let untrusted = DefaultTxManagerUntrustedInterfaces::new(ledger);
+        assert_eq!(
+            untrusted.is_valid(Arc::new(WellFormedTxContext::from(&tx))),
+            Err(TransactionValidationError::ContainsSpentKeyImage),
+        );
+    }
+
     #[test]
     /// `is_valid` should reject a transaction with an already used output
     /// public key.
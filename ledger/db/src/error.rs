@@ -1,8 +1,9 @@
 // Copyright (c) 2018-2021 The MobileCoin Foundation
 
 use displaydoc::Display;
+use mc_crypto_keys::CompressedRistrettoPublic;
 use mc_transaction_core::{membership_proofs::RangeError, BlockID, BlockIndex};
-use mc_util_lmdb::MetadataStoreError;
+use mc_util_lmdb::{CompactError, MetadataStoreError};
 
 /// A Ledger error kind.
 #[derive(Debug, Eq, PartialEq, Clone, Display)]
@@ -22,6 +23,9 @@ pub enum Error {
     /// InvalidBlockVersion: {0}
     InvalidBlockVersion(u32),
 
+    /// BlockVersionNotActive: version {0} at block {1}
+    BlockVersionNotActive(u32, BlockIndex),
+
     /// NoKeyImages
     NoKeyImages,
 
@@ -31,8 +35,11 @@ pub enum Error {
     /// KeyImageAlreadySpent
     KeyImageAlreadySpent,
 
-    /// DuplicateOutputPublicKey
-    DuplicateOutputPublicKey,
+    /// DuplicateOutputPublicKey: {0}
+    DuplicateOutputPublicKey(CompressedRistrettoPublic),
+
+    /// DuplicateOutputPublicKeyInBlock: {0}
+    DuplicateOutputPublicKeyInBlock(CompressedRistrettoPublic),
 
     /// InvalidBlockContents
     InvalidBlockContents,
@@ -46,6 +53,9 @@ pub enum Error {
     /// NoOutputs
     NoOutputs,
 
+    /// UnsortedOutputs
+    UnsortedOutputs,
+
     /// LMDB error, may mean database is opened multiple times in a process.
     BadRslot,
 
@@ -63,6 +73,24 @@ pub enum Error {
 
     /// Metadata store: {0}
     MetadataStore(MetadataStoreError),
+
+    /// CorruptedBlock: {0}
+    CorruptedBlock(BlockIndex),
+
+    /// ZeroValueOutput: {0}
+    ZeroValueOutput(BlockIndex),
+
+    /// MerkleRootMismatch
+    MerkleRootMismatch,
+
+    /// BlockContentsPruned: {0}
+    BlockContentsPruned(BlockIndex),
+
+    /// FeesNotIndexed: {0}
+    FeesNotIndexed(BlockIndex),
+
+    /// Compaction: {0}
+    Compaction(CompactError),
 }
 
 impl From<lmdb::Error> for Error {
@@ -75,6 +103,12 @@ impl From<lmdb::Error> for Error {
     }
 }
 
+impl From<CompactError> for Error {
+    fn from(src: CompactError) -> Self {
+        Error::Compaction(src)
+    }
+}
+
 impl From<mc_util_serial::decode::Error> for Error {
     fn from(_: mc_util_serial::decode::Error) -> Self {
         Error::Deserialization
@@ -10,7 +10,9 @@ extern crate test;
 mod error;
 mod ledger_trait;
 mod metrics;
+mod tip_notifier;
 
+pub mod read_only_ledger;
 pub mod tx_out_store;
 
 #[cfg(any(test, feature = "test_utils"))]
@@ -18,12 +20,13 @@ pub mod test_utils;
 
 use core::convert::TryInto;
 use lmdb::{
-    Database, DatabaseFlags, Environment, EnvironmentFlags, RoTransaction, RwTransaction,
+    Cursor, Database, DatabaseFlags, Environment, EnvironmentFlags, RoTransaction, RwTransaction,
     Transaction, WriteFlags,
 };
-use mc_common::logger::global_log;
-use mc_crypto_keys::CompressedRistrettoPublic;
+use mc_common::{logger::global_log, LruCache};
+use mc_crypto_keys::{CompressedRistrettoPublic, RistrettoPrivate, RistrettoPublic};
 use mc_transaction_core::{
+    onetime_keys::create_shared_secret,
     ring_signature::KeyImage,
     tx::{TxOut, TxOutMembershipProof},
     Block, BlockContents, BlockData, BlockID, BlockSignature, BLOCK_VERSION,
@@ -32,19 +35,32 @@ use mc_util_lmdb::MetadataStoreSettings;
 use mc_util_serial::{decode, encode, Message};
 use metrics::LedgerMetrics;
 use std::{
+    collections::{BTreeMap, HashSet},
+    convert::TryFrom,
     fs,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{mpsc::Receiver, Arc, Mutex},
     time::Instant,
 };
 
 pub use error::Error;
 pub use ledger_trait::{Ledger, MockLedger};
 pub use mc_util_lmdb::MetadataStore;
+pub use tip_notifier::TipNotifier;
 pub use tx_out_store::TxOutStore;
 
 const MAX_LMDB_FILE_SIZE: usize = 1_099_511_627_776; // 1 TB
 
+/// Maximum number of named LMDB databases in the environment.
+pub(crate) const MAX_LMDB_DBS: u32 = 22;
+
+/// The LMDB map is grown when opening the database if its file has reached this
+/// percentage of the requested map size.
+const MAP_SIZE_GROWTH_THRESHOLD_PERCENT: usize = 90;
+
+/// The factor by which the LMDB map size is multiplied when it is grown.
+const MAP_SIZE_GROWTH_FACTOR: usize = 2;
+
 // LMDB Database names.
 pub const COUNTS_DB_NAME: &str = "ledger_db:counts";
 pub const BLOCKS_DB_NAME: &str = "ledger_db:blocks";
@@ -53,9 +69,40 @@ pub const KEY_IMAGES_DB_NAME: &str = "ledger_db:key_images";
 pub const KEY_IMAGES_BY_BLOCK_DB_NAME: &str = "ledger_db:key_images_by_block";
 pub const TX_OUTS_BY_BLOCK_DB_NAME: &str = "ledger_db:tx_outs_by_block";
 pub const BLOCK_NUMBER_BY_TX_OUT_INDEX: &str = "ledger_db:block_number_by_tx_out_index";
+pub const FEES_BY_BLOCK_DB_NAME: &str = "ledger_db:fees_by_block";
+pub const BLOCK_INDEX_BY_HASH_DB_NAME: &str = "ledger_db:block_index_by_hash";
+pub const KEY_IMAGE_BY_TX_OUT_INDEX_DB_NAME: &str = "ledger_db:key_image_by_tx_out_index";
 
 /// Keys used by the `counts` database.
 pub const NUM_BLOCKS_KEY: &str = "num_blocks";
+pub const TOTAL_FEES_KEY: &str = "total_fees";
+pub const NUM_FEE_INDEXED_BLOCKS_KEY: &str = "num_fee_indexed_blocks";
+pub const NUM_PRUNED_TX_OUTS_KEY: &str = "num_pruned_tx_outs";
+
+/// Whether committed writes are flushed to disk before a commit returns.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Durability {
+    /// Flush every commit to disk. A committed block survives a crash of the
+    /// process or of the machine.
+    Safe,
+
+    /// Leave flushing commits to the operating system. A committed block
+    /// survives a crash of the process, but a crash of the machine or a power
+    /// loss can lose the latest blocks, and can corrupt the database if the
+    /// filesystem does not preserve the order of writes. Suited to bulk
+    /// imports that can be restarted from scratch, followed by
+    /// `LedgerDB::sync`.
+    Fast,
+}
+
+impl Durability {
+    fn environment_flags(self) -> EnvironmentFlags {
+        match self {
+            Durability::Safe => EnvironmentFlags::empty(),
+            Durability::Fast => EnvironmentFlags::NO_SYNC,
+        }
+    }
+}
 
 /// Metadata store settings that are used for version control.
 #[derive(Clone, Default, Debug)]
@@ -95,12 +142,31 @@ pub struct KeyImageList {
     pub key_images: Vec<KeyImage>,
 }
 
+/// Options for `LedgerDB::verify_integrity_with_options`.
+#[derive(Clone, Default)]
+pub struct IntegrityCheckOptions {
+    /// Output amounts are only visible to their recipient, so zero-value
+    /// outputs can only be detected for the accounts whose view private keys
+    /// are given here. A block containing a zero-value output decodable by one
+    /// of these keys fails the check with `Error::ZeroValueOutput`.
+    ///
+    /// The fee output of a block is minted by consensus and always carries the
+    /// block's total fee, so it is not expected to trip this check even when
+    /// the fee recipient's view key is included.
+    pub zero_value_output_view_keys: Vec<RistrettoPrivate>,
+}
+
 #[derive(Clone)]
 pub struct LedgerDB {
     env: Arc<Environment>,
 
     /// Aggregate counts about the ledger.
     /// * `NUM_BLOCKS_KEY` --> number of blocks in the ledger.
+    /// * `TOTAL_FEES_KEY` --> sum of the fees collected by all fee-indexed
+    ///   blocks.
+    /// * `NUM_FEE_INDEXED_BLOCKS_KEY` --> number of blocks after the origin
+    ///   block with an entry in `fees_by_block`.
+    /// * `NUM_PRUNED_TX_OUTS_KEY` --> number of TxOuts that were pruned.
     counts: Database,
 
     /// Blocks by block number. `block number -> Block`
@@ -130,9 +196,46 @@ pub struct LedgerDB {
     /// This map allows retrieval of the block a given TxOut belongs to.
     block_number_by_tx_out_index: Database,
 
+    /// Fees by block number. `block number -> fee value`.
+    /// Only blocks after the origin block that were appended while the fee
+    /// view private key was set have an entry, which is zero when the block
+    /// has no fee output.
+    fees_by_block: Database,
+
+    /// Block index by block id. `block id -> block number`.
+    block_index_by_hash: Database,
+
+    /// Key images of TxOuts, as recorded by their owners.
+    /// `TxOut global index -> KeyImage`. Only TxOuts with a recorded key image
+    /// can be pruned, once that key image is spent.
+    key_image_by_tx_out_index: Database,
+
+    /// View private key of the fee recipient, used to identify and decode the
+    /// aggregate fee output of appended blocks.
+    fee_view_private_key: Option<RistrettoPrivate>,
+
+    /// Number of most recent blocks whose outputs are kept when appending
+    /// blocks. `None` keeps the outputs of every block.
+    prune_depth: Option<u64>,
+
+    /// Recently read blocks, by block number, shared between clones of this
+    /// LedgerDB. `None` when caching is disabled.
+    block_cache: Option<Arc<Mutex<LruCache<u64, Block>>>>,
+
+    /// Highest block version permitted from a given block number on.
+    /// `activation block number -> block version`. Empty when block versions
+    /// are bounded by `BLOCK_VERSION` instead.
+    block_version_schedule: BTreeMap<u64, u32>,
+
     /// Location on filesystem.
     path: PathBuf,
 
+    /// Size of the LMDB map the environment was opened with.
+    map_size: usize,
+
+    /// Subscribers to be notified when blocks are appended.
+    tip_notifier: TipNotifier,
+
     /// Metrics.
     metrics: LedgerMetrics,
 }
@@ -153,23 +256,10 @@ impl Ledger for LedgerDB {
     ) -> Result<(), Error> {
         let start_time = Instant::now();
 
-        // Note: This function must update every LMDB database managed by LedgerDB.
-        let mut db_transaction = self.env.begin_rw_txn()?;
-
-        // Validate the block is safe to append.
-        self.validate_append_block(block, block_contents)?;
-
-        // Write key images included in block.
-        self.write_key_images(block.index, &block_contents.key_images, &mut db_transaction)?;
-
-        // Write information about TxOuts included in block.
-        self.write_tx_outs(block.index, &block_contents.outputs, &mut db_transaction)?;
-
-        // Write block.
-        self.write_block(block, signature.as_ref(), &mut db_transaction)?;
+        self.append_block_impl(block, block_contents, signature.as_ref())?;
 
-        // Commit.
-        db_transaction.commit()?;
+        // The write transaction has committed, so subscribers observe the new block.
+        self.tip_notifier.notify(block.index + 1);
 
         // Update metrics.
         self.metrics.blocks_written_count.inc();
@@ -206,8 +296,31 @@ impl Ledger for LedgerDB {
 
     /// Gets a Block by its index in the blockchain.
     fn get_block(&self, block_number: u64) -> Result<Block, Error> {
+        let block_cache = match &self.block_cache {
+            Some(block_cache) => block_cache,
+            None => {
+                let db_transaction = self.env.begin_ro_txn()?;
+                return self.get_block_impl(&db_transaction, block_number);
+            }
+        };
+
+        if let Some(block) = block_cache
+            .lock()
+            .expect("mutex poisoned")
+            .get(&block_number)
+        {
+            return Ok(block.clone());
+        }
+
+        // Only blocks that were found are cached, so a block is never cached
+        // before it is appended.
         let db_transaction = self.env.begin_ro_txn()?;
-        self.get_block_impl(&db_transaction, block_number)
+        let block = self.get_block_impl(&db_transaction, block_number)?;
+        block_cache
+            .lock()
+            .expect("mutex poisoned")
+            .put(block_number, block.clone());
+        Ok(block)
     }
 
     /// Get the contents of a block.
@@ -326,14 +439,50 @@ impl Ledger for LedgerDB {
 }
 
 impl LedgerDB {
-    /// Opens an existing Ledger Database in the given path.
-    #[allow(clippy::unreadable_literal)]
+    /// Opens an existing Ledger Database in the given path, with
+    /// `Durability::Fast`.
     pub fn open(path: &Path) -> Result<LedgerDB, Error> {
+        Self::open_with_map_size(path, MAX_LMDB_FILE_SIZE)
+    }
+
+    /// Opens an existing Ledger Database in the given path, with a given
+    /// durability of writes.
+    pub fn open_with_durability(path: &Path, durability: Durability) -> Result<LedgerDB, Error> {
+        Self::open_impl(path, MAX_LMDB_FILE_SIZE, durability)
+    }
+
+    /// Compacts the Ledger Database in the given path, reclaiming the space
+    /// left by deleted data such as pruned outputs, which LMDB otherwise keeps
+    /// in its file. The database must not be open, in this process or any
+    /// other. Returns the size of the data file before and after compaction.
+    pub fn compact(path: &Path) -> Result<(u64, u64), Error> {
+        Ok(mc_util_lmdb::compact_environment(
+            path,
+            MAX_LMDB_DBS,
+            MAX_LMDB_FILE_SIZE,
+        )?)
+    }
+
+    /// Opens an existing Ledger Database in the given path, with a given
+    /// LMDB map size and `Durability::Fast`. If the database file already
+    /// fills most of `map_size`, the map is opened larger so that appending
+    /// can continue. The map is never resized while the database is open, as
+    /// LMDB forbids that while other threads hold transactions; appending
+    /// fails with `lmdb::Error::MapFull` once it is full, and the database
+    /// must then be reopened.
+    pub fn open_with_map_size(path: &Path, map_size: usize) -> Result<LedgerDB, Error> {
+        // TODO - Fast is needed because currently our test cloud machines have slow
+        // disks.
+        Self::open_impl(path, map_size, Durability::Fast)
+    }
+
+    #[allow(clippy::unreadable_literal)]
+    fn open_impl(path: &Path, map_size: usize, durability: Durability) -> Result<LedgerDB, Error> {
+        let map_size = Self::map_size_for_existing_db(path, map_size);
         let env = Environment::new()
-            .set_max_dbs(22)
-            .set_map_size(MAX_LMDB_FILE_SIZE)
-            // TODO - needed because currently our test cloud machines have slow disks.
-            .set_flags(EnvironmentFlags::NO_SYNC)
+            .set_max_dbs(MAX_LMDB_DBS)
+            .set_map_size(map_size)
+            .set_flags(durability.environment_flags())
             .open(path)?;
 
         let metadata_store = MetadataStore::<LedgerDbMetadataStoreSettings>::new(&env)?;
@@ -351,6 +500,9 @@ impl LedgerDB {
         let key_images_by_block = env.open_db(Some(KEY_IMAGES_BY_BLOCK_DB_NAME))?;
         let tx_outs_by_block = env.open_db(Some(TX_OUTS_BY_BLOCK_DB_NAME))?;
         let block_number_by_tx_out_index = env.open_db(Some(BLOCK_NUMBER_BY_TX_OUT_INDEX))?;
+        let fees_by_block = open_or_create_db(&env, FEES_BY_BLOCK_DB_NAME)?;
+        let block_index_by_hash = open_or_build_block_index_by_hash(&env, blocks)?;
+        let key_image_by_tx_out_index = open_or_create_db(&env, KEY_IMAGE_BY_TX_OUT_INDEX_DB_NAME)?;
 
         let tx_out_store = TxOutStore::new(&env)?;
 
@@ -359,6 +511,8 @@ impl LedgerDB {
         let ledger_db = LedgerDB {
             env: Arc::new(env),
             path: path.to_path_buf(),
+            map_size,
+            tip_notifier: TipNotifier::default(),
             counts,
             blocks,
             block_signatures,
@@ -366,6 +520,13 @@ impl LedgerDB {
             key_images_by_block,
             tx_outs_by_block,
             block_number_by_tx_out_index,
+            fees_by_block,
+            block_index_by_hash,
+            key_image_by_tx_out_index,
+            fee_view_private_key: None,
+            prune_depth: None,
+            block_cache: None,
+            block_version_schedule: BTreeMap::new(),
             metadata_store,
             tx_out_store,
             metrics,
@@ -377,11 +538,50 @@ impl LedgerDB {
         Ok(ledger_db)
     }
 
+    /// The LMDB map size to open the database in `path` with: `map_size`,
+    /// grown until the data file fills less than the growth threshold of it.
+    fn map_size_for_existing_db(path: &Path, map_size: usize) -> usize {
+        let file_size = data_file_size(path).unwrap_or(0) as usize;
+        let mut grown_map_size = map_size;
+        while grown_map_size > 0
+            && file_size >= grown_map_size / 100 * MAP_SIZE_GROWTH_THRESHOLD_PERCENT
+        {
+            grown_map_size = grown_map_size.saturating_mul(MAP_SIZE_GROWTH_FACTOR);
+        }
+        if grown_map_size != map_size {
+            global_log::info!(
+                "Ledger db file is {} bytes, growing map from {} to {} bytes",
+                file_size,
+                map_size,
+                grown_map_size
+            );
+        }
+        grown_map_size
+    }
+
     /// Creates a fresh Ledger Database in the given path.
     pub fn create(path: &Path) -> Result<(), Error> {
+        Self::create_with_map_size(path, MAX_LMDB_FILE_SIZE)
+    }
+
+    /// Creates a fresh Ledger Database in the given path, with a given
+    /// durability of writes. The durability is not stored in the database, and
+    /// is chosen again whenever it is opened.
+    pub fn create_with_durability(path: &Path, durability: Durability) -> Result<(), Error> {
+        Self::create_impl(path, MAX_LMDB_FILE_SIZE, durability)
+    }
+
+    /// Creates a fresh Ledger Database in the given path, with a given LMDB map
+    /// size.
+    pub fn create_with_map_size(path: &Path, map_size: usize) -> Result<(), Error> {
+        Self::create_impl(path, map_size, Durability::Safe)
+    }
+
+    fn create_impl(path: &Path, map_size: usize, durability: Durability) -> Result<(), Error> {
         let env = Environment::new()
-            .set_max_dbs(22)
-            .set_map_size(MAX_LMDB_FILE_SIZE)
+            .set_max_dbs(MAX_LMDB_DBS)
+            .set_map_size(map_size)
+            .set_flags(durability.environment_flags())
             .open(path)?;
 
         let counts = env.create_db(Some(COUNTS_DB_NAME), DatabaseFlags::empty())?;
@@ -391,6 +591,12 @@ impl LedgerDB {
         env.create_db(Some(KEY_IMAGES_BY_BLOCK_DB_NAME), DatabaseFlags::empty())?;
         env.create_db(Some(TX_OUTS_BY_BLOCK_DB_NAME), DatabaseFlags::empty())?;
         env.create_db(Some(BLOCK_NUMBER_BY_TX_OUT_INDEX), DatabaseFlags::empty())?;
+        env.create_db(Some(FEES_BY_BLOCK_DB_NAME), DatabaseFlags::empty())?;
+        env.create_db(Some(BLOCK_INDEX_BY_HASH_DB_NAME), DatabaseFlags::empty())?;
+        env.create_db(
+            Some(KEY_IMAGE_BY_TX_OUT_INDEX_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
 
         MetadataStore::<LedgerDbMetadataStoreSettings>::create(&env)?;
         TxOutStore::create(&env)?;
@@ -408,6 +614,358 @@ impl LedgerDB {
         Ok(())
     }
 
+    /// Flush all committed writes to disk. With `Durability::Fast`, blocks
+    /// appended before this call are then as safe as with
+    /// `Durability::Safe`.
+    pub fn sync(&self) -> Result<(), Error> {
+        self.env.sync(true)?;
+        Ok(())
+    }
+
+    /// Get a handle to this ledger that can only be used for reading.
+    pub fn read_only(&self) -> Arc<dyn read_only_ledger::ReadOnlyLedger> {
+        Arc::new(self.clone())
+    }
+
+    /// Subscribe to changes of the ledger tip. The receiver gets the new
+    /// number of blocks after each block appended through this LedgerDB or
+    /// one of its clones. Appends made by other processes, or through a
+    /// separately opened LedgerDB, are not observed.
+    pub fn subscribe_to_tip(&self) -> Receiver<u64> {
+        self.tip_notifier.subscribe()
+    }
+
+    /// Get the size of the LMDB map.
+    pub fn map_size(&self) -> usize {
+        self.map_size
+    }
+
+    /// Write a block and its contents in a single LMDB transaction.
+    fn append_block_impl(
+        &self,
+        block: &Block,
+        block_contents: &BlockContents,
+        signature: Option<&BlockSignature>,
+    ) -> Result<(), Error> {
+        // Note: This function must update every LMDB database managed by LedgerDB.
+        let mut db_transaction = self.env.begin_rw_txn()?;
+
+        // Validate the block is safe to append.
+        self.validate_block(block, block_contents)?;
+
+        // Write key images included in block.
+        self.write_key_images(block.index, &block_contents.key_images, &mut db_transaction)?;
+
+        // Write information about TxOuts included in block.
+        self.write_tx_outs(block.index, &block_contents.outputs, &mut db_transaction)?;
+
+        // Write the fees collected in the block.
+        self.write_fees(block.index, &block_contents.outputs, &mut db_transaction)?;
+
+        // Write block.
+        self.write_block(block, signature, &mut db_transaction)?;
+
+        // Drop the spent outputs of blocks that fell behind the pruning horizon.
+        if let Some(prune_depth) = self.prune_depth {
+            self.prune_tx_outs(
+                (block.index + 1).saturating_sub(prune_depth),
+                &mut db_transaction,
+            )?;
+        }
+
+        // Commit.
+        db_transaction.commit()?;
+        Ok(())
+    }
+
+    /// Set the view private key of the fee recipient. Blocks appended
+    /// afterwards have their aggregate fee output decoded and recorded in the
+    /// fees index. The key is not persisted, so it must be set every time the
+    /// ledger is opened, before any block is appended, for the fees of every
+    /// block to be known.
+    pub fn set_fee_view_private_key(&mut self, fee_view_private_key: RistrettoPrivate) {
+        self.fee_view_private_key = Some(fee_view_private_key);
+    }
+
+    /// Set the number of most recent blocks whose outputs are kept. Once set,
+    /// appending a block prunes the spent outputs of the blocks that fall
+    /// behind this depth, which bounds the growth of the ledger for nodes that
+    /// do not need its full history.
+    ///
+    /// Only the owner of an output can compute its key image, so an output
+    /// is known to be spent only once its key image was recorded with
+    /// `set_tx_out_key_image` and appears in the ledger. Outputs that are not
+    /// known to be spent are kept. Only the body of a pruned output is
+    /// dropped: block headers, signatures, key images, output hashes and
+    /// public keys, and the Merkle hashes are retained, so new blocks are
+    /// still fully validated and proofs of membership can still be generated.
+    /// Pruned outputs can no longer be read, nor can the contents of their
+    /// blocks, and such reads return `Error::NotFound` and
+    /// `Error::BlockContentsPruned` respectively.
+    pub fn set_prune_depth(&mut self, prune_depth: u64) {
+        self.prune_depth = Some(prune_depth);
+    }
+
+    /// Record the key image of the TxOut with a given global index, which only
+    /// the owner of the TxOut can compute. The TxOut is pruned once its key
+    /// image is spent and it is behind the pruning horizon, which may be right
+    /// away.
+    pub fn set_tx_out_key_image(
+        &self,
+        tx_out_index: u64,
+        key_image: &KeyImage,
+    ) -> Result<(), Error> {
+        let mut db_transaction = self.env.begin_rw_txn()?;
+        if tx_out_index >= self.tx_out_store.num_tx_outs(&db_transaction)? {
+            return Err(Error::NotFound);
+        }
+        db_transaction.put(
+            self.key_image_by_tx_out_index,
+            &u64_to_key_bytes(tx_out_index),
+            &key_image,
+            WriteFlags::empty(),
+        )?;
+
+        if let Some(prune_depth) = self.prune_depth {
+            let num_blocks = key_bytes_to_u64(db_transaction.get(self.counts, &NUM_BLOCKS_KEY)?);
+            self.prune_tx_outs(num_blocks.saturating_sub(prune_depth), &mut db_transaction)?;
+        }
+
+        db_transaction.commit()?;
+        Ok(())
+    }
+
+    /// Keep up to `capacity` recently read blocks in memory, so that blocks
+    /// read repeatedly, such as the most recent ones, are served by
+    /// `get_block` without a database read. Blocks are never modified once
+    /// appended, so cached blocks cannot go stale. A capacity of 0 disables
+    /// the cache.
+    pub fn set_block_cache_capacity(&mut self, capacity: usize) {
+        self.block_cache = if capacity == 0 {
+            None
+        } else {
+            Some(Arc::new(Mutex::new(LruCache::new(capacity))))
+        };
+    }
+
+    /// Restrict the versions of appended blocks to a schedule of activation
+    /// heights, for protocol upgrades that must only take effect from a given
+    /// block on. Each entry maps a block index to the highest version that
+    /// blocks may have from that index on, until the next entry. Blocks below
+    /// the first entry must have version 0, and no block may have a lower
+    /// version than its parent.
+    ///
+    /// A schedule takes the place of the `BLOCK_VERSION` bound, and must not
+    /// permit versions this node cannot process. An empty schedule restores
+    /// the `BLOCK_VERSION` bound.
+    pub fn set_block_version_schedule(&mut self, schedule: BTreeMap<u64, u32>) {
+        self.block_version_schedule = schedule;
+    }
+
+    /// Get the highest block version the schedule permits at a given block
+    /// index, or None if no schedule is set.
+    fn max_scheduled_block_version(&self, block_index: u64) -> Option<u32> {
+        if self.block_version_schedule.is_empty() {
+            return None;
+        }
+        Some(
+            self.block_version_schedule
+                .range(..=block_index)
+                .next_back()
+                .map_or(0, |(_activation_index, version)| *version),
+        )
+    }
+
+    /// Get the number of TxOuts that were pruned.
+    pub fn num_pruned_tx_outs(&self) -> Result<u64, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        self.get_num_pruned_tx_outs_impl(&db_transaction)
+    }
+
+    /// Get the fees collected in a given block.
+    /// Blocks without a fee output, including the origin block, which holds
+    /// no transactions, return zero. Other blocks appended while the fee view
+    /// private key was not set return `Error::FeesNotIndexed`.
+    pub fn fees_in_block(&self, block_index: u64) -> Result<u64, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        let num_blocks = key_bytes_to_u64(&db_transaction.get(self.counts, &NUM_BLOCKS_KEY)?);
+        if block_index >= num_blocks {
+            return Err(Error::NotFound);
+        }
+        if block_index == 0 {
+            return Ok(0);
+        }
+
+        match db_transaction.get(self.fees_by_block, &u64_to_key_bytes(block_index)) {
+            Ok(fee_bytes) => Ok(key_bytes_to_u64(fee_bytes)),
+            Err(lmdb::Error::NotFound) => Err(Error::FeesNotIndexed(block_index)),
+            Err(e) => Err(Error::from(e)),
+        }
+    }
+
+    /// Get the index of the block with the given id, if it is in the ledger.
+    /// The reverse lookup is `get_block`.
+    pub fn get_block_index_by_hash(&self, hash: &BlockID) -> Result<Option<u64>, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        match db_transaction.get(self.block_index_by_hash, &hash) {
+            Ok(index_bytes) => Ok(Some(key_bytes_to_u64(index_bytes))),
+            Err(lmdb::Error::NotFound) => Ok(None),
+            Err(e) => Err(Error::from(e)),
+        }
+    }
+
+    /// Check whether the origin block of the ledger has the given id, e.g. to
+    /// make sure the ledger belongs to the expected network. Returns
+    /// `Error::NotFound` if the ledger is empty.
+    pub fn verify_origin_block_hash(&self, expected: &BlockID) -> Result<bool, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        Ok(self.get_block_impl(&db_transaction, 0)?.id == *expected)
+    }
+
+    /// Get the outputs of the origin block, which hold the initial allocation
+    /// of the ledger. Returns `Error::NotFound` if the ledger is empty.
+    pub fn origin_block_outputs(&self) -> Result<Vec<TxOut>, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        Ok(self.get_block_contents_impl(&db_transaction, 0)?.outputs)
+    }
+
+    /// Get proofs of membership for the TxOuts with the given indexes, all
+    /// relative to the TxOut set as it was once block `root_block` had been
+    /// appended. The proofs are generated within a single database
+    /// transaction, so they share the same root even if blocks are appended
+    /// in the meantime.
+    pub fn get_tx_out_proofs_at_root(
+        &self,
+        indexes: &[u64],
+        root_block: u64,
+    ) -> Result<Vec<TxOutMembershipProof>, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        let block = self.get_block_impl(&db_transaction, root_block)?;
+        indexes
+            .iter()
+            .map(|index| {
+                self.tx_out_store.get_merkle_proof_of_membership_as_of(
+                    *index,
+                    block.cumulative_txo_count,
+                    &db_transaction,
+                )
+            })
+            .collect()
+    }
+
+    /// Get up to `limit` TxOuts by their global index, starting at
+    /// `start_index`, in index order. The TxOuts are read within a single
+    /// database transaction, and fewer are returned once the end of the TxOut
+    /// set is reached. Returns `Error::NotFound` if a TxOut in the range was
+    /// pruned.
+    pub fn get_tx_outs(&self, start_index: u64, limit: u64) -> Result<Vec<TxOut>, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        let num_tx_outs = self.tx_out_store.num_tx_outs(&db_transaction)?;
+        let end_index = start_index.saturating_add(limit).min(num_tx_outs);
+        (start_index..end_index)
+            .map(|index| {
+                self.tx_out_store
+                    .get_tx_out_by_index(index, &db_transaction)
+            })
+            .collect()
+    }
+
+    /// Get the total fees collected by all blocks in the ledger.
+    /// Returns `Error::FeesNotIndexed` with the index of the first such block
+    /// if any block after the origin block was appended while the fee view
+    /// private key was not set, since the total would otherwise silently
+    /// leave out its fees.
+    pub fn total_fees(&self) -> Result<u128, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        let num_blocks = key_bytes_to_u64(&db_transaction.get(self.counts, &NUM_BLOCKS_KEY)?);
+        if self.get_num_fee_indexed_blocks_impl(&db_transaction)? < num_blocks.saturating_sub(1) {
+            for block_index in 1..num_blocks {
+                match db_transaction.get(self.fees_by_block, &u64_to_key_bytes(block_index)) {
+                    Ok(_) => {}
+                    Err(lmdb::Error::NotFound) => return Err(Error::FeesNotIndexed(block_index)),
+                    Err(e) => return Err(Error::from(e)),
+                }
+            }
+        }
+        self.get_total_fees_impl(&db_transaction)
+    }
+
+    /// Check the integrity of every block stored in the ledger. Each block must
+    /// be readable, link to its parent, have a valid id and match its
+    /// contents. Returns `Error::CorruptedBlock` for the first block that
+    /// fails these checks, and `Error::MerkleRootMismatch` if the stored
+    /// Merkle root differs from one recomputed from the TxOuts.
+    pub fn verify_integrity(&self) -> Result<(), Error> {
+        self.verify_integrity_with_options(&IntegrityCheckOptions::default())
+    }
+
+    /// Check the integrity of every block stored in the ledger, with
+    /// additional checks configured by `options`.
+    pub fn verify_integrity_with_options(
+        &self,
+        options: &IntegrityCheckOptions,
+    ) -> Result<(), Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        let num_blocks = key_bytes_to_u64(&db_transaction.get(self.counts, &NUM_BLOCKS_KEY)?);
+
+        let mut parent_id = BlockID::default();
+        for block_index in 0..num_blocks {
+            let (block, block_contents) = match (
+                self.get_block_impl(&db_transaction, block_index),
+                self.get_block_contents_impl(&db_transaction, block_index),
+            ) {
+                (Ok(block), Ok(block_contents)) => (block, block_contents),
+                // Only the header of a block with pruned outputs can be checked.
+                (Ok(block), Err(Error::BlockContentsPruned(_))) => {
+                    if block.index != block_index
+                        || block.parent_id != parent_id
+                        || !block.is_block_id_valid()
+                    {
+                        return Err(Error::CorruptedBlock(block_index));
+                    }
+                    parent_id = block.id;
+                    continue;
+                }
+                (Err(Error::Deserialization), _) | (_, Err(Error::Deserialization)) => {
+                    return Err(Error::CorruptedBlock(block_index));
+                }
+                (Err(err), _) | (_, Err(err)) => return Err(err),
+            };
+
+            if block.index != block_index
+                || block.parent_id != parent_id
+                || !block.is_block_id_valid()
+                || block.contents_hash != block_contents.hash()
+            {
+                return Err(Error::CorruptedBlock(block_index));
+            }
+
+            if contains_zero_value_output(
+                &block_contents.outputs,
+                &options.zero_value_output_view_keys,
+            ) {
+                return Err(Error::ZeroValueOutput(block_index));
+            }
+
+            parent_id = block.id;
+        }
+
+        // The Merkle root can only be recomputed when every TxOut is available.
+        if self.get_num_pruned_tx_outs_impl(&db_transaction)? > 0 {
+            return Ok(());
+        }
+
+        let root_hash = self.tx_out_store.get_root_merkle_hash(&db_transaction)?;
+        let recomputed_root_hash = self
+            .tx_out_store
+            .compute_root_merkle_hash_from_scratch(&db_transaction)?;
+        if root_hash != recomputed_root_hash {
+            return Err(Error::MerkleRootMismatch);
+        }
+
+        Ok(())
+    }
+
     /// Force an update of the metric gauges. This is useful when the ledger db
     /// is being updated externally (for example by mobilecoind), but we
     /// still want to publish the correct metrics. Users can call this
@@ -449,6 +1007,13 @@ impl LedgerDB {
             WriteFlags::empty(),
         )?;
 
+        db_transaction.put(
+            self.block_index_by_hash,
+            &block.id,
+            &u64_to_key_bytes(block.index),
+            WriteFlags::NO_OVERWRITE,
+        )?;
+
         if let Some(signature) = signature {
             db_transaction.put(
                 self.block_signatures,
@@ -461,6 +1026,59 @@ impl LedgerDB {
         Ok(())
     }
 
+    /// Record the value of the block's fee output, or zero if the block has
+    /// none. Nothing is recorded for the origin block, or when the fee view
+    /// private key is not set.
+    fn write_fees(
+        &self,
+        block_index: u64,
+        tx_outs: &[TxOut],
+        db_transaction: &mut RwTransaction,
+    ) -> Result<(), Error> {
+        let fee_view_private_key = match &self.fee_view_private_key {
+            Some(key) if block_index > 0 => key,
+            _ => return Ok(()),
+        };
+
+        // The fee output is the only output whose amount decodes correctly with the
+        // fee recipient's view key.
+        let fee = tx_outs
+            .iter()
+            .filter_map(|tx_out| {
+                let public_key = RistrettoPublic::try_from(&tx_out.public_key).ok()?;
+                let shared_secret = create_shared_secret(&public_key, fee_view_private_key);
+                tx_out.amount.get_value(&shared_secret).ok()
+            })
+            .map(|(value, _blinding)| value)
+            .next()
+            .unwrap_or(0);
+
+        db_transaction.put(
+            self.fees_by_block,
+            &u64_to_key_bytes(block_index),
+            &u64_to_key_bytes(fee),
+            WriteFlags::NO_OVERWRITE,
+        )?;
+
+        let num_fee_indexed_blocks = self.get_num_fee_indexed_blocks_impl(db_transaction)? + 1;
+        db_transaction.put(
+            self.counts,
+            &NUM_FEE_INDEXED_BLOCKS_KEY,
+            &u64_to_key_bytes(num_fee_indexed_blocks),
+            WriteFlags::empty(),
+        )?;
+
+        let total_fees = self.get_total_fees_impl(db_transaction)? + fee as u128;
+        db_transaction.put(
+            self.counts,
+            &TOTAL_FEES_KEY,
+            &total_fees.to_be_bytes(),
+            WriteFlags::empty(),
+        )?;
+
+        Ok(())
+    }
+
     fn write_key_images(
         &self,
         block_index: u64,
@@ -521,7 +1139,7 @@ impl LedgerDB {
 
         for tx_out in tx_outs {
             if self.contains_tx_out_public_key(&tx_out.public_key)? {
-                return Err(Error::DuplicateOutputPublicKey);
+                return Err(Error::DuplicateOutputPublicKey(tx_out.public_key.clone()));
             }
 
             let tx_out_index = self.tx_out_store.push(tx_out, db_transaction)?;
@@ -538,39 +1156,115 @@ impl LedgerDB {
         Ok(())
     }
 
-    /// Checks if a block can be appended to the db.
-    fn validate_append_block(
+    /// Prune the TxOuts of blocks with an index lower than
+    /// `num_blocks_behind_horizon` whose recorded key image is spent.
+    fn prune_tx_outs(
         &self,
-        block: &Block,
-        block_contents: &BlockContents,
+        num_blocks_behind_horizon: u64,
+        db_transaction: &mut RwTransaction,
     ) -> Result<(), Error> {
-        // Check version is correct
-        // Check if block is being appended at the correct place.
-        let num_blocks = self.num_blocks()?;
-        if num_blocks == 0 {
-            // This must be an origin block.
+        if num_blocks_behind_horizon == 0 {
+            return Ok(());
+        }
 
-            // The origin block is version 0
-            if block.version != 0 {
-                return Err(Error::InvalidBlockVersion(block.version));
-            }
+        // TxOuts with a lower index than this belong to blocks behind the horizon.
+        let bytes = db_transaction.get(
+            self.tx_outs_by_block,
+            &u64_to_key_bytes(num_blocks_behind_horizon - 1),
+        )?;
+        let value: TxOutsByBlockValue = decode(&bytes)?;
+        let end_tx_out_index = value.first_tx_out_index + value.num_tx_outs;
 
-            // The origin block is index '0' with default-initialized parent ID, by
-            // convention
-            if block.index != 0 {
-                return Err(Error::InvalidBlockIndex(block.index));
-            }
-            if block.parent_id != BlockID::default() {
-                return Err(Error::InvalidParentBlockID(block.id.clone()));
+        // Keys are big-endian TxOut indices, so the cursor visits them in order.
+        let mut spent_tx_out_indices = Vec::new();
+        {
+            let mut cursor = db_transaction.open_ro_cursor(self.key_image_by_tx_out_index)?;
+            for result in cursor.iter_start() {
+                let (key_bytes, key_image_bytes) = result?;
+                let tx_out_index = key_bytes_to_u64(key_bytes);
+                if tx_out_index >= end_tx_out_index {
+                    break;
+                }
+                match db_transaction.get(self.key_images, &key_image_bytes) {
+                    Ok(_) => spent_tx_out_indices.push(tx_out_index),
+                    Err(lmdb::Error::NotFound) => {}
+                    Err(e) => return Err(Error::from(e)),
+                }
             }
-        } else {
-            let last_block = self.get_block(num_blocks - 1)?;
+        }
+        if spent_tx_out_indices.is_empty() {
+            return Ok(());
+        }
+
+        for tx_out_index in spent_tx_out_indices.iter() {
+            self.tx_out_store.prune(*tx_out_index, db_transaction)?;
+            db_transaction.del(
+                self.key_image_by_tx_out_index,
+                &u64_to_key_bytes(*tx_out_index),
+                None,
+            )?;
+        }
+
+        let num_pruned_tx_outs =
+            self.get_num_pruned_tx_outs_impl(db_transaction)? + spent_tx_out_indices.len() as u64;
+        db_transaction.put(
+            self.counts,
+            &NUM_PRUNED_TX_OUTS_KEY,
+            &u64_to_key_bytes(num_pruned_tx_outs),
+            WriteFlags::empty(),
+        )?;
+
+        Ok(())
+    }
+
+    /// Checks if a block can be appended to the db, without writing anything.
+    /// This performs every check `append_block` does before writing a block.
+    ///
+    /// # Arguments
+    /// * `block` - A candidate block.
+    /// * `block_contents` - The contents of the candidate block.
+    pub fn validate_block(
+        &self,
+        block: &Block,
+        block_contents: &BlockContents,
+    ) -> Result<(), Error> {
+        // Check version is correct
+        // Check if block is being appended at the correct place.
+        let num_blocks = self.num_blocks()?;
+        if num_blocks == 0 {
+            // This must be an origin block.
+
+            // The origin block is version 0
+            if block.version != 0 {
+                return Err(Error::InvalidBlockVersion(block.version));
+            }
+
+            // The origin block is index '0' with default-initialized parent ID, by
+            // convention
+            if block.index != 0 {
+                return Err(Error::InvalidBlockIndex(block.index));
+            }
+            if block.parent_id != BlockID::default() {
+                return Err(Error::InvalidParentBlockID(block.id.clone()));
+            }
+        } else {
+            let last_block = self.get_block(num_blocks - 1)?;
 
             // The block's version should be bounded by
-            // [prev block version, max block version]
-            if block.version < last_block.version || block.version > BLOCK_VERSION {
+            // [prev block version, max block version]. The max block version is
+            // the one scheduled at the block's index, if a schedule is set.
+            if block.version < last_block.version {
                 return Err(Error::InvalidBlockVersion(block.version));
             }
+            match self.max_scheduled_block_version(block.index) {
+                Some(max_version) if block.version > max_version => {
+                    return Err(Error::BlockVersionNotActive(block.version, block.index));
+                }
+                None if block.version > BLOCK_VERSION => {
+                    return Err(Error::InvalidBlockVersion(block.version));
+                }
+                _ => {}
+            }
 
             // The block must have the correct index and parent.
             if block.index != num_blocks {
@@ -603,13 +1297,27 @@ impl LedgerDB {
             }
         }
 
-        // Check that none of the output public keys appear in the ledger.
+        // Check that no output public key appears twice in the block, or already
+        // appears in the ledger.
+        let mut output_public_keys = HashSet::new();
         for output in block_contents.outputs.iter() {
+            if !output_public_keys.insert(&output.public_key) {
+                return Err(Error::DuplicateOutputPublicKeyInBlock(
+                    output.public_key.clone(),
+                ));
+            }
             if self.contains_tx_out_public_key(&output.public_key)? {
-                return Err(Error::DuplicateOutputPublicKey);
+                return Err(Error::DuplicateOutputPublicKey(output.public_key.clone()));
             }
         }
 
+        // Outputs must be in canonical order, so that every node forming a block from
+        // the same transactions agrees on its contents. The origin block is minted
+        // rather than formed by consensus, and is exempt.
+        if block.index != 0 && !block_contents.has_sorted_outputs() {
+            return Err(Error::UnsortedOutputs);
+        }
+
         // Validate block id.
         if !block.is_block_id_valid() {
             return Err(Error::InvalidBlockID(block.id.clone()));
@@ -621,11 +1329,7 @@ impl LedgerDB {
 
     /// Get the database file size, in bytes.
     fn db_file_size(&self) -> std::io::Result<u64> {
-        let mut filename = self.path.clone();
-        filename.push("data.mdb");
-
-        let metadata = fs::metadata(filename)?;
-        Ok(metadata.len())
+        data_file_size(&self.path)
     }
 
     /// Implementatation of the `get_block` method that operates inside a given
@@ -654,8 +1358,13 @@ impl LedgerDB {
 
         let outputs = (value.first_tx_out_index..(value.first_tx_out_index + value.num_tx_outs))
             .map(|tx_out_index| {
-                self.tx_out_store
+                match self
+                    .tx_out_store
                     .get_tx_out_by_index(tx_out_index, db_transaction)
+                {
+                    Err(Error::NotFound) => Err(Error::BlockContentsPruned(block_number)),
+                    result => result,
+                }
             })
             .collect::<Result<Vec<TxOut>, Error>>()?;
 
@@ -670,6 +1379,43 @@ impl LedgerDB {
         })
     }
 
+    /// Implementation of the `total_fees` method that operates inside a given
+    /// transaction.
+    fn get_total_fees_impl(&self, db_transaction: &impl Transaction) -> Result<u128, Error> {
+        match db_transaction.get(self.counts, &TOTAL_FEES_KEY) {
+            Ok(total_fees_bytes) => Ok(u128::from_be_bytes(
+                total_fees_bytes
+                    .try_into()
+                    .map_err(|_| Error::Deserialization)?,
+            )),
+            Err(lmdb::Error::NotFound) => Ok(0),
+            Err(e) => Err(Error::from(e)),
+        }
+    }
+
+    /// Get the number of blocks after the origin block with an entry in
+    /// `fees_by_block`, inside a given transaction.
+    fn get_num_fee_indexed_blocks_impl(
+        &self,
+        db_transaction: &impl Transaction,
+    ) -> Result<u64, Error> {
+        match db_transaction.get(self.counts, &NUM_FEE_INDEXED_BLOCKS_KEY) {
+            Ok(num_fee_indexed_blocks_bytes) => Ok(key_bytes_to_u64(num_fee_indexed_blocks_bytes)),
+            Err(lmdb::Error::NotFound) => Ok(0),
+            Err(e) => Err(Error::from(e)),
+        }
+    }
+
+    /// Implementation of the `num_pruned_tx_outs` method that operates inside a
+    /// given transaction.
+    fn get_num_pruned_tx_outs_impl(&self, db_transaction: &impl Transaction) -> Result<u64, Error> {
+        match db_transaction.get(self.counts, &NUM_PRUNED_TX_OUTS_KEY) {
+            Ok(num_pruned_tx_outs_bytes) => Ok(key_bytes_to_u64(num_pruned_tx_outs_bytes)),
+            Err(lmdb::Error::NotFound) => Ok(0),
+            Err(e) => Err(Error::from(e)),
+        }
+    }
+
     /// Implementation of the `get_block_signature` method that operates inside
     /// a given transaction.
     fn get_block_signature_impl(
@@ -684,6 +1430,75 @@ impl LedgerDB {
     }
 }
 
+/// Get the size, in bytes, of the LMDB data file of the database in `path`.
+fn data_file_size(path: &Path) -> std::io::Result<u64> {
+    Ok(fs::metadata(path.join("data.mdb"))?.len())
+}
+
+/// Open a database, creating it if it does not exist yet. This is used for
+/// indices that were added without a ledger db version bump, and are populated
+/// as new blocks get appended.
+fn open_or_create_db(env: &Environment, name: &str) -> Result<Database, Error> {
+    match env.open_db(Some(name)) {
+        Ok(db) => Ok(db),
+        Err(lmdb::Error::NotFound) => Ok(env.create_db(Some(name), DatabaseFlags::empty())?),
+        Err(e) => Err(Error::from(e)),
+    }
+}
+
+/// Check whether any of the outputs has a value of zero, for the outputs whose
+/// amount can be decoded by one of the given view private keys.
+fn contains_zero_value_output(tx_outs: &[TxOut], view_private_keys: &[RistrettoPrivate]) -> bool {
+    tx_outs.iter().any(|tx_out| {
+        let public_key = match RistrettoPublic::try_from(&tx_out.public_key) {
+            Ok(public_key) => public_key,
+            Err(_) => return false,
+        };
+        view_private_keys.iter().any(|view_private_key| {
+            let shared_secret = create_shared_secret(&public_key, view_private_key);
+            matches!(tx_out.amount.get_value(&shared_secret), Ok((0, _)))
+        })
+    })
+}
+
+/// Open the block id -> block number index. Ledgers created before this index
+/// existed get it built once, from the blocks already stored.
+fn open_or_build_block_index_by_hash(
+    env: &Environment,
+    blocks: Database,
+) -> Result<Database, Error> {
+    match env.open_db(Some(BLOCK_INDEX_BY_HASH_DB_NAME)) {
+        Ok(db) => return Ok(db),
+        Err(lmdb::Error::NotFound) => {}
+        Err(e) => return Err(Error::from(e)),
+    };
+
+    global_log::info!("Building ledger db block index by hash");
+
+    let block_index_by_hash =
+        env.create_db(Some(BLOCK_INDEX_BY_HASH_DB_NAME), DatabaseFlags::empty())?;
+
+    let mut db_transaction = env.begin_rw_txn()?;
+    let existing_blocks = {
+        let mut cursor = db_transaction.open_ro_cursor(blocks)?;
+        cursor
+            .iter()
+            .map(|result| Ok(decode::<Block>(result?.1)?))
+            .collect::<Result<Vec<Block>, Error>>()?
+    };
+    for block in existing_blocks.iter() {
+        db_transaction.put(
+            block_index_by_hash,
+            &block.id,
+            &u64_to_key_bytes(block.index),
+            WriteFlags::NO_OVERWRITE,
+        )?;
+    }
+    db_transaction.commit()?;
+
+    Ok(block_index_by_hash)
+}
+
 // Specifies how we encode the u64 chunk number in lmdb
 // The lexicographical sorting of the numbers, done by lmdb, must match the
 // numeric order of the chunks. Thus we use Big Endian byte order here
@@ -702,7 +1517,10 @@ mod ledger_db_test {
     use core::convert::TryFrom;
     use mc_account_keys::AccountKey;
     use mc_crypto_keys::RistrettoPrivate;
-    use mc_transaction_core::compute_block_id;
+    use mc_transaction_core::{
+        compute_block_id,
+        membership_proofs::{compute_implied_merkle_root, hash_leaf},
+    };
     use mc_util_from_random::FromRandom;
     use rand::{rngs::StdRng, SeedableRng};
     use rand_core::RngCore;
@@ -758,10 +1576,11 @@ mod ledger_db_test {
             } else {
                 vec![]
             };
-            let block_contents = BlockContents::new(key_images, outputs.clone());
+            let mut block_contents = BlockContents::new(key_images, outputs);
+            block_contents.sort_outputs();
 
             let block = match parent_block {
-                None => Block::new_origin_block(&outputs),
+                None => Block::new_origin_block(&block_contents.outputs),
                 Some(parent) => Block::new_with_parent(
                     BLOCK_VERSION,
                     &parent,
@@ -863,7 +1682,8 @@ mod ledger_db_test {
 
         let key_images: Vec<KeyImage> = (0..5).map(|_i| KeyImage::from(rng.next_u64())).collect();
 
-        let block_contents = BlockContents::new(key_images.clone(), outputs);
+        let mut block_contents = BlockContents::new(key_images.clone(), outputs);
+        block_contents.sort_outputs();
         let block = Block::new_with_parent(
             BLOCK_VERSION,
             &origin_block,
@@ -910,115 +1730,668 @@ mod ledger_db_test {
     }
 
     #[test]
-    #[should_panic(expected = "called `Result::unwrap()` on an `Err` value: NoKeyImages")]
-    // Appending a non-origin block should fail if the block contains no key images.
-    fn test_append_block_fails_for_non_origin_blocks_without_key_images() {
+    // Fees should be tracked per block and cumulatively, based on each block's
+    // fee output.
+    fn test_fees_in_block() {
         let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
         let mut ledger_db = create_db();
 
-        // === Create and append the origin block. ===
-        // The origin block contains a single output belonging to the
-        // `origin_account_key`.
+        let fee_account_key = AccountKey::random(&mut rng);
+        ledger_db.set_fee_view_private_key(fee_account_key.view_private_key().clone());
 
         let origin_account_key = AccountKey::random(&mut rng);
         let (origin_block, origin_block_contents) =
             get_origin_block_and_contents(&origin_account_key);
+        ledger_db
+            .append_block(&origin_block, &origin_block_contents, None)
+            .unwrap();
+
+        // The origin block has no fee output.
+        assert_eq!(ledger_db.fees_in_block(0).unwrap(), 0);
+        assert_eq!(ledger_db.total_fees().unwrap(), 0);
+
+        // A fee of zero means the block has no fee output.
+        let fees: Vec<u64> = vec![10, 0, 250];
+
+        let mut parent_block = origin_block;
+        for (i, fee) in fees.iter().enumerate() {
+            let mut outputs = vec![TxOut::new(
+                1000,
+                &origin_account_key.default_subaddress(),
+                &RistrettoPrivate::from_random(&mut rng),
+                Default::default(),
+            )
+            .unwrap()];
+            if *fee > 0 {
+                outputs.push(
+                    TxOut::new(
+                        *fee,
+                        &fee_account_key.default_subaddress(),
+                        &RistrettoPrivate::from_random(&mut rng),
+                        Default::default(),
+                    )
+                    .unwrap(),
+                );
+            }
+
+            let key_images = vec![KeyImage::from(i as u64 + 1)];
+            let mut block_contents = BlockContents::new(key_images, outputs);
+            block_contents.sort_outputs();
+            let block = Block::new_with_parent(
+                BLOCK_VERSION,
+                &parent_block,
+                &Default::default(),
+                &block_contents,
+            );
+            ledger_db
+                .append_block(&block, &block_contents, None)
+                .unwrap();
+            parent_block = block;
+        }
+
+        assert_eq!(ledger_db.fees_in_block(1).unwrap(), 10);
+        assert_eq!(ledger_db.fees_in_block(2).unwrap(), 0);
+        assert_eq!(ledger_db.fees_in_block(3).unwrap(), 250);
+        assert_eq!(ledger_db.total_fees().unwrap(), 260);
+
+        // Blocks that are not in the ledger should not be found.
+        assert_eq!(ledger_db.fees_in_block(4), Err(Error::NotFound));
+    }
+
+    #[test]
+    // Fees of blocks appended without the fee view private key are unknown, and
+    // should not be reported as zero or left out of the total.
+    fn test_fees_not_indexed() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+        let fee_account_key = AccountKey::random(&mut rng);
 
+        let origin_account_key = AccountKey::random(&mut rng);
+        let (origin_block, origin_block_contents) =
+            get_origin_block_and_contents(&origin_account_key);
         ledger_db
             .append_block(&origin_block, &origin_block_contents, None)
             .unwrap();
 
-        // === Attempt to append a block without key images ===
-        let recipient_account_key = AccountKey::random(&mut rng);
-        let outputs: Vec<TxOut> = (0..4)
-            .map(|_i| {
-                TxOut::new(
-                    1000,
-                    &recipient_account_key.default_subaddress(),
+        // The origin block holds no transactions, so it never collects fees.
+        assert_eq!(ledger_db.fees_in_block(0).unwrap(), 0);
+        assert_eq!(ledger_db.total_fees().unwrap(), 0);
+
+        let mut parent_block = origin_block;
+        for i in 1..3u64 {
+            // Setting the key only indexes the fees of blocks appended afterwards.
+            if i == 2 {
+                ledger_db.set_fee_view_private_key(fee_account_key.view_private_key().clone());
+            }
+
+            let mut block_contents = BlockContents::new(
+                vec![KeyImage::from(i)],
+                vec![TxOut::new(
+                    10,
+                    &fee_account_key.default_subaddress(),
                     &RistrettoPrivate::from_random(&mut rng),
                     Default::default(),
                 )
-                .unwrap()
-            })
-            .collect();
+                .unwrap()],
+            );
+            block_contents.sort_outputs();
+            let block = Block::new_with_parent(
+                BLOCK_VERSION,
+                &parent_block,
+                &Default::default(),
+                &block_contents,
+            );
+            ledger_db
+                .append_block(&block, &block_contents, None)
+                .unwrap();
+            parent_block = block;
+        }
 
-        let key_images = Vec::new();
+        assert_eq!(ledger_db.fees_in_block(1), Err(Error::FeesNotIndexed(1)));
+        assert_eq!(ledger_db.fees_in_block(2).unwrap(), 10);
+        assert_eq!(ledger_db.total_fees(), Err(Error::FeesNotIndexed(1)));
+    }
 
-        let block_contents = BlockContents::new(key_images.clone(), outputs);
+    #[test]
+    // A corrupted block should be reported by the integrity check.
+    fn test_verify_integrity_detects_corrupted_block() {
+        let temp_dir = TempDir::new("test").unwrap();
+        let path = temp_dir.path();
+        LedgerDB::create(path).unwrap();
+
+        {
+            let mut ledger_db = LedgerDB::open(path).unwrap();
+            populate_db(&mut ledger_db, 5, 2);
+            assert_eq!(ledger_db.verify_integrity(), Ok(()));
+        }
+
+        crate::test_utils::corrupt_ledger_block(path, 3);
+
+        let ledger_db = LedgerDB::open(path).unwrap();
+        assert_eq!(ledger_db.verify_integrity(), Err(Error::CorruptedBlock(3)));
+    }
+
+    #[test]
+    // Zero-value outputs should be flagged by the integrity check when their
+    // recipient's view key is provided.
+    fn test_verify_integrity_flags_zero_value_outputs() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+
+        let origin_account_key = AccountKey::random(&mut rng);
+        let (origin_block, origin_block_contents) =
+            get_origin_block_and_contents(&origin_account_key);
+        ledger_db
+            .append_block(&origin_block, &origin_block_contents, None)
+            .unwrap();
+
+        let recipient_account_key = AccountKey::random(&mut rng);
+        let outputs = vec![
+            TxOut::new(
+                0,
+                &recipient_account_key.default_subaddress(),
+                &RistrettoPrivate::from_random(&mut rng),
+                Default::default(),
+            )
+            .unwrap(),
+            TxOut::new(
+                10,
+                &recipient_account_key.default_subaddress(),
+                &RistrettoPrivate::from_random(&mut rng),
+                Default::default(),
+            )
+            .unwrap(),
+        ];
+        let mut block_contents = BlockContents::new(vec![KeyImage::from(1)], outputs);
+        block_contents.sort_outputs();
         let block = Block::new_with_parent(
             BLOCK_VERSION,
             &origin_block,
             &Default::default(),
             &block_contents,
         );
-
-        // This is expected to fail.
         ledger_db
             .append_block(&block, &block_contents, None)
             .unwrap();
-    }
 
-    #[test]
-    #[ignore]
-    // A block that attempts a double spend should be rejected.
-    fn test_reject_double_spend() {
-        unimplemented!();
+        // Amounts are hidden without the recipient's view key.
+        assert_eq!(ledger_db.verify_integrity(), Ok(()));
+
+        let options = IntegrityCheckOptions {
+            zero_value_output_view_keys: vec![*origin_account_key.view_private_key()],
+        };
+        assert_eq!(ledger_db.verify_integrity_with_options(&options), Ok(()));
+
+        let options = IntegrityCheckOptions {
+            zero_value_output_view_keys: vec![*recipient_account_key.view_private_key()],
+        };
+        assert_eq!(
+            ledger_db.verify_integrity_with_options(&options),
+            Err(Error::ZeroValueOutput(1))
+        );
     }
 
     #[test]
-    // `num_blocks` should return the correct number of blocks.
-    fn test_num_blocks() {
-        let mut ledger_db = create_db();
-        assert_eq!(ledger_db.num_blocks().unwrap(), 0);
-        let n_blocks: u64 = 7;
-        populate_db(&mut ledger_db, n_blocks, 1);
-        assert_eq!(ledger_db.num_blocks().unwrap(), n_blocks);
+    // Reopening a ledger whose file nearly fills the requested LMDB map should
+    // open a larger map, so that appending can continue.
+    fn test_open_grows_map_size() {
+        let temp_dir = TempDir::new("test").unwrap();
+        let path = temp_dir.path();
+        let mut ledger_db = crate::test_utils::create_ledger_with_map_size(path, 1 << 24);
+        let (blocks, _) = populate_db(&mut ledger_db, 100, 10);
+        drop(ledger_db);
+
+        let small_map_size = 1 << 18; // 256 KB
+        assert!(data_file_size(path).unwrap() as usize >= small_map_size);
+        let mut ledger_db = LedgerDB::open_with_map_size(path, small_map_size).unwrap();
+        assert!(ledger_db.map_size() > small_map_size);
+        assert!(
+            data_file_size(path).unwrap() as usize
+                < ledger_db.map_size() / 100 * MAP_SIZE_GROWTH_THRESHOLD_PERCENT
+        );
+
+        // Appending keeps working on the reopened ledger.
+        let mut rng: StdRng = SeedableRng::from_seed([2u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+        let outputs = vec![TxOut::new(
+            1000,
+            &account_key.default_subaddress(),
+            &RistrettoPrivate::from_random(&mut rng),
+            Default::default(),
+        )
+        .unwrap()];
+        let block_contents = BlockContents::new(vec![KeyImage::from(rng.next_u64())], outputs);
+        let block = Block::new_with_parent(
+            BLOCK_VERSION,
+            &blocks[blocks.len() - 1],
+            &Default::default(),
+            &block_contents,
+        );
+        ledger_db
+            .append_block(&block, &block_contents, None)
+            .unwrap();
+
+        assert_eq!(ledger_db.num_blocks().unwrap(), blocks.len() as u64 + 1);
+        assert_eq!(ledger_db.verify_integrity(), Ok(()));
+        assert_eq!(ledger_db.get_block(blocks.len() as u64).unwrap(), block);
     }
 
     #[test]
-    // Getting a block by index should return the correct block, if it exists.
-    fn test_get_block_by_index() {
+    // Subscribers should be notified of the new number of blocks once an appended
+    // block is readable.
+    fn test_subscribe_to_tip() {
         let mut ledger_db = create_db();
-        let n_blocks = 43;
-        let (expected_blocks, _) = populate_db(&mut ledger_db, n_blocks, 1);
+        let receiver = ledger_db.subscribe_to_tip();
+
+        // A subscriber on another thread, holding a clone of the ledger.
+        let subscriber = {
+            let ledger_db = ledger_db.clone();
+            let receiver = ledger_db.subscribe_to_tip();
+            std::thread::spawn(move || {
+                let num_blocks = receiver.recv().unwrap();
+                // The notification arrives after the write transaction committed.
+                assert!(ledger_db.num_blocks().unwrap() >= num_blocks);
+                ledger_db.get_block(num_blocks - 1).unwrap();
+                num_blocks
+            })
+        };
 
-        for block_index in 0..n_blocks {
-            let block = ledger_db
-                .get_block(block_index as u64)
-                .unwrap_or_else(|_| panic!("Could not get block {:?}", block_index));
+        let (blocks, blocks_contents) = populate_db(&mut ledger_db, 3, 1);
+        assert_eq!(subscriber.join().unwrap(), 1);
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<u64>>(),
+            (1..=blocks.len() as u64).collect::<Vec<u64>>()
+        );
 
-            let expected_block: Block = expected_blocks.get(block_index as usize).unwrap().clone();
-            assert_eq!(block, expected_block);
-        }
+        // A failed append does not notify.
+        assert!(ledger_db
+            .append_block(&blocks[0], &blocks_contents[0], None)
+            .is_err());
+        assert!(receiver.try_recv().is_err());
     }
 
     #[test]
-    // Getting block contents by index should return the correct block contents, if
-    // that exists.
-    fn test_get_block_contents_by_index() {
+    // Proofs at a given block should all verify against the root of the TxOuts
+    // as of that block, even once later blocks have been appended.
+    fn test_get_tx_out_proofs_at_root() {
         let mut ledger_db = create_db();
-        let n_blocks = 43;
-        let (_, expected_block_contents) = populate_db(&mut ledger_db, n_blocks, 1);
+        let (_blocks, blocks_contents) = populate_db(&mut ledger_db, 4, 3);
 
-        for block_index in 0..n_blocks {
-            let block_contents = ledger_db
-                .get_block_contents(block_index as u64)
-                .unwrap_or_else(|_| panic!("Could not get block contents {:?}", block_index));
+        // The TxOut set as of block 1 contains the first 6 TxOuts.
+        let leaf_hashes: Vec<[u8; 32]> = blocks_contents[..2]
+            .iter()
+            .flat_map(|contents| contents.outputs.iter().map(hash_leaf))
+            .collect();
+        assert_eq!(leaf_hashes.len(), 6);
+        let expected_root = tx_out_store::compute_merkle_root(&leaf_hashes).unwrap();
+
+        let proofs = ledger_db.get_tx_out_proofs_at_root(&[0, 3, 5], 1).unwrap();
+        assert_eq!(proofs.len(), 3);
+        for (proof, index) in proofs.iter().zip([0u64, 3, 5].iter()) {
+            assert_eq!(proof.index, *index);
+            assert_eq!(proof.highest_index, 5);
+            let implied_root = compute_implied_merkle_root(proof).unwrap();
+            assert_eq!(implied_root.hash.0, expected_root);
+        }
 
-            let expected_block_contents = expected_block_contents
-                .get(block_index as usize)
+        // TxOuts appended after the root block cannot be proven against it.
+        assert_eq!(
+            ledger_db.get_tx_out_proofs_at_root(&[0, 6], 1),
+            Err(Error::IndexOutOfBounds(6))
+        );
+
+        // Proofs at the last block match the proofs against the current root.
+        assert_eq!(
+            ledger_db.get_tx_out_proofs_at_root(&[2, 7, 11], 3).unwrap(),
+            ledger_db
+                .get_tx_out_proof_of_memberships(&[2, 7, 11])
                 .unwrap()
-                .clone();
-            assert_eq!(block_contents, expected_block_contents);
-        }
+        );
+
+        // The root block must be in the ledger.
+        assert_eq!(
+            ledger_db.get_tx_out_proofs_at_root(&[0], 4),
+            Err(Error::NotFound)
+        );
     }
 
     #[test]
-    // Getting a block by its index should return an error if the block doesn't
-    // exist.
-    fn test_get_block_by_index_doesnt_exist() {
+    // TxOuts should be read back in order of their global index.
+    fn test_get_tx_outs() {
         let mut ledger_db = create_db();
-        let n_blocks = 43;
+        let (_blocks, blocks_contents) = populate_db(&mut ledger_db, 4, 3);
+        let tx_outs: Vec<TxOut> = blocks_contents
+            .iter()
+            .flat_map(|contents| contents.outputs.clone())
+            .collect();
+        assert_eq!(tx_outs.len(), 12);
+
+        // A range spanning several blocks.
+        assert_eq!(ledger_db.get_tx_outs(2, 5).unwrap(), tx_outs[2..7].to_vec());
+
+        // The whole set.
+        assert_eq!(ledger_db.get_tx_outs(0, 12).unwrap(), tx_outs);
+
+        // Reading stops at the end of the set.
+        assert_eq!(
+            ledger_db.get_tx_outs(10, 5).unwrap(),
+            tx_outs[10..].to_vec()
+        );
+        assert_eq!(ledger_db.get_tx_outs(12, 5).unwrap(), vec![]);
+        assert_eq!(ledger_db.get_tx_outs(0, 0).unwrap(), vec![]);
+    }
+
+    #[test]
+    // Compacting should shrink the database file and keep its data intact.
+    fn test_compact() {
+        let temp_dir = TempDir::new("test").unwrap();
+        let path = temp_dir.path();
+        LedgerDB::create(path).unwrap();
+
+        // Pruning deletes the outputs of all but the last 2 blocks. A key image
+        // spent in block 1 stands in for the key images of the pruned outputs.
+        let mut ledger_db = LedgerDB::open(path).unwrap();
+        ledger_db.set_prune_depth(2);
+        let (blocks, blocks_contents) = populate_db(&mut ledger_db, 20, 50);
+        for tx_out_index in 0..900 {
+            ledger_db
+                .set_tx_out_key_image(tx_out_index, &KeyImage::from(1))
+                .unwrap();
+        }
+        drop(ledger_db);
+
+        let size = fs::metadata(path.join("data.mdb")).unwrap().len();
+        let (size_before, size_after) = LedgerDB::compact(path).unwrap();
+        assert_eq!(size_before, size);
+        assert!(size_after < size_before);
+        assert_eq!(
+            fs::metadata(path.join("data.mdb")).unwrap().len(),
+            size_after
+        );
+
+        let ledger_db = LedgerDB::open(path).unwrap();
+        assert_eq!(ledger_db.num_blocks().unwrap(), 20);
+        assert_eq!(ledger_db.num_txos().unwrap(), 1000);
+        assert_eq!(ledger_db.num_pruned_tx_outs().unwrap(), 900);
+        for block in blocks.iter() {
+            assert_eq!(ledger_db.get_block(block.index).unwrap(), *block);
+        }
+        for block_index in 18..20 {
+            assert_eq!(
+                ledger_db.get_block_contents(block_index),
+                Ok(blocks_contents[block_index as usize].clone())
+            );
+        }
+        assert_eq!(ledger_db.get_tx_out_by_index(0), Err(Error::NotFound));
+    }
+
+    #[test]
+    // Cached blocks should match the blocks in the database.
+    fn test_block_cache() {
+        let mut ledger_db = create_db();
+        ledger_db.set_block_cache_capacity(2);
+        let (blocks, _) = populate_db(&mut ledger_db, 5, 2);
+
+        // Reads are served from the database and then from the cache, including
+        // once older blocks have been evicted.
+        for _ in 0..2 {
+            for block in blocks.iter() {
+                assert_eq!(ledger_db.get_block(block.index).unwrap(), *block);
+            }
+        }
+        for block in blocks.iter().rev() {
+            assert_eq!(ledger_db.get_block(block.index).unwrap(), *block);
+        }
+
+        // The cache is shared between clones.
+        let cloned_ledger_db = ledger_db.clone();
+        assert_eq!(cloned_ledger_db.get_block(0).unwrap(), blocks[0]);
+
+        // A block that is not in the ledger yet is not cached as missing.
+        assert_eq!(ledger_db.get_block(5), Err(Error::NotFound));
+
+        let mut rng: StdRng = SeedableRng::from_seed([2u8; 32]);
+        let recipient = AccountKey::random(&mut rng).default_subaddress();
+        let outputs: Vec<TxOut> = (0..2)
+            .map(|_i| {
+                TxOut::new(
+                    1000,
+                    &recipient,
+                    &RistrettoPrivate::from_random(&mut rng),
+                    Default::default(),
+                )
+                .unwrap()
+            })
+            .collect();
+        let mut block_contents = BlockContents::new(vec![KeyImage::from(rng.next_u64())], outputs);
+        block_contents.sort_outputs();
+        let block = Block::new_with_parent(
+            BLOCK_VERSION,
+            &blocks[4],
+            &Default::default(),
+            &block_contents,
+        );
+        ledger_db
+            .append_block(&block, &block_contents, None)
+            .unwrap();
+        assert_eq!(ledger_db.get_block(5).unwrap(), block);
+
+        // Disabling the cache still reads from the database.
+        ledger_db.set_block_cache_capacity(0);
+        assert_eq!(ledger_db.get_block(5).unwrap(), block);
+    }
+
+    #[test]
+    // The origin block id should only match the id of the first block.
+    fn test_verify_origin_block_hash() {
+        let mut ledger_db = create_db();
+        assert_eq!(
+            ledger_db.verify_origin_block_hash(&BlockID::default()),
+            Err(Error::NotFound)
+        );
+
+        let (blocks, _) = populate_db(&mut ledger_db, 3, 2);
+        assert_eq!(ledger_db.verify_origin_block_hash(&blocks[0].id), Ok(true));
+        assert_eq!(ledger_db.verify_origin_block_hash(&blocks[1].id), Ok(false));
+        assert_eq!(
+            ledger_db.verify_origin_block_hash(&BlockID([7u8; 32])),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    // Appended blocks should be found by their id.
+    fn test_get_block_index_by_hash() {
+        let temp_dir = TempDir::new("test").unwrap();
+        let path = temp_dir.path();
+        LedgerDB::create(path).unwrap();
+
+        let mut ledger_db = LedgerDB::open(path).unwrap();
+        let (blocks, _) = populate_db(&mut ledger_db, 5, 2);
+
+        for block in blocks.iter() {
+            assert_eq!(
+                ledger_db.get_block_index_by_hash(&block.id),
+                Ok(Some(block.index))
+            );
+            assert_eq!(ledger_db.get_block(block.index).unwrap(), *block);
+        }
+        assert_eq!(
+            ledger_db.get_block_index_by_hash(&BlockID([7u8; 32])),
+            Ok(None)
+        );
+
+        // A ledger without the index should have it built when opened.
+        {
+            let mut db_transaction = ledger_db.env.begin_rw_txn().unwrap();
+            unsafe {
+                db_transaction
+                    .drop_db(ledger_db.block_index_by_hash)
+                    .unwrap();
+            }
+            db_transaction.commit().unwrap();
+        }
+        drop(ledger_db);
+
+        let ledger_db = LedgerDB::open(path).unwrap();
+        for block in blocks.iter() {
+            assert_eq!(
+                ledger_db.get_block_index_by_hash(&block.id),
+                Ok(Some(block.index))
+            );
+        }
+    }
+
+    #[test]
+    // The origin block outputs should be readable back, along with their values.
+    fn test_origin_block_outputs() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+        assert_eq!(ledger_db.origin_block_outputs(), Err(Error::NotFound));
+
+        let allocations: Vec<(AccountKey, u64)> = [1000, 2000, 3000]
+            .iter()
+            .map(|value| (AccountKey::random(&mut rng), *value))
+            .collect();
+        let outputs: Vec<TxOut> = allocations
+            .iter()
+            .map(|(account_key, value)| {
+                TxOut::new(
+                    *value,
+                    &account_key.default_subaddress(),
+                    &RistrettoPrivate::from_random(&mut rng),
+                    Default::default(),
+                )
+                .unwrap()
+            })
+            .collect();
+        let block = Block::new_origin_block(&outputs);
+        let block_contents = BlockContents::new(vec![], outputs.clone());
+        ledger_db
+            .append_block(&block, &block_contents, None)
+            .unwrap();
+
+        let origin_outputs = ledger_db.origin_block_outputs().unwrap();
+        assert_eq!(origin_outputs, outputs);
+
+        for (tx_out, (account_key, value)) in origin_outputs.iter().zip(allocations.iter()) {
+            let public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
+            let shared_secret = create_shared_secret(&public_key, account_key.view_private_key());
+            let (decoded_value, _blinding) = tx_out.amount.get_value(&shared_secret).unwrap();
+            assert_eq!(decoded_value, *value);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "called `Result::unwrap()` on an `Err` value: NoKeyImages")]
+    // Appending a non-origin block should fail if the block contains no key images.
+    fn test_append_block_fails_for_non_origin_blocks_without_key_images() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+
+        // === Create and append the origin block. ===
+        // The origin block contains a single output belonging to the
+        // `origin_account_key`.
+
+        let origin_account_key = AccountKey::random(&mut rng);
+        let (origin_block, origin_block_contents) =
+            get_origin_block_and_contents(&origin_account_key);
+
+        ledger_db
+            .append_block(&origin_block, &origin_block_contents, None)
+            .unwrap();
+
+        // === Attempt to append a block without key images ===
+        let recipient_account_key = AccountKey::random(&mut rng);
+        let outputs: Vec<TxOut> = (0..4)
+            .map(|_i| {
+                TxOut::new(
+                    1000,
+                    &recipient_account_key.default_subaddress(),
+                    &RistrettoPrivate::from_random(&mut rng),
+                    Default::default(),
+                )
+                .unwrap()
+            })
+            .collect();
+
+        let key_images = Vec::new();
+
+        let block_contents = BlockContents::new(key_images.clone(), outputs);
+        let block = Block::new_with_parent(
+            BLOCK_VERSION,
+            &origin_block,
+            &Default::default(),
+            &block_contents,
+        );
+
+        // This is expected to fail.
+        ledger_db
+            .append_block(&block, &block_contents, None)
+            .unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    // A block that attempts a double spend should be rejected.
+    fn test_reject_double_spend() {
+        unimplemented!();
+    }
+
+    #[test]
+    // `num_blocks` should return the correct number of blocks.
+    fn test_num_blocks() {
+        let mut ledger_db = create_db();
+        assert_eq!(ledger_db.num_blocks().unwrap(), 0);
+        let n_blocks: u64 = 7;
+        populate_db(&mut ledger_db, n_blocks, 1);
+        assert_eq!(ledger_db.num_blocks().unwrap(), n_blocks);
+    }
+
+    #[test]
+    // Getting a block by index should return the correct block, if it exists.
+    fn test_get_block_by_index() {
+        let mut ledger_db = create_db();
+        let n_blocks = 43;
+        let (expected_blocks, _) = populate_db(&mut ledger_db, n_blocks, 1);
+
+        for block_index in 0..n_blocks {
+            let block = ledger_db
+                .get_block(block_index as u64)
+                .unwrap_or_else(|_| panic!("Could not get block {:?}", block_index));
+
+            let expected_block: Block = expected_blocks.get(block_index as usize).unwrap().clone();
+            assert_eq!(block, expected_block);
+        }
+    }
+
+    #[test]
+    // Getting block contents by index should return the correct block contents, if
+    // that exists.
+    fn test_get_block_contents_by_index() {
+        let mut ledger_db = create_db();
+        let n_blocks = 43;
+        let (_, expected_block_contents) = populate_db(&mut ledger_db, n_blocks, 1);
+
+        for block_index in 0..n_blocks {
+            let block_contents = ledger_db
+                .get_block_contents(block_index as u64)
+                .unwrap_or_else(|_| panic!("Could not get block contents {:?}", block_index));
+
+            let expected_block_contents = expected_block_contents
+                .get(block_index as usize)
+                .unwrap()
+                .clone();
+            assert_eq!(block_contents, expected_block_contents);
+        }
+    }
+
+    #[test]
+    // Getting a block by its index should return an error if the block doesn't
+    // exist.
+    fn test_get_block_by_index_doesnt_exist() {
+        let mut ledger_db = create_db();
+        let n_blocks = 43;
         populate_db(&mut ledger_db, n_blocks, 1);
 
         let out_of_range = 999;
@@ -1265,7 +2638,8 @@ mod ledger_db_test {
                 let key_images: Vec<KeyImage> =
                     (0..5).map(|_i| KeyImage::from(rng.next_u64())).collect();
 
-                let block_contents = BlockContents::new(key_images.clone(), outputs);
+                let mut block_contents = BlockContents::new(key_images.clone(), outputs);
+                block_contents.sort_outputs();
                 last_block = Block::new_with_parent(
                     version,
                     &last_block,
@@ -1339,28 +2713,110 @@ mod ledger_db_test {
     }
 
     #[test]
-    fn test_append_block_at_wrong_location() {
+    /// With a block version schedule, a new block version is rejected before
+    /// its activation index and accepted from it on.
+    fn test_append_block_with_version_schedule() {
         let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
         let mut ledger_db = create_db();
-        let account_key = AccountKey::random(&mut rng);
+        ledger_db.set_block_version_schedule(vec![(0, 0), (5, 1)].into_iter().collect());
 
-        // initialize a ledger with 3 blocks.
-        let n_blocks = 3;
-        let (blocks, _) = populate_db(&mut ledger_db, n_blocks, 2);
-        assert_eq!(ledger_db.num_blocks().unwrap(), n_blocks);
+        let (origin_block, origin_block_contents) =
+            get_origin_block_and_contents(&AccountKey::random(&mut rng));
+        ledger_db
+            .append_block(&origin_block, &origin_block_contents, None)
+            .unwrap();
 
-        let key_images = vec![KeyImage::from(rng.next_u64())];
+        let mut new_block = |version: u32, parent: &Block| -> (Block, BlockContents) {
+            let recipient_account_key = AccountKey::random(&mut rng);
+            let outputs: Vec<TxOut> = (0..4)
+                .map(|_i| {
+                    TxOut::new(
+                        1000,
+                        &recipient_account_key.default_subaddress(),
+                        &RistrettoPrivate::from_random(&mut rng),
+                        Default::default(),
+                    )
+                    .unwrap()
+                })
+                .collect();
+            let key_images: Vec<KeyImage> =
+                (0..5).map(|_i| KeyImage::from(rng.next_u64())).collect();
+            let mut block_contents = BlockContents::new(key_images, outputs);
+            block_contents.sort_outputs();
+            let block =
+                Block::new_with_parent(version, parent, &Default::default(), &block_contents);
+            (block, block_contents)
+        };
 
-        let tx_out = TxOut::new(
-            100,
-            &account_key.default_subaddress(),
-            &RistrettoPrivate::from_random(&mut rng),
-            Default::default(),
-        )
-        .unwrap();
+        let mut last_block = origin_block;
+        for _ in 1..4 {
+            let (block, block_contents) = new_block(0, &last_block);
+            ledger_db
+                .append_block(&block, &block_contents, None)
+                .unwrap();
+            last_block = block;
+        }
 
-        let outputs = vec![tx_out];
-        let block_contents = BlockContents::new(key_images, outputs);
+        // Version 1 is not active at block 4.
+        let (block, block_contents) = new_block(1, &last_block);
+        assert_eq!(
+            ledger_db.append_block(&block, &block_contents, None),
+            Err(Error::BlockVersionNotActive(1, 4))
+        );
+        let (block, block_contents) = new_block(0, &last_block);
+        ledger_db
+            .append_block(&block, &block_contents, None)
+            .unwrap();
+        last_block = block;
+
+        // Version 1 activates at block 5.
+        let (block, block_contents) = new_block(1, &last_block);
+        ledger_db
+            .append_block(&block, &block_contents, None)
+            .unwrap();
+        last_block = block;
+
+        // Versions cannot go back, nor past the scheduled version.
+        let (block, block_contents) = new_block(0, &last_block);
+        assert_eq!(
+            ledger_db.append_block(&block, &block_contents, None),
+            Err(Error::InvalidBlockVersion(0))
+        );
+        let (block, block_contents) = new_block(2, &last_block);
+        assert_eq!(
+            ledger_db.append_block(&block, &block_contents, None),
+            Err(Error::BlockVersionNotActive(2, 6))
+        );
+        let (block, block_contents) = new_block(1, &last_block);
+        ledger_db
+            .append_block(&block, &block_contents, None)
+            .unwrap();
+        assert_eq!(ledger_db.num_blocks().unwrap(), 7);
+    }
+
+    #[test]
+    fn test_append_block_at_wrong_location() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+        let account_key = AccountKey::random(&mut rng);
+
+        // initialize a ledger with 3 blocks.
+        let n_blocks = 3;
+        let (blocks, _) = populate_db(&mut ledger_db, n_blocks, 2);
+        assert_eq!(ledger_db.num_blocks().unwrap(), n_blocks);
+
+        let key_images = vec![KeyImage::from(rng.next_u64())];
+
+        let tx_out = TxOut::new(
+            100,
+            &account_key.default_subaddress(),
+            &RistrettoPrivate::from_random(&mut rng),
+            Default::default(),
+        )
+        .unwrap();
+
+        let outputs = vec![tx_out];
+        let block_contents = BlockContents::new(key_images, outputs);
 
         // Appending a block to a previously written location should fail.
         let mut new_block = Block::new(
@@ -1385,6 +2841,195 @@ mod ledger_db_test {
         );
     }
 
+    #[test]
+    // A block can be built on an earlier block, for a competing chain.
+    fn test_build_block_on_parent() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+        let account_key = AccountKey::random(&mut rng);
+
+        let n_blocks = 4;
+        let (blocks, _) = populate_db(&mut ledger_db, n_blocks, 2);
+
+        let new_block_contents = |rng: &mut StdRng| {
+            let tx_out = TxOut::new(
+                100,
+                &account_key.default_subaddress(),
+                &RistrettoPrivate::from_random(rng),
+                Default::default(),
+            )
+            .unwrap();
+            BlockContents::new(vec![KeyImage::from(rng.next_u64())], vec![tx_out])
+        };
+
+        // A block forking off block 1.
+        let block_contents = new_block_contents(&mut rng);
+        let fork_block = crate::test_utils::build_block_on_parent(&ledger_db, 1, &block_contents);
+        assert_eq!(fork_block.parent_id, blocks[1].id);
+        assert_eq!(fork_block.index, 2);
+        assert_eq!(
+            fork_block.cumulative_txo_count,
+            blocks[1].cumulative_txo_count + 1
+        );
+        assert_ne!(fork_block.id, blocks[2].id);
+
+        // It commits to the TxOut set as of its parent.
+        let proofs = ledger_db.get_tx_out_proofs_at_root(&[0], 1).unwrap();
+        assert_eq!(
+            fork_block.root_element,
+            compute_implied_merkle_root(&proofs[0]).unwrap()
+        );
+
+        // It does not fit on the main chain.
+        assert_eq!(
+            ledger_db.append_block(&fork_block, &block_contents, None),
+            Err(Error::InvalidBlockIndex(2))
+        );
+
+        // A block built on the tip can be appended.
+        let block_contents = new_block_contents(&mut rng);
+        let tip_block =
+            crate::test_utils::build_block_on_parent(&ledger_db, n_blocks - 1, &block_contents);
+        ledger_db
+            .append_block(&tip_block, &block_contents, None)
+            .unwrap();
+        assert_eq!(ledger_db.get_block(n_blocks).unwrap(), tip_block);
+    }
+
+    #[test]
+    /// Pruning drops the old outputs whose key image is spent, while new blocks
+    /// are still validated against their key images and output public keys.
+    fn test_prune_depth() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+        ledger_db.set_prune_depth(2);
+
+        // Blocks 0 through 4, with 3 outputs each. Block n spends `KeyImage::from(n)`.
+        let (blocks, blocks_contents) = populate_db(&mut ledger_db, 5, 3);
+        assert_eq!(ledger_db.num_txos().unwrap(), 15);
+
+        // No key image is known, so nothing is pruned.
+        assert_eq!(ledger_db.num_pruned_tx_outs().unwrap(), 0);
+        assert_eq!(
+            ledger_db.get_block_contents(0),
+            Ok(blocks_contents[0].clone())
+        );
+
+        // Output 0 is spent and behind the horizon, output 3 is unspent, and output 9
+        // is spent but within the last 2 blocks.
+        ledger_db
+            .set_tx_out_key_image(0, &KeyImage::from(1))
+            .unwrap();
+        ledger_db
+            .set_tx_out_key_image(3, &KeyImage::from(100))
+            .unwrap();
+        ledger_db
+            .set_tx_out_key_image(9, &KeyImage::from(2))
+            .unwrap();
+        assert_eq!(ledger_db.num_pruned_tx_outs().unwrap(), 1);
+        assert_eq!(
+            ledger_db.set_tx_out_key_image(15, &KeyImage::from(3)),
+            Err(Error::NotFound)
+        );
+
+        assert_eq!(
+            ledger_db.get_block_contents(0),
+            Err(Error::BlockContentsPruned(0))
+        );
+        for block_index in 1..5 {
+            assert_eq!(
+                ledger_db.get_block_contents(block_index),
+                Ok(blocks_contents[block_index as usize].clone())
+            );
+        }
+        for block_index in 0..5 {
+            assert_eq!(
+                ledger_db.get_block(block_index),
+                Ok(blocks[block_index as usize].clone())
+            );
+        }
+        assert_eq!(ledger_db.get_tx_out_by_index(0), Err(Error::NotFound));
+        assert_eq!(
+            ledger_db.get_tx_out_by_index(1),
+            Ok(blocks_contents[0].outputs[1].clone())
+        );
+
+        // Proofs of membership of pruned outputs are still valid.
+        let leaf_hashes: Vec<[u8; 32]> = blocks_contents
+            .iter()
+            .flat_map(|contents| contents.outputs.iter().map(hash_leaf))
+            .collect();
+        let expected_root = tx_out_store::compute_merkle_root(&leaf_hashes).unwrap();
+        let proofs = ledger_db.get_tx_out_proofs_at_root(&[0], 4).unwrap();
+        assert_eq!(proofs[0].index, 0);
+        assert_eq!(
+            compute_implied_merkle_root(&proofs[0]).unwrap().hash.0,
+            expected_root
+        );
+
+        // Pruned outputs are still indexed.
+        let pruned_tx_out = &blocks_contents[0].outputs[0];
+        assert_eq!(
+            ledger_db.get_tx_out_index_by_hash(&pruned_tx_out.hash()),
+            Ok(0)
+        );
+
+        ledger_db.verify_integrity().unwrap();
+
+        let account_key = AccountKey::random(&mut rng);
+        let new_tx_out = TxOut::new(
+            10,
+            &account_key.default_subaddress(),
+            &RistrettoPrivate::from_random(&mut rng),
+            Default::default(),
+        )
+        .unwrap();
+
+        // A key image spent in a block with pruned outputs is still rejected.
+        let contents = BlockContents::new(vec![KeyImage::from(1)], vec![new_tx_out.clone()]);
+        let block =
+            Block::new_with_parent(BLOCK_VERSION, &blocks[4], &Default::default(), &contents);
+        assert_eq!(
+            ledger_db.append_block(&block, &contents, None),
+            Err(Error::KeyImageAlreadySpent)
+        );
+
+        // The public key of a pruned output is still rejected.
+        let mut duplicate_tx_out = new_tx_out.clone();
+        duplicate_tx_out.public_key = pruned_tx_out.public_key.clone();
+        let contents = BlockContents::new(vec![KeyImage::from(5)], vec![duplicate_tx_out]);
+        let block =
+            Block::new_with_parent(BLOCK_VERSION, &blocks[4], &Default::default(), &contents);
+        assert_eq!(
+            ledger_db.append_block(&block, &contents, None),
+            Err(Error::DuplicateOutputPublicKey(
+                pruned_tx_out.public_key.clone()
+            ))
+        );
+
+        // A valid block spends output 3 and pushes block 3 behind the horizon, so
+        // outputs 3 and 9 are pruned.
+        let contents = BlockContents::new(vec![KeyImage::from(100)], vec![new_tx_out]);
+        let block =
+            Block::new_with_parent(BLOCK_VERSION, &blocks[4], &Default::default(), &contents);
+        ledger_db.append_block(&block, &contents, None).unwrap();
+        assert_eq!(ledger_db.num_blocks().unwrap(), 6);
+        assert_eq!(ledger_db.num_pruned_tx_outs().unwrap(), 3);
+        for block_index in &[1, 3] {
+            assert_eq!(
+                ledger_db.get_block_contents(*block_index),
+                Err(Error::BlockContentsPruned(*block_index))
+            );
+        }
+        assert_eq!(
+            ledger_db.get_block_contents(2),
+            Ok(blocks_contents[2].clone())
+        );
+        assert_eq!(ledger_db.get_block_contents(5), Ok(contents));
+
+        ledger_db.verify_integrity().unwrap();
+    }
+
     #[test]
     /// Appending a block with a spent key image should return
     /// Error::KeyImageAlreadySpent.
@@ -1458,7 +3103,7 @@ mod ledger_db_test {
 
     #[test]
     /// Appending a block with a pre-existing output public key should return
-    /// Error::DuplicateOutputPublicKey.
+    /// Error::DuplicateOutputPublicKey carrying that key.
     fn test_append_block_with_duplicate_output_public_key() {
         let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
         let mut ledger_db = create_db();
@@ -1497,10 +3142,263 @@ mod ledger_db_test {
 
         assert_eq!(
             ledger_db.append_block(&block_one, &block_one_contents, None),
-            Err(Error::DuplicateOutputPublicKey)
+            Err(Error::DuplicateOutputPublicKey(
+                existing_tx_out.public_key.clone()
+            ))
         );
     }
 
+    #[test]
+    /// Appending a block that contains the same output public key twice should
+    /// return Error::DuplicateOutputPublicKeyInBlock.
+    fn test_append_block_with_duplicate_output_public_key_in_block() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+
+        // Write a block to the ledger.
+        let origin_account_key = AccountKey::random(&mut rng);
+        let (origin_block, origin_block_contents) =
+            get_origin_block_and_contents(&origin_account_key);
+        ledger_db
+            .append_block(&origin_block, &origin_block_contents, None)
+            .unwrap();
+
+        // The next block contains two outputs with the same public key, neither of
+        // which is in the ledger yet.
+        let account_key = AccountKey::random(&mut rng);
+        let mut outputs: Vec<TxOut> = (0..2)
+            .map(|_| {
+                TxOut::new(
+                    33,
+                    &account_key.default_subaddress(),
+                    &RistrettoPrivate::from_random(&mut rng),
+                    Default::default(),
+                )
+                .unwrap()
+            })
+            .collect();
+        outputs[1].public_key = outputs[0].public_key.clone();
+        let duplicate_public_key = outputs[0].public_key.clone();
+        let block_one_contents = BlockContents::new(vec![KeyImage::from(rng.next_u64())], outputs);
+
+        let block_one = Block::new_with_parent(
+            BLOCK_VERSION,
+            &origin_block,
+            &Default::default(),
+            &block_one_contents,
+        );
+
+        assert_eq!(
+            ledger_db.append_block(&block_one, &block_one_contents, None),
+            Err(Error::DuplicateOutputPublicKeyInBlock(duplicate_public_key))
+        );
+        assert_eq!(ledger_db.num_blocks().unwrap(), 1);
+    }
+
+    #[test]
+    /// Appending a block whose outputs are not sorted by public key should
+    /// return Error::UnsortedOutputs.
+    fn test_append_block_with_unsorted_outputs() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+
+        let origin_account_key = AccountKey::random(&mut rng);
+        let (origin_block, origin_block_contents) =
+            get_origin_block_and_contents(&origin_account_key);
+        ledger_db
+            .append_block(&origin_block, &origin_block_contents, None)
+            .unwrap();
+
+        let account_key = AccountKey::random(&mut rng);
+        let mut outputs: Vec<TxOut> = (0..3)
+            .map(|_| {
+                TxOut::new(
+                    33,
+                    &account_key.default_subaddress(),
+                    &RistrettoPrivate::from_random(&mut rng),
+                    Default::default(),
+                )
+                .unwrap()
+            })
+            .collect();
+        // Deliberately list the outputs in descending order.
+        outputs.sort_by(|a, b| b.public_key.cmp(&a.public_key));
+        let block_one_contents = BlockContents::new(vec![KeyImage::from(rng.next_u64())], outputs);
+        assert!(!block_one_contents.has_sorted_outputs());
+
+        let block_one = Block::new_with_parent(
+            BLOCK_VERSION,
+            &origin_block,
+            &Default::default(),
+            &block_one_contents,
+        );
+
+        assert_eq!(
+            ledger_db.append_block(&block_one, &block_one_contents, None),
+            Err(Error::UnsortedOutputs)
+        );
+        assert_eq!(ledger_db.num_blocks().unwrap(), 1);
+    }
+
+    #[test]
+    /// Blocks loaded with Durability::Fast should all be there once the ledger
+    /// is synced, closed and reopened.
+    fn test_bulk_load_with_fast_durability() {
+        let temp_dir = TempDir::new("test").unwrap();
+        let path = temp_dir.path();
+        LedgerDB::create_with_durability(path, Durability::Fast).unwrap();
+
+        let (blocks, blocks_contents) = {
+            let mut ledger_db = LedgerDB::open_with_durability(path, Durability::Fast).unwrap();
+            let loaded = populate_db(&mut ledger_db, 20, 3);
+            ledger_db.sync().unwrap();
+            loaded
+        };
+
+        let ledger_db = LedgerDB::open_with_durability(path, Durability::Safe).unwrap();
+        assert_eq!(ledger_db.num_blocks().unwrap(), 20);
+        assert_eq!(ledger_db.num_txos().unwrap(), 60);
+        for (block, block_contents) in blocks.iter().zip(blocks_contents.iter()) {
+            assert_eq!(ledger_db.get_block(block.index).unwrap(), *block);
+            assert_eq!(
+                ledger_db.get_block_contents(block.index).unwrap(),
+                *block_contents
+            );
+        }
+        ledger_db.verify_integrity().unwrap();
+    }
+
+    #[test]
+    /// validate_block should accept a block that can be appended and reject
+    /// invalid ones, without writing anything.
+    fn test_validate_block() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+        let (blocks, _) = populate_db(&mut ledger_db, 2, 2);
+
+        let account_key = AccountKey::random(&mut rng);
+        let new_contents = |key_image: KeyImage, rng: &mut StdRng| {
+            let outputs: Vec<TxOut> = (0..2)
+                .map(|_| {
+                    TxOut::new(
+                        33,
+                        &account_key.default_subaddress(),
+                        &RistrettoPrivate::from_random(rng),
+                        Default::default(),
+                    )
+                    .unwrap()
+                })
+                .collect();
+            let mut block_contents = BlockContents::new(vec![key_image], outputs);
+            block_contents.sort_outputs();
+            block_contents
+        };
+
+        let block_contents = new_contents(KeyImage::from(rng.next_u64()), &mut rng);
+        let block = Block::new_with_parent(
+            BLOCK_VERSION,
+            &blocks[1],
+            &Default::default(),
+            &block_contents,
+        );
+
+        // A valid block passes, and is not written.
+        assert_eq!(ledger_db.validate_block(&block, &block_contents), Ok(()));
+        assert_eq!(ledger_db.num_blocks().unwrap(), 2);
+
+        // A block that does not extend the tip.
+        let stale_block = Block::new_with_parent(
+            BLOCK_VERSION,
+            &blocks[0],
+            &Default::default(),
+            &block_contents,
+        );
+        assert_eq!(
+            ledger_db.validate_block(&stale_block, &block_contents),
+            Err(Error::InvalidBlockIndex(1))
+        );
+
+        // Contents that do not match the block.
+        let other_contents = new_contents(KeyImage::from(rng.next_u64()), &mut rng);
+        assert_eq!(
+            ledger_db.validate_block(&block, &other_contents),
+            Err(Error::InvalidBlockContents)
+        );
+
+        // A key image that was already spent, in block 1.
+        let double_spend_contents = new_contents(KeyImage::from(1), &mut rng);
+        let double_spend_block = Block::new_with_parent(
+            BLOCK_VERSION,
+            &blocks[1],
+            &Default::default(),
+            &double_spend_contents,
+        );
+        assert_eq!(
+            ledger_db.validate_block(&double_spend_block, &double_spend_contents),
+            Err(Error::KeyImageAlreadySpent)
+        );
+
+        // A block whose id does not match its fields.
+        let mut invalid_id_block = block.clone();
+        invalid_id_block.id = blocks[1].id.clone();
+        assert_eq!(
+            ledger_db.validate_block(&invalid_id_block, &block_contents),
+            Err(Error::InvalidBlockID(blocks[1].id.clone()))
+        );
+
+        // None of the candidates were written, and the valid one can be appended.
+        assert_eq!(ledger_db.num_blocks().unwrap(), 2);
+        ledger_db
+            .append_block(&block, &block_contents, None)
+            .unwrap();
+        assert_eq!(ledger_db.num_blocks().unwrap(), 3);
+    }
+
+    #[test]
+    /// A block whose outputs were sorted into canonical order can be appended.
+    fn test_append_block_with_sorted_outputs() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+
+        let origin_account_key = AccountKey::random(&mut rng);
+        let (origin_block, origin_block_contents) =
+            get_origin_block_and_contents(&origin_account_key);
+        ledger_db
+            .append_block(&origin_block, &origin_block_contents, None)
+            .unwrap();
+
+        let account_key = AccountKey::random(&mut rng);
+        let mut outputs: Vec<TxOut> = (0..3)
+            .map(|_| {
+                TxOut::new(
+                    33,
+                    &account_key.default_subaddress(),
+                    &RistrettoPrivate::from_random(&mut rng),
+                    Default::default(),
+                )
+                .unwrap()
+            })
+            .collect();
+        outputs.sort_by(|a, b| b.public_key.cmp(&a.public_key));
+        let mut block_one_contents =
+            BlockContents::new(vec![KeyImage::from(rng.next_u64())], outputs);
+        block_one_contents.sort_outputs();
+        assert!(block_one_contents.has_sorted_outputs());
+
+        let block_one = Block::new_with_parent(
+            BLOCK_VERSION,
+            &origin_block,
+            &Default::default(),
+            &block_one_contents,
+        );
+
+        assert_eq!(
+            ledger_db.append_block(&block_one, &block_one_contents, None),
+            Ok(())
+        );
+        assert_eq!(ledger_db.get_block_contents(1), Ok(block_one_contents));
+    }
+
     #[test]
     // append_block rejects invalid blocks.
     fn test_append_invalid_blocks() {
@@ -1613,10 +3511,25 @@ mod ledger_db_test {
             &mut rng,
         );
 
+        // The generated blocks do not list their outputs in canonical order, so they
+        // are rebuilt with sorted outputs.
+        let mut parent_block = origin_block;
         for (block, block_contents) in &results {
+            let mut block_contents = block_contents.clone();
+            block_contents.sort_outputs();
+            let block = Block::new_with_parent(
+                block.version,
+                &parent_block,
+                &block.root_element,
+                &block_contents,
+            );
+
             println!("block {} containing {:?}", block.index, block_contents);
-            ledger_db.append_block(block, block_contents, None).unwrap();
+            ledger_db
+                .append_block(&block, &block_contents, None)
+                .unwrap();
             assert_eq!(block.cumulative_txo_count, ledger_db.num_txos().unwrap());
+            parent_block = block;
         }
     }
 
@@ -1646,4 +3559,35 @@ mod ledger_db_test {
 
         b.iter(|| ledger_db.get_block(rng.next_u64() % n_blocks).unwrap())
     }
+
+    #[bench]
+    #[ignore]
+    fn bench_get_recent_blocks(b: &mut Bencher) {
+        let mut ledger_db = create_db();
+        let n_blocks = 30;
+        let n_txs_per_block = 1;
+        let _ = populate_db(&mut ledger_db, n_blocks, n_txs_per_block);
+
+        b.iter(|| {
+            for block_number in n_blocks - 10..n_blocks {
+                ledger_db.get_block(block_number).unwrap();
+            }
+        })
+    }
+
+    #[bench]
+    #[ignore]
+    fn bench_get_recent_blocks_cached(b: &mut Bencher) {
+        let mut ledger_db = create_db();
+        ledger_db.set_block_cache_capacity(10);
+        let n_blocks = 30;
+        let n_txs_per_block = 1;
+        let _ = populate_db(&mut ledger_db, n_blocks, n_txs_per_block);
+
+        b.iter(|| {
+            for block_number in n_blocks - 10..n_blocks {
+                ledger_db.get_block(block_number).unwrap();
+            }
+        })
+    }
 }
@@ -0,0 +1,179 @@
+// Copyright (c) 2018-2021 The MobileCoin Foundation
+
+//! A read-only view of a ledger.
+//!
+//! `ReadOnlyLedger` exposes the getters of `Ledger` without `append_block`, so
+//! that code which must never write to the ledger can be handed a
+//! `dyn ReadOnlyLedger` instead of a full `Ledger`. Every `Ledger` implements
+//! it.
+//!
+//! The methods share their names with `Ledger`, so this trait is deliberately
+//! not re-exported at the crate root: importing both traits in the same scope
+//! makes calls on a concrete ledger ambiguous.
+
+use crate::{Error, Ledger};
+use mc_common::Hash;
+use mc_crypto_keys::CompressedRistrettoPublic;
+use mc_transaction_core::{
+    ring_signature::KeyImage,
+    tx::{TxOut, TxOutMembershipProof},
+    Block, BlockContents, BlockData, BlockIndex, BlockSignature,
+};
+
+pub trait ReadOnlyLedger: Send + Sync {
+    /// Get the total number of blocks in the ledger.
+    fn num_blocks(&self) -> Result<u64, Error>;
+
+    /// Gets a Block by its index in the blockchain.
+    fn get_block(&self, block_number: BlockIndex) -> Result<Block, Error>;
+
+    /// Get the contents of a block.
+    fn get_block_contents(&self, block_number: BlockIndex) -> Result<BlockContents, Error>;
+
+    /// Gets a block signature by its index in the blockchain.
+    fn get_block_signature(&self, block_number: BlockIndex) -> Result<BlockSignature, Error>;
+
+    /// Gets a block and all of its associated data by its index in the
+    /// blockchain.
+    fn get_block_data(&self, block_number: BlockIndex) -> Result<BlockData, Error>;
+
+    /// Gets block index by a TxOut global index.
+    fn get_block_index_by_tx_out_index(&self, tx_out_index: u64) -> Result<BlockIndex, Error>;
+
+    /// Get the total number of TxOuts in the ledger.
+    fn num_txos(&self) -> Result<u64, Error>;
+
+    /// Returns the index of the TxOut with the given hash.
+    fn get_tx_out_index_by_hash(&self, tx_out_hash: &Hash) -> Result<u64, Error>;
+
+    /// Returns the index of the TxOut with the given public key.
+    fn get_tx_out_index_by_public_key(
+        &self,
+        tx_out_public_key: &CompressedRistrettoPublic,
+    ) -> Result<u64, Error>;
+
+    /// Gets a TxOut by its index in the ledger.
+    fn get_tx_out_by_index(&self, index: u64) -> Result<TxOut, Error>;
+
+    /// Gets a proof of memberships for TxOuts with indexes `indexes`.
+    fn get_tx_out_proof_of_memberships(
+        &self,
+        indexes: &[u64],
+    ) -> Result<Vec<TxOutMembershipProof>, Error>;
+
+    /// Returns true if the Ledger contains the given TxOut public key.
+    fn contains_tx_out_public_key(
+        &self,
+        public_key: &CompressedRistrettoPublic,
+    ) -> Result<bool, Error>;
+
+    /// Returns true if the Ledger contains the given key image.
+    fn contains_key_image(&self, key_image: &KeyImage) -> Result<bool, Error>;
+
+    /// Checks if the ledger contains a given key image.
+    /// If so, returns the index of the block in which it entered the ledger.
+    /// Ok(None) is returned when the key image is not in the ledger.
+    fn check_key_image(&self, key_image: &KeyImage) -> Result<Option<BlockIndex>, Error>;
+
+    /// Gets the key images used by transactions in a single block.
+    fn get_key_images_by_block(&self, block_number: BlockIndex) -> Result<Vec<KeyImage>, Error>;
+}
+
+impl<L: Ledger + Sync> ReadOnlyLedger for L {
+    fn num_blocks(&self) -> Result<u64, Error> {
+        Ledger::num_blocks(self)
+    }
+
+    fn get_block(&self, block_number: BlockIndex) -> Result<Block, Error> {
+        Ledger::get_block(self, block_number)
+    }
+
+    fn get_block_contents(&self, block_number: BlockIndex) -> Result<BlockContents, Error> {
+        Ledger::get_block_contents(self, block_number)
+    }
+
+    fn get_block_signature(&self, block_number: BlockIndex) -> Result<BlockSignature, Error> {
+        Ledger::get_block_signature(self, block_number)
+    }
+
+    fn get_block_data(&self, block_number: BlockIndex) -> Result<BlockData, Error> {
+        Ledger::get_block_data(self, block_number)
+    }
+
+    fn get_block_index_by_tx_out_index(&self, tx_out_index: u64) -> Result<BlockIndex, Error> {
+        Ledger::get_block_index_by_tx_out_index(self, tx_out_index)
+    }
+
+    fn num_txos(&self) -> Result<u64, Error> {
+        Ledger::num_txos(self)
+    }
+
+    fn get_tx_out_index_by_hash(&self, tx_out_hash: &Hash) -> Result<u64, Error> {
+        Ledger::get_tx_out_index_by_hash(self, tx_out_hash)
+    }
+
+    fn get_tx_out_index_by_public_key(
+        &self,
+        tx_out_public_key: &CompressedRistrettoPublic,
+    ) -> Result<u64, Error> {
+        Ledger::get_tx_out_index_by_public_key(self, tx_out_public_key)
+    }
+
+    fn get_tx_out_by_index(&self, index: u64) -> Result<TxOut, Error> {
+        Ledger::get_tx_out_by_index(self, index)
+    }
+
+    fn get_tx_out_proof_of_memberships(
+        &self,
+        indexes: &[u64],
+    ) -> Result<Vec<TxOutMembershipProof>, Error> {
+        Ledger::get_tx_out_proof_of_memberships(self, indexes)
+    }
+
+    fn contains_tx_out_public_key(
+        &self,
+        public_key: &CompressedRistrettoPublic,
+    ) -> Result<bool, Error> {
+        Ledger::contains_tx_out_public_key(self, public_key)
+    }
+
+    fn contains_key_image(&self, key_image: &KeyImage) -> Result<bool, Error> {
+        Ledger::contains_key_image(self, key_image)
+    }
+
+    fn check_key_image(&self, key_image: &KeyImage) -> Result<Option<BlockIndex>, Error> {
+        Ledger::check_key_image(self, key_image)
+    }
+
+    fn get_key_images_by_block(&self, block_number: BlockIndex) -> Result<Vec<KeyImage>, Error> {
+        Ledger::get_key_images_by_block(self, block_number)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::get_mock_ledger;
+    use std::sync::Arc;
+
+    #[test]
+    // A read-only handle should see the same data as the ledger it wraps.
+    fn test_read_only_handle_matches_ledger() {
+        let ledger = get_mock_ledger(4);
+        let read_only: Arc<dyn ReadOnlyLedger> = Arc::new(ledger.clone());
+
+        let num_blocks = Ledger::num_blocks(&ledger).unwrap();
+        assert_eq!(read_only.num_blocks().unwrap(), num_blocks);
+        assert_eq!(
+            read_only.num_txos().unwrap(),
+            Ledger::num_txos(&ledger).unwrap()
+        );
+
+        for block_index in 0..num_blocks {
+            assert_eq!(
+                read_only.get_block(block_index).unwrap(),
+                Ledger::get_block(&ledger, block_index).unwrap()
+            );
+        }
+    }
+}
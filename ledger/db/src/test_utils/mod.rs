@@ -2,3 +2,176 @@
 
 pub mod mock_ledger;
 pub use mock_ledger::{get_mock_ledger, get_test_ledger_blocks, MockLedger};
+
+use crate::{u64_to_key_bytes, Ledger, LedgerDB, BLOCKS_DB_NAME, MAX_LMDB_DBS, MAX_LMDB_FILE_SIZE};
+use lmdb::{Environment, Transaction, WriteFlags};
+use mc_transaction_core::{
+    membership_proofs::compute_implied_merkle_root, Block, BlockContents, BLOCK_VERSION,
+};
+use std::path::Path;
+
+/// Create and open a ledger with a given initial LMDB map size. A small map
+/// size is useful for exercising the map growth path.
+pub fn create_ledger_with_map_size(ledger_path: &Path, map_size: usize) -> LedgerDB {
+    LedgerDB::create_with_map_size(ledger_path, map_size).expect("Failed creating ledger db");
+    LedgerDB::open_with_map_size(ledger_path, map_size).expect("Failed opening ledger db")
+}
+
+/// Flip a byte in the stored record of the given block, so that subsequent
+/// reads or integrity checks detect the corruption.
+///
+/// This is for testing only. The ledger at `ledger_path` must not be open
+/// while calling this.
+pub fn corrupt_ledger_block(ledger_path: &Path, block_index: u64) {
+    let env = Environment::new()
+        .set_max_dbs(MAX_LMDB_DBS)
+        .set_map_size(MAX_LMDB_FILE_SIZE)
+        .open(ledger_path)
+        .expect("Failed opening ledger db");
+    let blocks = env
+        .open_db(Some(BLOCKS_DB_NAME))
+        .expect("Failed opening blocks db");
+
+    let mut db_transaction = env.begin_rw_txn().unwrap();
+    let key = u64_to_key_bytes(block_index);
+    let mut block_bytes = db_transaction
+        .get(blocks, &key)
+        .expect("Block not found")
+        .to_vec();
+
+    // The last bytes of a block record belong to its contents hash, so flipping one
+    // keeps the record decodable but invalidates the block.
+    let last = block_bytes.len() - 1;
+    block_bytes[last] ^= 0xff;
+
+    db_transaction
+        .put(blocks, &key, &block_bytes, WriteFlags::empty())
+        .unwrap();
+    db_transaction.commit().unwrap();
+}
+
+/// Build a block whose parent is the block at `parent_index`, which need not be
+/// the tip of the ledger. This allows constructing the blocks of a competing
+/// chain for fork tests. The block commits to the TxOut set as it was at its
+/// parent, like a block appended right after the parent would.
+///
+/// The block can only be appended to `ledger_db` if the parent is its tip.
+pub fn build_block_on_parent(
+    ledger_db: &LedgerDB,
+    parent_index: u64,
+    block_contents: &BlockContents,
+) -> Block {
+    let parent = ledger_db
+        .get_block(parent_index)
+        .expect("Failed getting parent block");
+    let proofs = ledger_db
+        .get_tx_out_proofs_at_root(&[0], parent_index)
+        .expect("Failed getting membership proof");
+    let root_element =
+        compute_implied_merkle_root(&proofs[0]).expect("Failed computing merkle root");
+    Block::new_with_parent(BLOCK_VERSION, &parent, &root_element, block_contents)
+}
+
+/// Assert that two ledgers hold the same blocks, e.g. before and after a
+/// migration. Block counts are compared first, then each block's id and its
+/// numbers of key images and TxOuts. Panics describing the first mismatch.
+pub fn assert_ledgers_equal(a: &LedgerDB, b: &LedgerDB) {
+    let num_blocks = a.num_blocks().expect("Failed getting number of blocks");
+    let other_num_blocks = b.num_blocks().expect("Failed getting number of blocks");
+    assert_eq!(
+        num_blocks, other_num_blocks,
+        "Ledgers differ: {} blocks != {} blocks",
+        num_blocks, other_num_blocks
+    );
+
+    for block_index in 0..num_blocks {
+        let block = a.get_block(block_index).expect("Failed getting block");
+        let other_block = b.get_block(block_index).expect("Failed getting block");
+        assert_eq!(
+            block.id, other_block.id,
+            "Ledgers differ at block {}: id {:?} != {:?}",
+            block_index, block.id, other_block.id
+        );
+
+        let contents = a
+            .get_block_contents(block_index)
+            .expect("Failed getting block contents");
+        let other_contents = b
+            .get_block_contents(block_index)
+            .expect("Failed getting block contents");
+        assert_eq!(
+            contents.key_images.len(),
+            other_contents.key_images.len(),
+            "Ledgers differ at block {}: {} key images != {} key images",
+            block_index,
+            contents.key_images.len(),
+            other_contents.key_images.len()
+        );
+        assert_eq!(
+            contents.outputs.len(),
+            other_contents.outputs.len(),
+            "Ledgers differ at block {}: {} TxOuts != {} TxOuts",
+            block_index,
+            contents.outputs.len(),
+            other_contents.outputs.len()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_transaction_core::ring_signature::KeyImage;
+    use tempdir::TempDir;
+
+    /// Create a ledger holding the given blocks.
+    fn create_ledger(blocks_and_contents: &[(Block, BlockContents)]) -> LedgerDB {
+        let temp_dir = TempDir::new("test").unwrap();
+        let path = temp_dir.path();
+        LedgerDB::create(path).unwrap();
+        let mut ledger_db = LedgerDB::open(path).unwrap();
+        for (block, block_contents) in blocks_and_contents {
+            ledger_db.append_block(block, block_contents, None).unwrap();
+        }
+        ledger_db
+    }
+
+    #[test]
+    fn test_assert_ledgers_equal() {
+        let blocks_and_contents = get_test_ledger_blocks(5);
+        let ledger_db = create_ledger(&blocks_and_contents);
+        let copy = create_ledger(&blocks_and_contents);
+
+        assert_ledgers_equal(&ledger_db, &ledger_db);
+        assert_ledgers_equal(&ledger_db, &copy);
+        assert_ledgers_equal(&copy, &ledger_db);
+    }
+
+    #[test]
+    #[should_panic(expected = "Ledgers differ: 5 blocks != 4 blocks")]
+    fn test_assert_ledgers_equal_with_missing_block() {
+        let blocks_and_contents = get_test_ledger_blocks(5);
+        let ledger_db = create_ledger(&blocks_and_contents);
+        let truncated = create_ledger(&blocks_and_contents[..4]);
+
+        assert_ledgers_equal(&ledger_db, &truncated);
+    }
+
+    #[test]
+    #[should_panic(expected = "Ledgers differ at block 4: id")]
+    fn test_assert_ledgers_equal_with_modified_block() {
+        let blocks_and_contents = get_test_ledger_blocks(5);
+        let ledger_db = create_ledger(&blocks_and_contents);
+
+        // The same chain, except that the last block spends another key image.
+        let mut modified = create_ledger(&blocks_and_contents[..4]);
+        let mut block_contents = blocks_and_contents[4].1.clone();
+        block_contents.key_images = vec![KeyImage::from(1234)];
+        let block = build_block_on_parent(&modified, 3, &block_contents);
+        modified
+            .append_block(&block, &block_contents, None)
+            .unwrap();
+
+        assert_ledgers_equal(&ledger_db, &modified);
+    }
+}
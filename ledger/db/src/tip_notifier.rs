@@ -0,0 +1,70 @@
+// Copyright (c) 2018-2021 The MobileCoin Foundation
+
+//! Notifies subscribers when blocks are appended to a ledger.
+
+use std::sync::{
+    mpsc::{channel, Receiver, Sender},
+    Arc, Mutex,
+};
+
+/// A registry of subscribers interested in the number of blocks in a ledger.
+///
+/// Clones share the same subscribers, so a subscription made through one
+/// clone of a `LedgerDB` observes appends made through any other clone.
+#[derive(Clone, Default)]
+pub struct TipNotifier {
+    subscribers: Arc<Mutex<Vec<Sender<u64>>>>,
+}
+
+impl TipNotifier {
+    /// Subscribe to changes of the ledger tip. The receiver gets the new
+    /// number of blocks each time a block is appended.
+    ///
+    /// Dropping the receiver ends the subscription.
+    pub fn subscribe(&self) -> Receiver<u64> {
+        let (sender, receiver) = channel();
+        self.subscribers
+            .lock()
+            .expect("mutex poisoned")
+            .push(sender);
+        receiver
+    }
+
+    /// Send the new number of blocks to every subscriber, dropping subscribers
+    /// whose receiver is gone.
+    pub fn notify(&self, num_blocks: u64) {
+        self.subscribers
+            .lock()
+            .expect("mutex poisoned")
+            .retain(|sender| sender.send(num_blocks).is_ok());
+    }
+
+    /// The number of live subscriptions, as of the last notification.
+    pub fn num_subscribers(&self) -> usize {
+        self.subscribers.lock().expect("mutex poisoned").len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    // Every subscriber should be notified, and dropped subscribers should be
+    // forgotten.
+    fn test_notify_subscribers() {
+        let notifier = TipNotifier::default();
+        let receiver1 = notifier.subscribe();
+        let receiver2 = notifier.clone().subscribe();
+        assert_eq!(notifier.num_subscribers(), 2);
+
+        notifier.notify(3);
+        assert_eq!(receiver1.try_recv(), Ok(3));
+        assert_eq!(receiver2.try_recv(), Ok(3));
+
+        drop(receiver1);
+        notifier.notify(4);
+        assert_eq!(receiver2.try_recv(), Ok(4));
+        assert_eq!(notifier.num_subscribers(), 1);
+    }
+}
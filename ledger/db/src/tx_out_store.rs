@@ -142,11 +142,22 @@ impl TxOutStore {
             WriteFlags::NO_OVERWRITE,
         )?;
 
-        self.update_merkle_hashes(index, db_transaction)?;
+        self.update_merkle_hashes(index, &hash_leaf(tx_out), db_transaction)?;
 
         Ok(index)
     }
 
+    /// Deletes the body of the TxOut with the given index. Its hash and public
+    /// key remain indexed, and the Merkle hashes are unaffected, so duplicate
+    /// outputs are still detected and proofs of membership can still be
+    /// generated. Deleting a TxOut that was already deleted is a no-op.
+    pub fn prune(&self, index: u64, db_transaction: &mut RwTransaction) -> Result<(), Error> {
+        match db_transaction.del(self.tx_out_by_index, &u64_to_key_bytes(index), None) {
+            Ok(()) | Err(lmdb::Error::NotFound) => Ok(()),
+            Err(e) => Err(Error::from(e)),
+        }
+    }
+
     /// Get the total number of TxOuts in the ledger.
     pub fn num_tx_outs<T: Transaction>(&self, db_transaction: &T) -> Result<u64, Error> {
         Ok(key_bytes_to_u64(
@@ -175,6 +186,7 @@ impl TxOutStore {
     }
 
     /// Gets a TxOut by its index in the ledger.
+    /// Returns `Error::NotFound` if the TxOut was pruned.
     pub fn get_tx_out_by_index<T: Transaction>(
         &self,
         index: u64,
@@ -240,17 +252,52 @@ impl TxOutStore {
         }
     }
 
+    /// Gets the Merkle hash value for a node spanning the given range, in the
+    /// Merkle Tree of the first `num_tx_outs` TxOuts.
+    ///
+    /// Stored hashes of nodes whose leaves are all below `num_tx_outs` did not
+    /// change when later TxOuts were added, and are used as is. Nodes that
+    /// straddle `num_tx_outs` are recomputed from their children.
+    fn get_merkle_hash_as_of<T: Transaction>(
+        &self,
+        range: &Range,
+        num_tx_outs: u64,
+        db_transaction: &T,
+    ) -> Result<[u8; 32], Error> {
+        if range.from >= num_tx_outs {
+            return Ok(*NIL_HASH);
+        }
+        if range.to < num_tx_outs {
+            return self.get_merkle_hash(range, db_transaction);
+        }
+
+        let mid: u64 = (range.from + range.to) / 2;
+        let left_child_hash =
+            self.get_merkle_hash_as_of(&Range::new(range.from, mid)?, num_tx_outs, db_transaction)?;
+        let right_child_hash = self.get_merkle_hash_as_of(
+            &Range::new(mid + 1, range.to)?,
+            num_tx_outs,
+            db_transaction,
+        )?;
+        Ok(hash_nodes(&left_child_hash, &right_child_hash))
+    }
+
     /// Update Merkle Hashes to include the TxOut with the given index.
     ///
+    /// Only the nodes on the path from the new leaf to the root change, so this
+    /// writes one hash per level of the tree. The hash of each node on the
+    /// path is carried up to its parent, so only the sibling of each node needs
+    /// to be read back from the database.
+    ///
     /// # Arguments
     /// * `index` - The index of a TxOut that has not yet been included in the
     ///   Merkle Tree.
+    /// * `leaf_hash` - The leaf hash of that TxOut.
     /// * `db_transaction` - an LMDB transaction.
-    ///
-    /// Returns (the new Merkle root hash?) or an Error.
     fn update_merkle_hashes(
         &self,
         index: u64,
+        leaf_hash: &[u8; 32],
         db_transaction: &mut RwTransaction,
     ) -> Result<(), Error> {
         let num_tx_outs = self.num_tx_outs(db_transaction)?;
@@ -258,45 +305,60 @@ impl TxOutStore {
             return Err(Error::IndexOutOfBounds(index));
         }
 
-        let ranges = containing_ranges(index, num_tx_outs)?;
+        let mut ranges = containing_ranges(index, num_tx_outs)?.into_iter();
 
-        for (low, high) in ranges {
-            if low == high {
-                // Leaf.
-                let tx_out = self.get_tx_out_by_index(index, db_transaction)?;
-                let hash = hash_leaf(&tx_out);
-                let range = Range::new(low, low)?;
-                self.write_merkle_hash(&range, &hash, db_transaction)?;
-            } else {
-                // Internal node.
-                let mid: u64 = (low + high) / 2;
+        // Leaf.
+        let (low, high) = ranges.next().ok_or(Error::Range)?;
+        let mut hash = *leaf_hash;
+        self.write_merkle_hash(&Range::new(low, high)?, &hash, db_transaction)?;
 
-                // Left child.
-                let left_child_hash = {
-                    let left_child_range = Range::new(low, mid)?;
-                    self.get_merkle_hash(&left_child_range, db_transaction)?
-                };
+        // Internal nodes, from the leaf's parent up to the root.
+        for (low, high) in ranges {
+            let mid: u64 = (low + high) / 2;
 
-                // Right child.
+            let (left_child_hash, right_child_hash) = if index <= mid {
+                // The path continues through the left child.
                 let right_child_hash = if mid + 1 >= num_tx_outs {
                     // The right subtree contains no TxOuts, so use the nil hash.
                     *NIL_HASH
                 } else {
-                    // The right subtree contains some TxOuts, so look up the child's hash.
-                    let right_child_range = Range::new(mid + 1, high)?;
-                    self.get_merkle_hash(&right_child_range, db_transaction)?
+                    self.get_merkle_hash(&Range::new(mid + 1, high)?, db_transaction)?
                 };
+                (hash, right_child_hash)
+            } else {
+                // The path continues through the right child.
+                let left_child_hash =
+                    self.get_merkle_hash(&Range::new(low, mid)?, db_transaction)?;
+                (left_child_hash, hash)
+            };
 
-                // This node.
-                let hash = hash_nodes(&left_child_hash, &right_child_hash);
-                let range = Range::new(low, high)?;
-                self.write_merkle_hash(&range, &hash, db_transaction)?;
-            }
+            // This node.
+            hash = hash_nodes(&left_child_hash, &right_child_hash);
+            self.write_merkle_hash(&Range::new(low, high)?, &hash, db_transaction)?;
         }
 
         Ok(())
     }
 
+    /// Recompute the root hash of the Merkle Tree from the stored TxOuts,
+    /// ignoring the stored Merkle hashes.
+    ///
+    /// This reads every TxOut in the ledger, so it is only intended for
+    /// checking that the incrementally maintained hashes are correct.
+    pub fn compute_root_merkle_hash_from_scratch<T: Transaction>(
+        &self,
+        db_transaction: &T,
+    ) -> Result<[u8; 32], Error> {
+        let num_tx_outs = self.num_tx_outs(db_transaction)?;
+        let leaf_hashes = (0..num_tx_outs)
+            .map(|index| {
+                self.get_tx_out_by_index(index, db_transaction)
+                    .map(|tx_out| hash_leaf(&tx_out))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        compute_merkle_root(&leaf_hashes)
+    }
+
     /// Merkle proof-of-membership for TxOut with the given index.
     pub fn get_merkle_proof_of_membership<T: Transaction>(
         &self,
@@ -304,10 +366,33 @@ impl TxOutStore {
         db_transaction: &T,
     ) -> Result<TxOutMembershipProof, Error> {
         let num_tx_outs = self.num_tx_outs(db_transaction)?;
+        self.get_merkle_proof_of_membership_as_of(index, num_tx_outs, db_transaction)
+    }
+
+    /// Merkle proof-of-membership for TxOut with the given index, relative to
+    /// the Merkle Tree of the first `num_tx_outs` TxOuts. This allows proving
+    /// against the root the ledger had at an earlier block.
+    ///
+    /// # Arguments
+    /// * `index` - The index of the TxOut to prove.
+    /// * `num_tx_outs` - The number of TxOuts in the tree to prove against. It
+    ///   may not exceed the number of TxOuts in the store.
+    /// * `db_transaction` - an LMDB transaction.
+    pub fn get_merkle_proof_of_membership_as_of<T: Transaction>(
+        &self,
+        index: u64,
+        num_tx_outs: u64,
+        db_transaction: &T,
+    ) -> Result<TxOutMembershipProof, Error> {
         if index >= num_tx_outs {
             return Err(Error::IndexOutOfBounds(index));
         }
 
+        let current_num_tx_outs = self.num_tx_outs(db_transaction)?;
+        if num_tx_outs > current_num_tx_outs {
+            return Err(Error::IndexOutOfBounds(num_tx_outs - 1));
+        }
+
         // These pairs correspond to the ranges we will use for the proof elements
         // The first element always corresponds to the index
         let mut ranges_for_proof = vec![(index, index)];
@@ -338,8 +423,10 @@ impl TxOutStore {
                 // Note: Nil hashes could probably be omitted as an optimization if validation
                 // knows that it must supply them for any range where `low >= num_tx_outs`.
                 *NIL_HASH
-            } else {
+            } else if num_tx_outs == current_num_tx_outs {
                 self.get_merkle_hash(&range, db_transaction)?
+            } else {
+                self.get_merkle_hash_as_of(&range, num_tx_outs, db_transaction)?
             };
             elements.push(TxOutMembershipElement {
                 range,
@@ -356,6 +443,39 @@ impl TxOutStore {
     }
 }
 
+/// Computes the root hash of a Merkle Tree with the given leaf hashes.
+///
+/// The tree is padded to a power of two leaves, and empty subtrees hash to
+/// `NIL_HASH`, matching the hashes maintained by `TxOutStore`.
+pub fn compute_merkle_root(leaf_hashes: &[[u8; 32]]) -> Result<[u8; 32], Error> {
+    if leaf_hashes.is_empty() {
+        return Ok(*NIL_HASH);
+    }
+
+    let num_leaves_full_tree = (leaf_hashes.len() as u64)
+        .checked_next_power_of_two()
+        .ok_or(Error::CapacityExceeded)?;
+    Ok(compute_subtree_hash(leaf_hashes, num_leaves_full_tree))
+}
+
+/// The hash of the subtree whose leftmost `leaf_hashes.len()` leaves are
+/// present, out of `num_leaves` leaves.
+fn compute_subtree_hash(leaf_hashes: &[[u8; 32]], num_leaves: u64) -> [u8; 32] {
+    if leaf_hashes.is_empty() {
+        *NIL_HASH
+    } else if num_leaves == 1 {
+        leaf_hashes[0]
+    } else {
+        let half = num_leaves / 2;
+        let split = core::cmp::min(half as usize, leaf_hashes.len());
+        let (left, right) = leaf_hashes.split_at(split);
+        hash_nodes(
+            &compute_subtree_hash(left, half),
+            &compute_subtree_hash(right, half),
+        )
+    }
+}
+
 /// Converts this Range to bytes for use as an LMDB key.
 fn range_to_key_bytes(range: &Range) -> [u8; 16] {
     let mut bytes = [0u8; 16];
@@ -796,6 +916,7 @@ pub mod tx_out_store_tests {
     use rand::{rngs::StdRng, SeedableRng};
     use std::path::Path;
     use tempdir::TempDir;
+    use test::Bencher;
 
     fn get_env() -> Environment {
         let temp_dir = TempDir::new("test").unwrap();
@@ -1350,4 +1471,69 @@ pub mod tx_out_store_tests {
             Err(e) => panic!("Unexpected error {:?}", e),
         }
     }
+
+    #[test]
+    // The incrementally maintained root hash should match a from-scratch
+    // recomputation after every push.
+    fn test_root_merkle_hash_matches_recomputation() {
+        let (tx_out_store, env) = init_tx_out_store();
+        let mut rw_transaction = env.begin_rw_txn().unwrap();
+        assert_eq!(
+            *NIL_HASH,
+            tx_out_store
+                .compute_root_merkle_hash_from_scratch(&rw_transaction)
+                .unwrap()
+        );
+
+        for tx_out in &get_tx_outs(37) {
+            tx_out_store.push(tx_out, &mut rw_transaction).unwrap();
+            assert_eq!(
+                tx_out_store.get_root_merkle_hash(&rw_transaction).unwrap(),
+                tx_out_store
+                    .compute_root_merkle_hash_from_scratch(&rw_transaction)
+                    .unwrap()
+            );
+        }
+    }
+
+    // Number of blocks, each with a single TxOut, appended by the benches below.
+    // FIXME(MC-526): Like the benches in `ledger_db_test`, these are marked
+    // ignore because they are slow without optimizations.
+    const NUM_BENCH_TX_OUTS: u32 = 10_000;
+
+    #[bench]
+    #[ignore]
+    // Append one TxOut per block and read the root after each append, using the
+    // incrementally maintained Merkle hashes.
+    fn bench_append_with_incremental_root(b: &mut Bencher) {
+        let tx_outs = get_tx_outs(NUM_BENCH_TX_OUTS);
+        b.iter(|| {
+            let (tx_out_store, env) = init_tx_out_store();
+            for tx_out in &tx_outs {
+                let mut rw_transaction = env.begin_rw_txn().unwrap();
+                tx_out_store.push(tx_out, &mut rw_transaction).unwrap();
+                tx_out_store.get_root_merkle_hash(&rw_transaction).unwrap();
+                rw_transaction.commit().unwrap();
+            }
+        })
+    }
+
+    #[bench]
+    #[ignore]
+    // Append one TxOut per block and recompute the root from scratch after each
+    // append.
+    fn bench_append_with_recomputed_root(b: &mut Bencher) {
+        let tx_outs = get_tx_outs(NUM_BENCH_TX_OUTS);
+        b.iter(|| {
+            let (tx_out_store, env) = init_tx_out_store();
+            for tx_out in &tx_outs {
+                let mut rw_transaction = env.begin_rw_txn().unwrap();
+                tx_out_store.push(tx_out, &mut rw_transaction).unwrap();
+                tx_out_store
+                    .compute_root_merkle_hash_from_scratch(&rw_transaction)
+                    .unwrap();
+                rw_transaction.commit().unwrap();
+            }
+        })
+    }
 }
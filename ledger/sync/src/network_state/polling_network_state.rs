@@ -10,7 +10,8 @@ use mc_common::{
     ResponderId,
 };
 use mc_connection::{
-    BlockchainConnection, Connection, ConnectionManager, RetryableBlockchainConnection,
+    BlockchainConnection, Connection, ConnectionManager, ConnectionTimeouts,
+    RetryableBlockchainConnection,
 };
 use mc_consensus_scp::{
     core_types::Ballot, msg::ExternalizePayload, Msg, QuorumSet, SlotIndex, Topic,
@@ -23,7 +24,7 @@ use std::{
     str::FromStr,
     sync::{Arc, Condvar, Mutex},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 // Since PollingNetworkState is not a full-fledged consensus node, it does not
@@ -40,6 +41,9 @@ pub struct PollingNetworkState<BC: BlockchainConnection> {
     /// check logic.
     scp_network_state: SCPNetworkState<ResponderId>,
 
+    /// When the last poll finished, if any.
+    last_poll: Option<Instant>,
+
     /// Logger.
     logger: Logger,
 }
@@ -58,12 +62,30 @@ impl<BC: BlockchainConnection + 'static> PollingNetworkState<BC> {
         Self {
             manager,
             scp_network_state: SCPNetworkState::new(local_node_id, quorum_set),
+            last_poll: None,
             logger,
         }
     }
 
     /// Polls peers to find out the current state of the network.
     pub fn poll(&mut self) {
+        self.poll_impl(None)
+    }
+
+    /// Polls peers, waiting at most `timeout` for them to respond. Peers that
+    /// have not responded by then keep the block index they reported in
+    /// earlier polls.
+    pub fn poll_with_timeout(&mut self, timeout: Duration) {
+        self.poll_impl(Some(timeout))
+    }
+
+    /// How long ago the last poll finished, or None if there has not been one
+    /// yet.
+    pub fn last_poll_age(&self) -> Option<Duration> {
+        self.last_poll.map(|last_poll| last_poll.elapsed())
+    }
+
+    fn poll_impl(&mut self, max_wait: Option<Duration>) {
         type ResultsMap = HashMap<ResponderId, Option<BlockIndex>>;
         let results_and_condvar = Arc::new((Mutex::new(ResultsMap::default()), Condvar::new()));
 
@@ -111,14 +133,38 @@ impl<BC: BlockchainConnection + 'static> PollingNetworkState<BC> {
                 .expect("Failed spawning polling thread!");
         }
 
-        // Wait until we get all results.
+        // Wait until we get all results, or until the connection timeouts expire.
+        // Peers that have not responded by then are left out of this poll.
         let &(ref lock, ref condvar) = &*results_and_condvar;
         let num_peers = self.manager.len();
-        let results = condvar //.wait(lock.lock().unwrap()).unwrap();
-            .wait_while(lock.lock().unwrap(), |ref mut results| {
-                results.len() < num_peers
-            })
-            .expect("waiting on condvar failed");
+        let poll_timeout = match (Self::get_poll_timeout(self.manager.timeouts()), max_wait) {
+            (Some(poll_timeout), Some(max_wait)) => Some(poll_timeout.min(max_wait)),
+            (poll_timeout, max_wait) => poll_timeout.or(max_wait),
+        };
+        let results = match poll_timeout {
+            Some(timeout) => {
+                let (results, wait_result) = condvar
+                    .wait_timeout_while(lock.lock().unwrap(), timeout, |ref mut results| {
+                        results.len() < num_peers
+                    })
+                    .expect("waiting on condvar failed");
+                if wait_result.timed_out() {
+                    log::warn!(
+                        self.logger,
+                        "Polling timed out after {:?}, {} of {} peers responded",
+                        timeout,
+                        results.len(),
+                        num_peers
+                    );
+                }
+                results
+            }
+            None => condvar //.wait(lock.lock().unwrap()).unwrap();
+                .wait_while(lock.lock().unwrap(), |ref mut results| {
+                    results.len() < num_peers
+                })
+                .expect("waiting on condvar failed"),
+        };
 
         log::debug!(
             self.logger,
@@ -140,12 +186,23 @@ impl<BC: BlockchainConnection + 'static> PollingNetworkState<BC> {
                 ));
             }
         }
+
+        self.last_poll = Some(Instant::now());
     }
 
     pub fn peer_to_current_block_index(&self) -> &HashMap<ResponderId, BlockIndex> {
         self.scp_network_state.peer_to_current_slot()
     }
 
+    /// How long a poll waits for peers to respond: long enough to connect and
+    /// make one request. Polls wait indefinitely if there is no request
+    /// timeout.
+    fn get_poll_timeout(timeouts: ConnectionTimeouts) -> Option<Duration> {
+        timeouts
+            .request
+            .map(|request| request + timeouts.connect.unwrap_or_default())
+    }
+
     fn get_retry_iterator() -> Box<dyn Iterator<Item = Duration>> {
         // Start at 50ms, make 10 attempts (total would be 7150ms)
         Box::new(Fibonacci::from_millis(50).take(10))
@@ -178,3 +235,76 @@ impl<BC: BlockchainConnection> NetworkState for PollingNetworkState<BC> {
         self.scp_network_state.highest_block_index_on_network()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_common::logger::test_with_logger;
+    use mc_connection_test_utils::{test_client_uri, MockBlockchainConnection};
+    use mc_ledger_db::test_utils::get_mock_ledger;
+    use std::time::Instant;
+
+    #[test_with_logger]
+    // A peer that does not respond within the timeouts should not stall polling,
+    // which should complete with the peers that did respond.
+    fn test_poll_skips_unresponsive_peers(logger: Logger) {
+        let ledger = get_mock_ledger(10);
+        let peers = vec![
+            MockBlockchainConnection::new(test_client_uri(1), ledger.clone(), 0, None),
+            MockBlockchainConnection::new(test_client_uri(2), ledger.clone(), 0, None),
+            // Sleeps well past the request timeout.
+            MockBlockchainConnection::new(test_client_uri(3), ledger, 10_000, None),
+        ];
+        let quorum_set = QuorumSet::new_with_node_ids(
+            2,
+            peers
+                .iter()
+                .map(|peer| peer.uri().responder_id().unwrap())
+                .collect(),
+        );
+        let timeouts = ConnectionTimeouts {
+            connect: None,
+            request: Some(Duration::from_millis(500)),
+        };
+        let manager = ConnectionManager::new_with_timeouts(peers, timeouts, logger.clone());
+        let mut network_state = PollingNetworkState::new(quorum_set, manager, logger);
+
+        let start = Instant::now();
+        network_state.poll();
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let expected: HashMap<ResponderId, BlockIndex> = (1..=2)
+            .map(|node_id| (test_client_uri(node_id).responder_id().unwrap(), 9))
+            .collect();
+        assert_eq!(network_state.peer_to_current_block_index(), &expected);
+        assert_eq!(network_state.highest_block_index_on_network(), Some(9));
+    }
+
+    #[test_with_logger]
+    // Polling with a timeout should not wait for unresponsive peers, even when
+    // the connections have no request timeout.
+    fn test_poll_with_timeout(logger: Logger) {
+        let ledger = get_mock_ledger(10);
+        let peers = vec![
+            MockBlockchainConnection::new(test_client_uri(1), ledger.clone(), 0, None),
+            MockBlockchainConnection::new(test_client_uri(2), ledger, 10_000, None),
+        ];
+        let quorum_set = QuorumSet::new_with_node_ids(
+            1,
+            peers
+                .iter()
+                .map(|peer| peer.uri().responder_id().unwrap())
+                .collect(),
+        );
+        let manager = ConnectionManager::new(peers, logger.clone());
+        let mut network_state = PollingNetworkState::new(quorum_set, manager, logger);
+        assert_eq!(network_state.last_poll_age(), None);
+
+        let start = Instant::now();
+        network_state.poll_with_timeout(Duration::from_millis(500));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        assert!(network_state.last_poll_age().unwrap() < Duration::from_secs(5));
+        assert_eq!(network_state.highest_block_index_on_network(), Some(9));
+    }
+}
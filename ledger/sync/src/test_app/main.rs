@@ -40,10 +40,23 @@ fn _make_ledger_long(ledger: &mut LedgerDB) {
         &mut rng,
     );
 
+    // The generated blocks do not list their outputs in canonical order, so they
+    // are rebuilt with sorted outputs.
+    let mut parent_block = last_block;
     for (block, block_contents) in &results {
+        let mut block_contents = block_contents.clone();
+        block_contents.sort_outputs();
+        let block = Block::new_with_parent(
+            block.version,
+            &parent_block,
+            &block.root_element,
+            &block_contents,
+        );
+
         println!("block {} containing {:?}", block.index, block_contents);
-        ledger.append_block(block, block_contents, None).unwrap();
+        ledger.append_block(&block, &block_contents, None).unwrap();
         assert_eq!(block.cumulative_txo_count, ledger.num_txos().unwrap());
+        parent_block = block;
     }
 }
 
@@ -133,12 +133,14 @@ impl From<&mc_mobilecoind_api::GetMonitorStatusResponse> for JsonMonitorStatusRe
 #[derive(Serialize, Default, Debug)]
 pub struct JsonBalanceResponse {
     pub balance: String,
+    pub partial: bool,
 }
 
 impl From<&mc_mobilecoind_api::GetBalanceResponse> for JsonBalanceResponse {
     fn from(src: &mc_mobilecoind_api::GetBalanceResponse) -> Self {
         Self {
             balance: src.balance.to_string(),
+            partial: src.partial,
         }
     }
 }
@@ -151,6 +153,8 @@ pub struct JsonUnspentTxOut {
     pub value: String, // Needs to be String since Javascript ints are not 64 bit.
     pub attempted_spend_height: u64,
     pub attempted_spend_tombstone: u64,
+    #[serde(default)]
+    pub received_block_index: u64,
     pub monitor_id: String,
 }
 
@@ -163,6 +167,7 @@ impl From<&mc_mobilecoind_api::UnspentTxOut> for JsonUnspentTxOut {
             value: src.value.to_string(),
             attempted_spend_height: src.get_attempted_spend_height(),
             attempted_spend_tombstone: src.get_attempted_spend_tombstone(),
+            received_block_index: src.get_received_block_index(),
             monitor_id: hex::encode(&src.get_monitor_id()),
         }
     }
@@ -194,6 +199,7 @@ impl TryFrom<&JsonUnspentTxOut> for mc_mobilecoind_api::UnspentTxOut {
         );
         utxo.set_attempted_spend_height(src.attempted_spend_height);
         utxo.set_attempted_spend_tombstone(src.attempted_spend_tombstone);
+        utxo.set_received_block_index(src.received_block_index);
         utxo.set_monitor_id(
             hex::decode(&src.monitor_id)
                 .map_err(|err| format!("Failed to decode monitor id hex: {}", err))?,
@@ -944,6 +950,8 @@ pub struct JsonTxProposal {
     pub fee: u64,
     pub outlay_index_to_tx_out_index: Vec<(usize, usize)>,
     pub outlay_confirmation_numbers: Vec<Vec<u8>>,
+    #[serde(default)]
+    pub network_name: String,
 }
 
 impl From<&mc_mobilecoind_api::TxProposal> for JsonTxProposal {
@@ -964,6 +972,7 @@ impl From<&mc_mobilecoind_api::TxProposal> for JsonTxProposal {
             fee: src.get_fee(),
             outlay_index_to_tx_out_index: outlay_map,
             outlay_confirmation_numbers: src.get_outlay_confirmation_numbers().to_vec(),
+            network_name: src.get_network_name().to_owned(),
         }
     }
 }
@@ -1003,6 +1012,7 @@ impl TryFrom<&JsonTxProposal> for mc_mobilecoind_api::TxProposal {
         proposal.set_outlay_confirmation_numbers(RepeatedField::from_vec(
             src.outlay_confirmation_numbers.clone(),
         ));
+        proposal.set_network_name(src.network_name.clone());
 
         Ok(proposal)
     }
@@ -1336,6 +1346,7 @@ mod test {
             let value = 789;
             let attempted_spend_height = 1000;
             let attempted_spend_tombstone = 1234;
+            let received_block_index = 42;
 
             // make proto UnspentTxOut
             let mut unspent = mc_mobilecoind_api::UnspentTxOut::new();
@@ -1345,6 +1356,7 @@ mod test {
             unspent.set_value(value);
             unspent.set_attempted_spend_height(attempted_spend_height);
             unspent.set_attempted_spend_tombstone(attempted_spend_tombstone);
+            unspent.set_received_block_index(received_block_index);
             unspent
         };
 
@@ -22,6 +22,11 @@ pub use autogenerated_code::{mobilecoind_api::*, *};
 
 pub type MobilecoindUri = Uri<MobilecoindScheme>;
 
+/// Metadata key of the `ErrorCode` of a failed RPC, sent in the response
+/// metadata of the call as its numeric value. Failures without one, such as
+/// those raised by the transport, are `Unspecified`.
+pub const ERROR_CODE_METADATA_KEY: &str = "mobilecoind-error-code";
+
 /// Mobilecoind  Uri Scheme
 #[derive(Debug, Hash, Ord, PartialOrd, Eq, PartialEq, Clone)]
 pub struct MobilecoindScheme {}
@@ -4,10 +4,13 @@
 
 use mc_attest_core::{MrSignerVerifier, Verifier, DEBUG_ENCLAVE};
 use mc_common::logger::{create_app_logger, log, o, Logger};
-use mc_ledger_db::{Ledger, LedgerDB};
+use mc_ledger_db::{Error as LedgerError, Ledger, LedgerDB};
 use mc_ledger_sync::{LedgerSyncServiceThread, PollingNetworkState, ReqwestTransactionsFetcher};
 use mc_mobilecoind::{
-    config::Config, database::Database, payments::TransactionsManager, service::Service,
+    config::Config,
+    database::Database,
+    payments::TransactionsManager,
+    service::{Service, ServiceConfig},
 };
 use mc_watcher::{watcher::WatcherSyncThread, watcher_db::create_or_open_rw_watcher_db};
 use std::{
@@ -26,6 +29,11 @@ fn main() {
     let _sentry_guard = mc_common::sentry::init();
     let (logger, _global_logger_guard) = create_app_logger(o!());
 
+    // The databases can only be compacted while nothing has them open.
+    if config.compact_dbs {
+        compact_dbs(&config, &logger);
+    }
+
     let mut mr_signer_verifier =
         MrSignerVerifier::from(mc_consensus_enclave_measurement::sigstruct());
     mr_signer_verifier.allow_hardening_advisory("INTEL-SA-00334");
@@ -54,6 +62,11 @@ fn main() {
     // Create the ledger_db.
     let ledger_db = create_or_open_ledger_db(&config, &logger, &transactions_fetcher);
 
+    // Refuse to serve a ledger belonging to a different network.
+    config
+        .verify_origin_block_id(&ledger_db, &logger)
+        .expect("Could not verify the origin block of the ledger");
+
     // Start ledger sync thread unless running in offline mode.
     let _ledger_sync_service_thread = if config.offline {
         None
@@ -112,13 +125,22 @@ fn main() {
             let mobilecoind_db = Database::new(mobilecoind_db, logger.clone())
                 .expect("Could not open mobilecoind_db");
 
-            let transactions_manager = TransactionsManager::new(
+            let mut transactions_manager = TransactionsManager::new(
                 ledger_db.clone(),
                 mobilecoind_db.clone(),
                 peer_manager,
                 config.get_fog_resolver_factory(logger.clone()),
+                config.min_local_fee,
                 logger.clone(),
             );
+            transactions_manager
+                .set_max_pending_txs_per_monitor(config.max_pending_txs_per_monitor)
+                .expect("Invalid max-pending-txs-per-monitor");
+            if let Some(max_inputs) = config.max_inputs {
+                transactions_manager
+                    .set_max_inputs(max_inputs)
+                    .expect("Invalid max-inputs");
+            }
 
             let _api_server = Service::new(
                 ledger_db,
@@ -128,6 +150,14 @@ fn main() {
                 network_state,
                 listen_uri,
                 config.num_workers,
+                ServiceConfig {
+                    block_processing_retry: config.block_processing_retry(),
+                    network_state_refresh: config.network_state_refresh(),
+                    allow_account_key_export: config.allow_account_key_export,
+                    max_message_size: config.max_message_size,
+                    fee_recipient: None,
+                    network_name: config.network_name.clone(),
+                },
                 logger,
             );
 
@@ -151,13 +181,51 @@ fn main() {
     }
 }
 
+fn compact_dbs(config: &Config, logger: &Logger) {
+    if Path::new(&config.ledger_db).join("data.mdb").exists() {
+        let (size_before, size_after) =
+            LedgerDB::compact(&config.ledger_db).expect("Could not compact ledger_db");
+        log::info!(
+            logger,
+            "Ledger DB {:?} compacted from {} to {} bytes",
+            config.ledger_db,
+            size_before,
+            size_after
+        );
+    }
+
+    if let Some(mobilecoind_db) = &config.mobilecoind_db {
+        if mobilecoind_db.join("data.mdb").exists() {
+            let (size_before, size_after) =
+                Database::compact(mobilecoind_db).expect("Could not compact mobilecoind_db");
+            log::info!(
+                logger,
+                "Mobilecoind DB {:?} compacted from {} to {} bytes",
+                mobilecoind_db,
+                size_before,
+                size_after
+            );
+        }
+    }
+}
+
+/// Open the ledger db, recording the fees of the blocks appended to it if the
+/// fee view private key is configured.
+fn open_ledger_db(config: &Config) -> Result<LedgerDB, LedgerError> {
+    let mut ledger_db = LedgerDB::open(&config.ledger_db)?;
+    if let Some(fee_view_private_key) = config.fee_view_private_key {
+        ledger_db.set_fee_view_private_key(fee_view_private_key);
+    }
+    Ok(ledger_db)
+}
+
 fn create_or_open_ledger_db(
     config: &Config,
     logger: &Logger,
     transactions_fetcher: &ReqwestTransactionsFetcher,
 ) -> LedgerDB {
     // Attempt to open the ledger and see if it has anything in it.
-    if let Ok(ledger_db) = LedgerDB::open(&config.ledger_db) {
+    if let Ok(ledger_db) = open_ledger_db(config) {
         if let Ok(num_blocks) = ledger_db.num_blocks() {
             if num_blocks > 0 {
                 // Successfully opened a ledger that has blocks in it.
@@ -212,7 +280,7 @@ fn create_or_open_ledger_db(
             let block_data = transactions_fetcher
                 .get_origin_block_and_transactions()
                 .expect("Failed to download initial transactions");
-            let mut db = LedgerDB::open(&config.ledger_db).expect("Could not open ledger_db");
+            let mut db = open_ledger_db(config).expect("Could not open ledger_db");
             db.append_block(
                 block_data.block(),
                 block_data.contents(),
@@ -225,7 +293,7 @@ fn create_or_open_ledger_db(
 
     // Open ledger and verify it has (at least) the origin block.
     log::debug!(logger, "Opening Ledger DB {:?}", config.ledger_db);
-    let ledger_db = LedgerDB::open(&config.ledger_db)
+    let ledger_db = open_ledger_db(config)
         .unwrap_or_else(|_| panic!("Could not open ledger db inside {:?}", config.ledger_db));
 
     let num_blocks = ledger_db
@@ -2,15 +2,22 @@
 
 //! Configuration parameters for mobilecoind
 
+use crate::{service::NetworkStateRefresh, sync::BlockProcessingRetry};
 use displaydoc::Display;
 use mc_attest_core::{MrSignerVerifier, Verifier, DEBUG_ENCLAVE};
-use mc_common::{logger::Logger, ResponderId};
+use mc_common::{
+    logger::{log, Logger},
+    ResponderId,
+};
 use mc_connection::{ConnectionManager, HardcodedCredentialsProvider, ThickClient};
 use mc_consensus_scp::QuorumSet;
+use mc_crypto_keys::RistrettoPrivate;
 use mc_fog_report_connection::GrpcFogReportConnection;
 use mc_fog_report_validation::FogResolver;
+use mc_ledger_db::{Ledger, LedgerDB};
 use mc_mobilecoind_api::MobilecoindUri;
 use mc_sgx_css::Signature;
+use mc_transaction_core::BlockID;
 use mc_util_uri::{ConnectionUri, ConsensusClientUri, FogUri};
 #[cfg(feature = "ip-check")]
 use reqwest::{
@@ -48,6 +55,15 @@ pub struct Config {
     #[structopt(long, parse(try_from_str=parse_quorum_set_from_json))]
     quorum_set: Option<QuorumSet<ResponderId>>,
 
+    /// Path to a JSON file holding the quorum set for ledger syncing, in the
+    /// same format as `--quorum-set`.
+    #[structopt(
+        long,
+        conflicts_with = "quorum-set",
+        parse(try_from_str=load_quorum_set_file)
+    )]
+    quorum_set_file: Option<QuorumSet<ResponderId>>,
+
     /// URLs to use for transaction data.
     ///
     /// For example: https://s3-us-west-1.amazonaws.com/mobilecoin.chain/node1.test.mobilecoin.com/
@@ -67,34 +83,137 @@ pub struct Config {
     #[structopt(long)]
     pub listen_uri: Option<MobilecoindUri>,
 
-    /// Number of worker threads to use for view key scanning.
-    /// Defaults to number of logical CPU cores.
+    /// Number of worker threads to use for view key scanning, bounding how many
+    /// monitors are scanned concurrently. Defaults to number of logical CPU
+    /// cores. A value of 1 scans monitors one at a time.
     #[structopt(long)]
     pub num_workers: Option<usize>,
 
+    /// How many times processing a block for a monitor is retried when it
+    /// fails, before giving up until the next sync cycle.
+    #[structopt(long, default_value = "3")]
+    pub block_processing_retries: usize,
+
+    /// How many milliseconds to wait before retrying to process a block. The
+    /// delay doubles with every further retry.
+    #[structopt(long, default_value = "100", parse(try_from_str=parse_duration_in_millis))]
+    pub block_processing_retry_backoff: Duration,
+
+    /// How many milliseconds old the polled network state may be when an RPC
+    /// reporting the network height is answered. Older state is re-polled
+    /// first. If not set, RPCs answer from the state polled every
+    /// `--poll-interval`.
+    #[structopt(long, parse(try_from_str=parse_duration_in_millis))]
+    pub network_state_max_age: Option<Duration>,
+
+    /// How many milliseconds a re-poll of stale network state waits for peers
+    /// before answering from the previously polled state.
+    #[structopt(long, default_value = "1000", parse(try_from_str=parse_duration_in_millis))]
+    pub network_state_refresh_timeout: Duration,
+
+    /// Name of the network mobilecoind is connected to, e.g. "main" or
+    /// "test". When set, transaction proposals are labeled with it, and
+    /// proposals labeled for a different network are refused on submission.
+    /// This guards clients against submitting a proposal to the wrong
+    /// mobilecoind. The label is not signed, so it does not stop anyone from
+    /// replaying a transaction on another network.
+    #[structopt(long)]
+    pub network_name: Option<String>,
+
+    /// Hex-encoded id of the origin block of the network mobilecoind is
+    /// connected to. When set, mobilecoind refuses to start if the origin
+    /// block of its ledger has a different id.
+    #[structopt(long, parse(try_from_str=parse_block_id))]
+    pub origin_block_id: Option<BlockID>,
+
+    /// Start even if the origin block of the ledger does not have the id given
+    /// by `--origin-block-id`. Only meant for ephemeral dev networks whose
+    /// origin block is regenerated often; never set it in production.
+    #[structopt(long)]
+    pub skip_origin_check: bool,
+
+    /// Maximum number of transactions a single monitor may have pending at
+    /// once. Further transactions are refused until pending ones land or
+    /// expire. Unlimited if not set.
+    #[structopt(long)]
+    pub max_pending_txs_per_monitor: Option<usize>,
+
+    /// Maximum number of inputs transactions built by mobilecoind may use.
+    /// Must be greater than zero, and is capped at the limit consensus
+    /// enforces, which is also the default.
+    #[structopt(long)]
+    pub max_inputs: Option<usize>,
+
+    /// Lowest fee, in picoMOB, transactions built by mobilecoind pay. Fees
+    /// below it, whether requested or required by the network, are raised to
+    /// it.
+    #[structopt(long, default_value = "0")]
+    pub min_local_fee: u64,
+
+    /// Compact the ledger and mobilecoind databases before opening them,
+    /// reclaiming the space left by deleted data. LMDB never shrinks its files
+    /// on its own.
+    #[structopt(long)]
+    pub compact_dbs: bool,
+
     /// Offline mode.
     #[structopt(long)]
     pub offline: bool,
 
+    /// Allow the ExportAccountKey API to return the account keys of monitors.
+    /// Disabled by default, since anyone with access to the API could then
+    /// retrieve the private keys of every monitored account.
+    #[structopt(long)]
+    pub allow_account_key_export: bool,
+
+    /// Maximum size, in bytes, of a gRPC message the API sends or receives.
+    /// Defaults to the gRPC limits (4 MiB for received messages).
+    ///
+    /// Raising it lets large responses, such as the UTXO list of a busy
+    /// account, go through instead of failing with RESOURCE_EXHAUSTED, at the
+    /// cost of letting a single request or response use that much memory.
+    #[structopt(long)]
+    pub max_message_size: Option<usize>,
+
     /// Fog ingest enclave CSS file (needed in order to enable sending
     /// transactions to fog recipients).
     #[structopt(long, parse(try_from_str=load_css_file))]
     pub fog_ingest_enclave_css: Option<Signature>,
+
+    /// Hex-encoded view private key of the fee recipient, used to record the
+    /// fees collected by each block appended to the ledger db.
+    #[structopt(long, parse(try_from_str=parse_ristretto_private_from_hex))]
+    pub fee_view_private_key: Option<RistrettoPrivate>,
 }
 
 fn parse_duration_in_seconds(src: &str) -> Result<Duration, std::num::ParseIntError> {
     Ok(Duration::from_secs(u64::from_str(src)?))
 }
 
-fn parse_quorum_set_from_json(src: &str) -> Result<QuorumSet<ResponderId>, String> {
-    let quorum_set: QuorumSet<ResponderId> = serde_json::from_str(src)
-        .map_err(|err| format!("Error parsing quorum set {}: {:?}", src, err))?;
+fn parse_duration_in_millis(src: &str) -> Result<Duration, std::num::ParseIntError> {
+    Ok(Duration::from_millis(u64::from_str(src)?))
+}
 
-    if !quorum_set.is_valid() {
-        return Err(format!("Invalid quorum set: {:?}", quorum_set));
-    }
+fn parse_block_id(src: &str) -> Result<BlockID, String> {
+    let bytes = hex::decode(src).map_err(|err| format!("Invalid block id {}: {}", src, err))?;
+    BlockID::try_from(&bytes[..]).map_err(|err| format!("Invalid block id {}: {}", src, err))
+}
+
+fn parse_ristretto_private_from_hex(src: &str) -> Result<RistrettoPrivate, String> {
+    let bytes = hex::decode(src).map_err(|err| format!("Invalid private key: {}", err))?;
+    RistrettoPrivate::try_from(&bytes[..]).map_err(|err| format!("Invalid private key: {:?}", err))
+}
 
-    Ok(quorum_set)
+fn parse_quorum_set_from_json(src: &str) -> Result<QuorumSet<ResponderId>, String> {
+    QuorumSet::from_json(src.as_bytes())
+        .map_err(|err| format!("Invalid quorum set {}: {}", src, err))
+}
+
+fn load_quorum_set_file(filename: &str) -> Result<QuorumSet<ResponderId>, String> {
+    let file = fs::File::open(filename)
+        .map_err(|err| format!("Failed opening file '{}': {}", filename, err))?;
+    QuorumSet::from_json(file)
+        .map_err(|err| format!("Invalid quorum set in '{}': {}", filename, err))
 }
 
 fn load_css_file(filename: &str) -> Result<Signature, String> {
@@ -118,6 +237,12 @@ pub enum ConfigError {
 
     /// Data missing in the response {0}
     DataMissing(String),
+
+    /// Ledger error {0}
+    Ledger(mc_ledger_db::Error),
+
+    /// Origin block id {1} does not match the expected {0}
+    OriginBlockMismatch(BlockID, BlockID),
 }
 
 impl From<serde_json::Error> for ConfigError {
@@ -132,10 +257,69 @@ impl From<reqwest::Error> for ConfigError {
     }
 }
 
+impl From<mc_ledger_db::Error> for ConfigError {
+    fn from(e: mc_ledger_db::Error) -> Self {
+        Self::Ledger(e)
+    }
+}
+
 impl Config {
+    /// How processing a block for a monitor is retried when it fails.
+    pub fn block_processing_retry(&self) -> BlockProcessingRetry {
+        BlockProcessingRetry {
+            max_retries: self.block_processing_retries,
+            initial_backoff: self.block_processing_retry_backoff,
+        }
+    }
+
+    /// When stale network state is re-polled before answering RPCs, if at all.
+    pub fn network_state_refresh(&self) -> Option<NetworkStateRefresh> {
+        self.network_state_max_age
+            .map(|max_age| NetworkStateRefresh {
+                max_age,
+                timeout: self.network_state_refresh_timeout,
+            })
+    }
+
+    /// Check that the origin block of the ledger has the id given by
+    /// `--origin-block-id`, if any. With `--skip-origin-check`, a mismatch is
+    /// only logged.
+    pub fn verify_origin_block_id(
+        &self,
+        ledger_db: &LedgerDB,
+        logger: &Logger,
+    ) -> Result<(), ConfigError> {
+        if self.skip_origin_check {
+            log::warn!(
+                logger,
+                "Origin block check is disabled by --skip-origin-check. This is only safe on ephemeral test networks!"
+            );
+        }
+
+        if let Some(expected) = &self.origin_block_id {
+            if !ledger_db.verify_origin_block_hash(expected)? {
+                let actual = ledger_db.get_block(0)?.id;
+                if !self.skip_origin_check {
+                    return Err(ConfigError::OriginBlockMismatch(expected.clone(), actual));
+                }
+                log::warn!(
+                    logger,
+                    "IGNORING ORIGIN BLOCK MISMATCH: expected {}, ledger has {}. The ledger may belong to a different network!",
+                    expected,
+                    actual
+                );
+            }
+        }
+        Ok(())
+    }
+
     pub fn quorum_set(&self) -> QuorumSet<ResponderId> {
         // If we have an explicit quorum set, use that.
-        if let Some(quorum_set) = &self.quorum_set {
+        if let Some(quorum_set) = self
+            .quorum_set
+            .as_ref()
+            .or_else(|| self.quorum_set_file.as_ref())
+        {
             return quorum_set.clone();
         }
 
@@ -318,3 +502,125 @@ impl PeersConfig {
         ConnectionManager::new(peers, logger.clone())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{get_origin_block_id, get_test_databases};
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn config_with_origin_block_id(origin_block_id: &BlockID) -> Config {
+        Config::from_iter_safe(&[
+            "mobilecoind",
+            "--offline",
+            "--origin-block-id",
+            hex::encode(origin_block_id).as_str(),
+        ])
+        .unwrap()
+    }
+
+    #[test_with_logger]
+    fn test_verify_origin_block_id(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let (ledger_db, _mobilecoind_db) = get_test_databases(3, &[], 2, logger.clone(), &mut rng);
+        let origin_block_id = get_origin_block_id(&ledger_db);
+
+        // Not configured.
+        let config = Config::from_iter_safe(&["mobilecoind", "--offline"]).unwrap();
+        assert!(config.verify_origin_block_id(&ledger_db, &logger).is_ok());
+
+        // Matching.
+        let config = config_with_origin_block_id(&origin_block_id);
+        assert!(config.verify_origin_block_id(&ledger_db, &logger).is_ok());
+
+        // Mismatching.
+        let wrong_block_id = BlockID([7u8; 32]);
+        let config = config_with_origin_block_id(&wrong_block_id);
+        match config.verify_origin_block_id(&ledger_db, &logger) {
+            Err(ConfigError::OriginBlockMismatch(expected, actual)) => {
+                assert_eq!(expected, wrong_block_id);
+                assert_eq!(actual, origin_block_id);
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_fee_view_private_key() {
+        let config = Config::from_iter_safe(&["mobilecoind", "--offline"]).unwrap();
+        assert!(config.fee_view_private_key.is_none());
+
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let key = RistrettoPrivate::from_random(&mut rng);
+        let config = Config::from_iter_safe(&[
+            "mobilecoind",
+            "--offline",
+            "--fee-view-private-key",
+            hex::encode(key.to_bytes()).as_str(),
+        ])
+        .unwrap();
+        assert_eq!(
+            config.fee_view_private_key.unwrap().to_bytes(),
+            key.to_bytes()
+        );
+
+        // Keys of the wrong length are rejected.
+        assert!(Config::from_iter_safe(&[
+            "mobilecoind",
+            "--offline",
+            "--fee-view-private-key",
+            "0102",
+        ])
+        .is_err());
+    }
+
+    #[test_with_logger]
+    fn test_skip_origin_check(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([24u8; 32]);
+        let (ledger_db, _mobilecoind_db) = get_test_databases(3, &[], 2, logger.clone(), &mut rng);
+        let wrong_block_id = BlockID([7u8; 32]);
+
+        // Enforced by default.
+        let config = config_with_origin_block_id(&wrong_block_id);
+        assert!(!config.skip_origin_check);
+        match config.verify_origin_block_id(&ledger_db, &logger) {
+            Err(ConfigError::OriginBlockMismatch(..)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Bypassed with a warning when the flag is set.
+        let config = Config::from_iter_safe(&[
+            "mobilecoind",
+            "--offline",
+            "--origin-block-id",
+            hex::encode(&wrong_block_id).as_str(),
+            "--skip-origin-check",
+        ])
+        .unwrap();
+        assert!(config.skip_origin_check);
+        assert!(config.verify_origin_block_id(&ledger_db, &logger).is_ok());
+    }
+
+    #[test]
+    fn test_network_state_refresh() {
+        let config = Config::from_iter_safe(&["mobilecoind", "--offline"]).unwrap();
+        assert_eq!(config.network_state_refresh(), None);
+
+        let config = Config::from_iter_safe(&[
+            "mobilecoind",
+            "--offline",
+            "--network-state-max-age",
+            "2000",
+        ])
+        .unwrap();
+        assert_eq!(
+            config.network_state_refresh(),
+            Some(NetworkStateRefresh {
+                max_age: Duration::from_millis(2000),
+                timeout: Duration::from_millis(1000),
+            })
+        );
+    }
+}
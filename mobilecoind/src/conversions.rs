@@ -4,7 +4,7 @@
 //! types.
 
 use crate::{
-    payments::{Outlay, TxProposal},
+    payments::{FeePriority, Outlay, TxProposal},
     utxo_store::UnspentTxOut,
 };
 use mc_account_keys::PublicAddress;
@@ -18,6 +18,16 @@ use mc_transaction_core::{
 use protobuf::RepeatedField;
 use std::convert::TryFrom;
 
+impl From<mc_mobilecoind_api::FeePriority> for FeePriority {
+    fn from(src: mc_mobilecoind_api::FeePriority) -> Self {
+        match src {
+            mc_mobilecoind_api::FeePriority::Low => FeePriority::Low,
+            mc_mobilecoind_api::FeePriority::Normal => FeePriority::Normal,
+            mc_mobilecoind_api::FeePriority::High => FeePriority::High,
+        }
+    }
+}
+
 impl From<&UnspentTxOut> for mc_mobilecoind_api::UnspentTxOut {
     fn from(src: &UnspentTxOut) -> Self {
         let mut dst = Self::new();
@@ -28,6 +38,7 @@ impl From<&UnspentTxOut> for mc_mobilecoind_api::UnspentTxOut {
         dst.set_value(src.value);
         dst.set_attempted_spend_height(src.attempted_spend_height);
         dst.set_attempted_spend_tombstone(src.attempted_spend_tombstone);
+        dst.set_received_block_index(src.received_block_index);
 
         dst
     }
@@ -43,6 +54,7 @@ impl TryFrom<&mc_mobilecoind_api::UnspentTxOut> for UnspentTxOut {
         let value = src.value;
         let attempted_spend_height = src.attempted_spend_height;
         let attempted_spend_tombstone = src.attempted_spend_tombstone;
+        let received_block_index = src.received_block_index;
 
         Ok(Self {
             tx_out,
@@ -51,6 +63,7 @@ impl TryFrom<&mc_mobilecoind_api::UnspentTxOut> for UnspentTxOut {
             value,
             attempted_spend_height,
             attempted_spend_tombstone,
+            received_block_index,
         })
     }
 }
@@ -198,6 +211,7 @@ mod test {
         let value = 789;
         let attempted_spend_height = 1000;
         let attempted_spend_tombstone = 1234;
+        let received_block_index = 42;
 
         let rust = UnspentTxOut {
             tx_out: tx_out.clone(),
@@ -206,6 +220,7 @@ mod test {
             value,
             attempted_spend_height,
             attempted_spend_tombstone,
+            received_block_index,
         };
 
         let proto = mc_mobilecoind_api::UnspentTxOut::from(&rust);
@@ -219,6 +234,7 @@ mod test {
         assert_eq!(value, proto.value);
         assert_eq!(attempted_spend_height, proto.attempted_spend_height);
         assert_eq!(attempted_spend_tombstone, proto.attempted_spend_tombstone);
+        assert_eq!(received_block_index, proto.received_block_index);
 
         // Proto -> Rust
         assert_eq!(rust, UnspentTxOut::try_from(&proto).unwrap());
@@ -282,6 +298,7 @@ mod test {
             let value = 789;
             let attempted_spend_height = 1000;
             let attempted_spend_tombstone = 1234;
+            let received_block_index = 42;
 
             UnspentTxOut {
                 tx_out: tx_out.clone(),
@@ -290,6 +307,7 @@ mod test {
                 value,
                 attempted_spend_height,
                 attempted_spend_tombstone,
+                received_block_index,
             }
         };
 
@@ -0,0 +1,23 @@
+// Copyright (c) 2018-2021 The MobileCoin Foundation
+
+use mc_util_metrics::{Histogram, IntCounter, IntGauge, OpMetrics};
+
+lazy_static::lazy_static! {
+    pub static ref OP_COUNTERS: OpMetrics = OpMetrics::new_and_registered("mobilecoind");
+}
+
+lazy_static::lazy_static! {
+    // Time spent waiting to begin a database write transaction (in seconds).
+    pub static ref DB_WRITE_LOCK_WAIT_TIME: Histogram = OP_COUNTERS.histogram("db_write_lock_wait_time");
+
+    // Number of database write transactions that had to wait for the write lock
+    // held by another writer.
+    pub static ref DB_WRITE_LOCK_CONTENDED: IntCounter = OP_COUNTERS.counter("db_write_lock_contended");
+
+    // Number of blocks scanned by monitors. A block scanned for several monitors
+    // is counted once per monitor.
+    pub static ref BLOCKS_SCANNED: IntCounter = OP_COUNTERS.counter("blocks_scanned");
+
+    // Blocks scanned per second, averaged over a recent window.
+    pub static ref SCAN_BLOCKS_PER_SECOND: IntGauge = OP_COUNTERS.gauge("scan_blocks_per_second");
+}
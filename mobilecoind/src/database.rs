@@ -3,26 +3,45 @@
 //! The mobilecoind database
 
 use crate::{
+    counters,
     db_crypto::DbCryptoProvider,
     error::Error,
+    monitor_snapshot::{MonitorSnapshot, MonitorStoreSnapshot, ProcessedBlockSnapshot},
     monitor_store::{MonitorData, MonitorId, MonitorStore},
-    processed_block_store::{ProcessedBlockStore, ProcessedTxOut},
+    pending_tx_store::{PendingTx, PendingTxStore},
+    processed_block_store::{ProcessedBlockStore, ProcessedTxOut, ProcessedTxOutDirection},
     subaddress_store::{SubaddressId, SubaddressSPKId, SubaddressStore},
     utxo_store::{UtxoId, UtxoStore},
 };
 
 use crate::utxo_store::UnspentTxOut;
-use lmdb::{Environment, Transaction};
+use lmdb::{Environment, RwTransaction, Transaction};
+use mc_account_keys::AccountKey;
 use mc_common::{
     logger::{log, Logger},
     HashMap,
 };
-use mc_transaction_core::ring_signature::KeyImage;
+use mc_crypto_keys::{CompressedRistrettoPublic, RistrettoPublic};
+use mc_ledger_db::Ledger;
+use mc_transaction_core::{ring_signature::KeyImage, tx::TxOutConfirmationNumber};
 use mc_util_lmdb::{MetadataStore, MetadataStoreSettings};
-use std::{path::Path, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    io::{Read, Write},
+    ops::Range,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 // LMDB Constants
 const MAX_LMDB_FILE_SIZE: usize = 1_099_511_627_776; // 1 TB
+const MAX_LMDB_DBS: u32 = 20;
+
+/// Beginning a write transaction that takes at least this long is counted as
+/// having contended for the write lock with another writer.
+const WRITE_LOCK_CONTENTION_THRESHOLD: Duration = Duration::from_millis(1);
 
 /// Metadata store settings that are used for version control.
 #[derive(Clone, Default, Debug)]
@@ -42,6 +61,92 @@ impl MetadataStoreSettings for MobilecoindDbMetadataStoreSettings {
     const DB_NAME: &'static str = "mobilecoind_db_metadata";
 }
 
+/// Options for `Database::add_monitor_with_options`.
+#[derive(Clone, Debug, Default)]
+pub struct AddMonitorOptions {
+    /// Reject the monitor with `Error::MonitorNameExists` if a different
+    /// monitor already has the same name. Unnamed monitors are never
+    /// considered duplicates. Disabled by default.
+    pub require_unique_name: bool,
+}
+
+/// An output received by a monitor, as returned by
+/// `Database::outputs_for_subaddress`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MatchedTxOut {
+    /// The public key of the TxOut.
+    pub public_key: CompressedRistrettoPublic,
+
+    /// Key image of the TxOut.
+    pub key_image: KeyImage,
+
+    /// Value of the TxOut.
+    pub value: u64,
+
+    /// Index of the block the TxOut was received in.
+    pub block_index: u64,
+
+    /// Index of the block the TxOut was spent in, or None if it is unspent.
+    pub spent_block_index: Option<u64>,
+}
+
+impl MatchedTxOut {
+    pub fn is_spent(&self) -> bool {
+        self.spent_block_index.is_some()
+    }
+}
+
+/// The outputs a monitor received and spent in a range of blocks, as returned
+/// by `Database::utxo_delta`. An output both received and spent within the
+/// range appears in both lists.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct UtxoDelta {
+    /// Outputs received in the range, ordered by block.
+    pub added: Vec<ProcessedTxOut>,
+
+    /// Outputs spent in the range, ordered by block.
+    pub spent: Vec<ProcessedTxOut>,
+}
+
+/// The unspent outputs of a monitor across all of its subaddresses, as returned
+/// by `Database::get_account_state`. Both fields are read together, so the
+/// outputs are exactly those of the blocks the monitor processed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AccountState {
+    /// The monitor data, including how far the monitor has synced.
+    pub data: MonitorData,
+
+    /// The unspent outputs of the monitor, ordered by subaddress.
+    pub utxos: Vec<UnspentTxOut>,
+}
+
+/// A receipt given by the sender of a payment to its recipient.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Receipt {
+    /// The public key of the TxOut sent to the recipient.
+    pub tx_public_key: CompressedRistrettoPublic,
+
+    /// Confirmation number proving the sender created the TxOut.
+    pub confirmation_number: TxOutConfirmationNumber,
+
+    /// Tombstone block of the transaction that contains the TxOut.
+    pub tombstone_block: u64,
+}
+
+/// The status of the payment a `Receipt` refers to, as returned by
+/// `Database::verify_receipt`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ReceiptStatus {
+    /// The output was received by the monitor in the given block.
+    Confirmed(u64),
+
+    /// The output has not been received yet, but still can be.
+    Pending,
+
+    /// The tombstone block passed without the output being received.
+    Failed,
+}
+
 /// The main mobilecoind database.
 #[derive(Clone)]
 pub struct Database {
@@ -63,6 +168,9 @@ pub struct Database {
     /// Processed block store.
     processed_block_store: ProcessedBlockStore,
 
+    /// Pending transactions store.
+    pending_tx_store: PendingTxStore,
+
     /// Metadata store.
     metadata_store: MetadataStore<MobilecoindDbMetadataStoreSettings>,
 
@@ -74,7 +182,7 @@ impl Database {
     pub fn new<P: AsRef<Path>>(path: P, logger: Logger) -> Result<Self, Error> {
         let env = Arc::new(
             Environment::new()
-                .set_max_dbs(10)
+                .set_max_dbs(MAX_LMDB_DBS)
                 .set_map_size(MAX_LMDB_FILE_SIZE)
                 .open(path.as_ref())?,
         );
@@ -100,17 +208,53 @@ impl Database {
         let subaddress_store = SubaddressStore::new(env.clone(), logger.clone())?;
         let utxo_store = UtxoStore::new(env.clone(), logger.clone())?;
         let processed_block_store = ProcessedBlockStore::new(env.clone(), logger.clone())?;
+        let pending_tx_store = PendingTxStore::new(env.clone(), logger.clone())?;
 
-        Ok(Self {
+        let db = Self {
             env,
             crypto_provider,
             monitor_store,
             subaddress_store,
             utxo_store,
             processed_block_store,
+            pending_tx_store,
             metadata_store,
             logger,
-        })
+        };
+
+        // An encrypted database can only be validated once it is unlocked.
+        if db.is_unlocked() {
+            db.quarantine_invalid_monitors()?;
+        }
+
+        Ok(db)
+    }
+
+    /// Begin a write transaction, recording how long it waited for the write
+    /// lock of the environment.
+    fn begin_rw_txn(&self) -> Result<RwTransaction, Error> {
+        let start = Instant::now();
+        let db_txn = self.env.begin_rw_txn()?;
+        let waited = start.elapsed();
+
+        counters::DB_WRITE_LOCK_WAIT_TIME.observe(waited.as_secs_f64());
+        if waited >= WRITE_LOCK_CONTENTION_THRESHOLD {
+            counters::DB_WRITE_LOCK_CONTENDED.inc();
+        }
+
+        Ok(db_txn)
+    }
+
+    /// Compact the database in the given path, reclaiming the space left by
+    /// deleted data such as removed monitors, which LMDB otherwise keeps in its
+    /// file. The database must not be open, in this process or any other.
+    /// Returns the size of the data file before and after compaction.
+    pub fn compact<P: AsRef<Path>>(path: P) -> Result<(u64, u64), Error> {
+        Ok(mc_util_lmdb::compact_environment(
+            path.as_ref(),
+            MAX_LMDB_DBS,
+            MAX_LMDB_FILE_SIZE,
+        )?)
     }
 
     /// Check if data is currently being encrypted.
@@ -128,7 +272,33 @@ impl Database {
     /// database. This also stores it for future encryption/decryption
     /// operations.
     pub fn check_and_store_password(&self, password: &[u8]) -> Result<(), Error> {
-        Ok(self.crypto_provider.check_and_store_password(password)?)
+        self.crypto_provider.check_and_store_password(password)?;
+        self.quarantine_invalid_monitors()
+    }
+
+    /// Mark monitors whose data fails validation as errored, so that they are
+    /// skipped instead of failing every operation that lists monitors.
+    fn quarantine_invalid_monitors(&self) -> Result<(), Error> {
+        let mut db_txn = self.begin_rw_txn()?;
+        let quarantined = self.monitor_store.quarantine_invalid(&mut db_txn)?;
+        db_txn.commit()?;
+
+        if !quarantined.is_empty() {
+            log::error!(
+                self.logger,
+                "Quarantined {} monitors with invalid data",
+                quarantined.len()
+            );
+        }
+        Ok(())
+    }
+
+    /// Get the monitors that were quarantined because their data failed
+    /// validation, along with a description of the problem. Errored monitors
+    /// are not synced, and are not listed by `get_monitor_map`.
+    pub fn errored_monitors(&self) -> Result<HashMap<MonitorId, String>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.monitor_store.get_errored(&db_txn)
     }
 
     /// Re-encrypt the encrypted parts of the database with a new password.
@@ -136,7 +306,7 @@ impl Database {
     /// since part of the re-encryption process relies on being able to
     /// decrypt the existing data.
     pub fn re_encrypt(&self, new_password: &[u8]) -> Result<(), Error> {
-        let mut db_txn = self.env.begin_rw_txn()?;
+        let mut db_txn = self.begin_rw_txn()?;
 
         // Currently only the monitor store stores encrypted data.
         self.monitor_store.re_encrypt(&mut db_txn, new_password)?;
@@ -148,9 +318,33 @@ impl Database {
     }
 
     pub fn add_monitor(&self, data: &MonitorData) -> Result<MonitorId, Error> {
+        self.add_monitor_with_options(data, &AddMonitorOptions::default())
+    }
+
+    /// Add a monitor, with additional checks configured by `options`.
+    pub fn add_monitor_with_options(
+        &self,
+        data: &MonitorData,
+        options: &AddMonitorOptions,
+    ) -> Result<MonitorId, Error> {
         mc_common::trace_time!(self.logger, "add_monitor");
 
-        let mut db_txn = self.env.begin_rw_txn()?;
+        let mut db_txn = self.begin_rw_txn()?;
+
+        // The name check happens in the same write transaction as the insert, so
+        // two monitors with the same name cannot be added concurrently.
+        if options.require_unique_name && !data.name.is_empty() {
+            let monitor_id = MonitorId::from(data);
+            let name_taken = self
+                .monitor_store
+                .get_map(&db_txn)?
+                .into_iter()
+                .any(|(id, other)| id != monitor_id && other.name == data.name);
+            if name_taken {
+                return Err(Error::MonitorNameExists(data.name.clone()));
+            }
+        }
+
         let id = self.monitor_store.add(&mut db_txn, data)?;
 
         //for index in 0..data.num_subaddresses {
@@ -163,16 +357,45 @@ impl Database {
         Ok(id)
     }
 
+    /// Add a monitor that starts scanning at the current tip of the ledger.
+    ///
+    /// Only blocks appended to the ledger after this call are scanned. Any
+    /// funds the account received in earlier blocks are skipped and will
+    /// not show up in the monitor's balance. Use `add_monitor` with an
+    /// explicit `first_block` when importing an account that may have
+    /// existing funds.
+    pub fn add_monitor_from_tip(
+        &self,
+        ledger_db: &impl Ledger,
+        account_key: &AccountKey,
+        subaddress_range: Range<u64>,
+    ) -> Result<MonitorId, Error> {
+        let first_block = ledger_db.num_blocks()?;
+        let num_subaddresses = subaddress_range.end.saturating_sub(subaddress_range.start);
+
+        let data = MonitorData::new(
+            account_key.clone(),
+            subaddress_range.start,
+            num_subaddresses,
+            first_block,
+            "",
+        )?;
+
+        self.add_monitor(&data)
+    }
+
     pub fn remove_monitor(&self, id: &MonitorId) -> Result<(), Error> {
         mc_common::trace_time!(self.logger, "remove_monitor");
 
-        let mut db_txn = self.env.begin_rw_txn()?;
+        let mut db_txn = self.begin_rw_txn()?;
 
         let data = self.monitor_store.get_data(&db_txn, &id)?;
 
         for index in data.subaddress_indexes() {
             self.subaddress_store.delete(&mut db_txn, &data, index)?;
             self.utxo_store.remove_utxos(&mut db_txn, id, index)?;
+            self.monitor_store
+                .clear_subaddress_used(&mut db_txn, id, index)?;
         }
 
         self.processed_block_store.remove(&mut db_txn, id)?;
@@ -184,6 +407,17 @@ impl Database {
         Ok(())
     }
 
+    /// Pause or resume syncing a monitor. A paused monitor keeps its data and
+    /// picks up from its `next_block` once resumed.
+    pub fn set_monitor_paused(&self, id: &MonitorId, paused: bool) -> Result<(), Error> {
+        let mut db_txn = self.begin_rw_txn()?;
+        let mut data = self.monitor_store.get_data(&db_txn, id)?;
+        data.paused = paused;
+        self.monitor_store.set_data(&mut db_txn, id, &data)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
     pub fn get_monitor_data(&self, id: &MonitorId) -> Result<MonitorData, Error> {
         let db_txn = self.env.begin_ro_txn()?;
         self.monitor_store.get_data(&db_txn, id)
@@ -199,6 +433,139 @@ impl Database {
         self.monitor_store.get_ids(&db_txn)
     }
 
+    /// Get all monitors along with how many blocks they lag behind
+    /// `ledger_tip`, the number of blocks in the ledger. Monitors are sorted
+    /// by lag, furthest behind first. A monitor that is ahead of `ledger_tip`
+    /// has a lag of zero.
+    pub fn monitors_by_lag(&self, ledger_tip: u64) -> Result<Vec<(MonitorId, u64)>, Error> {
+        let mut monitors_by_lag: Vec<(MonitorId, u64)> = self
+            .get_monitor_map()?
+            .into_iter()
+            .map(|(monitor_id, data)| (monitor_id, ledger_tip.saturating_sub(data.next_block)))
+            .collect();
+        monitors_by_lag
+            .sort_by(|(id_a, lag_a), (id_b, lag_b)| lag_b.cmp(lag_a).then_with(|| id_a.cmp(id_b)));
+        Ok(monitors_by_lag)
+    }
+
+    /// Get the lowest subaddress index of a monitor that has not been handed
+    /// out by this method before and has not received any outputs, and mark it
+    /// as used. If every subaddress in the monitor's range is used, the range
+    /// is extended by one subaddress.
+    ///
+    /// All of this happens inside a single write transaction, so concurrent
+    /// callers always get distinct subaddresses.
+    pub fn get_next_unused_subaddress(&self, monitor_id: &MonitorId) -> Result<u64, Error> {
+        let mut db_txn = self.begin_rw_txn()?;
+
+        let mut data = self.monitor_store.get_data(&db_txn, monitor_id)?;
+
+        let mut unused_index = None;
+        for index in data.subaddress_indexes() {
+            if self
+                .monitor_store
+                .is_subaddress_used(&db_txn, monitor_id, index)?
+            {
+                continue;
+            }
+
+            // Subaddresses that received funds before usage was tracked are also used.
+            if !self
+                .utxo_store
+                .get_utxos(&db_txn, monitor_id, index)?
+                .is_empty()
+            {
+                self.monitor_store
+                    .mark_subaddress_used(&mut db_txn, monitor_id, index)?;
+                continue;
+            }
+
+            unused_index = Some(index);
+            break;
+        }
+
+        let index = match unused_index {
+            Some(index) => index,
+            None => {
+                // Grow the monitored range to include one more subaddress. The monitor id
+                // only hashes the range the monitor was created with, so it does not change.
+                let index = data.first_subaddress + data.num_subaddresses;
+                data.extend_subaddresses(1);
+                self.monitor_store
+                    .set_data(&mut db_txn, monitor_id, &data)?;
+                self.subaddress_store
+                    .insert(&mut db_txn, monitor_id, &data, index)?;
+                log::info!(
+                    self.logger,
+                    "Extended monitor {} to {} subaddresses",
+                    monitor_id,
+                    data.num_subaddresses
+                );
+                index
+            }
+        };
+
+        self.monitor_store
+            .mark_subaddress_used(&mut db_txn, monitor_id, index)?;
+
+        db_txn.commit()?;
+        Ok(index)
+    }
+
+    /// Cancel a payment request for a subaddress handed out by
+    /// `get_next_unused_subaddress`, so that the subaddress is handed out
+    /// again. Fails with `Error::PaymentRequestPartiallyPaid` if the
+    /// subaddress has already received outputs.
+    pub fn cancel_payment_request(
+        &self,
+        monitor_id: &MonitorId,
+        subaddress_index: u64,
+    ) -> Result<(), Error> {
+        let mut db_txn = self.begin_rw_txn()?;
+
+        let data = self.monitor_store.get_data(&db_txn, monitor_id)?;
+        if !data.subaddress_indexes().contains(&subaddress_index)
+            || !self
+                .monitor_store
+                .is_subaddress_used(&db_txn, monitor_id, subaddress_index)?
+        {
+            return Err(Error::InvalidArgument(
+                "subaddress_index".to_string(),
+                format!(
+                    "Subaddress {} was not handed out for a payment request",
+                    subaddress_index
+                ),
+            ));
+        }
+
+        // Outputs that were received and then spent are no longer in the utxo
+        // store, but are still recorded as processed.
+        let mut received = !self
+            .utxo_store
+            .get_utxos(&db_txn, monitor_id, subaddress_index)?
+            .is_empty();
+        if !received {
+            self.processed_block_store.for_each_processed_tx_out(
+                &db_txn,
+                monitor_id,
+                |_block_index, processed_tx_out| {
+                    received |= processed_tx_out.subaddress_index == subaddress_index
+                        && processed_tx_out.direction == ProcessedTxOutDirection::Received as i32;
+                    Ok(())
+                },
+            )?;
+        }
+        if received {
+            return Err(Error::PaymentRequestPartiallyPaid(subaddress_index));
+        }
+
+        self.monitor_store
+            .clear_subaddress_used(&mut db_txn, monitor_id, subaddress_index)?;
+
+        db_txn.commit()?;
+        Ok(())
+    }
+
     pub fn get_subaddress_id_by_spk(
         &self,
         subaddress_spk: &SubaddressSPKId,
@@ -223,13 +590,296 @@ impl Database {
         self.utxo_store.get_utxos(&db_txn, monitor_id, index)
     }
 
+    /// Get the unspent outputs of a monitor across all of its subaddresses,
+    /// along with its data, from a single read transaction.
+    pub fn get_account_state(&self, monitor_id: &MonitorId) -> Result<AccountState, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+
+        let data = self.monitor_store.get_data(&db_txn, monitor_id)?;
+
+        let mut utxos = Vec::new();
+        for index in data.subaddress_indexes() {
+            utxos.extend(self.utxo_store.get_utxos(&db_txn, monitor_id, index)?);
+        }
+
+        Ok(AccountState { data, utxos })
+    }
+
+    /// Get the unspent outputs of a monitor, across all of its subaddresses,
+    /// that are worth less than `fee_per_input`, the marginal fee of spending
+    /// an output as an additional transaction input. Spending such outputs
+    /// costs more than they are worth.
+    pub fn dust_outputs(
+        &self,
+        monitor_id: &MonitorId,
+        fee_per_input: u64,
+    ) -> Result<Vec<UnspentTxOut>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+
+        let data = self.monitor_store.get_data(&db_txn, monitor_id)?;
+
+        let mut dust = Vec::new();
+        for index in data.subaddress_indexes() {
+            dust.extend(
+                self.utxo_store
+                    .get_utxos(&db_txn, monitor_id, index)?
+                    .into_iter()
+                    .filter(|utxo| utxo.value < fee_per_input),
+            );
+        }
+        Ok(dust)
+    }
+
+    /// Get every output received on a subaddress of a monitor, spent or
+    /// unspent, in the order they were received. Only blocks the monitor has
+    /// already synced are considered.
+    pub fn outputs_for_subaddress(
+        &self,
+        monitor_id: &MonitorId,
+        subaddress_index: u64,
+    ) -> Result<Vec<MatchedTxOut>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+
+        let data = self.monitor_store.get_data(&db_txn, monitor_id)?;
+        if !data.subaddress_indexes().contains(&subaddress_index) {
+            return Err(Error::InvalidArgument(
+                "subaddress_index".to_string(),
+                format!(
+                    "{} is outside the monitor's subaddress range",
+                    subaddress_index
+                ),
+            ));
+        }
+
+        let mut outputs = Vec::new();
+        let mut spent_block_indexes: HashMap<KeyImage, u64> = HashMap::default();
+        for (block_index, processed_tx_outs) in self
+            .processed_block_store
+            .get_all_processed_blocks(&db_txn, monitor_id)?
+        {
+            for processed_tx_out in processed_tx_outs {
+                if processed_tx_out.subaddress_index != subaddress_index {
+                    continue;
+                }
+
+                if processed_tx_out.direction == ProcessedTxOutDirection::Received as i32 {
+                    outputs.push(MatchedTxOut {
+                        public_key: processed_tx_out.public_key,
+                        key_image: processed_tx_out.key_image,
+                        value: processed_tx_out.value,
+                        block_index,
+                        spent_block_index: None,
+                    });
+                } else if processed_tx_out.direction == ProcessedTxOutDirection::Spent as i32 {
+                    spent_block_indexes.insert(processed_tx_out.key_image, block_index);
+                }
+            }
+        }
+
+        for output in outputs.iter_mut() {
+            output.spent_block_index = spent_block_indexes.get(&output.key_image).cloned();
+        }
+        Ok(outputs)
+    }
+
+    /// Get the subaddresses of a monitor that received at least one output,
+    /// along with the total value they received, ordered by subaddress index.
+    /// Outputs that have since been spent are included in the totals.
+    pub fn funded_subaddresses(&self, monitor_id: &MonitorId) -> Result<Vec<(u64, u64)>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+
+        // Make sure the monitor exists.
+        self.monitor_store.get_data(&db_txn, monitor_id)?;
+
+        let mut received_values = BTreeMap::<u64, u64>::new();
+        self.processed_block_store.for_each_processed_tx_out(
+            &db_txn,
+            monitor_id,
+            |_block_index, processed_tx_out| {
+                if processed_tx_out.direction == ProcessedTxOutDirection::Received as i32 {
+                    *received_values
+                        .entry(processed_tx_out.subaddress_index)
+                        .or_default() += processed_tx_out.value;
+                }
+                Ok(())
+            },
+        )?;
+
+        Ok(received_values.into_iter().collect())
+    }
+
+    /// Look up the output of a monitor with a given key image, such as one seen
+    /// in the ledger or reported by another party. Returns None if the monitor
+    /// never received the output, and the block it was spent in otherwise, if
+    /// any. Only blocks the monitor has already synced are considered.
+    pub fn lookup_spent_by_key_image(
+        &self,
+        monitor_id: &MonitorId,
+        key_image: &KeyImage,
+    ) -> Result<Option<MatchedTxOut>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+
+        // Make sure the monitor exists.
+        self.monitor_store.get_data(&db_txn, monitor_id)?;
+
+        let block_index = match self.processed_block_store.get_block_index_by_key_image(
+            &db_txn,
+            monitor_id,
+            key_image,
+            ProcessedTxOutDirection::Received,
+        )? {
+            Some(block_index) => block_index,
+            None => return Ok(None),
+        };
+        let spent_block_index = self.processed_block_store.get_block_index_by_key_image(
+            &db_txn,
+            monitor_id,
+            key_image,
+            ProcessedTxOutDirection::Spent,
+        )?;
+
+        Ok(self
+            .processed_block_store
+            .get_processed_block(&db_txn, monitor_id, block_index)?
+            .into_iter()
+            .find(|processed_tx_out| {
+                processed_tx_out.direction == ProcessedTxOutDirection::Received as i32
+                    && processed_tx_out.key_image == *key_image
+            })
+            .map(|processed_tx_out| MatchedTxOut {
+                public_key: processed_tx_out.public_key,
+                key_image: processed_tx_out.key_image,
+                value: processed_tx_out.value,
+                block_index,
+                spent_block_index,
+            }))
+    }
+
+    /// Check whether the output a receipt refers to was received by a monitor.
+    /// Only blocks the monitor has already synced are considered, so a receipt
+    /// is only `Failed` once the monitor synced up to its tombstone block.
+    /// Returns `Error::InvalidArgument` if the monitor received the output but
+    /// the confirmation number does not match it.
+    pub fn verify_receipt(
+        &self,
+        monitor_id: &MonitorId,
+        receipt: &Receipt,
+    ) -> Result<ReceiptStatus, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+
+        let data = self.monitor_store.get_data(&db_txn, monitor_id)?;
+        let block_index = self
+            .processed_block_store
+            .get_all_processed_blocks(&db_txn, monitor_id)?
+            .into_iter()
+            .find(|(_block_index, processed_tx_outs)| {
+                processed_tx_outs.iter().any(|processed_tx_out| {
+                    processed_tx_out.direction == ProcessedTxOutDirection::Received as i32
+                        && processed_tx_out.public_key == receipt.tx_public_key
+                })
+            })
+            .map(|(block_index, _processed_tx_outs)| block_index);
+
+        match block_index {
+            Some(block_index) => {
+                // Only the party constructing the transaction could have created the
+                // correct confirmation number.
+                let tx_public_key = RistrettoPublic::try_from(&receipt.tx_public_key)?;
+                if !receipt
+                    .confirmation_number
+                    .validate(&tx_public_key, data.account_key.view_private_key())
+                {
+                    return Err(Error::InvalidArgument(
+                        "receipt.confirmation_number".to_string(),
+                        "does not match the output".to_string(),
+                    ));
+                }
+                Ok(ReceiptStatus::Confirmed(block_index))
+            }
+            None if data.next_block >= receipt.tombstone_block => Ok(ReceiptStatus::Failed),
+            None => Ok(ReceiptStatus::Pending),
+        }
+    }
+
+    /// Get the balance of a monitor, across all of its subaddresses, once its
+    /// in-flight transactions land: the inputs reserved by pending
+    /// transactions are subtracted and their expected change is added.
+    /// A pending transaction no longer counts once its inputs are spent, as
+    /// the change is then part of the confirmed balance, or once the monitor
+    /// synced up to its tombstone block, as it can then no longer land.
+    ///
+    /// Fails with `BalanceOverflow` if the balance won't fit in a u64.
+    pub fn projected_balance(&self, monitor_id: &MonitorId) -> Result<u64, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+
+        let data = self.monitor_store.get_data(&db_txn, monitor_id)?;
+
+        let mut utxo_values = HashMap::default();
+        for index in data.subaddress_indexes() {
+            for utxo in self.utxo_store.get_utxos(&db_txn, monitor_id, index)? {
+                utxo_values.insert(UtxoId::from(&utxo), utxo.value);
+            }
+        }
+        let mut balance = checked_sum(utxo_values.values().cloned())?;
+
+        for pending_tx in self.pending_tx_store.get_all(&db_txn)? {
+            if pending_tx.is_expired(data.next_block) {
+                continue;
+            }
+
+            let reserved_values: Vec<u64> = pending_tx
+                .utxo_ids
+                .iter()
+                .filter_map(|utxo_id| utxo_values.get(utxo_id).cloned())
+                .collect();
+            if reserved_values.is_empty() {
+                continue;
+            }
+
+            let reserved_value = checked_sum(reserved_values)?;
+            balance = balance
+                .saturating_sub(reserved_value)
+                .checked_add(pending_tx.change_value)
+                .ok_or(Error::BalanceOverflow)?;
+        }
+
+        Ok(balance)
+    }
+
+    /// Get the combined balance of every monitor: the total value of their
+    /// unspent outputs, read in a single transaction so that it is consistent
+    /// across monitors. The db doesn't know the height of the ledger, so
+    /// outputs count regardless of the confirmations their monitor requires.
+    ///
+    /// Outputs are never double counted, even across monitors of the same
+    /// account: monitors can't share subaddresses, adding one fails with
+    /// `SubaddressSPKIdExists`, and the db stores each output only once,
+    /// storing it again fails with `DuplicateUnspentTxOut`.
+    ///
+    /// Fails with `BalanceOverflow` if the balance won't fit in a u64.
+    pub fn total_balance_all_monitors(&self) -> Result<u64, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+
+        let mut balance: u64 = 0;
+        for (monitor_id, data) in self.monitor_store.get_map(&db_txn)? {
+            for index in data.subaddress_indexes() {
+                let utxos = self.utxo_store.get_utxos(&db_txn, &monitor_id, index)?;
+                let subaddress_balance = checked_sum(utxos.iter().map(|utxo| utxo.value))?;
+                balance = balance
+                    .checked_add(subaddress_balance)
+                    .ok_or(Error::BalanceOverflow)?;
+            }
+        }
+        Ok(balance)
+    }
+
     pub fn update_attempted_spend(
         &self,
         utxo_ids: &[UtxoId],
         attempted_spend_height: u64,
         attempted_spend_tombstone: u64,
     ) -> Result<(), Error> {
-        let mut db_txn = self.env.begin_rw_txn()?;
+        let mut db_txn = self.begin_rw_txn()?;
 
         self.utxo_store.update_attempted_spend(
             &mut db_txn,
@@ -251,7 +901,7 @@ impl Database {
         discovered_utxos: &[UnspentTxOut],
         spent_key_images: &[KeyImage],
     ) -> Result<(), Error> {
-        let mut db_txn = self.env.begin_rw_txn()?;
+        let mut db_txn = self.begin_rw_txn()?;
 
         // Get monitor data.
         let mut monitor_data = self.monitor_store.get_data(&db_txn, monitor_id)?;
@@ -271,6 +921,28 @@ impl Database {
         for utxo in discovered_utxos {
             self.utxo_store
                 .append_utxo(&mut db_txn, &monitor_id, utxo.subaddress_index, &utxo)?;
+            self.monitor_store.mark_subaddress_used(
+                &mut db_txn,
+                monitor_id,
+                utxo.subaddress_index,
+            )?;
+        }
+
+        // Keep watching gap_limit subaddresses past the highest one that received
+        // funds.
+        if monitor_data.gap_limit > 0 {
+            if let Some(highest_index) = discovered_utxos
+                .iter()
+                .map(|utxo| utxo.subaddress_index)
+                .max()
+            {
+                self.extend_to_gap_limit(
+                    &mut db_txn,
+                    monitor_id,
+                    &mut monitor_data,
+                    highest_index,
+                )?;
+            }
         }
 
         // Remove spent utxos
@@ -320,62 +992,569 @@ impl Database {
         Ok(())
     }
 
-    /// Get processed block information for a given (monitor id, block number).
-    pub fn get_processed_block(
+    /// Grow the range of a monitor so that it covers `gap_limit` subaddresses
+    /// past `highest_index`. Growth stops early at a subaddress that another
+    /// monitor already watches.
+    fn extend_to_gap_limit(
         &self,
+        db_txn: &mut RwTransaction,
         monitor_id: &MonitorId,
-        block_num: u64,
-    ) -> Result<Vec<ProcessedTxOut>, Error> {
+        monitor_data: &mut MonitorData,
+        highest_index: u64,
+    ) -> Result<(), Error> {
+        let end = monitor_data.subaddress_indexes().end;
+        let required_end = highest_index
+            .saturating_add(1)
+            .saturating_add(monitor_data.gap_limit);
+
+        for index in end..required_end {
+            match self
+                .subaddress_store
+                .insert(db_txn, monitor_id, monitor_data, index)
+            {
+                Ok(()) => monitor_data.extend_subaddresses(1),
+                Err(Error::SubaddressSPKIdExists) => {
+                    log::warn!(
+                        self.logger,
+                        "Subaddress {} of monitor {} is watched by another monitor",
+                        index,
+                        monitor_id
+                    );
+                    break;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        if monitor_data.subaddress_indexes().end > end {
+            log::info!(
+                self.logger,
+                "Extended monitor {} to {} subaddresses",
+                monitor_id,
+                monitor_data.num_subaddresses
+            );
+        }
+        Ok(())
+    }
+
+    /// Store a transaction that was submitted to the network.
+    pub fn add_pending_tx(&self, pending_tx: &PendingTx) -> Result<(), Error> {
+        let mut db_txn = self.begin_rw_txn()?;
+        self.pending_tx_store.insert(&mut db_txn, pending_tx)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Forget a previously stored pending transaction.
+    pub fn remove_pending_tx(&self, tx_hash: &[u8]) -> Result<(), Error> {
+        let mut db_txn = self.begin_rw_txn()?;
+        self.pending_tx_store.remove(&mut db_txn, tx_hash)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Get all stored pending transactions.
+    pub fn get_pending_txs(&self) -> Result<Vec<PendingTx>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.pending_tx_store.get_all(&db_txn)
+    }
+
+    /// Remove pending transactions whose tombstone block has been reached in a
+    /// ledger with `num_blocks` blocks. Returns the removed transactions.
+    pub fn remove_expired_pending_txs(&self, num_blocks: u64) -> Result<Vec<PendingTx>, Error> {
+        let mut db_txn = self.begin_rw_txn()?;
+        let expired = self
+            .pending_tx_store
+            .remove_expired(&mut db_txn, num_blocks)?;
+        db_txn.commit()?;
+        Ok(expired)
+    }
+
+    /// Get the index of the earliest block in which a monitor received funds,
+    /// or None if it has not received any so far. This is derived from the
+    /// processed block records, so only blocks the monitor has already synced
+    /// are considered.
+    pub fn first_funded_block(&self, monitor_id: &MonitorId) -> Result<Option<u64>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+
+        // Ensure the monitor exists.
+        let _ = self.monitor_store.get_data(&db_txn, monitor_id)?;
+
+        self.processed_block_store
+            .get_first_received_block_index(&db_txn, monitor_id)
+    }
+
+    /// Get the outputs a monitor received and spent in blocks `from_block`
+    /// (inclusive) to `to_block` (exclusive), so that a client whose state
+    /// reflects the blocks before `from_block` can bring it up to `to_block`.
+    /// The range must lie within the blocks the monitor has processed.
+    pub fn utxo_delta(
+        &self,
+        monitor_id: &MonitorId,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<UtxoDelta, Error> {
+        if from_block > to_block {
+            return Err(Error::InvalidArgument(
+                "from_block".to_string(),
+                format!("{} is greater than to_block {}", from_block, to_block),
+            ));
+        }
+
         let db_txn = self.env.begin_ro_txn()?;
 
-        // Get monitor data to see if the monitor has synced this block.
         let monitor_data = self.monitor_store.get_data(&db_txn, monitor_id)?;
-        if block_num < monitor_data.first_block {
+        if from_block < monitor_data.first_block {
             return Err(Error::BlockIndexTooSmall(
-                block_num,
+                from_block,
                 monitor_data.first_block,
             ));
         }
-        if block_num >= monitor_data.next_block {
+        if to_block > monitor_data.next_block {
             return Err(Error::BlockNotYetProcessed(
-                block_num,
+                to_block - 1,
                 monitor_data.next_block,
             ));
         }
 
-        self.processed_block_store
-            .get_processed_block(&db_txn, monitor_id, block_num)
+        let (added, spent) = self
+            .processed_block_store
+            .get_processed_tx_outs_in_range(&db_txn, monitor_id, from_block, to_block)?
+            .into_iter()
+            .partition(|processed_tx_out| {
+                processed_tx_out.direction == ProcessedTxOutDirection::Received as i32
+            });
+
+        Ok(UtxoDelta { added, spent })
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use crate::{error::Error, test_utils::get_test_databases};
-    use mc_account_keys::AccountKey;
-    use mc_common::logger::{test_with_logger, Logger};
-    use rand::{rngs::StdRng, SeedableRng};
-    use std::iter::FromIterator;
-    use tempdir::TempDir;
+    /// Write the activity of a monitor to `writer` as CSV, with a row per
+    /// output it received or spent, ordered by block. `spent_in_block` is the
+    /// block the output was spent in, and is empty for received outputs that
+    /// are still unspent. Rows are written as they are read from the database.
+    pub fn export_activity_csv(
+        &self,
+        monitor_id: &MonitorId,
+        writer: &mut impl Write,
+    ) -> Result<(), Error> {
+        let db_txn = self.env.begin_ro_txn()?;
 
-    // Test that encryption happy path works as expected.
-    #[test_with_logger]
-    fn test_encryption_happy_path(logger: Logger) {
-        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
-        let account_key = AccountKey::random(&mut rng);
+        // Fails if the monitor does not exist.
+        self.monitor_store.get_data(&db_txn, monitor_id)?;
 
-        let mobilecoind_db_tmp =
-            TempDir::new("mobilecoind_db").expect("Could not make tempdir for mobilecoind db");
-        let mobilecoind_db_path = mobilecoind_db_tmp
-            .path()
-            .to_str()
-            .expect("Could not get path as string");
+        // Received rows need the block their output was spent in, which comes
+        // later in the iteration.
+        let mut spent_block_indexes: HashMap<KeyImage, u64> = HashMap::default();
+        self.processed_block_store.for_each_processed_tx_out(
+            &db_txn,
+            monitor_id,
+            |block_index, processed_tx_out| {
+                if processed_tx_out.direction == ProcessedTxOutDirection::Spent as i32 {
+                    spent_block_indexes.insert(processed_tx_out.key_image, block_index);
+                }
+                Ok(())
+            },
+        )?;
 
-        let mobilecoind_db = Database::new(mobilecoind_db_path.to_string(), logger.clone())
-            .expect("failed creating new mobilecoind db");
+        writeln!(
+            writer,
+            "block_index,direction,amount,subaddress_index,spent_in_block"
+        )?;
+        self.processed_block_store.for_each_processed_tx_out(
+            &db_txn,
+            monitor_id,
+            |block_index, processed_tx_out| {
+                let direction =
+                    if processed_tx_out.direction == ProcessedTxOutDirection::Received as i32 {
+                        "received"
+                    } else {
+                        "spent"
+                    };
+                let spent_in_block = spent_block_indexes
+                    .get(&processed_tx_out.key_image)
+                    .map(|block_index| block_index.to_string())
+                    .unwrap_or_default();
+                writeln!(
+                    writer,
+                    "{},{},{},{},{}",
+                    block_index,
+                    direction,
+                    processed_tx_out.value,
+                    processed_tx_out.subaddress_index,
+                    spent_in_block
+                )?;
+                Ok(())
+            },
+        )
+    }
 
-        // The db starts unencrypted.
-        assert!(!mobilecoind_db.is_db_encrypted());
+    /// Write a snapshot of every monitor, along with the outputs it matched and
+    /// spent, to `writer`. The snapshot contains the monitors' account keys.
+    pub fn export_monitor_snapshot(&self, writer: &mut impl Write) -> Result<(), Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+
+        let mut monitor_map: Vec<(MonitorId, MonitorData)> =
+            self.monitor_store.get_map(&db_txn)?.into_iter().collect();
+        monitor_map.sort_by(|(id_a, _), (id_b, _)| id_a.cmp(id_b));
+
+        let mut monitors = Vec::new();
+        for (monitor_id, data) in monitor_map {
+            let mut unspent_tx_outs = Vec::new();
+            for index in data.subaddress_indexes() {
+                unspent_tx_outs.extend(self.utxo_store.get_utxos(&db_txn, &monitor_id, index)?);
+            }
+
+            let processed_blocks = self
+                .processed_block_store
+                .get_all_processed_blocks(&db_txn, &monitor_id)?
+                .into_iter()
+                .map(|(block_index, processed_tx_outs)| ProcessedBlockSnapshot {
+                    block_index,
+                    processed_tx_outs,
+                })
+                .collect();
+
+            monitors.push(MonitorSnapshot {
+                data,
+                unspent_tx_outs,
+                processed_blocks,
+            });
+        }
+
+        writer.write_all(&mc_util_serial::encode(&MonitorStoreSnapshot { monitors }))?;
+        Ok(())
+    }
+
+    /// Restore the monitors in a snapshot written by `export_monitor_snapshot`,
+    /// without rescanning the ledger. Every output in the snapshot must be in
+    /// `ledger_db`, and no monitor may have synced past the end of it.
+    /// Either all monitors are imported, or none are.
+    pub fn import_monitor_snapshot(
+        &self,
+        reader: &mut impl Read,
+        ledger_db: &impl Ledger,
+    ) -> Result<Vec<MonitorId>, Error> {
+        let mut snapshot_bytes = Vec::new();
+        reader.read_to_end(&mut snapshot_bytes)?;
+        let snapshot: MonitorStoreSnapshot = mc_util_serial::decode(&snapshot_bytes)?;
+
+        // Check the snapshot against the ledger before writing anything.
+        let num_blocks = ledger_db.num_blocks()?;
+        for monitor in snapshot.monitors.iter() {
+            if monitor.data.next_block > num_blocks {
+                return Err(Error::InvalidArgument(
+                    "snapshot".to_string(),
+                    format!(
+                        "monitor synced to block {}, but the ledger has {} blocks",
+                        monitor.data.next_block, num_blocks
+                    ),
+                ));
+            }
+
+            let subaddress_indexes = monitor.data.subaddress_indexes();
+            if let Some(utxo) = monitor
+                .unspent_tx_outs
+                .iter()
+                .find(|utxo| !subaddress_indexes.contains(&utxo.subaddress_index))
+            {
+                return Err(Error::InvalidArgument(
+                    "snapshot".to_string(),
+                    format!(
+                        "subaddress index {} is not monitored",
+                        utxo.subaddress_index
+                    ),
+                ));
+            }
+
+            let public_keys = monitor
+                .unspent_tx_outs
+                .iter()
+                .map(|utxo| &utxo.tx_out.public_key)
+                .chain(monitor.processed_blocks.iter().flat_map(|block| {
+                    block
+                        .processed_tx_outs
+                        .iter()
+                        .map(|processed_tx_out| &processed_tx_out.public_key)
+                }));
+            for public_key in public_keys {
+                if !ledger_db.contains_tx_out_public_key(public_key)? {
+                    return Err(Error::TxOutNotInLedger(*public_key));
+                }
+            }
+        }
+
+        let mut db_txn = self.begin_rw_txn()?;
+
+        let mut monitor_ids = Vec::new();
+        for monitor in snapshot.monitors.iter() {
+            let monitor_id = self.monitor_store.add(&mut db_txn, &monitor.data)?;
+
+            for index in monitor.data.subaddress_indexes() {
+                self.subaddress_store
+                    .insert(&mut db_txn, &monitor_id, &monitor.data, index)?;
+            }
+
+            for utxo in monitor.unspent_tx_outs.iter() {
+                self.utxo_store.append_utxo(
+                    &mut db_txn,
+                    &monitor_id,
+                    utxo.subaddress_index,
+                    utxo,
+                )?;
+                self.monitor_store.mark_subaddress_used(
+                    &mut db_txn,
+                    &monitor_id,
+                    utxo.subaddress_index,
+                )?;
+            }
+
+            for block in monitor.processed_blocks.iter() {
+                self.processed_block_store.insert(
+                    &mut db_txn,
+                    &monitor_id,
+                    block.block_index,
+                    &block.processed_tx_outs,
+                )?;
+            }
+
+            monitor_ids.push(monitor_id);
+        }
+
+        db_txn.commit()?;
+
+        log::info!(
+            self.logger,
+            "Imported {} monitors from snapshot",
+            monitor_ids.len()
+        );
+        Ok(monitor_ids)
+    }
+
+    /// Get processed block information for a given (monitor id, block number).
+    pub fn get_processed_block(
+        &self,
+        monitor_id: &MonitorId,
+        block_num: u64,
+    ) -> Result<Vec<ProcessedTxOut>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+
+        // Get monitor data to see if the monitor has synced this block.
+        let monitor_data = self.monitor_store.get_data(&db_txn, monitor_id)?;
+        if block_num < monitor_data.first_block {
+            return Err(Error::BlockIndexTooSmall(
+                block_num,
+                monitor_data.first_block,
+            ));
+        }
+        if block_num >= monitor_data.next_block {
+            return Err(Error::BlockNotYetProcessed(
+                block_num,
+                monitor_data.next_block,
+            ));
+        }
+
+        self.processed_block_store
+            .get_processed_block(&db_txn, monitor_id, block_num)
+    }
+}
+
+/// Sum up output values, failing with `BalanceOverflow` if the sum won't fit
+/// in a u64.
+fn checked_sum(values: impl IntoIterator<Item = u64>) -> Result<u64, Error> {
+    values.into_iter().try_fold(0u64, |sum, value| {
+        sum.checked_add(value).ok_or(Error::BalanceOverflow)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        error::Error,
+        scan_throughput::ScanThroughputMeter,
+        sync::{BlockProcessingRetry, SyncThread},
+        test_utils::{
+            get_block_tx_outs_for_account, get_test_databases, get_test_monitor_data_and_id,
+            wait_for_monitors,
+        },
+    };
+    use mc_account_keys::AccountKey;
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_transaction_core::{get_tx_out_shared_secret, tx::TxOut};
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::iter::FromIterator;
+    use tempdir::TempDir;
+
+    // A monitor whose data fails validation should be quarantined when the
+    // database is opened, without preventing healthy monitors from syncing.
+    #[test_with_logger]
+    fn test_invalid_monitors_are_quarantined(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+        let account_keys: Vec<_> = (0..2).map(|_i| AccountKey::random(&mut rng)).collect();
+        let recipients: Vec<_> = account_keys
+            .iter()
+            .map(AccountKey::default_subaddress)
+            .collect();
+
+        let (ledger_db, _mobilecoind_db) =
+            get_test_databases(0, &recipients, 5, logger.clone(), &mut rng);
+
+        let mobilecoind_db_tmp =
+            TempDir::new("mobilecoind_db").expect("Could not make tempdir for mobilecoind db");
+        let mobilecoind_db_path = mobilecoind_db_tmp
+            .path()
+            .to_str()
+            .expect("Could not get path as string");
+
+        let (healthy_monitor_id, corrupt_monitor_id) = {
+            let mobilecoind_db = Database::new(mobilecoind_db_path.to_string(), logger.clone())
+                .expect("failed creating new mobilecoind db");
+
+            let monitor_ids: Vec<MonitorId> = account_keys
+                .iter()
+                .map(|account_key| {
+                    let data = MonitorData::new(
+                        account_key.clone(),
+                        0,  // first_subaddress
+                        1,  // num_subaddresses
+                        0,  // first_block
+                        "", // name
+                    )
+                    .unwrap();
+                    mobilecoind_db.add_monitor(&data).unwrap()
+                })
+                .collect();
+
+            // Corrupt the account key of the second monitor.
+            let mut data = mobilecoind_db.get_monitor_data(&monitor_ids[1]).unwrap();
+            data.account_key = AccountKey::random(&mut rng);
+            let mut db_txn = mobilecoind_db.env.begin_rw_txn().unwrap();
+            mobilecoind_db
+                .monitor_store
+                .set_data(&mut db_txn, &monitor_ids[1], &data)
+                .unwrap();
+            db_txn.commit().unwrap();
+
+            (monitor_ids[0], monitor_ids[1])
+        };
+
+        // Reopening the database quarantines the corrupt monitor.
+        let mobilecoind_db = Database::new(mobilecoind_db_path.to_string(), logger.clone())
+            .expect("failed opening mobilecoind db");
+        let errored_monitors = mobilecoind_db.errored_monitors().unwrap();
+        assert_eq!(errored_monitors.len(), 1);
+        assert!(errored_monitors.contains_key(&corrupt_monitor_id));
+        assert_eq!(
+            mobilecoind_db.get_monitor_ids().unwrap(),
+            vec![healthy_monitor_id]
+        );
+
+        // The healthy monitor is still synced.
+        let mut sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            None,
+            BlockProcessingRetry::default(),
+            ScanThroughputMeter::default(),
+            logger.clone(),
+        );
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        sync_thread.stop();
+
+        let monitor_map = mobilecoind_db.get_monitor_map().unwrap();
+        assert_eq!(monitor_map.len(), 1);
+        assert_eq!(monitor_map[&healthy_monitor_id].next_block, 5);
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&healthy_monitor_id, 0)
+            .unwrap();
+        assert_eq!(utxos.len(), 5);
+    }
+
+    // Compacting should shrink the database file and keep its data intact.
+    #[test_with_logger]
+    fn test_compact(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let mobilecoind_db_tmp =
+            TempDir::new("mobilecoind_db").expect("Could not make tempdir for mobilecoind db");
+        let mobilecoind_db_path = mobilecoind_db_tmp.path();
+
+        // Add monitors, and remove all but one.
+        let kept_monitor_data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,   // first_subaddress
+            200, // num_subaddresses
+            0,   // first_block
+            "",  // name
+        )
+        .unwrap();
+        let kept_monitor_id = {
+            let mobilecoind_db = Database::new(mobilecoind_db_path, logger.clone())
+                .expect("failed creating new mobilecoind db");
+
+            let kept_monitor_id = mobilecoind_db.add_monitor(&kept_monitor_data).unwrap();
+            for _ in 0..10 {
+                let data = MonitorData::new(
+                    AccountKey::random(&mut rng),
+                    0,   // first_subaddress
+                    200, // num_subaddresses
+                    0,   // first_block
+                    "",  // name
+                )
+                .unwrap();
+                let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+                mobilecoind_db.remove_monitor(&monitor_id).unwrap();
+            }
+
+            kept_monitor_id
+        };
+
+        let (size_before, size_after) = Database::compact(mobilecoind_db_path).unwrap();
+        assert!(size_after < size_before);
+
+        let mobilecoind_db = Database::new(mobilecoind_db_path, logger.clone())
+            .expect("failed opening mobilecoind db");
+        assert_eq!(
+            mobilecoind_db.get_monitor_ids().unwrap(),
+            vec![kept_monitor_id]
+        );
+        assert_eq!(
+            mobilecoind_db.get_monitor_data(&kept_monitor_id).unwrap(),
+            kept_monitor_data
+        );
+        for subaddress_index in 0..200 {
+            let subaddress_spk = SubaddressSPKId::from(
+                kept_monitor_data
+                    .account_key
+                    .subaddress(subaddress_index)
+                    .spend_public_key(),
+            );
+            let subaddress_id = mobilecoind_db
+                .get_subaddress_id_by_spk(&subaddress_spk)
+                .unwrap();
+            assert_eq!(subaddress_id.monitor_id, kept_monitor_id);
+            assert_eq!(subaddress_id.index, subaddress_index);
+        }
+    }
+
+    // Test that encryption happy path works as expected.
+    #[test_with_logger]
+    fn test_encryption_happy_path(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+
+        let mobilecoind_db_tmp =
+            TempDir::new("mobilecoind_db").expect("Could not make tempdir for mobilecoind db");
+        let mobilecoind_db_path = mobilecoind_db_tmp
+            .path()
+            .to_str()
+            .expect("Could not get path as string");
+
+        let mobilecoind_db = Database::new(mobilecoind_db_path.to_string(), logger.clone())
+            .expect("failed creating new mobilecoind db");
+
+        // The db starts unencrypted.
+        assert!(!mobilecoind_db.is_db_encrypted());
         assert!(mobilecoind_db.is_unlocked());
 
         // We should be able to insert a monitor at this point.
@@ -621,4 +1800,1365 @@ mod test {
             .add_monitor(&initial_data)
             .expect("failed adding monitor");
     }
+
+    // Requesting unused subaddresses, including concurrently, should return
+    // distinct subaddresses and extend the monitor's range when needed.
+    #[test_with_logger]
+    fn test_get_next_unused_subaddress(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let (_ledger_db, mobilecoind_db) =
+            get_test_databases(3, &vec![], 10, logger.clone(), &mut rng);
+
+        let account_key = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            3,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        // Sequential requests hand out the subaddresses in order.
+        assert_eq!(
+            mobilecoind_db
+                .get_next_unused_subaddress(&monitor_id)
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            mobilecoind_db
+                .get_next_unused_subaddress(&monitor_id)
+                .unwrap(),
+            1
+        );
+
+        // Concurrent requests, which exhaust the initial range.
+        let handles: Vec<_> = (0..6)
+            .map(|_| {
+                let mobilecoind_db = mobilecoind_db.clone();
+                let monitor_id = monitor_id.clone();
+                std::thread::spawn(move || {
+                    mobilecoind_db
+                        .get_next_unused_subaddress(&monitor_id)
+                        .unwrap()
+                })
+            })
+            .collect();
+        let mut indexes: Vec<u64> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        indexes.sort();
+        assert_eq!(indexes, vec![2, 3, 4, 5, 6, 7]);
+
+        // The monitor now covers all handed out subaddresses, and the new ones can be
+        // matched by the sync code.
+        let monitor_data = mobilecoind_db.get_monitor_data(&monitor_id).unwrap();
+        assert_eq!(monitor_data.first_subaddress, 0);
+        assert_eq!(monitor_data.num_subaddresses, 8);
+        assert_eq!(monitor_data.num_extended_subaddresses, 5);
+
+        // Growing the range keeps the monitor id, so re-adding the monitor as it was
+        // created is detected.
+        assert_eq!(MonitorId::from(&monitor_data), monitor_id);
+        match mobilecoind_db.add_monitor(&data) {
+            Err(Error::MonitorIdExists) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        let subaddress_spk = SubaddressSPKId::from(account_key.subaddress(7).spend_public_key());
+        assert_eq!(
+            mobilecoind_db
+                .get_subaddress_id_by_spk(&subaddress_spk)
+                .unwrap(),
+            SubaddressId::new(&monitor_id, 7)
+        );
+    }
+
+    // Canceling an unpaid payment request should release its subaddress, while
+    // canceling one whose subaddress received funds should fail.
+    #[test_with_logger]
+    fn test_cancel_payment_request(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let (ledger_db, mobilecoind_db) =
+            get_test_databases(3, &vec![], 10, logger.clone(), &mut rng);
+
+        let monitor_data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,  // first_subaddress
+            3,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        for expected_index in 0..3 {
+            assert_eq!(
+                mobilecoind_db
+                    .get_next_unused_subaddress(&monitor_id)
+                    .unwrap(),
+                expected_index
+            );
+        }
+
+        // Unpaid: the subaddress is handed out again.
+        mobilecoind_db
+            .cancel_payment_request(&monitor_id, 1)
+            .unwrap();
+        assert_eq!(
+            mobilecoind_db
+                .get_next_unused_subaddress(&monitor_id)
+                .unwrap(),
+            1
+        );
+
+        // Partially paid: subaddress 2 receives an output, which is later spent.
+        let utxo = UnspentTxOut {
+            tx_out: ledger_db.get_block_contents(0).unwrap().outputs[0].clone(),
+            subaddress_index: 2,
+            key_image: KeyImage::from(2),
+            value: 10,
+            attempted_spend_height: 0,
+            attempted_spend_tombstone: 0,
+            received_block_index: 0,
+        };
+        mobilecoind_db
+            .block_processed(&monitor_id, 0, &[utxo.clone()], &[])
+            .unwrap();
+        match mobilecoind_db.cancel_payment_request(&monitor_id, 2) {
+            Err(Error::PaymentRequestPartiallyPaid(2)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        mobilecoind_db
+            .block_processed(&monitor_id, 1, &[], &[utxo.key_image])
+            .unwrap();
+        match mobilecoind_db.cancel_payment_request(&monitor_id, 2) {
+            Err(Error::PaymentRequestPartiallyPaid(2)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert_eq!(
+            mobilecoind_db
+                .get_next_unused_subaddress(&monitor_id)
+                .unwrap(),
+            3
+        );
+
+        // Subaddresses that were not handed out cannot be canceled.
+        match mobilecoind_db.cancel_payment_request(&monitor_id, 10) {
+            Err(Error::InvalidArgument(_, _)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    // Monitors should be listed furthest behind first, with monitors ahead of the
+    // tip reporting zero lag.
+    #[test_with_logger]
+    fn test_monitors_by_lag(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let (_ledger_db, mobilecoind_db) =
+            get_test_databases(3, &vec![], 10, logger.clone(), &mut rng);
+
+        assert_eq!(mobilecoind_db.monitors_by_lag(10).unwrap(), vec![]);
+
+        let mut add_monitor = |first_block: u64| {
+            let data = MonitorData::new(
+                AccountKey::random(&mut rng),
+                0,           // first_subaddress
+                1,           // num_subaddresses
+                first_block, // first_block
+                "",          // name
+            )
+            .unwrap();
+            mobilecoind_db.add_monitor(&data).unwrap()
+        };
+
+        let synced = add_monitor(10);
+        let far_behind = add_monitor(0);
+        let ahead = add_monitor(15);
+        let behind = add_monitor(7);
+
+        let monitors_by_lag = mobilecoind_db.monitors_by_lag(10).unwrap();
+        assert_eq!(monitors_by_lag[..2], [(far_behind, 10), (behind, 3)]);
+
+        // Monitors with equal lag are ordered by id.
+        let mut zero_lag = vec![(synced, 0), (ahead, 0)];
+        zero_lag.sort();
+        assert_eq!(monitors_by_lag[2..], zero_lag[..]);
+    }
+
+    // Duplicate monitor names are allowed by default, and rejected when unique
+    // names are required.
+    #[test_with_logger]
+    fn test_add_monitor_with_unique_name(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let (_ledger_db, mobilecoind_db) =
+            get_test_databases(3, &vec![], 10, logger.clone(), &mut rng);
+
+        let mut new_monitor_data = |name: &str| {
+            MonitorData::new(
+                AccountKey::random(&mut rng),
+                0, // first_subaddress
+                1, // num_subaddresses
+                0, // first_block
+                name,
+            )
+            .unwrap()
+        };
+        let strict = AddMonitorOptions {
+            require_unique_name: true,
+        };
+
+        // Permissive mode accepts duplicate names.
+        mobilecoind_db
+            .add_monitor(&new_monitor_data("exchange"))
+            .unwrap();
+        mobilecoind_db
+            .add_monitor(&new_monitor_data("exchange"))
+            .unwrap();
+
+        // Strict mode rejects a name that is already taken, without adding the
+        // monitor.
+        let duplicate = new_monitor_data("exchange");
+        match mobilecoind_db.add_monitor_with_options(&duplicate, &strict) {
+            Err(Error::MonitorNameExists(name)) => assert_eq!(name, "exchange"),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(mobilecoind_db.get_monitor_ids().unwrap().len(), 2);
+
+        // Strict mode accepts a new name, but not a second monitor with it.
+        mobilecoind_db
+            .add_monitor_with_options(&new_monitor_data("payroll"), &strict)
+            .unwrap();
+        match mobilecoind_db.add_monitor_with_options(&new_monitor_data("payroll"), &strict) {
+            Err(Error::MonitorNameExists(name)) => assert_eq!(name, "payroll"),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Re-adding an existing monitor reports the monitor itself, not its name.
+        let data = new_monitor_data("savings");
+        mobilecoind_db
+            .add_monitor_with_options(&data, &strict)
+            .unwrap();
+        match mobilecoind_db.add_monitor_with_options(&data, &strict) {
+            Err(Error::MonitorIdExists) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Unnamed monitors never conflict.
+        mobilecoind_db
+            .add_monitor_with_options(&new_monitor_data(""), &strict)
+            .unwrap();
+        mobilecoind_db
+            .add_monitor_with_options(&new_monitor_data(""), &strict)
+            .unwrap();
+    }
+
+    // The first funded block is the earliest block a monitor received an output
+    // in, and is unaffected by later receives and spends.
+    #[test_with_logger]
+    fn test_first_funded_block(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let (ledger_db, mobilecoind_db) =
+            get_test_databases(3, &vec![], 10, logger.clone(), &mut rng);
+
+        let monitor_data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        let utxo_in_block = |block_num: u64| UnspentTxOut {
+            tx_out: ledger_db.get_block_contents(block_num).unwrap().outputs[0].clone(),
+            subaddress_index: 0,
+            key_image: KeyImage::from(block_num),
+            value: 10,
+            attempted_spend_height: 0,
+            attempted_spend_tombstone: 0,
+            received_block_index: block_num,
+        };
+
+        // Nothing has been received yet.
+        for block_num in 0..4 {
+            mobilecoind_db
+                .block_processed(&monitor_id, block_num, &[], &[])
+                .unwrap();
+        }
+        assert_eq!(
+            mobilecoind_db.first_funded_block(&monitor_id).unwrap(),
+            None
+        );
+
+        // Fund the monitor in block 4, then receive and spend in later blocks.
+        let first_utxo = utxo_in_block(4);
+        mobilecoind_db
+            .block_processed(&monitor_id, 4, &[first_utxo.clone()], &[])
+            .unwrap();
+        mobilecoind_db
+            .block_processed(&monitor_id, 5, &[], &[])
+            .unwrap();
+        mobilecoind_db
+            .block_processed(&monitor_id, 6, &[utxo_in_block(6)], &[first_utxo.key_image])
+            .unwrap();
+
+        assert_eq!(
+            mobilecoind_db.first_funded_block(&monitor_id).unwrap(),
+            Some(4)
+        );
+
+        // Unknown monitors are an error rather than unfunded.
+        let (_data, unknown_monitor_id) = get_test_monitor_data_and_id(&mut rng);
+        match mobilecoind_db.first_funded_block(&unknown_monitor_id) {
+            Err(Error::MonitorIdNotFound) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    // utxo_delta should return the outputs received and spent in a range of
+    // processed blocks.
+    #[test_with_logger]
+    fn test_utxo_delta(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let (ledger_db, mobilecoind_db) =
+            get_test_databases(3, &vec![], 10, logger.clone(), &mut rng);
+
+        let monitor_data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        let utxo_in_block = |block_num: u64| UnspentTxOut {
+            tx_out: ledger_db.get_block_contents(block_num).unwrap().outputs[0].clone(),
+            subaddress_index: 0,
+            key_image: KeyImage::from(block_num),
+            value: 10 * block_num,
+            attempted_spend_height: 0,
+            attempted_spend_tombstone: 0,
+            received_block_index: block_num,
+        };
+
+        // Receive in blocks 2 and 4, then spend the first output and receive
+        // another one in block 6.
+        let utxo_2 = utxo_in_block(2);
+        let utxo_4 = utxo_in_block(4);
+        let utxo_6 = utxo_in_block(6);
+        for block_num in 0..8 {
+            let (discovered, spent) = match block_num {
+                2 => (vec![utxo_2.clone()], vec![]),
+                4 => (vec![utxo_4.clone()], vec![]),
+                6 => (vec![utxo_6.clone()], vec![utxo_2.key_image]),
+                _ => (vec![], vec![]),
+            };
+            mobilecoind_db
+                .block_processed(&monitor_id, block_num, &discovered, &spent)
+                .unwrap();
+        }
+
+        let received = |utxo: &UnspentTxOut| ProcessedTxOut::from_received_utxo(utxo);
+        let spent = |utxo: &UnspentTxOut| ProcessedTxOut::from_spent_utxo(utxo);
+
+        // Every processed block.
+        assert_eq!(
+            mobilecoind_db.utxo_delta(&monitor_id, 0, 8).unwrap(),
+            UtxoDelta {
+                added: vec![received(&utxo_2), received(&utxo_4), received(&utxo_6)],
+                spent: vec![spent(&utxo_2)],
+            }
+        );
+
+        // The range excludes `to_block`.
+        assert_eq!(
+            mobilecoind_db.utxo_delta(&monitor_id, 3, 6).unwrap(),
+            UtxoDelta {
+                added: vec![received(&utxo_4)],
+                spent: vec![],
+            }
+        );
+        assert_eq!(
+            mobilecoind_db.utxo_delta(&monitor_id, 5, 7).unwrap(),
+            UtxoDelta {
+                added: vec![received(&utxo_6)],
+                spent: vec![spent(&utxo_2)],
+            }
+        );
+        assert_eq!(
+            mobilecoind_db.utxo_delta(&monitor_id, 8, 8).unwrap(),
+            UtxoDelta::default()
+        );
+
+        // Blocks the monitor has not processed yet.
+        match mobilecoind_db.utxo_delta(&monitor_id, 6, 9) {
+            Err(Error::BlockNotYetProcessed(8, 8)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Reversed range.
+        match mobilecoind_db.utxo_delta(&monitor_id, 6, 5) {
+            Err(Error::InvalidArgument(_, _)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Blocks before the first block of the monitor.
+        let monitor_data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            5,  // first_block
+            "", // name
+        )
+        .unwrap();
+        let late_monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+        match mobilecoind_db.utxo_delta(&late_monitor_id, 4, 5) {
+            Err(Error::BlockIndexTooSmall(4, 5)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(
+            mobilecoind_db.utxo_delta(&late_monitor_id, 5, 5).unwrap(),
+            UtxoDelta::default()
+        );
+
+        // Unknown monitors.
+        let (_data, unknown_monitor_id) = get_test_monitor_data_and_id(&mut rng);
+        match mobilecoind_db.utxo_delta(&unknown_monitor_id, 0, 0) {
+            Err(Error::MonitorIdNotFound) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_get_account_state(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let (ledger_db, mobilecoind_db) =
+            get_test_databases(3, &vec![], 10, logger.clone(), &mut rng);
+
+        let monitor_data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,  // first_subaddress
+            2,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        let utxo_in_block = |block_num: u64, subaddress_index: u64| UnspentTxOut {
+            tx_out: ledger_db.get_block_contents(block_num).unwrap().outputs[0].clone(),
+            subaddress_index,
+            key_image: KeyImage::from(block_num),
+            value: 10 * block_num,
+            attempted_spend_height: 0,
+            attempted_spend_tombstone: 0,
+            received_block_index: block_num,
+        };
+
+        // Receive on both subaddresses, then spend the first output.
+        let utxo_2 = utxo_in_block(2, 1);
+        let utxo_3 = utxo_in_block(3, 0);
+        let utxo_4 = utxo_in_block(4, 1);
+        for block_num in 0..6 {
+            let (discovered, spent) = match block_num {
+                2 => (vec![utxo_2.clone()], vec![]),
+                3 => (vec![utxo_3.clone()], vec![]),
+                4 => (vec![utxo_4.clone()], vec![]),
+                5 => (vec![], vec![utxo_2.key_image]),
+                _ => (vec![], vec![]),
+            };
+            mobilecoind_db
+                .block_processed(&monitor_id, block_num, &discovered, &spent)
+                .unwrap();
+        }
+
+        let state = mobilecoind_db.get_account_state(&monitor_id).unwrap();
+        assert_eq!(state.data.next_block, 6);
+        assert_eq!(state.utxos, vec![utxo_3, utxo_4]);
+
+        // Unknown monitors.
+        let (_data, unknown_monitor_id) = get_test_monitor_data_and_id(&mut rng);
+        match mobilecoind_db.get_account_state(&unknown_monitor_id) {
+            Err(Error::MonitorIdNotFound) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_export_activity_csv(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let (ledger_db, mobilecoind_db) =
+            get_test_databases(3, &vec![], 10, logger.clone(), &mut rng);
+
+        let monitor_data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,  // first_subaddress
+            2,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        let utxo_in_block = |block_num: u64, subaddress_index: u64| UnspentTxOut {
+            tx_out: ledger_db.get_block_contents(block_num).unwrap().outputs[0].clone(),
+            subaddress_index,
+            key_image: KeyImage::from(block_num),
+            value: 10 * block_num,
+            attempted_spend_height: 0,
+            attempted_spend_tombstone: 0,
+            received_block_index: block_num,
+        };
+
+        // Receive in blocks 2 and 4, then spend the first output in block 6.
+        let utxo_2 = utxo_in_block(2, 0);
+        let utxo_4 = utxo_in_block(4, 1);
+        for block_num in 0..8 {
+            let (discovered, spent) = match block_num {
+                2 => (vec![utxo_2.clone()], vec![]),
+                4 => (vec![utxo_4.clone()], vec![]),
+                6 => (vec![], vec![utxo_2.key_image]),
+                _ => (vec![], vec![]),
+            };
+            mobilecoind_db
+                .block_processed(&monitor_id, block_num, &discovered, &spent)
+                .unwrap();
+        }
+
+        let mut csv = Vec::new();
+        mobilecoind_db
+            .export_activity_csv(&monitor_id, &mut csv)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "block_index,direction,amount,subaddress_index,spent_in_block\n\
+             2,received,20,0,6\n\
+             4,received,40,1,\n\
+             6,spent,20,0,6\n"
+        );
+
+        // A monitor without activity only gets the header.
+        let monitor_data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+        let idle_monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+        let mut csv = Vec::new();
+        mobilecoind_db
+            .export_activity_csv(&idle_monitor_id, &mut csv)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "block_index,direction,amount,subaddress_index,spent_in_block\n"
+        );
+
+        // Unknown monitors.
+        let (_data, unknown_monitor_id) = get_test_monitor_data_and_id(&mut rng);
+        match mobilecoind_db.export_activity_csv(&unknown_monitor_id, &mut Vec::new()) {
+            Err(Error::MonitorIdNotFound) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    // Only outputs worth less than the fee of spending them are dust, across all
+    // of a monitor's subaddresses.
+    #[test_with_logger]
+    fn test_dust_outputs(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let (ledger_db, mobilecoind_db) =
+            get_test_databases(4, &vec![], 10, logger.clone(), &mut rng);
+
+        let monitor_data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,  // first_subaddress
+            2,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        let fee_per_input = 1000;
+
+        // Outputs from block 1, with the given (subaddress index, value).
+        let outputs = ledger_db.get_block_contents(1).unwrap().outputs;
+        let utxos: Vec<UnspentTxOut> = [(0, 999), (0, 1000), (1, 1), (1, 5000)]
+            .iter()
+            .zip(outputs.iter())
+            .enumerate()
+            .map(|(i, ((subaddress_index, value), tx_out))| UnspentTxOut {
+                tx_out: tx_out.clone(),
+                subaddress_index: *subaddress_index,
+                key_image: KeyImage::from(i as u64),
+                value: *value,
+                attempted_spend_height: 0,
+                attempted_spend_tombstone: 0,
+                received_block_index: 1,
+            })
+            .collect();
+        assert_eq!(utxos.len(), 4);
+
+        assert_eq!(
+            mobilecoind_db
+                .dust_outputs(&monitor_id, fee_per_input)
+                .unwrap(),
+            vec![]
+        );
+
+        mobilecoind_db
+            .block_processed(&monitor_id, 0, &utxos, &[])
+            .unwrap();
+
+        let mut dust = mobilecoind_db
+            .dust_outputs(&monitor_id, fee_per_input)
+            .unwrap();
+        dust.sort_by_key(|utxo| utxo.value);
+        assert_eq!(dust, vec![utxos[2].clone(), utxos[0].clone()]);
+
+        // Nothing is dust without a fee.
+        assert_eq!(mobilecoind_db.dust_outputs(&monitor_id, 0).unwrap(), vec![]);
+    }
+
+    // The total balance should count the unspent outputs of every monitor once.
+    #[test_with_logger]
+    fn test_total_balance_all_monitors(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let (ledger_db, mobilecoind_db) =
+            get_test_databases(4, &vec![], 10, logger.clone(), &mut rng);
+        assert_eq!(mobilecoind_db.total_balance_all_monitors().unwrap(), 0);
+
+        // Two monitors for one account, on separate subaddresses, and a monitor
+        // for another account.
+        let account_key = AccountKey::random(&mut rng);
+        let monitor_ids: Vec<MonitorId> = [
+            (account_key.clone(), 0),
+            (account_key, 2),
+            (AccountKey::random(&mut rng), 0),
+        ]
+        .iter()
+        .map(|(account_key, first_subaddress)| {
+            let data = MonitorData::new(
+                account_key.clone(),
+                *first_subaddress,
+                2,  // num_subaddresses
+                0,  // first_block
+                "", // name
+            )
+            .unwrap();
+            mobilecoind_db.add_monitor(&data).unwrap()
+        })
+        .collect();
+
+        // Outputs from block 1, with the given (subaddress index, value).
+        let outputs = ledger_db.get_block_contents(1).unwrap().outputs;
+        let utxos: Vec<UnspentTxOut> = [(1, 100), (2, 200), (0, 300), (0, 400)]
+            .iter()
+            .zip(outputs.iter())
+            .enumerate()
+            .map(|(i, ((subaddress_index, value), tx_out))| UnspentTxOut {
+                tx_out: tx_out.clone(),
+                subaddress_index: *subaddress_index,
+                key_image: KeyImage::from(i as u64),
+                value: *value,
+                attempted_spend_height: 0,
+                attempted_spend_tombstone: 0,
+                received_block_index: 1,
+            })
+            .collect();
+        assert_eq!(utxos.len(), 4);
+
+        mobilecoind_db
+            .block_processed(&monitor_ids[0], 0, &utxos[0..1], &[])
+            .unwrap();
+        mobilecoind_db
+            .block_processed(&monitor_ids[1], 0, &utxos[1..2], &[])
+            .unwrap();
+        mobilecoind_db
+            .block_processed(&monitor_ids[2], 0, &utxos[2..4], &[])
+            .unwrap();
+        assert_eq!(
+            mobilecoind_db.total_balance_all_monitors().unwrap(),
+            100 + 200 + 300 + 400
+        );
+
+        // Spent outputs no longer count.
+        mobilecoind_db
+            .block_processed(&monitor_ids[2], 1, &[], &[utxos[3].key_image])
+            .unwrap();
+        assert_eq!(
+            mobilecoind_db.total_balance_all_monitors().unwrap(),
+            100 + 200 + 300
+        );
+
+        // An output can't be held by two monitors, so it can't be counted twice.
+        let mut shared_utxo = utxos[0].clone();
+        shared_utxo.subaddress_index = 3;
+        match mobilecoind_db.block_processed(&monitor_ids[1], 1, &[shared_utxo], &[]) {
+            Err(Error::DuplicateUnspentTxOut) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(
+            mobilecoind_db.total_balance_all_monitors().unwrap(),
+            100 + 200 + 300
+        );
+
+        // A total that won't fit in a u64 is an error rather than wrapping around.
+        let big_utxo = UnspentTxOut {
+            tx_out: ledger_db.get_block_contents(2).unwrap().outputs[0].clone(),
+            subaddress_index: 0,
+            key_image: KeyImage::from(10),
+            value: u64::MAX,
+            attempted_spend_height: 0,
+            attempted_spend_tombstone: 0,
+            received_block_index: 2,
+        };
+        mobilecoind_db
+            .block_processed(&monitor_ids[2], 2, &[big_utxo], &[])
+            .unwrap();
+        match mobilecoind_db.total_balance_all_monitors() {
+            Err(Error::BalanceOverflow) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    // The projected balance should reflect the spend and change of a pending
+    // transaction, and revert to the confirmed balance once the transaction can no
+    // longer land.
+    #[test_with_logger]
+    fn test_projected_balance(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let (ledger_db, mobilecoind_db) =
+            get_test_databases(4, &vec![], 10, logger.clone(), &mut rng);
+
+        let monitor_data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,  // first_subaddress
+            2,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        // Outputs from block 1, with the given (subaddress index, value).
+        let outputs = ledger_db.get_block_contents(1).unwrap().outputs;
+        let utxos: Vec<UnspentTxOut> = [(0, 100), (0, 200), (1, 300)]
+            .iter()
+            .zip(outputs.iter())
+            .enumerate()
+            .map(|(i, ((subaddress_index, value), tx_out))| UnspentTxOut {
+                tx_out: tx_out.clone(),
+                subaddress_index: *subaddress_index,
+                key_image: KeyImage::from(i as u64),
+                value: *value,
+                attempted_spend_height: 0,
+                attempted_spend_tombstone: 0,
+                received_block_index: 1,
+            })
+            .collect();
+        assert_eq!(utxos.len(), 3);
+
+        mobilecoind_db
+            .block_processed(&monitor_id, 0, &utxos, &[])
+            .unwrap();
+        assert_eq!(mobilecoind_db.projected_balance(&monitor_id).unwrap(), 600);
+
+        // A pending transaction spending 300 from the first two outputs, returning 50
+        // as change.
+        let pending_tx = PendingTx {
+            tx_hash: vec![1; 32],
+            utxo_ids: vec![UtxoId::from(&utxos[0]), UtxoId::from(&utxos[1])],
+            submitted_block_height: 1,
+            tombstone_block: 3,
+            monitor_id: Some(monitor_id),
+            change_value: 50,
+        };
+        mobilecoind_db.add_pending_tx(&pending_tx).unwrap();
+        assert_eq!(
+            mobilecoind_db.projected_balance(&monitor_id).unwrap(),
+            600 - 300 + 50
+        );
+
+        // Still pending before the tombstone block.
+        mobilecoind_db
+            .block_processed(&monitor_id, 1, &[], &[])
+            .unwrap();
+        assert_eq!(
+            mobilecoind_db.projected_balance(&monitor_id).unwrap(),
+            600 - 300 + 50
+        );
+
+        // Once the tombstone block passes, the transaction failed and the inputs are
+        // available again.
+        mobilecoind_db
+            .block_processed(&monitor_id, 2, &[], &[])
+            .unwrap();
+        assert_eq!(mobilecoind_db.projected_balance(&monitor_id).unwrap(), 600);
+
+        // A projected balance that won't fit in a u64 is an error rather than
+        // wrapping around.
+        let pending_tx = PendingTx {
+            tx_hash: vec![2; 32],
+            utxo_ids: vec![UtxoId::from(&utxos[2])],
+            submitted_block_height: 3,
+            tombstone_block: 10,
+            monitor_id: Some(monitor_id),
+            change_value: u64::MAX,
+        };
+        mobilecoind_db.add_pending_tx(&pending_tx).unwrap();
+        match mobilecoind_db.projected_balance(&monitor_id) {
+            Err(Error::BalanceOverflow) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    // Only subaddresses that received outputs should be listed, with the total
+    // value they received, spent or not.
+    #[test_with_logger]
+    fn test_funded_subaddresses(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let (ledger_db, mobilecoind_db) =
+            get_test_databases(3, &vec![], 10, logger.clone(), &mut rng);
+
+        let monitor_data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,  // first_subaddress
+            4,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+        assert_eq!(
+            mobilecoind_db.funded_subaddresses(&monitor_id).unwrap(),
+            vec![]
+        );
+
+        let utxo = |block_num: u64, output_index: usize, subaddress_index: u64| UnspentTxOut {
+            tx_out: ledger_db.get_block_contents(block_num).unwrap().outputs[output_index].clone(),
+            subaddress_index,
+            key_image: KeyImage::from(block_num * 10 + output_index as u64),
+            value: 10 + block_num,
+            attempted_spend_height: 0,
+            attempted_spend_tombstone: 0,
+            received_block_index: block_num,
+        };
+
+        // Subaddress 1 receives twice, and subaddress 3 receives once and spends it.
+        let utxo_1a = utxo(1, 0, 1);
+        let utxo_1b = utxo(2, 0, 1);
+        let utxo_3 = utxo(2, 1, 3);
+        for block_num in 0..4 {
+            let (discovered, spent) = match block_num {
+                1 => (vec![utxo_1a.clone()], vec![]),
+                2 => (vec![utxo_1b.clone(), utxo_3.clone()], vec![]),
+                3 => (vec![], vec![utxo_3.key_image]),
+                _ => (vec![], vec![]),
+            };
+            mobilecoind_db
+                .block_processed(&monitor_id, block_num, &discovered, &spent)
+                .unwrap();
+        }
+
+        assert_eq!(
+            mobilecoind_db.funded_subaddresses(&monitor_id).unwrap(),
+            vec![(1, utxo_1a.value + utxo_1b.value), (3, utxo_3.value)]
+        );
+    }
+
+    // Outputs received by a monitor should be found by their key image, along
+    // with the block they were spent in.
+    #[test_with_logger]
+    fn test_lookup_spent_by_key_image(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let (ledger_db, mobilecoind_db) =
+            get_test_databases(3, &vec![], 10, logger.clone(), &mut rng);
+
+        let monitor_data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        let utxo_in_block = |block_num: u64| UnspentTxOut {
+            tx_out: ledger_db.get_block_contents(block_num).unwrap().outputs[0].clone(),
+            subaddress_index: 0,
+            key_image: KeyImage::from(block_num),
+            value: 10 * block_num,
+            attempted_spend_height: 0,
+            attempted_spend_tombstone: 0,
+            received_block_index: block_num,
+        };
+
+        // Receive in blocks 2 and 4, and spend the first output in block 6.
+        let utxo_2 = utxo_in_block(2);
+        let utxo_4 = utxo_in_block(4);
+        for block_num in 0..8 {
+            let (discovered, spent) = match block_num {
+                2 => (vec![utxo_2.clone()], vec![]),
+                4 => (vec![utxo_4.clone()], vec![]),
+                6 => (vec![], vec![utxo_2.key_image]),
+                _ => (vec![], vec![]),
+            };
+            mobilecoind_db
+                .block_processed(&monitor_id, block_num, &discovered, &spent)
+                .unwrap();
+        }
+
+        assert_eq!(
+            mobilecoind_db
+                .lookup_spent_by_key_image(&monitor_id, &utxo_2.key_image)
+                .unwrap(),
+            Some(MatchedTxOut {
+                public_key: utxo_2.tx_out.public_key,
+                key_image: utxo_2.key_image,
+                value: utxo_2.value,
+                block_index: 2,
+                spent_block_index: Some(6),
+            })
+        );
+        assert_eq!(
+            mobilecoind_db
+                .lookup_spent_by_key_image(&monitor_id, &utxo_4.key_image)
+                .unwrap()
+                .map(|matched| matched.spent_block_index),
+            Some(None)
+        );
+
+        // Key images of outputs the monitor does not own are not found.
+        assert_eq!(
+            mobilecoind_db
+                .lookup_spent_by_key_image(&monitor_id, &KeyImage::from(100))
+                .unwrap(),
+            None
+        );
+
+        // Other monitors do not see the outputs.
+        let (other_monitor_data, _) = get_test_monitor_data_and_id(&mut rng);
+        let other_monitor_id = mobilecoind_db.add_monitor(&other_monitor_data).unwrap();
+        assert_eq!(
+            mobilecoind_db
+                .lookup_spent_by_key_image(&other_monitor_id, &utxo_2.key_image)
+                .unwrap(),
+            None
+        );
+
+        // Removing the monitor removes its outputs from the index.
+        mobilecoind_db.remove_monitor(&monitor_id).unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+        assert_eq!(
+            mobilecoind_db
+                .lookup_spent_by_key_image(&monitor_id, &utxo_2.key_image)
+                .unwrap(),
+            None
+        );
+    }
+
+    // Every output received on a subaddress should be listed, along with where it
+    // was spent.
+    #[test_with_logger]
+    fn test_outputs_for_subaddress(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let (ledger_db, mobilecoind_db) =
+            get_test_databases(3, &vec![], 10, logger.clone(), &mut rng);
+
+        let monitor_data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,  // first_subaddress
+            3,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        let utxo = |block_num: u64, output_index: usize, subaddress_index: u64| UnspentTxOut {
+            tx_out: ledger_db.get_block_contents(block_num).unwrap().outputs[output_index].clone(),
+            subaddress_index,
+            key_image: KeyImage::from(block_num * 10 + output_index as u64),
+            value: 10 + block_num,
+            attempted_spend_height: 0,
+            attempted_spend_tombstone: 0,
+            received_block_index: block_num,
+        };
+        let matched =
+            |utxo: &UnspentTxOut, block_index: u64, spent_block_index: Option<u64>| MatchedTxOut {
+                public_key: utxo.tx_out.public_key,
+                key_image: utxo.key_image,
+                value: utxo.value,
+                block_index,
+                spent_block_index,
+            };
+
+        // Receive on subaddresses 0 and 1, then spend one of the subaddress 0 outputs.
+        let utxo_0a = utxo(0, 0, 0);
+        let utxo_1 = utxo(0, 1, 1);
+        let utxo_0b = utxo(1, 0, 0);
+        mobilecoind_db
+            .block_processed(&monitor_id, 0, &[utxo_0a.clone(), utxo_1.clone()], &[])
+            .unwrap();
+        mobilecoind_db
+            .block_processed(&monitor_id, 1, &[utxo_0b.clone()], &[])
+            .unwrap();
+        mobilecoind_db
+            .block_processed(&monitor_id, 2, &[], &[utxo_0a.key_image])
+            .unwrap();
+
+        let outputs = mobilecoind_db
+            .outputs_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        assert_eq!(
+            outputs,
+            vec![matched(&utxo_0a, 0, Some(2)), matched(&utxo_0b, 1, None)]
+        );
+        assert!(outputs[0].is_spent());
+        assert!(!outputs[1].is_spent());
+
+        assert_eq!(
+            mobilecoind_db
+                .outputs_for_subaddress(&monitor_id, 1)
+                .unwrap(),
+            vec![matched(&utxo_1, 0, None)]
+        );
+
+        // A subaddress that was never used has no outputs.
+        assert_eq!(
+            mobilecoind_db
+                .outputs_for_subaddress(&monitor_id, 2)
+                .unwrap(),
+            vec![]
+        );
+
+        // Subaddresses outside the monitor's range are rejected.
+        match mobilecoind_db.outputs_for_subaddress(&monitor_id, 3) {
+            Err(Error::InvalidArgument(_, _)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    // A receipt is confirmed once its output is received by the monitor, and
+    // fails if the monitor syncs past its tombstone block without receiving it.
+    #[test_with_logger]
+    fn test_verify_receipt(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            2,
+            &[account_key.default_subaddress()],
+            5,
+            logger.clone(),
+            &mut rng,
+        );
+
+        let monitor_data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        // Each block has a single output sent to the account.
+        let own_tx_out = |block_index: u64| {
+            get_block_tx_outs_for_account(&ledger_db, block_index, &account_key)[0].clone()
+        };
+        let other_tx_out = |block_index: u64| {
+            let own_tx_out = own_tx_out(block_index);
+            ledger_db
+                .get_block_contents(block_index)
+                .unwrap()
+                .outputs
+                .into_iter()
+                .find(|tx_out| *tx_out != own_tx_out)
+                .unwrap()
+        };
+        let utxo = |block_index: u64| UnspentTxOut {
+            tx_out: own_tx_out(block_index),
+            subaddress_index: 0,
+            key_image: KeyImage::from(block_index),
+            value: 10,
+            attempted_spend_height: 0,
+            attempted_spend_tombstone: 0,
+            received_block_index: block_index,
+        };
+        let receipt = |tx_out: TxOut, tombstone_block: u64| {
+            let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
+            let shared_secret =
+                get_tx_out_shared_secret(account_key.view_private_key(), &tx_public_key);
+            Receipt {
+                tx_public_key: tx_out.public_key,
+                confirmation_number: TxOutConfirmationNumber::from(&shared_secret),
+                tombstone_block,
+            }
+        };
+
+        mobilecoind_db
+            .block_processed(&monitor_id, 0, &[], &[])
+            .unwrap();
+        mobilecoind_db
+            .block_processed(&monitor_id, 1, &[utxo(1)], &[])
+            .unwrap();
+
+        // An output the monitor received.
+        assert_eq!(
+            mobilecoind_db
+                .verify_receipt(&monitor_id, &receipt(own_tx_out(1), 10))
+                .unwrap(),
+            ReceiptStatus::Confirmed(1)
+        );
+
+        // A confirmation number for a different output is refused.
+        let mut forged_receipt = receipt(own_tx_out(1), 10);
+        forged_receipt.confirmation_number = receipt(own_tx_out(0), 10).confirmation_number;
+        match mobilecoind_db.verify_receipt(&monitor_id, &forged_receipt) {
+            Err(Error::InvalidArgument(_, _)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // Outputs the monitor has not received yet, one of which belongs to someone
+        // else.
+        let own_receipt = receipt(own_tx_out(3), 10);
+        let other_receipt = receipt(other_tx_out(2), 4);
+        assert_eq!(
+            mobilecoind_db
+                .verify_receipt(&monitor_id, &own_receipt)
+                .unwrap(),
+            ReceiptStatus::Pending
+        );
+        assert_eq!(
+            mobilecoind_db
+                .verify_receipt(&monitor_id, &other_receipt)
+                .unwrap(),
+            ReceiptStatus::Pending
+        );
+
+        mobilecoind_db
+            .block_processed(&monitor_id, 2, &[utxo(2)], &[])
+            .unwrap();
+        mobilecoind_db
+            .block_processed(&monitor_id, 3, &[utxo(3)], &[])
+            .unwrap();
+
+        assert_eq!(
+            mobilecoind_db
+                .verify_receipt(&monitor_id, &own_receipt)
+                .unwrap(),
+            ReceiptStatus::Confirmed(3)
+        );
+        assert_eq!(
+            mobilecoind_db
+                .verify_receipt(&monitor_id, &other_receipt)
+                .unwrap(),
+            ReceiptStatus::Failed
+        );
+    }
+
+    // Exporting a snapshot and importing it into an empty database should restore
+    // the monitors, and outputs that are not in the ledger should be refused.
+    #[test_with_logger]
+    fn test_monitor_snapshot_round_trip(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        let (ledger_db, mobilecoind_db) =
+            get_test_databases(3, &vec![], 10, logger.clone(), &mut rng);
+
+        let new_mobilecoind_db = || {
+            let mobilecoind_db_tmp =
+                TempDir::new("mobilecoind_db").expect("Could not make tempdir for mobilecoind db");
+            Database::new(mobilecoind_db_tmp.path(), logger.clone())
+                .expect("failed creating new mobilecoind db")
+        };
+
+        let monitor_data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,  // first_subaddress
+            2,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        let utxo =
+            |block_num: u64, output_index: usize, subaddress_index: u64, value: u64| UnspentTxOut {
+                tx_out: ledger_db.get_block_contents(block_num).unwrap().outputs[output_index]
+                    .clone(),
+                subaddress_index,
+                key_image: KeyImage::from(value),
+                value,
+                attempted_spend_height: 0,
+                attempted_spend_tombstone: 0,
+                received_block_index: block_num,
+            };
+
+        // Receive two outputs in block 0, and in block 1 receive another one and
+        // spend one of the first two.
+        let received_in_block_0 = vec![utxo(0, 0, 0, 10), utxo(0, 1, 1, 20)];
+        mobilecoind_db
+            .block_processed(&monitor_id, 0, &received_in_block_0, &[])
+            .unwrap();
+        mobilecoind_db
+            .block_processed(
+                &monitor_id,
+                1,
+                &[utxo(1, 0, 0, 30)],
+                &[received_in_block_0[0].key_image],
+            )
+            .unwrap();
+        mobilecoind_db
+            .block_processed(&monitor_id, 2, &[], &[])
+            .unwrap();
+
+        let mut snapshot_bytes = Vec::new();
+        mobilecoind_db
+            .export_monitor_snapshot(&mut snapshot_bytes)
+            .unwrap();
+
+        // Restore into an empty database.
+        let restored_db = new_mobilecoind_db();
+        assert_eq!(
+            restored_db
+                .import_monitor_snapshot(&mut snapshot_bytes.as_slice(), &ledger_db)
+                .unwrap(),
+            vec![monitor_id]
+        );
+
+        assert_eq!(
+            restored_db.get_monitor_map().unwrap(),
+            mobilecoind_db.get_monitor_map().unwrap()
+        );
+        for index in 0..2 {
+            assert_eq!(
+                restored_db
+                    .get_utxos_for_subaddress(&monitor_id, index)
+                    .unwrap(),
+                mobilecoind_db
+                    .get_utxos_for_subaddress(&monitor_id, index)
+                    .unwrap()
+            );
+        }
+        for block_num in 0..3 {
+            assert_eq!(
+                restored_db
+                    .get_processed_block(&monitor_id, block_num)
+                    .unwrap(),
+                mobilecoind_db
+                    .get_processed_block(&monitor_id, block_num)
+                    .unwrap()
+            );
+        }
+        assert_eq!(
+            restored_db
+                .get_utxos_for_subaddress(&monitor_id, 0)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // The restored database exports the same snapshot.
+        let mut restored_snapshot_bytes = Vec::new();
+        restored_db
+            .export_monitor_snapshot(&mut restored_snapshot_bytes)
+            .unwrap();
+        assert_eq!(restored_snapshot_bytes, snapshot_bytes);
+
+        // Monitors that already exist are not imported again.
+        match restored_db.import_monitor_snapshot(&mut snapshot_bytes.as_slice(), &ledger_db) {
+            Err(Error::MonitorIdExists) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // A snapshot with an output from a different ledger is refused, and nothing
+        // is imported.
+        let (other_ledger_db, _other_mobilecoind_db) =
+            get_test_databases(3, &vec![], 1, logger.clone(), &mut rng);
+        let foreign_tx_out = other_ledger_db.get_block_contents(0).unwrap().outputs[0].clone();
+
+        let mut snapshot: MonitorStoreSnapshot = mc_util_serial::decode(&snapshot_bytes).unwrap();
+        snapshot.monitors[0].unspent_tx_outs[0].tx_out = foreign_tx_out.clone();
+        let tampered_snapshot_bytes = mc_util_serial::encode(&snapshot);
+
+        let empty_db = new_mobilecoind_db();
+        match empty_db.import_monitor_snapshot(&mut tampered_snapshot_bytes.as_slice(), &ledger_db)
+        {
+            Err(Error::TxOutNotInLedger(public_key)) => {
+                assert_eq!(public_key, foreign_tx_out.public_key)
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(empty_db.get_monitor_ids().unwrap(), vec![]);
+    }
+
+    // Writers that have to wait for the write lock held by another writer should
+    // be counted as contended.
+    #[test_with_logger]
+    fn test_write_lock_contention_is_counted(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+        let (_ledger_db, mobilecoind_db) =
+            get_test_databases(0, &vec![], 1, logger.clone(), &mut rng);
+
+        let contended_before = counters::DB_WRITE_LOCK_CONTENDED.get();
+
+        // Hold the write lock for a while from another thread.
+        let (locked_sender, locked_receiver) = std::sync::mpsc::channel();
+        let holder = {
+            let mobilecoind_db = mobilecoind_db.clone();
+            std::thread::spawn(move || {
+                let db_txn = mobilecoind_db.env.begin_rw_txn().unwrap();
+                locked_sender.send(()).unwrap();
+                std::thread::sleep(Duration::from_millis(100));
+                db_txn.commit().unwrap();
+            })
+        };
+        locked_receiver.recv().unwrap();
+
+        // Concurrent writers all wait for the lock to be released.
+        let writers: Vec<_> = (0..4)
+            .map(|_i| {
+                let mobilecoind_db = mobilecoind_db.clone();
+                let (data, monitor_id) = get_test_monitor_data_and_id(&mut rng);
+                std::thread::spawn(move || {
+                    assert_eq!(mobilecoind_db.add_monitor(&data).unwrap(), monitor_id);
+                })
+            })
+            .collect();
+
+        holder.join().unwrap();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        assert_eq!(mobilecoind_db.get_monitor_ids().unwrap().len(), 4);
+        assert!(counters::DB_WRITE_LOCK_CONTENDED.get() > contended_before);
+    }
 }
@@ -4,12 +4,15 @@
 
 use crate::db_crypto::DbCryptoError;
 use failure::Fail;
+use grpcio::RpcStatusCode;
 use lmdb::Error as LmdbError;
 use mc_connection::Error as ConnectionError;
 use mc_consensus_api::ConversionError;
-use mc_crypto_keys::KeyError;
+use mc_crypto_keys::{CompressedRistrettoPublic, KeyError};
 use mc_ledger_db::Error as LedgerDbError;
-use mc_util_lmdb::MetadataStoreError;
+use mc_mobilecoind_api::ErrorCode;
+use mc_transaction_core::validation::TransactionValidationError;
+use mc_util_lmdb::{CompactError, MetadataStoreError};
 use mc_util_serial::{decode::Error as DecodeError, encode::Error as EncodeError};
 use prost::DecodeError as ProstDecodeError;
 use retry::Error as RetryError;
@@ -52,6 +55,9 @@ pub enum Error {
     #[fail(display = "An entry in MonitorStore already exists for this MonitorId key")]
     MonitorIdExists,
 
+    #[fail(display = "A monitor named '{}' already exists", _0)]
+    MonitorNameExists(String),
+
     #[fail(display = "No matching key in MonitorStore was found")]
     MonitorIdNotFound,
 
@@ -88,8 +94,11 @@ pub enum Error {
     #[fail(display = "Insufficient funds")]
     InsufficientFunds,
 
-    #[fail(display = "Insufficient funds due to UTXO fragmentation")]
-    InsufficientFundsFragmentedUtxos,
+    #[fail(
+        display = "Amount too fragmented, consolidate first: {} inputs needed but at most {} allowed",
+        _0, _1
+    )]
+    InsufficientFundsFragmentedUtxos(usize, usize),
 
     #[fail(display = "The validator node we're tying to talk to was not found")]
     NodeNotFound,
@@ -117,6 +126,70 @@ pub enum Error {
 
     #[fail(display = "Db encryption: {}", _0)]
     DbCrypto(DbCryptoError),
+
+    #[fail(
+        display = "Too many pending transactions: monitor already has {} pending transactions",
+        _0
+    )]
+    TooManyPendingTxs(usize),
+
+    #[fail(display = "TxOut with public key {} is not in the ledger", _0)]
+    TxOutNotInLedger(CompressedRistrettoPublic),
+
+    #[fail(
+        display = "Ledger advanced from block {} to block {} since the transaction was built, rebuild transaction",
+        _0, _1
+    )]
+    LedgerAdvanced(u64, u64),
+
+    #[fail(display = "Compaction error: {}", _0)]
+    Compaction(CompactError),
+
+    #[fail(
+        display = "Request already partially paid: subaddress {} has received funds",
+        _0
+    )]
+    PaymentRequestPartiallyPaid(u64),
+
+    #[fail(display = "Balance won't fit in u64, fetch utxo list instead")]
+    BalanceOverflow,
+}
+
+impl Error {
+    /// The gRPC status code of a call failed by this error, and the code
+    /// reported to clients so that they can tell the errors they handle apart
+    /// without matching on the message.
+    pub fn rpc_codes(&self) -> (RpcStatusCode, ErrorCode) {
+        match self {
+            Self::InvalidArgument(_, _) => {
+                (RpcStatusCode::INVALID_ARGUMENT, ErrorCode::Unspecified)
+            }
+            Self::InsufficientFunds | Self::InsufficientFundsFragmentedUtxos(_, _) => {
+                (RpcStatusCode::INTERNAL, ErrorCode::InsufficientFunds)
+            }
+            Self::Connection(RetryError::Operation {
+                error:
+                    ConnectionError::TransactionValidation(
+                        TransactionValidationError::TombstoneBlockExceeded,
+                    ),
+                ..
+            }) => (RpcStatusCode::INTERNAL, ErrorCode::TombstoneExceeded),
+            Self::MonitorIdNotFound => (RpcStatusCode::INTERNAL, ErrorCode::InvalidMonitor),
+            Self::BlockNotYetProcessed(_, _) => (RpcStatusCode::INTERNAL, ErrorCode::LedgerBehind),
+            Self::LedgerAdvanced(_, _) => {
+                (RpcStatusCode::FAILED_PRECONDITION, ErrorCode::LedgerBehind)
+            }
+            Self::TooManyPendingTxs(_) => (
+                RpcStatusCode::RESOURCE_EXHAUSTED,
+                ErrorCode::TooManyPendingTxs,
+            ),
+            Self::PaymentRequestPartiallyPaid(_) => (
+                RpcStatusCode::FAILED_PRECONDITION,
+                ErrorCode::PaymentRequestPartiallyPaid,
+            ),
+            _ => (RpcStatusCode::INTERNAL, ErrorCode::Unspecified),
+        }
+    }
 }
 
 impl From<RetryError<ConnectionError>> for Error {
@@ -179,6 +252,12 @@ impl From<KeyError> for Error {
     }
 }
 
+impl From<CompactError> for Error {
+    fn from(e: CompactError) -> Self {
+        Self::Compaction(e)
+    }
+}
+
 impl From<MetadataStoreError> for Error {
     fn from(e: MetadataStoreError) -> Self {
         Self::MetadataStore(e)
@@ -10,14 +10,19 @@ pub mod payments;
 pub mod service;
 
 mod conversions;
+mod counters;
 mod database_key;
 mod db_crypto;
 mod error;
+mod monitor_snapshot;
 mod monitor_store;
+mod pending_tx_store;
 mod processed_block_store;
+mod scan_throughput;
 mod subaddress_store;
 mod sync;
 mod utxo_store;
+pub use sync::BlockProcessingRetry;
 pub use utxo_store::UnspentTxOut;
 
 #[cfg(any(test, feature = "test_utils"))]
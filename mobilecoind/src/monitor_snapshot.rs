@@ -0,0 +1,48 @@
+// Copyright (c) 2018-2021 The MobileCoin Foundation
+
+//! Snapshots of monitor state.
+//! * A snapshot holds, for each monitor, its data along with the outputs it
+//!   matched and spent, so that the monitor store can be rebuilt without
+//!   rescanning the ledger.
+//! * Snapshots contain account keys, and must be kept as secret as the database
+//!   itself.
+
+use crate::{
+    monitor_store::MonitorData, processed_block_store::ProcessedTxOut, utxo_store::UnspentTxOut,
+};
+use prost::Message;
+
+/// The outputs a monitor received or spent in a single block.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct ProcessedBlockSnapshot {
+    /// The block index.
+    #[prost(uint64, tag = "1")]
+    pub block_index: u64,
+
+    /// The received and spent outputs.
+    #[prost(message, repeated, tag = "2")]
+    pub processed_tx_outs: Vec<ProcessedTxOut>,
+}
+
+/// The state of a single monitor.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct MonitorSnapshot {
+    /// The monitor data, including how far the monitor has synced.
+    #[prost(message, required, tag = "1")]
+    pub data: MonitorData,
+
+    /// Outputs received by the monitor that have not been spent.
+    #[prost(message, repeated, tag = "2")]
+    pub unspent_tx_outs: Vec<UnspentTxOut>,
+
+    /// Outputs received and spent by the monitor, by block.
+    #[prost(message, repeated, tag = "3")]
+    pub processed_blocks: Vec<ProcessedBlockSnapshot>,
+}
+
+/// The state of every monitor in the database.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct MonitorStoreSnapshot {
+    #[prost(message, repeated, tag = "1")]
+    pub monitors: Vec<MonitorSnapshot>,
+}
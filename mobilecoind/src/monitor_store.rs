@@ -3,8 +3,13 @@
 //! Database storage for monitors
 //! * Provides monitor configuration and status from MonitorId.
 //! * MonitorId is a hash of the instantiation parameters.
+//! * Monitors whose data fails validation are quarantined: they are marked
+//!   errored, and skipped when listing monitors.
 
-use crate::{database_key::DatabaseByteArrayKey, db_crypto::DbCryptoProvider, error::Error};
+use crate::{
+    database_key::DatabaseByteArrayKey, db_crypto::DbCryptoProvider, error::Error,
+    subaddress_store::SubaddressId,
+};
 
 use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
 use mc_account_keys::AccountKey;
@@ -20,6 +25,8 @@ use std::{convert::TryFrom, ops::Range, sync::Arc};
 // LMDB Database Names
 pub const MONITOR_ID_TO_MONITOR_DATA_DB_NAME: &str =
     "mobilecoind_db:monitor_store:monitor_id_to_monitor_data";
+pub const USED_SUBADDRESS_IDS_DB_NAME: &str = "mobilecoind_db:monitor_store:used_subaddress_ids";
+pub const ERRORED_MONITOR_IDS_DB_NAME: &str = "mobilecoind_db:monitor_store:errored_monitor_ids";
 
 /// Type used as the stored data in the monitor_id_to_monitor_data database.
 #[derive(Clone, Eq, Hash, PartialEq, Message)]
@@ -47,6 +54,35 @@ pub struct MonitorData {
     /// Optional monitor name.
     #[prost(string, tag = "6")]
     pub name: String,
+
+    /// Whether the monitor is paused. Paused monitors are not synced, and
+    /// resume from `next_block` once unpaused.
+    #[prost(bool, tag = "7")]
+    pub paused: bool,
+
+    /// Number of subaddresses to keep watching past the highest one that
+    /// received funds, like the gap limit of HD wallets. When non-zero, the
+    /// range grows as funds arrive near its end. Zero keeps the range fixed.
+    ///
+    /// The range only grows once a block has been processed, so outputs sent
+    /// further than the gap past the highest funded subaddress are missed, as
+    /// are outputs to newly covered subaddresses in the same block as the
+    /// output that grew the range. Re-adding the monitor with a wider range
+    /// finds them.
+    #[prost(uint64, tag = "8")]
+    pub gap_limit: u64,
+
+    /// Number of subaddresses the range grew by after the monitor was created,
+    /// included in `num_subaddresses`.
+    #[prost(uint64, tag = "9")]
+    pub num_extended_subaddresses: u64,
+
+    /// Number of blocks, counting the one it is in, an output must have been
+    /// in the ledger for before it is spendable. Outputs that are too recent
+    /// are left out of balances and are not selected as inputs. Zero and one
+    /// make every output spendable right away.
+    #[prost(uint64, tag = "10")]
+    pub confirmations_required: u64,
 }
 
 impl MonitorData {
@@ -72,12 +108,28 @@ impl MonitorData {
             // The next block we need to sync is our first block.
             next_block: first_block,
             name: name.to_owned(),
+            paused: false,
+            gap_limit: 0,
+            num_extended_subaddresses: 0,
+            confirmations_required: 0,
         })
     }
 
     pub fn subaddress_indexes(&self) -> Range<u64> {
         self.first_subaddress..self.first_subaddress + self.num_subaddresses
     }
+
+    /// Whether an output received in block `block_index` has enough
+    /// confirmations to be spent, in a ledger of `num_blocks` blocks.
+    pub fn is_confirmed(&self, block_index: u64, num_blocks: u64) -> bool {
+        num_blocks.saturating_sub(block_index) >= self.confirmations_required
+    }
+
+    /// Grow the range by `num_subaddresses` subaddresses past its end.
+    pub fn extend_subaddresses(&mut self, num_subaddresses: u64) {
+        self.num_subaddresses += num_subaddresses;
+        self.num_extended_subaddresses += num_subaddresses;
+    }
 }
 
 /// Type used as the key in the monitor_id_to_monitor_data database
@@ -89,6 +141,8 @@ impl From<&MonitorData> for MonitorId {
     // Name isn't included here - two monitors with identical address/subaddress
     // range/first_block should have the same id even if they have a different
     // name,
+    // The subaddress range is the one the monitor was created with, so that the
+    // id does not change when the range grows.
     fn from(src: &MonitorData) -> MonitorId {
         // The structure of mc_account_keys::PublicAddress changed when the fog
         // signature scheme was implemented. This re-implements the original
@@ -133,7 +187,7 @@ impl From<&MonitorData> for MonitorId {
                     .to_vec(),
             },
             first_subaddress: src.first_subaddress,
-            num_subaddresses: src.num_subaddresses,
+            num_subaddresses: src.num_subaddresses - src.num_extended_subaddresses,
             first_block: src.first_block,
         };
 
@@ -154,6 +208,13 @@ pub struct MonitorStore {
     /// Mapping of MonitorId -> MonitorData
     monitor_id_to_monitor_data: Database,
 
+    /// Set of SubaddressIds that have been handed out or have received outputs.
+    used_subaddress_ids: Database,
+
+    /// Mapping of quarantined MonitorId -> Description of the validation
+    /// failure.
+    errored_monitor_ids: Database,
+
     /// Logger.
     logger: Logger,
 }
@@ -170,10 +231,18 @@ impl MonitorStore {
             DatabaseFlags::empty(),
         )?;
 
+        let used_subaddress_ids =
+            env.create_db(Some(USED_SUBADDRESS_IDS_DB_NAME), DatabaseFlags::empty())?;
+
+        let errored_monitor_ids =
+            env.create_db(Some(ERRORED_MONITOR_IDS_DB_NAME), DatabaseFlags::empty())?;
+
         Ok(Self {
             env,
             crypto_provider,
             monitor_id_to_monitor_data,
+            used_subaddress_ids,
+            errored_monitor_ids,
             logger,
         })
     }
@@ -212,7 +281,10 @@ impl MonitorStore {
         monitor_id: &MonitorId,
     ) -> Result<(), Error> {
         db_txn.del(self.monitor_id_to_monitor_data, monitor_id, None)?;
-        Ok(())
+        match db_txn.del(self.errored_monitor_ids, monitor_id, None) {
+            Ok(_) | Err(lmdb::Error::NotFound) => Ok(()),
+            Err(err) => Err(Error::Lmdb(err)),
+        }
     }
 
     /// Get the MonitorData for a given `monitor_id`.
@@ -232,11 +304,13 @@ impl MonitorStore {
         }
     }
 
-    /// Get a hashmap of all MonitorId -> MonitorData.
+    /// Get a hashmap of all MonitorId -> MonitorData. Errored monitors are
+    /// skipped.
     pub fn get_map(
         &self,
         db_txn: &impl Transaction,
     ) -> Result<HashMap<MonitorId, MonitorData>, Error> {
+        let errored_monitor_ids = self.get_errored(db_txn)?;
         let mut cursor = db_txn.open_ro_cursor(self.monitor_id_to_monitor_data)?;
 
         cursor
@@ -247,17 +321,22 @@ impl MonitorStore {
                     .and_then(|(key_bytes, value_bytes)| {
                         let monitor_id = MonitorId::try_from(key_bytes)
                             .map_err(|_| Error::KeyDeserializationError)?;
+                        if errored_monitor_ids.contains_key(&monitor_id) {
+                            return Ok(None);
+                        }
                         let value_bytes = self.crypto_provider.decrypt(value_bytes)?;
                         let data: MonitorData = mc_util_serial::decode(&value_bytes)?;
 
-                        Ok((monitor_id, data))
+                        Ok(Some((monitor_id, data)))
                     })
             })
+            .filter_map(Result::transpose)
             .collect::<Result<HashMap<_, _>, Error>>()
     }
 
-    /// Get a list of all MonitorIds in database.
+    /// Get a list of all MonitorIds in database. Errored monitors are skipped.
     pub fn get_ids(&self, db_txn: &impl Transaction) -> Result<Vec<MonitorId>, Error> {
+        let errored_monitor_ids = self.get_errored(db_txn)?;
         let mut cursor = db_txn.open_ro_cursor(self.monitor_id_to_monitor_data)?;
         cursor
             .iter()
@@ -268,9 +347,109 @@ impl MonitorStore {
                         MonitorId::try_from(key_bytes).map_err(|_| Error::KeyDeserializationError)
                     })
             })
+            .filter(|result| match result {
+                Ok(monitor_id) => !errored_monitor_ids.contains_key(monitor_id),
+                Err(_) => true,
+            })
             .collect::<Result<Vec<_>, Error>>()
     }
 
+    /// Get a hashmap of all errored MonitorId -> Description of the validation
+    /// failure.
+    pub fn get_errored(
+        &self,
+        db_txn: &impl Transaction,
+    ) -> Result<HashMap<MonitorId, String>, Error> {
+        let mut cursor = db_txn.open_ro_cursor(self.errored_monitor_ids)?;
+        cursor
+            .iter()
+            .map(|result| {
+                result
+                    .map_err(Error::from)
+                    .and_then(|(key_bytes, value_bytes)| {
+                        let monitor_id = MonitorId::try_from(key_bytes)
+                            .map_err(|_| Error::KeyDeserializationError)?;
+                        Ok((
+                            monitor_id,
+                            String::from_utf8_lossy(value_bytes).into_owned(),
+                        ))
+                    })
+            })
+            .collect::<Result<HashMap<_, _>, Error>>()
+    }
+
+    /// Validate the data of every monitor, and mark monitors whose data cannot
+    /// be decoded, or does not match their MonitorId, as errored. Returns the
+    /// newly errored monitors. This requires the crypto provider to be
+    /// unlocked.
+    pub fn quarantine_invalid<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+    ) -> Result<Vec<MonitorId>, Error> {
+        let errored_monitor_ids = self.get_errored(db_txn)?;
+
+        let entries = {
+            let mut cursor = db_txn.open_ro_cursor(self.monitor_id_to_monitor_data)?;
+            cursor
+                .iter()
+                .map(|result| {
+                    result
+                        .map_err(Error::from)
+                        .and_then(|(key_bytes, value_bytes)| {
+                            let monitor_id = MonitorId::try_from(key_bytes)
+                                .map_err(|_| Error::KeyDeserializationError)?;
+                            Ok((monitor_id, value_bytes.to_vec()))
+                        })
+                })
+                .collect::<Result<Vec<_>, Error>>()?
+        };
+
+        let mut quarantined = Vec::new();
+        for (monitor_id, value_bytes) in entries {
+            if errored_monitor_ids.contains_key(&monitor_id) {
+                continue;
+            }
+
+            let reason = match self.validate(&monitor_id, &value_bytes) {
+                Ok(()) => continue,
+                Err(reason) => reason,
+            };
+            log::error!(
+                self.logger,
+                "Quarantining monitor {}: {}",
+                monitor_id,
+                reason
+            );
+            db_txn.put(
+                self.errored_monitor_ids,
+                &monitor_id,
+                &reason,
+                WriteFlags::empty(),
+            )?;
+            quarantined.push(monitor_id);
+        }
+
+        Ok(quarantined)
+    }
+
+    /// Check that stored monitor data decodes to a valid monitor with the given
+    /// MonitorId, returning a description of the problem if it does not.
+    fn validate(&self, monitor_id: &MonitorId, value_bytes: &[u8]) -> Result<(), String> {
+        let value_bytes = self
+            .crypto_provider
+            .decrypt(value_bytes)
+            .map_err(|err| format!("failed decrypting monitor data: {}", err))?;
+        let data: MonitorData = mc_util_serial::decode(&value_bytes)
+            .map_err(|err| format!("failed decoding monitor data: {}", err))?;
+        if data.num_subaddresses == 0 {
+            return Err("monitor data has no subaddresses".to_owned());
+        }
+        if MonitorId::from(&data) != *monitor_id {
+            return Err("monitor data does not match the monitor id".to_owned());
+        }
+        Ok(())
+    }
+
     /// Set the MonitorData for an existing monitor
     pub fn set_data<'env>(
         &self,
@@ -297,6 +476,52 @@ impl MonitorStore {
         }
     }
 
+    /// Mark a subaddress of a given monitor as used.
+    pub fn mark_subaddress_used<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+        index: u64,
+    ) -> Result<(), Error> {
+        let subaddress_id = SubaddressId::new(monitor_id, index);
+        db_txn.put(
+            self.used_subaddress_ids,
+            &subaddress_id.to_bytes(),
+            b"",
+            WriteFlags::empty(),
+        )?;
+        Ok(())
+    }
+
+    /// Check whether a subaddress of a given monitor has been marked as used.
+    pub fn is_subaddress_used(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: &MonitorId,
+        index: u64,
+    ) -> Result<bool, Error> {
+        let subaddress_id = SubaddressId::new(monitor_id, index);
+        match db_txn.get(self.used_subaddress_ids, &subaddress_id.to_bytes()) {
+            Ok(_) => Ok(true),
+            Err(lmdb::Error::NotFound) => Ok(false),
+            Err(err) => Err(Error::Lmdb(err)),
+        }
+    }
+
+    /// Forget usage information for a subaddress of a given monitor.
+    pub fn clear_subaddress_used<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+        index: u64,
+    ) -> Result<(), Error> {
+        let subaddress_id = SubaddressId::new(monitor_id, index);
+        match db_txn.del(self.used_subaddress_ids, &subaddress_id.to_bytes(), None) {
+            Ok(_) | Err(lmdb::Error::NotFound) => Ok(()),
+            Err(err) => Err(Error::Lmdb(err)),
+        }
+    }
+
     /// Re-encrypt the encrypted parts of the database with a new password.
     /// This will fail if the current password is not set in the crypto_provider
     /// since part of the re-encryption process relies on being able to
@@ -2,7 +2,13 @@
 
 //! Construct and submit transactions to the validator network.
 
-use crate::{database::Database, error::Error, monitor_store::MonitorId, utxo_store::UnspentTxOut};
+use crate::{
+    database::Database,
+    error::Error,
+    monitor_store::{MonitorData, MonitorId},
+    pending_tx_store::PendingTx,
+    utxo_store::{UnspentTxOut, UtxoId},
+};
 use mc_account_keys::{AccountKey, PublicAddress};
 use mc_common::{
     logger::{log, o, Logger},
@@ -12,16 +18,20 @@ use mc_connection::{
     BlockchainConnection, ConnectionManager, RetryableBlockchainConnection,
     RetryableUserTxConnection, UserTxConnection,
 };
-use mc_crypto_keys::RistrettoPublic;
+use mc_crypto_keys::{CompressedRistrettoPublic, RistrettoPublic};
 use mc_crypto_rand::{CryptoRng, RngCore};
 use mc_fog_report_validation::FogPubkeyResolver;
 use mc_ledger_db::{Error as LedgerError, Ledger, LedgerDB};
 use mc_transaction_core::{
-    constants::{MAX_INPUTS, MILLIMOB_TO_PICOMOB, RING_SIZE},
+    constants::{MAX_INPUTS, MAX_OUTPUTS, MILLIMOB_TO_PICOMOB, MINIMUM_FEE, RING_SIZE},
+    membership_proofs::{compute_implied_merkle_root, is_membership_proof_valid, Range},
     onetime_keys::recover_onetime_private_key,
-    ring_signature::KeyImage,
-    tx::{Tx, TxOut, TxOutConfirmationNumber, TxOutMembershipProof},
-    BlockIndex,
+    ring_signature::{CurveScalar, KeyImage, RingMLSAG, SignatureRctBulletproofs},
+    tx::{
+        Tx, TxIn, TxOut, TxOutConfirmationNumber, TxOutMembershipElement, TxOutMembershipProof,
+        TxPrefix,
+    },
+    BlockIndex, CompressedCommitment,
 };
 use mc_transaction_std::{InputCredentials, TransactionBuilder};
 use mc_util_uri::FogUri;
@@ -34,7 +44,7 @@ use std::{
     str::FromStr,
     sync::{
         atomic::{AtomicUsize, Ordering},
-        Arc,
+        Arc, Mutex,
     },
 };
 
@@ -46,10 +56,139 @@ pub const DEFAULT_NEW_TX_BLOCK_ATTEMPTS: u64 = 50;
 /// Default ring size
 pub const DEFAULT_RING_SIZE: usize = RING_SIZE;
 
+/// Number of TxOuts `estimate_tx_size` assumes the ledger holds. It determines
+/// the length of the membership proofs included in the transaction.
+pub const TX_SIZE_ESTIMATE_NUM_TX_OUTS: u64 = 1 << 24;
+
+/// How far, in percent of the actual size, a transaction size estimate may be
+/// off.
+pub const TX_SIZE_ESTIMATE_TOLERANCE_PERCENT: usize = 10;
+
+/// Number of blocks the ledger may advance past the block the membership
+/// proofs of a transaction were generated against, before the transaction is
+/// refused for submission and needs to be rebuilt.
+pub const MAX_MEMBERSHIP_PROOF_AGE: u64 = 10;
+
 /// The original hard-coded 10mMOB fee, used as a fallback when calls to
 /// consensus fail or we have no peers.
 const FALLBACK_FEE: u64 = 10 * MILLIMOB_TO_PICOMOB;
 
+/// How urgently a transaction should be included in the ledger. Higher
+/// priorities pay a multiple of the base fee.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FeePriority {
+    Low,
+    Normal,
+    High,
+}
+
+impl Default for FeePriority {
+    fn default() -> Self {
+        FeePriority::Normal
+    }
+}
+
+/// The factor by which the base fee gets multiplied for each `FeePriority`.
+/// The normal priority, which clients get unless they ask otherwise, pays the
+/// base fee by default, and only the high priority pays more.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FeePriorityMultipliers {
+    pub low: u64,
+    pub normal: u64,
+    pub high: u64,
+}
+
+impl Default for FeePriorityMultipliers {
+    fn default() -> Self {
+        Self {
+            low: 1,
+            normal: 1,
+            high: 2,
+        }
+    }
+}
+
+impl FeePriorityMultipliers {
+    /// Get the multiplier for a given priority.
+    pub fn get(&self, priority: FeePriority) -> u64 {
+        match priority {
+            FeePriority::Low => self.low,
+            FeePriority::Normal => self.normal,
+            FeePriority::High => self.high,
+        }
+    }
+}
+
+/// How mixins are sampled out of the TxOuts in the ledger.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MixinDistribution {
+    /// Every TxOut is equally likely to be sampled.
+    Uniform,
+
+    /// The age of sampled TxOuts, counted in TxOuts back from the most recent
+    /// one, follows a gamma distribution with the given shape and scale. Real
+    /// inputs tend to be recent outputs, so such mixins are harder to tell
+    /// apart from them than uniformly sampled ones. Ages beyond the oldest
+    /// TxOut are replaced by a uniformly sampled TxOut.
+    Gamma { shape: f64, scale: f64 },
+}
+
+impl Default for MixinDistribution {
+    fn default() -> Self {
+        MixinDistribution::Uniform
+    }
+}
+
+impl MixinDistribution {
+    /// Sample the index of one of the first `num_txos` TxOuts.
+    fn sample_index<R: Rng>(&self, num_txos: u64, rng: &mut R) -> u64 {
+        match *self {
+            MixinDistribution::Uniform => rng.gen_range(0..num_txos),
+            MixinDistribution::Gamma { shape, scale } => {
+                let age = sample_standard_gamma(shape, rng) * scale;
+                if age < num_txos as f64 {
+                    num_txos - 1 - age as u64
+                } else {
+                    rng.gen_range(0..num_txos)
+                }
+            }
+        }
+    }
+}
+
+/// Sample a gamma distribution with the given shape and a scale of 1, using the
+/// method of Marsaglia and Tsang.
+fn sample_standard_gamma<R: Rng>(shape: f64, rng: &mut R) -> f64 {
+    if shape < 1.0 {
+        // Sample with a shape above 1, and scale the sample back down.
+        let u: f64 = rng.gen();
+        return sample_standard_gamma(shape + 1.0, rng) * u.powf(1.0 / shape);
+    }
+
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+    loop {
+        let x = sample_standard_normal(rng);
+        let v = 1.0 + c * x;
+        if v <= 0.0 {
+            continue;
+        }
+        let v = v * v * v;
+        let u: f64 = rng.gen();
+        if u < 1.0 - 0.0331 * x.powi(4) || u.ln() < 0.5 * x * x + d * (1.0 - v + v.ln()) {
+            return d * v;
+        }
+    }
+}
+
+/// Sample a standard normal distribution, using the Box-Muller transform.
+fn sample_standard_normal<R: Rng>(rng: &mut R) -> f64 {
+    // `gen` samples [0, 1), so `u1` is in (0, 1] and has a finite logarithm.
+    let u1: f64 = 1.0 - rng.gen::<f64>();
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
 /// An outlay - the API representation of a desired transaction output.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Outlay {
@@ -60,6 +199,33 @@ pub struct Outlay {
     pub receiver: PublicAddress,
 }
 
+/// Material for building a transaction without access to the ledger, exported
+/// ahead of time from a ledger snapshot.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OfflineTxMaterial {
+    /// UTXOs to spend, with membership proofs.
+    pub inputs: Vec<(UnspentTxOut, TxOutMembershipProof)>,
+
+    /// A ring of mixins for each input, with membership proofs.
+    pub rings: Vec<Vec<(TxOut, TxOutMembershipProof)>>,
+
+    /// The Merkle root all membership proofs are checked against.
+    pub root: TxOutMembershipElement,
+}
+
+/// The outcome of a consolidation, computed without building it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ConsolidationPreview {
+    /// Number of UTXOs the consolidation would spend.
+    pub num_inputs: usize,
+
+    /// Value of the consolidated output.
+    pub output_value: u64,
+
+    /// Fee the consolidation would pay.
+    pub fee: u64,
+}
+
 /// A single pending transaction.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct TxProposal {
@@ -111,6 +277,29 @@ pub struct TransactionsManager<
     /// This is abstracted because in tests, we don't want to form grpc
     /// connections to fog
     fog_resolver_factory: Arc<dyn Fn(&[FogUri]) -> Result<FPR, String> + Send + Sync>,
+
+    /// Maximum number of inputs a built transaction is allowed to have.
+    /// Defaults to MAX_INPUTS.
+    max_inputs: usize,
+
+    /// Transactions submitted to the network that may still land, keyed by tx
+    /// hash. This is persisted in the mobilecoind database so that input
+    /// reservations survive restarts.
+    pending_txs: Arc<Mutex<HashMap<Vec<u8>, PendingTx>>>,
+
+    /// Multipliers applied to the base fee for each fee priority.
+    fee_priority_multipliers: FeePriorityMultipliers,
+
+    /// Maximum number of transactions a single monitor may have pending at
+    /// once. Unlimited if not set.
+    max_pending_txs_per_monitor: Option<usize>,
+
+    /// Lowest fee transactions built by this manager may pay, regardless of the
+    /// fee required by the network.
+    min_local_fee: u64,
+
+    /// How mixins are sampled out of the ledger.
+    mixin_distribution: MixinDistribution,
 }
 
 impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolver> Clone
@@ -124,17 +313,166 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             logger: self.logger.clone(),
             submit_node_offset: self.submit_node_offset.clone(),
             fog_resolver_factory: self.fog_resolver_factory.clone(),
+            max_inputs: self.max_inputs,
+            pending_txs: self.pending_txs.clone(),
+            fee_priority_multipliers: self.fee_priority_multipliers,
+            max_pending_txs_per_monitor: self.max_pending_txs_per_monitor,
+            min_local_fee: self.min_local_fee,
+            mixin_distribution: self.mixin_distribution,
+        }
+    }
+}
+
+/// Split the UTXOs of a monitor into those that have the confirmations the
+/// monitor requires, and those that are too recent to be spent yet.
+pub fn split_by_confirmations<L: mc_ledger_db::read_only_ledger::ReadOnlyLedger + ?Sized>(
+    ledger_db: &L,
+    monitor_data: &MonitorData,
+    utxos: Vec<UnspentTxOut>,
+) -> Result<(Vec<UnspentTxOut>, Vec<UnspentTxOut>), Error> {
+    if monitor_data.confirmations_required <= 1 {
+        return Ok((utxos, Vec::new()));
+    }
+
+    let num_blocks = ledger_db.num_blocks()?;
+    let mut confirmed = Vec::new();
+    let mut unconfirmed = Vec::new();
+    for utxo in utxos {
+        let tx_out_index = ledger_db.get_tx_out_index_by_public_key(&utxo.tx_out.public_key)?;
+        let block_index = ledger_db.get_block_index_by_tx_out_index(tx_out_index)?;
+        if monitor_data.is_confirmed(block_index, num_blocks) {
+            confirmed.push(utxo);
+        } else {
+            unconfirmed.push(utxo);
         }
     }
+    Ok((confirmed, unconfirmed))
 }
 
-fn get_fee<T: BlockchainConnection + UserTxConnection + 'static>(
+/// Estimate the fee of a transaction. A non-zero `opt_fee` is used as is,
+/// otherwise the base fee (the highest minimum fee of the configured consensus
+/// nodes) is multiplied according to `priority`.
+pub fn estimate_fee<T: BlockchainConnection + UserTxConnection + 'static>(
     peer_manager: &ConnectionManager<T>,
     opt_fee: u64,
+    priority: FeePriority,
+    multipliers: &FeePriorityMultipliers,
 ) -> u64 {
     if opt_fee > 0 {
         opt_fee
-    } else if peer_manager.is_empty() {
+    } else {
+        get_base_fee(peer_manager).saturating_mul(multipliers.get(priority))
+    }
+}
+
+/// Estimate the serialized size, in bytes, of a transaction with the given
+/// number of inputs, outputs (including change) and ring size, assuming a
+/// ledger of TX_SIZE_ESTIMATE_NUM_TX_OUTS outputs.
+pub fn estimate_tx_size(num_inputs: usize, num_outputs: usize, ring_size: usize) -> usize {
+    estimate_tx_size_for_ledger(
+        num_inputs,
+        num_outputs,
+        ring_size,
+        TX_SIZE_ESTIMATE_NUM_TX_OUTS,
+    )
+}
+
+/// Estimate the serialized size, in bytes, of a transaction whose membership
+/// proofs are relative to a ledger holding `num_tx_outs` outputs.
+///
+/// A placeholder transaction of the same shape is encoded, so the estimate
+/// only differs from the real size by the length of variable-length integers.
+pub fn estimate_tx_size_for_ledger(
+    num_inputs: usize,
+    num_outputs: usize,
+    ring_size: usize,
+    num_tx_outs: u64,
+) -> usize {
+    let highest_index = num_tx_outs.max(1) - 1;
+
+    let mut tx_out = TxOut::default();
+    tx_out.amount.masked_value = u64::MAX;
+
+    // A proof holds the leaf itself, and a sibling for each level of the tree.
+    let proof_len = 1 + num_tx_outs.next_power_of_two().trailing_zeros() as usize;
+    let proof = TxOutMembershipProof::new(
+        highest_index,
+        highest_index,
+        vec![
+            TxOutMembershipElement::new(
+                Range {
+                    from: highest_index / 2,
+                    to: highest_index,
+                },
+                [0u8; 32],
+            );
+            proof_len
+        ],
+    );
+    let tx_in = TxIn {
+        ring: vec![tx_out.clone(); ring_size],
+        proofs: vec![proof; ring_size],
+    };
+
+    let ring_signature = RingMLSAG {
+        c_zero: CurveScalar::default(),
+        responses: vec![CurveScalar::default(); 2 * ring_size],
+        key_image: KeyImage::default(),
+    };
+
+    // A single 64-bit bulletproof covers the pseudo-outputs and the outputs,
+    // padded to a power of two. It holds 9 curve points and scalars, plus two
+    // for every round of the inner product argument.
+    let num_range_proof_values = (num_inputs + num_outputs).next_power_of_two();
+    let num_rounds = 6 + num_range_proof_values.trailing_zeros() as usize;
+    let range_proof_len = 32 * (9 + 2 * num_rounds);
+
+    let tx = Tx {
+        prefix: TxPrefix::new(
+            vec![tx_in; num_inputs],
+            vec![tx_out; num_outputs],
+            MINIMUM_FEE,
+            u64::from(u32::MAX),
+        ),
+        signature: SignatureRctBulletproofs {
+            ring_signatures: vec![ring_signature; num_inputs],
+            pseudo_output_commitments: vec![CompressedCommitment::default(); num_inputs],
+            range_proof_bytes: vec![0u8; range_proof_len],
+        },
+    };
+    mc_util_serial::encode(&tx).len()
+}
+
+/// Check whether the size estimate of a built transaction is within
+/// TX_SIZE_ESTIMATE_TOLERANCE_PERCENT of its actual size.
+fn is_tx_size_estimate_accurate(tx: &Tx) -> bool {
+    let (ring_size, num_tx_outs) = tx
+        .prefix
+        .inputs
+        .first()
+        .and_then(|tx_in| tx_in.proofs.first().map(|proof| (tx_in.ring.len(), proof)))
+        .map_or((0, 0), |(ring_size, proof)| {
+            (ring_size, proof.highest_index + 1)
+        });
+    let estimate = estimate_tx_size_for_ledger(
+        tx.prefix.inputs.len(),
+        tx.prefix.outputs.len(),
+        ring_size,
+        num_tx_outs,
+    );
+    let actual = mc_util_serial::encode(tx).len();
+    let difference = if estimate > actual {
+        estimate - actual
+    } else {
+        actual - estimate
+    };
+    difference * 100 <= actual * TX_SIZE_ESTIMATE_TOLERANCE_PERCENT
+}
+
+fn get_base_fee<T: BlockchainConnection + UserTxConnection + 'static>(
+    peer_manager: &ConnectionManager<T>,
+) -> u64 {
+    if peer_manager.is_empty() {
         FALLBACK_FEE
     } else {
         // iterate an owned list of connections in parallel, get the block info for
@@ -165,9 +503,11 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         mobilecoind_db: Database,
         peer_manager: ConnectionManager<T>,
         fog_resolver_factory: Arc<dyn Fn(&[FogUri]) -> Result<FPR, String> + Send + Sync>,
+        min_local_fee: u64,
         logger: Logger,
     ) -> Self {
         let mut rng = rand::thread_rng();
+        let pending_txs = Self::load_pending_txs(&ledger_db, &mobilecoind_db, &logger);
         Self {
             ledger_db,
             mobilecoind_db,
@@ -175,7 +515,258 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             logger,
             submit_node_offset: Arc::new(AtomicUsize::new(rng.next_u64() as usize)),
             fog_resolver_factory,
+            max_inputs: MAX_INPUTS as usize,
+            pending_txs: Arc::new(Mutex::new(pending_txs)),
+            fee_priority_multipliers: FeePriorityMultipliers::default(),
+            max_pending_txs_per_monitor: None,
+            min_local_fee,
+            mixin_distribution: MixinDistribution::default(),
+        }
+    }
+
+    /// Load pending transactions persisted by a previous instance, dropping the
+    /// ones whose tombstone block has passed.
+    fn load_pending_txs(
+        ledger_db: &LedgerDB,
+        mobilecoind_db: &Database,
+        logger: &Logger,
+    ) -> HashMap<Vec<u8>, PendingTx> {
+        let result = ledger_db
+            .num_blocks()
+            .map_err(Error::from)
+            .and_then(|num_blocks| mobilecoind_db.remove_expired_pending_txs(num_blocks))
+            .and_then(|expired| {
+                log::debug!(logger, "Dropped {} expired pending txs", expired.len());
+                mobilecoind_db.get_pending_txs()
+            });
+
+        match result {
+            Ok(pending_txs) => pending_txs
+                .into_iter()
+                .map(|pending_tx| (pending_tx.tx_hash.clone(), pending_tx))
+                .collect(),
+            Err(err) => {
+                log::error!(logger, "Failed loading pending txs: {}", err);
+                HashMap::default()
+            }
+        }
+    }
+
+    /// Record that a transaction was submitted at a given block height,
+    /// reserving its inputs until it lands or its tombstone block passes.
+    /// Fails with `TooManyPendingTxs` if the monitor spending the inputs
+    /// already has the maximum number of pending transactions.
+    pub fn reserve_inputs(
+        &self,
+        tx_proposal: &TxProposal,
+        submitted_block_height: u64,
+    ) -> Result<(), Error> {
+        let pending_tx = PendingTx {
+            tx_hash: tx_proposal.tx.tx_hash().to_vec(),
+            utxo_ids: tx_proposal.utxos.iter().map(UtxoId::from).collect(),
+            submitted_block_height,
+            tombstone_block: tx_proposal.tx.prefix.tombstone_block,
+            monitor_id: self.get_monitor_id_of_inputs(tx_proposal),
+            change_value: Self::get_change_value(tx_proposal),
+        };
+
+        let num_blocks = self.ledger_db.num_blocks()?;
+        let mut pending_txs = self.pending_txs.lock().expect("mutex poisoned");
+        if !pending_txs.contains_key(&pending_tx.tx_hash) {
+            self.check_pending_txs_limit(&pending_txs, pending_tx.monitor_id.as_ref(), num_blocks)?;
+        }
+        self.mobilecoind_db.add_pending_tx(&pending_tx)?;
+        pending_txs.insert(pending_tx.tx_hash.clone(), pending_tx);
+        Ok(())
+    }
+
+    /// Get the transactions whose inputs are currently reserved. Transactions
+    /// whose tombstone block has passed are dropped.
+    pub fn pending_txs(&self) -> Result<Vec<PendingTx>, Error> {
+        let num_blocks = self.ledger_db.num_blocks()?;
+
+        let mut pending_txs = self.pending_txs.lock().expect("mutex poisoned");
+        for pending_tx in self.mobilecoind_db.remove_expired_pending_txs(num_blocks)? {
+            pending_txs.remove(&pending_tx.tx_hash);
+        }
+        pending_txs.retain(|_, pending_tx| !pending_tx.is_expired(num_blocks));
+
+        Ok(pending_txs.values().cloned().collect())
+    }
+
+    /// Get the value a tx proposal returns to the sender as change: whatever
+    /// the inputs hold beyond the outlays and the fee.
+    fn get_change_value(tx_proposal: &TxProposal) -> u64 {
+        let input_value: u64 = tx_proposal.utxos.iter().map(|utxo| utxo.value).sum();
+        let outlay_value: u64 = tx_proposal.outlays.iter().map(|outlay| outlay.value).sum();
+        input_value
+            .saturating_sub(outlay_value)
+            .saturating_sub(tx_proposal.tx.prefix.fee)
+    }
+
+    /// Get the monitor that owns the inputs of a tx proposal, if any.
+    fn get_monitor_id_of_inputs(&self, tx_proposal: &TxProposal) -> Option<MonitorId> {
+        let utxo = tx_proposal.utxos.first()?;
+        self.mobilecoind_db
+            .get_subaddress_id_by_utxo_id(&UtxoId::from(utxo))
+            .ok()
+            .map(|subaddress_id| subaddress_id.monitor_id)
+    }
+
+    /// Check that a monitor is allowed another pending transaction. Expired
+    /// transactions do not count towards the limit.
+    fn check_pending_txs_limit(
+        &self,
+        pending_txs: &HashMap<Vec<u8>, PendingTx>,
+        monitor_id: Option<&MonitorId>,
+        num_blocks: u64,
+    ) -> Result<(), Error> {
+        let (max_pending_txs, monitor_id) = match (self.max_pending_txs_per_monitor, monitor_id) {
+            (Some(max_pending_txs), Some(monitor_id)) => (max_pending_txs, monitor_id),
+            _ => return Ok(()),
+        };
+
+        let num_pending_txs = pending_txs
+            .values()
+            .filter(|pending_tx| {
+                !pending_tx.is_expired(num_blocks)
+                    && pending_tx.monitor_id.as_ref() == Some(monitor_id)
+            })
+            .count();
+        if num_pending_txs >= max_pending_txs {
+            return Err(Error::TooManyPendingTxs(num_pending_txs));
+        }
+        Ok(())
+    }
+
+    /// Drop the reservation of the inputs of a tx proposal, if any.
+    fn release_inputs(&self, tx_proposal: &TxProposal) -> Result<(), Error> {
+        let tx_hash = tx_proposal.tx.tx_hash().to_vec();
+        let mut pending_txs = self.pending_txs.lock().expect("mutex poisoned");
+        self.mobilecoind_db.remove_pending_tx(&tx_hash)?;
+        pending_txs.remove(&tx_hash);
+        Ok(())
+    }
+
+    /// Get the ids of the UTXOs reserved by pending transactions.
+    pub fn reserved_utxo_ids(&self) -> Result<HashSet<UtxoId>, Error> {
+        Ok(self
+            .pending_txs()?
+            .into_iter()
+            .flat_map(|pending_tx| pending_tx.utxo_ids.into_iter())
+            .collect())
+    }
+
+    /// Limit the number of inputs transactions built by this manager may use.
+    /// The limit is capped at MAX_INPUTS, since consensus rejects transactions
+    /// with more inputs than that.
+    pub fn set_max_inputs(&mut self, max_inputs: usize) -> Result<(), Error> {
+        if max_inputs == 0 {
+            return Err(Error::InvalidArgument(
+                "max_inputs".to_owned(),
+                "must be greater than zero".to_owned(),
+            ));
+        }
+        self.max_inputs = max_inputs.min(MAX_INPUTS as usize);
+        Ok(())
+    }
+
+    /// The maximum number of inputs transactions built by this manager may use.
+    pub fn max_inputs(&self) -> usize {
+        self.max_inputs
+    }
+
+    /// Limit the number of transactions a single monitor may have pending at
+    /// once, so that one client cannot tie up an unbounded number of input
+    /// reservations. `None` removes the limit.
+    pub fn set_max_pending_txs_per_monitor(
+        &mut self,
+        max_pending_txs: Option<usize>,
+    ) -> Result<(), Error> {
+        if max_pending_txs == Some(0) {
+            return Err(Error::InvalidArgument(
+                "max_pending_txs_per_monitor".to_owned(),
+                "must be greater than zero".to_owned(),
+            ));
+        }
+        self.max_pending_txs_per_monitor = max_pending_txs;
+        Ok(())
+    }
+
+    /// The maximum number of transactions a single monitor may have pending at
+    /// once, if limited.
+    pub fn max_pending_txs_per_monitor(&self) -> Option<usize> {
+        self.max_pending_txs_per_monitor
+    }
+
+    /// The lowest fee transactions built by this manager may pay.
+    pub fn min_local_fee(&self) -> u64 {
+        self.min_local_fee
+    }
+
+    /// The fee a transaction built by this manager pays, given the requested
+    /// fee and priority. Fees below the local minimum are raised to it.
+    fn get_fee(&self, opt_fee: u64, priority: FeePriority) -> u64 {
+        estimate_fee(
+            &self.peer_manager,
+            opt_fee,
+            priority,
+            &self.fee_priority_multipliers,
+        )
+        .max(self.min_local_fee)
+    }
+
+    /// Configure how mixins are sampled out of the ledger. The parameters of a
+    /// gamma distribution must be positive.
+    pub fn set_mixin_distribution(
+        &mut self,
+        mixin_distribution: MixinDistribution,
+    ) -> Result<(), Error> {
+        if let MixinDistribution::Gamma { shape, scale } = mixin_distribution {
+            // Written so that NaN is rejected too.
+            if !(shape > 0.0 && shape.is_finite() && scale > 0.0 && scale.is_finite()) {
+                return Err(Error::InvalidArgument(
+                    "mixin_distribution".to_owned(),
+                    "gamma shape and scale must be positive".to_owned(),
+                ));
+            }
+        }
+        self.mixin_distribution = mixin_distribution;
+        Ok(())
+    }
+
+    /// How mixins are sampled out of the ledger.
+    pub fn mixin_distribution(&self) -> MixinDistribution {
+        self.mixin_distribution
+    }
+
+    /// Configure the factor by which the base fee is multiplied for each fee
+    /// priority. Multipliers must be non-zero, since a zero fee is rejected by
+    /// consensus, must not decrease with the priority, and the high priority
+    /// must pay more than the normal one.
+    pub fn set_fee_priority_multipliers(
+        &mut self,
+        multipliers: FeePriorityMultipliers,
+    ) -> Result<(), Error> {
+        if multipliers.low == 0 || multipliers.normal == 0 || multipliers.high == 0 {
+            return Err(Error::InvalidArgument(
+                "fee_priority_multipliers".to_owned(),
+                "must be greater than zero".to_owned(),
+            ));
+        }
+        if multipliers.low > multipliers.normal || multipliers.normal >= multipliers.high {
+            return Err(Error::InvalidArgument(
+                "fee_priority_multipliers".to_owned(),
+                "must satisfy low <= normal < high".to_owned(),
+            ));
         }
+        self.fee_priority_multipliers = multipliers;
+        Ok(())
+    }
+
+    /// The factors by which the base fee is multiplied for each fee priority.
+    pub fn fee_priority_multipliers(&self) -> FeePriorityMultipliers {
+        self.fee_priority_multipliers
     }
 
     /// Create a TxProposal.
@@ -183,10 +774,14 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     /// # Arguments
     /// * `send_monitor_id` - ???
     /// * `change_subaddress` - Recipient of any change.
-    /// * `inputs` - UTXOs that will be spent by the transaction.
+    /// * `inputs` - UTXOs that may be spent by the transaction. Those without
+    ///   the confirmations the monitor requires are left out.
     /// * `outlays` - Output amounts and recipients.
     /// * `opt_fee` - Transaction fee in picoMOB. If zero, defaults to MIN_FEE.
+    /// * `priority` - Fee priority, used when `opt_fee` is zero.
     /// * `opt_tombstone` - Tombstone block. If zero, sets to default.
+    /// * `logger` - Logger for the lines logged while building, e.g. the one of
+    ///   the call being handled.
     pub fn build_transaction(
         &self,
         sender_monitor_id: &MonitorId,
@@ -194,9 +789,72 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         inputs: &[UnspentTxOut],
         outlays: &[Outlay],
         opt_fee: u64,
+        priority: FeePriority,
+        opt_tombstone: u64,
+        logger: &Logger,
+    ) -> Result<TxProposal, Error> {
+        self.build_transaction_impl(
+            sender_monitor_id,
+            change_subaddress,
+            None,
+            inputs,
+            outlays,
+            opt_fee,
+            priority,
+            opt_tombstone,
+            logger,
+        )
+    }
+
+    /// Create a TxProposal that spends a given output, such as the one a
+    /// receipt refers to, with the rest of the inputs selected as usual.
+    /// The output comes first in the proposal's UTXOs. Note that the inputs of
+    /// the Tx itself are sorted, so it is not necessarily the first of those.
+    ///
+    /// # Arguments
+    /// * `first_input` - Public key of the output to spend, which must be an
+    ///   unspent output of the sender monitor that is not reserved by a pending
+    ///   transaction.
+    ///
+    /// The other arguments are as in `build_transaction`.
+    pub fn build_transaction_with_first_input(
+        &self,
+        sender_monitor_id: &MonitorId,
+        change_subaddress: u64,
+        first_input: &CompressedRistrettoPublic,
+        inputs: &[UnspentTxOut],
+        outlays: &[Outlay],
+        opt_fee: u64,
+        priority: FeePriority,
+        opt_tombstone: u64,
+        logger: &Logger,
+    ) -> Result<TxProposal, Error> {
+        self.build_transaction_impl(
+            sender_monitor_id,
+            change_subaddress,
+            Some(first_input),
+            inputs,
+            outlays,
+            opt_fee,
+            priority,
+            opt_tombstone,
+            logger,
+        )
+    }
+
+    fn build_transaction_impl(
+        &self,
+        sender_monitor_id: &MonitorId,
+        change_subaddress: u64,
+        first_input: Option<&CompressedRistrettoPublic>,
+        inputs: &[UnspentTxOut],
+        outlays: &[Outlay],
+        opt_fee: u64,
+        priority: FeePriority,
         opt_tombstone: u64,
+        logger: &Logger,
     ) -> Result<TxProposal, Error> {
-        let logger = self.logger.new(o!("sender_monitor_id" => sender_monitor_id.to_string(), "outlays" => format!("{:?}", outlays)));
+        let logger = logger.new(o!("sender_monitor_id" => sender_monitor_id.to_string(), "outlays" => format!("{:?}", outlays)));
         log::trace!(logger, "Building pending transaction...");
 
         // Must have at least one output
@@ -209,6 +867,16 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         // Get sender monitor data.
         let sender_monitor_data = self.mobilecoind_db.get_monitor_data(sender_monitor_id)?;
 
+        // Outputs without the confirmations the monitor requires cannot be spent yet,
+        // and those reserved by pending transactions would be double-spent.
+        let (inputs, _) =
+            split_by_confirmations(&self.ledger_db, &sender_monitor_data, inputs.to_vec())?;
+        let reserved_utxo_ids = self.reserved_utxo_ids()?;
+        let inputs: Vec<UnspentTxOut> = inputs
+            .into_iter()
+            .filter(|utxo| !reserved_utxo_ids.contains(&UtxoId::from(utxo)))
+            .collect();
+
         // Figure out total amount of transaction (excluding fee).
         let total_value: u64 = outlays.iter().map(|outlay| outlay.value).sum();
         log::trace!(
@@ -219,11 +887,31 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
 
         // Figure out the fee (involves network round-trips to consensus, unless
         // opt_fee is non-zero
-        let fee = get_fee(&self.peer_manager, opt_fee);
+        let fee = self.get_fee(opt_fee, priority);
 
         // Select the UTXOs to be used for this transaction.
-        let selected_utxos =
-            Self::select_utxos_for_value(inputs, total_value + fee, MAX_INPUTS as usize)?;
+        let selected_utxos = match first_input {
+            None => Self::select_utxos_for_value(&inputs, total_value + fee, self.max_inputs)?,
+            Some(public_key) => {
+                let first_utxo = self.get_first_input(sender_monitor_id, public_key)?;
+                let remaining_value = (total_value + fee).saturating_sub(first_utxo.value);
+
+                let mut selected_utxos = vec![first_utxo.clone()];
+                if remaining_value > 0 {
+                    let other_inputs: Vec<UnspentTxOut> = inputs
+                        .iter()
+                        .filter(|utxo| utxo.tx_out.public_key != first_utxo.tx_out.public_key)
+                        .cloned()
+                        .collect();
+                    selected_utxos.extend(Self::select_utxos_for_value(
+                        &other_inputs,
+                        remaining_value,
+                        self.max_inputs - 1,
+                    )?);
+                }
+                selected_utxos
+            }
+        };
         log::trace!(
             logger,
             "Selected {} utxos ({:?})",
@@ -279,7 +967,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             tombstone_block,
             &self.fog_resolver_factory,
             &mut rng,
-            &self.logger,
+            &logger,
         )?;
         log::trace!(logger, "Tx constructed, hash={}", tx_proposal.tx.tx_hash());
 
@@ -292,13 +980,17 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     /// # Arguments
     /// * `monitor_id` - Monitor ID of the inputs to spend.
     /// * `subaddress_index` - Subaddress of the inputs to spend.
+    /// * `fee` - Transaction fee in picoMOB. If zero, defaults to the normal
+    ///   priority fee.
+    /// * `logger` - Logger for the lines logged while building.
     pub fn generate_optimization_tx(
         &self,
         monitor_id: &MonitorId,
         subaddress_index: u64,
         fee: u64,
+        logger: &Logger,
     ) -> Result<TxProposal, Error> {
-        let logger = self.logger.new(
+        let logger = logger.new(
             o!("monitor_id" => monitor_id.to_string(), "subaddress_index" => subaddress_index),
         );
         log::trace!(logger, "Generating optimization transaction...");
@@ -308,17 +1000,20 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
 
         let num_blocks_in_ledger = self.ledger_db.num_blocks()?;
 
-        let fee = get_fee(&self.peer_manager, fee);
+        let fee = self.get_fee(fee, FeePriority::Normal);
 
         // Select UTXOs that will be spent by this transaction.
         let selected_utxos = {
-            let inputs = self
-                .mobilecoind_db
-                .get_utxos_for_subaddress(monitor_id, subaddress_index)?;
+            let (inputs, _) = split_by_confirmations(
+                &self.ledger_db,
+                &monitor_data,
+                self.mobilecoind_db
+                    .get_utxos_for_subaddress(monitor_id, subaddress_index)?,
+            )?;
             Self::select_utxos_for_optimization(
                 num_blocks_in_ledger,
                 &inputs,
-                MAX_INPUTS as usize,
+                self.max_inputs,
                 fee,
             )?
         };
@@ -387,7 +1082,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             tombstone_block,
             &self.fog_resolver_factory,
             &mut rng,
-            &self.logger,
+            &logger,
         )?;
         log::trace!(
             logger,
@@ -398,33 +1093,322 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         Ok(tx_proposal)
     }
 
-    /// Create a TxProposal that sends the total value of all inputs minus the
-    /// fee to a single receiver.
+    /// Preview what `build_consolidation` would do for a monitor right now,
+    /// given the same input limit: how many UTXOs it would spend, the value of
+    /// the consolidated output and the fee. Nothing is built or reserved.
     ///
     /// # Arguments
-    /// * `account_key` -Account key that owns the inputs.
-    /// * `inputs` - UTXOs that will be spent by the transaction.
-    /// * `receiver` - The single receiver of the transaction's outputs.
-    /// * `fee` - Transaction fee in picoMOB. If zero, defaults to the highest
-    ///   fee set by configured consensus nodes, or the hard-coded MINIMUM_FEE.
-    pub fn generate_tx_from_tx_list(
+    /// * `monitor_id` - Monitor ID of the inputs to spend.
+    /// * `max_inputs` - Maximum number of UTXOs to spend, capped at the input
+    ///   limit of this manager.
+    pub fn consolidation_preview(
         &self,
-        account_key: &AccountKey,
-        inputs: &[UnspentTxOut],
-        receiver: &PublicAddress,
+        monitor_id: &MonitorId,
+        max_inputs: usize,
+    ) -> Result<ConsolidationPreview, Error> {
+        let num_blocks_in_ledger = self.ledger_db.num_blocks()?;
+
+        let fee = self.get_fee(0, FeePriority::Normal);
+
+        let selected_utxos = self.select_monitor_utxos_for_consolidation(
+            monitor_id,
+            num_blocks_in_ledger,
+            fee,
+            max_inputs,
+        )?;
+        let total_value: u64 = selected_utxos.iter().map(|utxo| utxo.value).sum();
+
+        Ok(ConsolidationPreview {
+            num_inputs: selected_utxos.len(),
+            output_value: total_value - fee,
+            fee,
+        })
+    }
+
+    /// Select the UTXOs of a monitor a consolidation would spend, across all of
+    /// its subaddresses, up to `max_inputs` of them. UTXOs reserved by pending
+    /// transactions are skipped, as are UTXOs without the confirmations the
+    /// monitor requires. Both `consolidation_preview` and `build_consolidation`
+    /// select through here, so that they always pick the same UTXOs.
+    fn select_monitor_utxos_for_consolidation(
+        &self,
+        monitor_id: &MonitorId,
+        num_blocks_in_ledger: u64,
         fee: u64,
+        max_inputs: usize,
+    ) -> Result<Vec<UnspentTxOut>, Error> {
+        if max_inputs == 0 {
+            return Err(Error::InvalidArgument(
+                "max_inputs".to_owned(),
+                "must be greater than zero".to_owned(),
+            ));
+        }
+
+        let monitor_data = self.mobilecoind_db.get_monitor_data(monitor_id)?;
+
+        let reserved_utxo_ids = self.reserved_utxo_ids()?;
+        let mut inputs = Vec::new();
+        for index in monitor_data.subaddress_indexes() {
+            inputs.extend(
+                self.mobilecoind_db
+                    .get_utxos_for_subaddress(monitor_id, index)?
+                    .into_iter()
+                    .filter(|utxo| !reserved_utxo_ids.contains(&UtxoId::from(utxo))),
+            );
+        }
+        let (inputs, _) = split_by_confirmations(&self.ledger_db, &monitor_data, inputs)?;
+        Self::select_utxos_for_consolidation(
+            num_blocks_in_ledger,
+            &inputs,
+            max_inputs.min(self.max_inputs),
+            fee,
+        )
+    }
+
+    /// Create a TxProposal that consolidates the smallest UTXOs of a monitor,
+    /// across all of its subaddresses, into a single output. As many UTXOs as
+    /// fit within the input limit are spent, and their combined value minus
+    /// the fee is sent to the given subaddress. UTXOs reserved by pending
+    /// transactions are not spent.
+    ///
+    /// # Arguments
+    /// * `monitor_id` - Monitor ID of the inputs to spend.
+    /// * `subaddress_index` - Subaddress receiving the consolidated output.
+    /// * `max_inputs` - Maximum number of UTXOs to spend, capped at the input
+    ///   limit of this manager.
+    pub fn build_consolidation(
+        &self,
+        monitor_id: &MonitorId,
+        subaddress_index: u64,
+        max_inputs: usize,
+        logger: &Logger,
     ) -> Result<TxProposal, Error> {
-        let logger = self.logger.new(o!("receiver" => receiver.to_string()));
-        log::trace!(logger, "Generating txo list transaction...");
+        let logger = logger.new(
+            o!("monitor_id" => monitor_id.to_string(), "subaddress_index" => subaddress_index),
+        );
+        log::trace!(logger, "Building consolidation transaction...");
 
-        let fee = get_fee(&self.peer_manager, fee);
+        // Get monitor data.
+        let monitor_data = self.mobilecoind_db.get_monitor_data(monitor_id)?;
 
-        // All inputs are to be spent
-        let total_value: u64 = inputs.iter().map(|utxo| utxo.value).sum();
+        let num_blocks_in_ledger = self.ledger_db.num_blocks()?;
 
-        if total_value < fee {
-            return Err(Error::InsufficientFunds);
-        }
+        let fee = self.get_fee(0, FeePriority::Normal);
+
+        // Select UTXOs that will be spent by this transaction.
+        let selected_utxos = self.select_monitor_utxos_for_consolidation(
+            monitor_id,
+            num_blocks_in_ledger,
+            fee,
+            max_inputs,
+        )?;
+
+        log::trace!(
+            logger,
+            "Selected {} utxos: {:?}",
+            selected_utxos.len(),
+            selected_utxos
+        );
+
+        // Figure out total amount of transaction (excluding fee).
+        let total_value: u64 = selected_utxos.iter().map(|utxo| utxo.value).sum();
+        log::trace!(
+            logger,
+            "Total transaction value excluding fees: {}",
+            total_value
+        );
+
+        // The selected_utxos with corresponding proofs of membership.
+        let selected_utxos_with_proofs: Vec<(UnspentTxOut, TxOutMembershipProof)> = {
+            let outputs: Vec<TxOut> = selected_utxos
+                .iter()
+                .map(|utxo| utxo.tx_out.clone())
+                .collect();
+            let proofs = self.get_membership_proofs(&outputs)?;
+
+            selected_utxos.into_iter().zip(proofs.into_iter()).collect()
+        };
+        log::trace!(logger, "Got membership proofs");
+
+        // A ring of mixins for each selected UTXO.
+        let rings = {
+            let excluded_tx_out_indices: Vec<u64> = selected_utxos_with_proofs
+                .iter()
+                .map(|(_, proof)| proof.index)
+                .collect();
+
+            self.get_rings(
+                DEFAULT_RING_SIZE, // TODO configurable ring size
+                selected_utxos_with_proofs.len(),
+                &excluded_tx_out_indices,
+            )?
+        };
+        log::trace!(logger, "Got {} rings", rings.len());
+
+        // Come up with tombstone block.
+        let tombstone_block = num_blocks_in_ledger + DEFAULT_NEW_TX_BLOCK_ATTEMPTS;
+        log::trace!(logger, "Tombstone block set to {}", tombstone_block);
+
+        // The entire value, minus the fee, goes to the consolidated output.
+        let outlays = vec![Outlay {
+            receiver: monitor_data.account_key.subaddress(subaddress_index),
+            value: total_value - fee,
+        }];
+
+        // Build and return the TxProposal object
+        let mut rng = rand::thread_rng();
+        let tx_proposal = Self::build_tx_proposal(
+            &selected_utxos_with_proofs,
+            rings,
+            fee,
+            &monitor_data.account_key,
+            subaddress_index,
+            &outlays,
+            tombstone_block,
+            &self.fog_resolver_factory,
+            &mut rng,
+            &logger,
+        )?;
+        log::trace!(
+            logger,
+            "Consolidation tx constructed, hash={}",
+            tx_proposal.tx.tx_hash()
+        );
+
+        Ok(tx_proposal)
+    }
+
+    /// Create the TxProposals paying a batch of recipients, such as a payroll,
+    /// from the UTXOs of a monitor across all of its subaddresses. Recipients
+    /// are split across as many transactions as the output limit requires.
+    /// Each transaction pays the normal priority fee and sends its change to
+    /// the monitor's first subaddress.
+    ///
+    /// The batch is built as a whole: inputs are assigned to every
+    /// transaction before any is built, and their inputs are reserved once all
+    /// of them are built. If the monitor can't fund every transaction of the
+    /// batch, nothing is built or reserved. Change is not spendable until it
+    /// lands, so a transaction cannot be funded by the change of another one.
+    /// UTXOs already reserved by pending transactions are not spent.
+    ///
+    /// # Arguments
+    /// * `monitor_id` - Monitor ID of the inputs to spend.
+    /// * `recipients` - Recipients and the amounts they are paid.
+    /// * `logger` - Logger for the lines logged while building.
+    pub fn build_batch_payment(
+        &self,
+        monitor_id: &MonitorId,
+        recipients: &[(PublicAddress, u64)],
+        logger: &Logger,
+    ) -> Result<Vec<TxProposal>, Error> {
+        let logger = logger
+            .new(o!("monitor_id" => monitor_id.to_string(), "num_recipients" => recipients.len()));
+        log::trace!(logger, "Building batch payment...");
+
+        if recipients.is_empty() {
+            return Err(Error::TxBuildError(
+                "Must have at least one destination".into(),
+            ));
+        }
+
+        let monitor_data = self.mobilecoind_db.get_monitor_data(monitor_id)?;
+
+        let fee = self.get_fee(0, FeePriority::Normal);
+
+        // Every transaction keeps an output for change.
+        let outlays: Vec<Outlay> = recipients
+            .iter()
+            .map(|(receiver, value)| Outlay {
+                receiver: receiver.clone(),
+                value: *value,
+            })
+            .collect();
+        let batches: Vec<&[Outlay]> = outlays.chunks(MAX_OUTPUTS as usize - 1).collect();
+
+        let reserved_utxo_ids = self.reserved_utxo_ids()?;
+        let mut inputs = Vec::new();
+        for index in monitor_data.subaddress_indexes() {
+            inputs.extend(
+                self.mobilecoind_db
+                    .get_utxos_for_subaddress(monitor_id, index)?
+                    .into_iter()
+                    .filter(|utxo| !reserved_utxo_ids.contains(&UtxoId::from(utxo))),
+            );
+        }
+        let (mut inputs, _) = split_by_confirmations(&self.ledger_db, &monitor_data, inputs)?;
+
+        // Assign inputs to every transaction before building any of them. Each
+        // transaction spends inputs left over by the previous ones.
+        let mut batch_inputs = Vec::with_capacity(batches.len());
+        for batch in batches.iter() {
+            let value = batch
+                .iter()
+                .fold(fee, |total, outlay| total.saturating_add(outlay.value));
+            let selected_utxos = Self::select_utxos_for_value(&inputs, value, self.max_inputs)?;
+            let selected_utxo_ids: HashSet<UtxoId> =
+                selected_utxos.iter().map(UtxoId::from).collect();
+            inputs.retain(|utxo| !selected_utxo_ids.contains(&UtxoId::from(utxo)));
+            batch_inputs.push(selected_utxos);
+        }
+
+        let mut tx_proposals = Vec::with_capacity(batches.len());
+        for (batch, inputs) in batches.iter().zip(batch_inputs.iter()) {
+            tx_proposals.push(self.build_transaction(
+                monitor_id,
+                monitor_data.first_subaddress,
+                inputs,
+                batch,
+                fee,
+                FeePriority::Normal,
+                0,
+                &logger,
+            )?);
+        }
+        log::trace!(logger, "Built {} transactions", tx_proposals.len());
+
+        // Reserve the inputs of the whole batch, or of none of it.
+        let num_blocks = self.ledger_db.num_blocks()?;
+        for (i, tx_proposal) in tx_proposals.iter().enumerate() {
+            if let Err(err) = self.reserve_inputs(tx_proposal, num_blocks) {
+                for reserved_tx_proposal in &tx_proposals[..i] {
+                    self.release_inputs(reserved_tx_proposal)?;
+                }
+                return Err(err);
+            }
+        }
+
+        Ok(tx_proposals)
+    }
+
+    /// Create a TxProposal that sends the total value of all inputs minus the
+    /// fee to a single receiver.
+    ///
+    /// # Arguments
+    /// * `account_key` -Account key that owns the inputs.
+    /// * `inputs` - UTXOs that will be spent by the transaction.
+    /// * `receiver` - The single receiver of the transaction's outputs.
+    /// * `fee` - Transaction fee in picoMOB. If zero, defaults to the highest
+    ///   fee set by configured consensus nodes, or the hard-coded MINIMUM_FEE.
+    /// * `logger` - Logger for the lines logged while building.
+    pub fn generate_tx_from_tx_list(
+        &self,
+        account_key: &AccountKey,
+        inputs: &[UnspentTxOut],
+        receiver: &PublicAddress,
+        fee: u64,
+        logger: &Logger,
+    ) -> Result<TxProposal, Error> {
+        let logger = logger.new(o!("receiver" => receiver.to_string()));
+        log::trace!(logger, "Generating txo list transaction...");
+
+        let fee = self.get_fee(fee, FeePriority::Normal);
+
+        // All inputs are to be spent
+        let total_value: u64 = inputs.iter().map(|utxo| utxo.value).sum();
+
+        if total_value < fee {
+            return Err(Error::InsufficientFunds);
+        }
 
         log::trace!(
             logger,
@@ -471,7 +1455,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             tombstone_block,
             &self.fog_resolver_factory,
             &mut rng,
-            &self.logger,
+            &logger,
         )?;
         log::trace!(
             logger,
@@ -482,14 +1466,35 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         Ok(tx_proposal)
     }
 
-    /// Submit a previously built tx proposal to the network.
-    pub fn submit_tx_proposal(&self, tx_proposal: &TxProposal) -> Result<u64, Error> {
+    /// Submit a previously built tx proposal to the network, logging to
+    /// `logger`.
+    pub fn submit_tx_proposal(
+        &self,
+        tx_proposal: &TxProposal,
+        logger: &Logger,
+    ) -> Result<u64, Error> {
         // Pick a peer to submit to.
         let responder_ids = self.peer_manager.responder_ids();
         if responder_ids.is_empty() {
             return Err(Error::NoPeersConfigured);
         }
 
+        // Refuse to submit transactions built against an outdated ledger.
+        self.check_membership_proofs_are_recent(&tx_proposal.tx)?;
+
+        // Refuse to submit if the inputs could not be reserved afterwards.
+        {
+            let num_blocks = self.ledger_db.num_blocks()?;
+            let pending_txs = self.pending_txs.lock().expect("mutex poisoned");
+            if !pending_txs.contains_key(&tx_proposal.tx.tx_hash().to_vec()) {
+                self.check_pending_txs_limit(
+                    &pending_txs,
+                    self.get_monitor_id_of_inputs(tx_proposal).as_ref(),
+                    num_blocks,
+                )?;
+            }
+        }
+
         let idx = self.submit_node_offset.fetch_add(1, Ordering::SeqCst);
         let responder_id = &responder_ids[idx % responder_ids.len()];
 
@@ -502,16 +1507,93 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             .map_err(Error::from)?;
 
         log::info!(
-            self.logger,
+            logger,
             "Tx {} submitted at block height {}",
             tx_proposal.tx,
             block_height
         );
 
+        // Reserve the inputs. The transaction was already sent to the network, so
+        // failing to persist the reservation is not treated as a submission error.
+        if let Err(err) = self.reserve_inputs(tx_proposal, block_height) {
+            log::error!(
+                logger,
+                "Failed reserving inputs of tx {}: {}",
+                tx_proposal.tx,
+                err
+            );
+        }
+
         // Successfully submitted.
         Ok(block_height)
     }
 
+    /// Check that the membership proofs of a transaction were generated against
+    /// the ledger as of one of its last MAX_MEMBERSHIP_PROOF_AGE blocks.
+    fn check_membership_proofs_are_recent(&self, tx: &Tx) -> Result<(), Error> {
+        let last_block = self.ledger_db.num_blocks()? - 1;
+
+        // Proofs generated together cover the same TxOuts, so it is enough to
+        // check each distinct highest index once.
+        let highest_indices: HashSet<u64> = tx
+            .prefix
+            .inputs
+            .iter()
+            .flat_map(|tx_in| tx_in.proofs.iter().map(|proof| proof.highest_index))
+            .collect();
+
+        for highest_index in highest_indices {
+            // A proof covers the TxOuts of the blocks up to the one holding its
+            // highest index.
+            let proof_block = self
+                .ledger_db
+                .get_block_index_by_tx_out_index(highest_index)?;
+            if last_block - proof_block > MAX_MEMBERSHIP_PROOF_AGE {
+                return Err(Error::LedgerAdvanced(proof_block, last_block));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the output a transaction is required to spend, checking that it is
+    /// an unspent output of the given monitor and not already reserved by a
+    /// pending transaction.
+    fn get_first_input(
+        &self,
+        monitor_id: &MonitorId,
+        public_key: &CompressedRistrettoPublic,
+    ) -> Result<UnspentTxOut, Error> {
+        let utxo = self
+            .mobilecoind_db
+            .get_account_state(monitor_id)?
+            .utxos
+            .into_iter()
+            .find(|utxo| utxo.tx_out.public_key == *public_key)
+            .ok_or_else(|| {
+                Error::InvalidArgument(
+                    "first_input".to_string(),
+                    "is not an unspent output of the monitor".to_string(),
+                )
+            })?;
+
+        if self.reserved_utxo_ids()?.contains(&UtxoId::from(&utxo)) {
+            return Err(Error::InvalidArgument(
+                "first_input".to_string(),
+                "is reserved by a pending transaction".to_string(),
+            ));
+        }
+
+        let monitor_data = self.mobilecoind_db.get_monitor_data(monitor_id)?;
+        let (confirmed, _) = split_by_confirmations(&self.ledger_db, &monitor_data, vec![utxo])?;
+        confirmed.into_iter().next().ok_or_else(|| {
+            Error::InvalidArgument(
+                "first_input".to_string(),
+                "does not have the confirmations the monitor requires".to_string(),
+            )
+        })
+    }
+
     /// Returns a subset of UTXOs totalling at least the given amount.
     // TODO: This method should take attempted_spend_height into account.
     fn select_utxos_for_value(
@@ -530,10 +1612,23 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             .map(|utxo| utxo.value)
             .sum();
         if value > max_spendable_amount {
-            // See if we merged the UTXOs we would be able to spend this amount.
-            let total_utxos_value: u64 = sorted_utxos.iter().map(|utxo| utxo.value).sum();
-            if total_utxos_value >= value {
-                return Err(Error::InsufficientFundsFragmentedUtxos);
+            // See if we merged the UTXOs we would be able to spend this amount. If so,
+            // report how many inputs (largest first) covering the value would take, so
+            // callers can suggest consolidating first.
+            let mut inputs_needed = 0;
+            let mut covered_value: u64 = 0;
+            for utxo in sorted_utxos.iter() {
+                if covered_value >= value {
+                    break;
+                }
+                covered_value = covered_value.saturating_add(utxo.value);
+                inputs_needed += 1;
+            }
+            if covered_value >= value {
+                return Err(Error::InsufficientFundsFragmentedUtxos(
+                    inputs_needed,
+                    max_inputs,
+                ));
             } else {
                 return Err(Error::InsufficientFunds);
             }
@@ -645,6 +1740,51 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         }
     }
 
+    /// Select UTXOs for consolidation. The smallest spendable UTXOs are
+    /// preferred, to reduce fragmentation: up to `max_inputs` of them are
+    /// selected, skipping the very smallest ones if their combined value does
+    /// not cover the fee.
+    ///
+    /// Returns selected UTXOs
+    fn select_utxos_for_consolidation(
+        num_blocks_in_ledger: u64,
+        inputs: &[UnspentTxOut],
+        max_inputs: usize,
+        fee: u64,
+    ) -> Result<Vec<UnspentTxOut>, Error> {
+        if max_inputs < 2 {
+            return Err(Error::InvalidArgument(
+                "max_inputs".to_owned(),
+                "need at least 2 inputs to be able to merge".to_owned(),
+            ));
+        }
+
+        let mut spendable_inputs: Vec<&UnspentTxOut> = inputs
+            .iter()
+            .filter(|utxo| num_blocks_in_ledger >= utxo.attempted_spend_tombstone)
+            .collect();
+
+        // Consolidating a single UTXO would not reduce the number of UTXOs.
+        if spendable_inputs.len() < 2 {
+            return Err(Error::OptimizationNotBeneficial(
+                "Not enough spendable UTXOs to consolidate".to_owned(),
+            ));
+        }
+
+        spendable_inputs.sort_by_key(|utxo| utxo.value);
+
+        let num_inputs = spendable_inputs.len().min(max_inputs);
+        spendable_inputs
+            .windows(num_inputs)
+            .find(|window| window.iter().map(|utxo| utxo.value).sum::<u64>() > fee)
+            .map(|window| window.iter().cloned().cloned().collect())
+            .ok_or_else(|| {
+                Error::OptimizationNotBeneficial(
+                    "Consolidating UTXOs would result in a loss".to_owned(),
+                )
+            })
+    }
+
     /// Get membership proofs for a list of transaction outputs.
     pub fn get_membership_proofs(
         &self,
@@ -667,37 +1807,9 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         let num_requested = ring_size * num_rings;
         let num_txos = self.ledger_db.num_txos()?;
 
-        // Check that the ledger contains enough tx outs.
-        if excluded_tx_out_indices.len() as u64 > num_txos {
-            return Err(Error::InvalidArgument(
-                "excluded_tx_out_indices".to_string(),
-                "exceeds amount of tx outs in ledger".to_string(),
-            ));
-        }
-
-        if num_requested > (num_txos as usize - excluded_tx_out_indices.len()) {
-            return Err(Error::InsufficientTxOuts);
-        }
-
-        // Randomly sample `num_requested` indices of TxOuts to use as mixins.
-        let mixin_indices: Vec<u64> = {
-            let mut rng = rand::thread_rng();
-            let mut samples: HashSet<u64> = HashSet::default();
-            while samples.len() < num_requested {
-                let index = rng.gen_range(0..num_txos);
-                if excluded_tx_out_indices.contains(&index) {
-                    continue;
-                }
-                samples.insert(index);
-            }
-            samples.into_iter().collect()
-        };
-
-        let mixins_result: Result<Vec<TxOut>, _> = mixin_indices
-            .iter()
-            .map(|&index| self.ledger_db.get_tx_out_by_index(index))
-            .collect();
-        let mixins: Vec<TxOut> = mixins_result?;
+        // Randomly sample `num_requested` TxOuts to use as mixins.
+        let (mixin_indices, mixins) =
+            self.sample_mixins(num_txos, num_requested, excluded_tx_out_indices)?;
 
         let membership_proofs = self
             .ledger_db
@@ -717,15 +1829,219 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         Ok(result)
     }
 
-    /// Create a TxProposal.
+    /// Randomly sample `num_requested` distinct TxOuts out of the first
+    /// `num_txos`, skipping `excluded_tx_out_indices` and pruned TxOuts.
+    /// Returns their indices and the TxOuts.
+    fn sample_mixins(
+        &self,
+        num_txos: u64,
+        num_requested: usize,
+        excluded_tx_out_indices: &[u64],
+    ) -> Result<(Vec<u64>, Vec<TxOut>), Error> {
+        let mut excluded_tx_out_indices = excluded_tx_out_indices.to_vec();
+        let mut mixin_indices = Vec::with_capacity(num_requested);
+        let mut mixins = Vec::with_capacity(num_requested);
+        while mixins.len() < num_requested {
+            let sampled_indices = Self::sample_mixin_indices(
+                num_txos,
+                num_requested - mixins.len(),
+                &excluded_tx_out_indices,
+                &self.mixin_distribution,
+            )?;
+            for index in sampled_indices {
+                match self.ledger_db.get_tx_out_by_index(index) {
+                    Ok(tx_out) => {
+                        mixin_indices.push(index);
+                        mixins.push(tx_out);
+                    }
+                    // Pruned TxOuts cannot be mixins, so sample again in their place.
+                    Err(LedgerError::NotFound) => {}
+                    Err(err) => return Err(err.into()),
+                }
+                excluded_tx_out_indices.push(index);
+            }
+        }
+        Ok((mixin_indices, mixins))
+    }
+
+    /// Randomly sample `num_requested` distinct indices out of the first
+    /// `num_txos` TxOuts, skipping `excluded_tx_out_indices`.
+    fn sample_mixin_indices(
+        num_txos: u64,
+        num_requested: usize,
+        excluded_tx_out_indices: &[u64],
+        distribution: &MixinDistribution,
+    ) -> Result<Vec<u64>, Error> {
+        // Check that there are enough tx outs to sample from.
+        if excluded_tx_out_indices.len() as u64 > num_txos {
+            return Err(Error::InvalidArgument(
+                "excluded_tx_out_indices".to_string(),
+                "exceeds amount of tx outs in ledger".to_string(),
+            ));
+        }
+
+        if num_requested > (num_txos as usize - excluded_tx_out_indices.len()) {
+            return Err(Error::InsufficientTxOuts);
+        }
+
+        let mut rng = rand::thread_rng();
+        let mut samples: HashSet<u64> = HashSet::default();
+        while samples.len() < num_requested {
+            let mut index = distribution.sample_index(num_txos, &mut rng);
+            if *distribution == MixinDistribution::Uniform {
+                if excluded_tx_out_indices.contains(&index) {
+                    continue;
+                }
+            } else {
+                // Skewed distributions keep hitting the same recent TxOuts, so
+                // rather than resampling, move on to the next older TxOut that
+                // is available. There is one, since enough TxOuts were checked
+                // to be available.
+                while excluded_tx_out_indices.contains(&index) || samples.contains(&index) {
+                    index = index.checked_sub(1).unwrap_or(num_txos - 1);
+                }
+            }
+            samples.insert(index);
+        }
+        Ok(samples.into_iter().collect())
+    }
+
+    /// Export everything needed to build a transaction spending `inputs`
+    /// without access to the ledger: the inputs and a ring of mixins for each,
+    /// all with membership proofs against the root of the ledger's last block.
     ///
     /// # Arguments
-    /// * `inputs` - UTXOs to spend, with membership proofs.
-    /// * `rings` - A set of mixins for each input, with membership proofs.
-    /// * `fee` - Transaction fee, in picoMOB.
-    /// * `from_account_key` - Owns the inputs. Also the recipient of any
-    ///   change.
-    /// * `change_subaddress` - Subaddress for change recipient.
+    /// * `inputs` - UTXOs that will be spent by the transaction.
+    /// * `ring_size` - Number of mixins sampled for each input.
+    pub fn export_offline_tx_material(
+        &self,
+        inputs: &[UnspentTxOut],
+        ring_size: usize,
+    ) -> Result<OfflineTxMaterial, Error> {
+        if inputs.is_empty() {
+            return Err(Error::InvalidArgument(
+                "inputs".to_string(),
+                "must not be empty".to_string(),
+            ));
+        }
+
+        // Pin all proofs to a single block, so that they share the same root even if
+        // blocks get appended while exporting.
+        let root_block = self.ledger_db.num_blocks()? - 1;
+        let num_txos = self.ledger_db.get_block(root_block)?.cumulative_txo_count;
+
+        let input_indices = inputs
+            .iter()
+            .map(|utxo| self.ledger_db.get_tx_out_index_by_hash(&utxo.tx_out.hash()))
+            .collect::<Result<Vec<u64>, LedgerError>>()?;
+        let (mixin_indices, mixins) =
+            self.sample_mixins(num_txos, ring_size * inputs.len(), &input_indices)?;
+
+        let all_indices: Vec<u64> = input_indices
+            .iter()
+            .chain(mixin_indices.iter())
+            .cloned()
+            .collect();
+        let mut proofs = self
+            .ledger_db
+            .get_tx_out_proofs_at_root(&all_indices, root_block)?;
+        let mixin_proofs = proofs.split_off(input_indices.len());
+
+        let root = compute_implied_merkle_root(&proofs[0])
+            .map_err(|err| Error::TxBuildError(format!("Invalid membership proof: {:?}", err)))?;
+
+        let mixins_with_proofs: Vec<(TxOut, TxOutMembershipProof)> =
+            mixins.into_iter().zip(mixin_proofs.into_iter()).collect();
+        let rings = mixins_with_proofs
+            .chunks(ring_size)
+            .map(|chunk| chunk.to_vec())
+            .collect();
+
+        Ok(OfflineTxMaterial {
+            inputs: inputs.iter().cloned().zip(proofs.into_iter()).collect(),
+            rings,
+            root,
+        })
+    }
+
+    /// Create a TxProposal purely from previously exported material, without
+    /// access to the ledger. Every membership proof must be valid against
+    /// `material.root`, or no transaction is built.
+    ///
+    /// # Arguments
+    /// * `material` - Inputs and rings, with membership proofs, and their root.
+    /// * `fee` - Transaction fee, in picoMOB.
+    /// * `from_account_key` - Owns the inputs. Also the recipient of any
+    ///   change.
+    /// * `change_subaddress` - Subaddress for change recipient.
+    /// * `destinations` - Outputs of the transaction.
+    /// * `tombstone_block` - Tombstone block of the transaction.
+    /// * `fog_resolver_factory` - Provides Fog key reports, when Fog is
+    ///   enabled.
+    /// * `rng` -
+    /// * `logger` - Logger
+    pub fn build_tx_proposal_offline(
+        material: OfflineTxMaterial,
+        fee: u64,
+        from_account_key: &AccountKey,
+        change_subaddress: u64,
+        destinations: &[Outlay],
+        tombstone_block: BlockIndex,
+        fog_resolver_factory: &Arc<dyn Fn(&[FogUri]) -> Result<FPR, String> + Send + Sync>,
+        rng: &mut (impl RngCore + CryptoRng),
+        logger: &Logger,
+    ) -> Result<TxProposal, Error> {
+        let tx_outs_with_proofs = material
+            .inputs
+            .iter()
+            .map(|(utxo, proof)| (&utxo.tx_out, proof))
+            .chain(
+                material
+                    .rings
+                    .iter()
+                    .flat_map(|ring| ring.iter().map(|(tx_out, proof)| (tx_out, proof))),
+            );
+        for (tx_out, proof) in tx_outs_with_proofs {
+            match is_membership_proof_valid(tx_out, proof, &material.root.hash.0) {
+                Ok(true) => {}
+                Ok(false) => {
+                    return Err(Error::TxBuildError(format!(
+                        "Membership proof for TxOut {} does not match the supplied root",
+                        proof.index
+                    )))
+                }
+                Err(err) => {
+                    return Err(Error::TxBuildError(format!(
+                        "Invalid membership proof for TxOut {}: {:?}",
+                        proof.index, err
+                    )))
+                }
+            }
+        }
+
+        Self::build_tx_proposal(
+            &material.inputs,
+            material.rings,
+            fee,
+            from_account_key,
+            change_subaddress,
+            destinations,
+            tombstone_block,
+            fog_resolver_factory,
+            rng,
+            logger,
+        )
+    }
+
+    /// Create a TxProposal.
+    ///
+    /// # Arguments
+    /// * `inputs` - UTXOs to spend, with membership proofs.
+    /// * `rings` - A set of mixins for each input, with membership proofs.
+    /// * `fee` - Transaction fee, in picoMOB.
+    /// * `from_account_key` - Owns the inputs. Also the recipient of any
+    ///   change.
+    /// * `change_subaddress` - Subaddress for change recipient.
     /// * `destinations` - Outputs of the transaction.
     /// * `tombstone_block` - Tombstone block of the transaciton.
     /// * `fog_pubkey_resolver` - Provides Fog key report, when Fog is enabled.
@@ -897,6 +2213,15 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         let tx = tx_builder
             .build(rng)
             .map_err(|err| Error::TxBuildError(format!("build tx failed: {}", err)))?;
+        if !is_tx_size_estimate_accurate(&tx) {
+            log::warn!(
+                logger,
+                "Transaction size estimate is off by more than {}% for {} inputs and {} outputs",
+                TX_SIZE_ESTIMATE_TOLERANCE_PERCENT,
+                tx.prefix.inputs.len(),
+                tx.prefix.outputs.len()
+            );
+        }
 
         // Map each TxOut in the constructed transaction to its respective outlay.
         let outlay_index_to_tx_out_index = tx
@@ -954,10 +2279,23 @@ fn extract_fog_uri(addr: &PublicAddress) -> Result<Option<FogUri>, Error> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{
+        monitor_store::MonitorData,
+        test_utils::{
+            add_block_to_ledger_db, assert_fog_hint_decrypts_to, decrypt_fog_hint,
+            get_fog_enabled_account, get_test_databases, get_test_monitor_data_and_id,
+            DEFAULT_PER_RECIPIENT_AMOUNT,
+        },
+    };
+    use mc_account_keys::DEFAULT_SUBADDRESS_INDEX;
+    use mc_common::logger::test_with_logger;
     use mc_connection::{HardcodedCredentialsProvider, ThickClient};
     use mc_crypto_keys::RistrettoPrivate;
-    use mc_fog_report_validation::MockFogPubkeyResolver;
-    use mc_transaction_core::constants::{MILLIMOB_TO_PICOMOB, MINIMUM_FEE};
+    use mc_fog_report_validation::{FullyValidatedFogPubkey, MockFogPubkeyResolver};
+    use mc_transaction_core::{
+        constants::{MAX_OUTPUTS, MILLIMOB_TO_PICOMOB, MINIMUM_FEE},
+        get_tx_out_shared_secret,
+    };
     use mc_util_from_random::FromRandom;
     use rand::{rngs::StdRng, SeedableRng};
 
@@ -983,6 +2321,7 @@ mod test {
                 value: 1,
                 attempted_spend_height: 0,
                 attempted_spend_tombstone: 0,
+                received_block_index: 0,
             })
             .collect()
     }
@@ -1036,13 +2375,40 @@ mod test {
         match TransactionsManager::<ThickClient<HardcodedCredentialsProvider>, MockFogPubkeyResolver>::select_utxos_for_value(
             &utxos, 5, 4,
         ) {
-            Err(Error::InsufficientFundsFragmentedUtxos) => {
-                // Expected.
+            Err(Error::InsufficientFundsFragmentedUtxos(inputs_needed, max_inputs)) => {
+                assert_eq!(inputs_needed, 5);
+                assert_eq!(max_inputs, 4);
             }
             _ => panic!("Did not get expected error"),
         };
     }
 
+    #[test]
+    fn test_select_utxos_for_value_reports_inputs_needed_for_many_tiny_utxos() {
+        // 100 utxos worth 1 each. Sending 40 requires 40 inputs, which is more than
+        // MAX_INPUTS allows.
+        let utxos = generate_utxos(100);
+        match TransactionsManager::<ThickClient<HardcodedCredentialsProvider>, MockFogPubkeyResolver>::select_utxos_for_value(
+            &utxos, 40, MAX_INPUTS as usize,
+        ) {
+            Err(Error::InsufficientFundsFragmentedUtxos(inputs_needed, max_inputs)) => {
+                assert_eq!(inputs_needed, 40);
+                assert_eq!(max_inputs, MAX_INPUTS as usize);
+            }
+            _ => panic!("Did not get expected error"),
+        };
+
+        // Sending MAX_INPUTS is still possible.
+        let selected_utxos = TransactionsManager::<
+            ThickClient<HardcodedCredentialsProvider>,
+            MockFogPubkeyResolver,
+        >::select_utxos_for_value(
+            &utxos, MAX_INPUTS, MAX_INPUTS as usize
+        )
+        .unwrap();
+        assert_eq!(selected_utxos.len(), MAX_INPUTS as usize);
+    }
+
     #[test]
     fn test_select_utxos_for_value_errors_if_insufficient_funds() {
         let utxos = generate_utxos(10);
@@ -1106,6 +2472,45 @@ mod test {
         }
     }
 
+    // Consolidation should prefer the smallest UTXOs, skipping ones that do not
+    // cover the fee.
+    #[test]
+    fn test_select_utxos_for_consolidation_selects_smallest_inputs() {
+        let mut utxos = generate_utxos(6);
+
+        utxos[0].value = 100 * MILLIMOB_TO_PICOMOB;
+        utxos[1].value = 200 * MILLIMOB_TO_PICOMOB;
+        utxos[2].value = 150 * MILLIMOB_TO_PICOMOB;
+        utxos[3].value = 300 * MILLIMOB_TO_PICOMOB;
+        utxos[4].value = 2000 * MILLIMOB_TO_PICOMOB;
+        utxos[5].value = 1000 * MILLIMOB_TO_PICOMOB;
+
+        let select = |max_inputs, fee| {
+            TransactionsManager::<
+                ThickClient<HardcodedCredentialsProvider>,
+                MockFogPubkeyResolver,
+            >::select_utxos_for_consolidation(1000, &utxos, max_inputs, fee)
+        };
+
+        // With max_inputs=3 the three smallest are selected.
+        assert_eq!(
+            select(3, MINIMUM_FEE).unwrap(),
+            vec![utxos[0].clone(), utxos[2].clone(), utxos[1].clone()]
+        );
+
+        // Everything fits when max_inputs is large enough.
+        assert_eq!(select(10, MINIMUM_FEE).unwrap().len(), 6);
+
+        // The smallest are skipped when they do not cover the fee.
+        assert_eq!(
+            select(2, 400 * MILLIMOB_TO_PICOMOB).unwrap(),
+            vec![utxos[1].clone(), utxos[3].clone()]
+        );
+
+        // Nothing covers the fee.
+        assert!(select(2, 3000 * MILLIMOB_TO_PICOMOB).is_err());
+    }
+
     // Test behavior around the fee amount (off by one, exact fee, etc).
     #[test]
     fn test_select_utxos_for_optimization_behavior_around_fee() {
@@ -1208,4 +2613,1330 @@ mod test {
         >::select_utxos_for_optimization(1000, &utxos[0..2], 3, MINIMUM_FEE);
         assert!(result.is_err());
     }
+
+    type TestTransactionsManager =
+        TransactionsManager<ThickClient<HardcodedCredentialsProvider>, MockFogPubkeyResolver>;
+
+    #[test]
+    fn test_sample_mixin_indices_follows_gamma_distribution() {
+        let num_txos = 1_000_000;
+        let (shape, scale) = (2.0, 1000.0);
+        let distribution = MixinDistribution::Gamma { shape, scale };
+
+        // Sample rings many times, excluding a recent "real input" each time.
+        let mut ages: Vec<f64> = Vec::new();
+        for run in 0..1000 {
+            let real_input = num_txos - 1 - run;
+            let indices = TestTransactionsManager::sample_mixin_indices(
+                num_txos,
+                RING_SIZE,
+                &[real_input],
+                &distribution,
+            )
+            .unwrap();
+            assert_eq!(indices.len(), RING_SIZE);
+            assert!(!indices.contains(&real_input));
+            ages.extend(indices.iter().map(|index| (num_txos - 1 - index) as f64));
+        }
+
+        // The mean and variance of the ages should be close to those of the
+        // distribution: `shape * scale` and `shape * scale^2`.
+        let n = ages.len() as f64;
+        let mean = ages.iter().sum::<f64>() / n;
+        let variance = ages.iter().map(|age| (age - mean).powi(2)).sum::<f64>() / (n - 1.0);
+
+        let expected_mean = shape * scale;
+        let expected_variance = shape * scale * scale;
+        assert!(
+            (mean - expected_mean).abs() < 0.05 * expected_mean,
+            "mean {} expected {}",
+            mean,
+            expected_mean
+        );
+        assert!(
+            (variance - expected_variance).abs() < 0.1 * expected_variance,
+            "variance {} expected {}",
+            variance,
+            expected_variance
+        );
+
+        // Uniform samples are nowhere near as recent.
+        let indices = TestTransactionsManager::sample_mixin_indices(
+            num_txos,
+            1000,
+            &[],
+            &MixinDistribution::Uniform,
+        )
+        .unwrap();
+        let uniform_mean = indices
+            .iter()
+            .map(|index| (num_txos - 1 - index) as f64)
+            .sum::<f64>()
+            / 1000.0;
+        assert!(uniform_mean > 10.0 * expected_mean);
+    }
+
+    #[test]
+    fn test_sample_mixin_indices_skips_excluded_indices() {
+        // Nearly every sample lands on the few most recent TxOuts.
+        let distribution = MixinDistribution::Gamma {
+            shape: 1.0,
+            scale: 0.1,
+        };
+        let excluded = [99, 98, 95];
+
+        for _ in 0..100 {
+            let indices =
+                TestTransactionsManager::sample_mixin_indices(100, 11, &excluded, &distribution)
+                    .unwrap();
+            let unique: HashSet<u64> = indices.iter().cloned().collect();
+            assert_eq!(unique.len(), 11);
+            assert!(indices.iter().all(|index| !excluded.contains(index)));
+        }
+
+        // Every available TxOut is eventually used.
+        let mut indices =
+            TestTransactionsManager::sample_mixin_indices(100, 97, &excluded, &distribution)
+                .unwrap();
+        indices.sort_unstable();
+        let expected: Vec<u64> = (0..100).filter(|index| !excluded.contains(index)).collect();
+        assert_eq!(indices, expected);
+
+        assert!(
+            TestTransactionsManager::sample_mixin_indices(100, 98, &excluded, &distribution)
+                .is_err()
+        );
+    }
+
+    #[test_with_logger]
+    fn test_set_mixin_distribution_rejects_invalid_parameters(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let (ledger_db, mobilecoind_db) = get_test_databases(3, &[], 1, logger.clone(), &mut rng);
+        let mut manager = TestTransactionsManager::new(
+            ledger_db,
+            mobilecoind_db,
+            ConnectionManager::new(vec![], logger.clone()),
+            Arc::new(|_| Ok(MockFogPubkeyResolver::new())),
+            0,
+            logger,
+        );
+
+        for (shape, scale) in &[
+            (0.0, 1.0),
+            (1.0, -1.0),
+            (f64::NAN, 1.0),
+            (1.0, f64::INFINITY),
+        ] {
+            assert!(manager
+                .set_mixin_distribution(MixinDistribution::Gamma {
+                    shape: *shape,
+                    scale: *scale,
+                })
+                .is_err());
+        }
+        assert_eq!(manager.mixin_distribution(), MixinDistribution::Uniform);
+
+        let distribution = MixinDistribution::Gamma {
+            shape: 0.5,
+            scale: 10.0,
+        };
+        manager.set_mixin_distribution(distribution).unwrap();
+        assert_eq!(manager.mixin_distribution(), distribution);
+    }
+
+    #[test_with_logger]
+    fn test_pending_txs_persist_across_restarts(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let recipient = AccountKey::random(&mut rng).default_subaddress();
+        let (mut ledger_db, mobilecoind_db) =
+            get_test_databases(3, &[recipient.clone()], 10, logger.clone(), &mut rng);
+
+        let new_manager = |ledger_db: &LedgerDB| {
+            TransactionsManager::new(
+                ledger_db.clone(),
+                mobilecoind_db.clone(),
+                ConnectionManager::<ThickClient<HardcodedCredentialsProvider>>::new(
+                    vec![],
+                    logger.clone(),
+                ),
+                Arc::new(|_| Ok(MockFogPubkeyResolver::new())),
+                0,
+                logger.clone(),
+            )
+        };
+
+        let new_proposal = |utxos: Vec<UnspentTxOut>, tombstone_block: u64| {
+            let mut tx = Tx::default();
+            tx.prefix.tombstone_block = tombstone_block;
+            TxProposal {
+                utxos,
+                outlays: vec![],
+                tx,
+                outlay_index_to_tx_out_index: HashMap::default(),
+                outlay_confirmation_numbers: vec![],
+            }
+        };
+
+        let mut utxos = generate_utxos(2);
+        utxos[1].key_image = KeyImage::from(1);
+        let expiring_proposal = new_proposal(vec![utxos[0].clone()], 12);
+        let valid_proposal = new_proposal(vec![utxos[1].clone()], 20);
+
+        // Reserve the inputs of both proposals.
+        let manager = new_manager(&ledger_db);
+        manager.reserve_inputs(&expiring_proposal, 10).unwrap();
+        manager.reserve_inputs(&valid_proposal, 10).unwrap();
+        assert_eq!(manager.pending_txs().unwrap().len(), 2);
+        drop(manager);
+
+        // Move the ledger past the tombstone block of the first proposal.
+        for _ in 0..3 {
+            add_block_to_ledger_db(&mut ledger_db, &[recipient.clone()], 1, &[], &mut rng);
+        }
+
+        // A new manager should only pick up the reservation that is still valid.
+        let manager = new_manager(&ledger_db);
+        let pending_txs = manager.pending_txs().unwrap();
+        assert_eq!(pending_txs.len(), 1);
+        assert_eq!(pending_txs[0].tx_hash, valid_proposal.tx.tx_hash().to_vec());
+        assert_eq!(
+            manager.reserved_utxo_ids().unwrap(),
+            vec![UtxoId::from(&utxos[1])]
+                .into_iter()
+                .collect::<HashSet<_>>()
+        );
+
+        // The expired reservation should also be gone from the database.
+        assert_eq!(mobilecoind_db.get_pending_txs().unwrap(), pending_txs);
+    }
+
+    #[test_with_logger]
+    fn test_max_pending_txs_per_monitor(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([24u8; 32]);
+        let recipient = AccountKey::random(&mut rng).default_subaddress();
+        let (mut ledger_db, mobilecoind_db) =
+            get_test_databases(3, &[recipient.clone()], 10, logger.clone(), &mut rng);
+
+        // Give a monitor a few outputs to spend.
+        let (monitor_data, _) = get_test_monitor_data_and_id(&mut rng);
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+        let mut utxos = generate_utxos(4);
+        for (i, utxo) in utxos.iter_mut().enumerate() {
+            utxo.key_image = KeyImage::from(i as u64);
+        }
+        mobilecoind_db
+            .block_processed(&monitor_id, 0, &utxos, &[])
+            .unwrap();
+
+        let mut manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            ConnectionManager::<ThickClient<HardcodedCredentialsProvider>>::new(
+                vec![],
+                logger.clone(),
+            ),
+            Arc::new(|_| Ok(MockFogPubkeyResolver::new())),
+            0,
+            logger.clone(),
+        );
+        assert!(manager.set_max_pending_txs_per_monitor(Some(0)).is_err());
+        manager.set_max_pending_txs_per_monitor(Some(2)).unwrap();
+
+        let new_proposal = |utxo: &UnspentTxOut, tombstone_block: u64| {
+            let mut tx = Tx::default();
+            tx.prefix.tombstone_block = tombstone_block;
+            TxProposal {
+                utxos: vec![utxo.clone()],
+                outlays: vec![],
+                tx,
+                outlay_index_to_tx_out_index: HashMap::default(),
+                outlay_confirmation_numbers: vec![],
+            }
+        };
+
+        // Reach the limit.
+        manager
+            .reserve_inputs(&new_proposal(&utxos[0], 12), 10)
+            .unwrap();
+        manager
+            .reserve_inputs(&new_proposal(&utxos[1], 20), 10)
+            .unwrap();
+        match manager.reserve_inputs(&new_proposal(&utxos[2], 21), 10) {
+            Err(Error::TooManyPendingTxs(2)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // Inputs that do not belong to a monitor are not limited.
+        let mut foreign_utxo = generate_utxos(1).pop().unwrap();
+        foreign_utxo.key_image = KeyImage::from(100);
+        manager
+            .reserve_inputs(&new_proposal(&foreign_utxo, 22), 10)
+            .unwrap();
+
+        // Once the first transaction expires, its slot is freed.
+        for _ in 0..2 {
+            add_block_to_ledger_db(&mut ledger_db, &[recipient.clone()], 1, &[], &mut rng);
+        }
+        manager
+            .reserve_inputs(&new_proposal(&utxos[2], 21), 12)
+            .unwrap();
+        match manager.reserve_inputs(&new_proposal(&utxos[3], 23), 12) {
+            Err(Error::TooManyPendingTxs(2)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_estimate_fee_priorities(logger: Logger) {
+        let peer_manager =
+            ConnectionManager::<ThickClient<HardcodedCredentialsProvider>>::new(vec![], logger);
+        let multipliers = FeePriorityMultipliers {
+            low: 1,
+            normal: 2,
+            high: 5,
+        };
+
+        let low_fee = estimate_fee(&peer_manager, 0, FeePriority::Low, &multipliers);
+        let normal_fee = estimate_fee(&peer_manager, 0, FeePriority::Normal, &multipliers);
+        let high_fee = estimate_fee(&peer_manager, 0, FeePriority::High, &multipliers);
+
+        assert_eq!(low_fee, FALLBACK_FEE);
+        assert!(high_fee > normal_fee);
+        assert!(normal_fee > low_fee);
+
+        // An explicit fee is used regardless of the priority.
+        assert_eq!(
+            estimate_fee(&peer_manager, 1234, FeePriority::High, &multipliers),
+            1234
+        );
+    }
+
+    #[test]
+    fn test_default_fee_priority_multipliers() {
+        let multipliers = FeePriorityMultipliers::default();
+        // Clients that do not pick a priority pay the base fee.
+        assert_eq!(FeePriority::default(), FeePriority::Normal);
+        assert_eq!(multipliers.get(FeePriority::Low), 1);
+        assert_eq!(multipliers.get(FeePriority::Normal), 1);
+        assert_eq!(multipliers.get(FeePriority::High), 2);
+    }
+
+    #[test_with_logger]
+    fn test_set_fee_priority_multipliers_rejects_invalid(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let (ledger_db, mobilecoind_db) = get_test_databases(3, &[], 10, logger.clone(), &mut rng);
+        let mut manager = TransactionsManager::new(
+            ledger_db,
+            mobilecoind_db,
+            ConnectionManager::<ThickClient<HardcodedCredentialsProvider>>::new(
+                vec![],
+                logger.clone(),
+            ),
+            Arc::new(|_| Ok(MockFogPubkeyResolver::new())),
+            0,
+            logger,
+        );
+
+        let multipliers = FeePriorityMultipliers {
+            low: 0,
+            normal: 1,
+            high: 2,
+        };
+        assert!(manager.set_fee_priority_multipliers(multipliers).is_err());
+        assert_eq!(
+            manager.fee_priority_multipliers(),
+            FeePriorityMultipliers::default()
+        );
+
+        // A higher priority must not pay a lower fee, and the high priority must
+        // pay more than the normal one.
+        for multipliers in &[
+            FeePriorityMultipliers {
+                low: 1,
+                normal: 3,
+                high: 3,
+            },
+            FeePriorityMultipliers {
+                low: 2,
+                normal: 1,
+                high: 4,
+            },
+        ] {
+            assert!(manager.set_fee_priority_multipliers(*multipliers).is_err());
+        }
+        assert_eq!(
+            manager.fee_priority_multipliers(),
+            FeePriorityMultipliers::default()
+        );
+
+        for multipliers in &[
+            FeePriorityMultipliers {
+                low: 2,
+                normal: 2,
+                high: 3,
+            },
+            FeePriorityMultipliers {
+                low: 1,
+                normal: 3,
+                high: 9,
+            },
+        ] {
+            manager.set_fee_priority_multipliers(*multipliers).unwrap();
+            assert_eq!(manager.fee_priority_multipliers(), *multipliers);
+        }
+    }
+
+    /// The outputs of an account, at its default subaddress, in the given
+    /// blocks.
+    fn get_utxos_in_blocks(
+        ledger_db: &LedgerDB,
+        account_key: &AccountKey,
+        block_indexes: std::ops::Range<u64>,
+    ) -> Vec<UnspentTxOut> {
+        block_indexes
+            .flat_map(|block_index| {
+                let outputs = ledger_db.get_block_contents(block_index).unwrap().outputs;
+                outputs.into_iter().map(move |tx_out| (block_index, tx_out))
+            })
+            .filter_map(|(block_index, tx_out)| {
+                let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
+                let shared_secret =
+                    get_tx_out_shared_secret(account_key.view_private_key(), &tx_public_key);
+                let (value, _blinding) = tx_out.amount.get_value(&shared_secret).ok()?;
+                let onetime_private_key = recover_onetime_private_key(
+                    &tx_public_key,
+                    account_key.view_private_key(),
+                    &account_key.default_subaddress_spend_private(),
+                );
+                Some(UnspentTxOut {
+                    tx_out,
+                    subaddress_index: DEFAULT_SUBADDRESS_INDEX,
+                    key_image: KeyImage::from(&onetime_private_key),
+                    value,
+                    attempted_spend_height: 0,
+                    attempted_spend_tombstone: 0,
+                    received_block_index: block_index,
+                })
+            })
+            .collect()
+    }
+
+    #[test_with_logger]
+    fn test_min_local_fee(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([44u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let recipient = AccountKey::random(&mut rng).default_subaddress();
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            5,
+            &[sender.default_subaddress()],
+            12,
+            logger.clone(),
+            &mut rng,
+        );
+
+        let monitor_data = MonitorData::new(
+            sender.clone(),
+            DEFAULT_SUBADDRESS_INDEX, // first subaddress
+            1,                        // number of subaddresses
+            0,                        // first block
+            "",                       // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        // Without peers the computed fee is the fallback fee, below the floor.
+        let min_local_fee = FALLBACK_FEE * 5;
+        let manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db,
+            ConnectionManager::<ThickClient<HardcodedCredentialsProvider>>::new(
+                vec![],
+                logger.clone(),
+            ),
+            Arc::new(|_| Ok(MockFogPubkeyResolver::new())),
+            min_local_fee,
+            logger.clone(),
+        );
+        assert_eq!(manager.min_local_fee(), min_local_fee);
+
+        let utxos = get_utxos_in_blocks(&ledger_db, &sender, 1..3);
+        let outlays = vec![Outlay {
+            value: 1000,
+            receiver: recipient,
+        }];
+
+        for (opt_fee, expected_fee) in &[
+            (0, min_local_fee),
+            (FALLBACK_FEE, min_local_fee),
+            (min_local_fee * 2, min_local_fee * 2),
+        ] {
+            let tx_proposal = manager
+                .build_transaction(
+                    &monitor_id,
+                    DEFAULT_SUBADDRESS_INDEX,
+                    &utxos,
+                    &outlays,
+                    *opt_fee,
+                    FeePriority::Normal,
+                    0,
+                    &logger,
+                )
+                .unwrap();
+            assert_eq!(tx_proposal.tx.prefix.fee, *expected_fee);
+            assert_eq!(tx_proposal.fee(), *expected_fee);
+
+            // The change accounts for the raised fee.
+            let input_value: u64 = tx_proposal.utxos.iter().map(|utxo| utxo.value).sum();
+            let change: Vec<u64> = tx_proposal
+                .tx
+                .prefix
+                .outputs
+                .iter()
+                .filter_map(|tx_out| {
+                    let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
+                    let shared_secret =
+                        get_tx_out_shared_secret(sender.view_private_key(), &tx_public_key);
+                    tx_out
+                        .amount
+                        .get_value(&shared_secret)
+                        .ok()
+                        .map(|(value, _blinding)| value)
+                })
+                .collect();
+            assert_eq!(change, vec![input_value - 1000 - expected_fee]);
+        }
+    }
+
+    #[test_with_logger]
+    fn test_build_consolidation(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([45u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            5,
+            &[sender.default_subaddress()],
+            12,
+            logger.clone(),
+            &mut rng,
+        );
+
+        let monitor_data = MonitorData::new(
+            sender.clone(),
+            DEFAULT_SUBADDRESS_INDEX, // first subaddress
+            1,                        // number of subaddresses
+            0,                        // first block
+            "",                       // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        // The sender has many small UTXOs.
+        let utxos = get_utxos_in_blocks(&ledger_db, &sender, 1..12);
+        assert_eq!(utxos.len(), 11);
+        mobilecoind_db
+            .block_processed(&monitor_id, 0, &utxos, &[])
+            .unwrap();
+
+        let mut manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db,
+            ConnectionManager::<ThickClient<HardcodedCredentialsProvider>>::new(
+                vec![],
+                logger.clone(),
+            ),
+            Arc::new(|_| Ok(MockFogPubkeyResolver::new())),
+            0,
+            logger.clone(),
+        );
+        manager.set_max_inputs(5).unwrap();
+
+        let tx_proposal = manager
+            .build_consolidation(
+                &monitor_id,
+                DEFAULT_SUBADDRESS_INDEX,
+                MAX_INPUTS as usize,
+                &logger,
+            )
+            .unwrap();
+
+        // Five UTXOs are replaced by a single one, reducing the UTXO count.
+        assert_eq!(tx_proposal.utxos.len(), 5);
+        assert_eq!(tx_proposal.tx.prefix.inputs.len(), 5);
+        assert_eq!(tx_proposal.tx.prefix.outputs.len(), 1);
+
+        // The output holds the combined value minus the fee.
+        let tx_out = &tx_proposal.tx.prefix.outputs[0];
+        let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
+        let shared_secret = get_tx_out_shared_secret(sender.view_private_key(), &tx_public_key);
+        let (value, _blinding) = tx_out.amount.get_value(&shared_secret).unwrap();
+        assert_eq!(value, 5 * DEFAULT_PER_RECIPIENT_AMOUNT - tx_proposal.fee());
+    }
+
+    #[test_with_logger]
+    fn test_build_batch_payment(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([46u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            5,
+            &[sender.default_subaddress()],
+            12,
+            logger.clone(),
+            &mut rng,
+        );
+
+        let monitor_data = MonitorData::new(
+            sender.clone(),
+            DEFAULT_SUBADDRESS_INDEX, // first subaddress
+            1,                        // number of subaddresses
+            0,                        // first block
+            "",                       // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        let utxos = get_utxos_in_blocks(&ledger_db, &sender, 1..12);
+        assert_eq!(utxos.len(), 11);
+        mobilecoind_db
+            .block_processed(&monitor_id, 0, &utxos, &[])
+            .unwrap();
+
+        let manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db,
+            ConnectionManager::<ThickClient<HardcodedCredentialsProvider>>::new(
+                vec![],
+                logger.clone(),
+            ),
+            Arc::new(|_| Ok(MockFogPubkeyResolver::new())),
+            0,
+            logger.clone(),
+        );
+
+        // More recipients than fit in a single transaction.
+        let num_recipients = MAX_OUTPUTS as usize + 4;
+
+        // A batch the monitor can't fund reserves nothing.
+        let recipients: Vec<(PublicAddress, u64)> = (0..num_recipients)
+            .map(|_| {
+                (
+                    AccountKey::random(&mut rng).default_subaddress(),
+                    DEFAULT_PER_RECIPIENT_AMOUNT,
+                )
+            })
+            .collect();
+        match manager.build_batch_payment(&monitor_id, &recipients, &logger) {
+            Err(Error::InsufficientFunds) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert!(manager.reserved_utxo_ids().unwrap().is_empty());
+
+        // The outputs cover the total, but the first transaction takes more of them
+        // than its share, and its change can't fund the second one.
+        let value = DEFAULT_PER_RECIPIENT_AMOUNT * 11 / num_recipients as u64 - FALLBACK_FEE;
+        assert!(
+            value * num_recipients as u64 + 2 * FALLBACK_FEE <= 11 * DEFAULT_PER_RECIPIENT_AMOUNT
+        );
+        let recipients: Vec<(PublicAddress, u64)> = (0..num_recipients)
+            .map(|_| (AccountKey::random(&mut rng).default_subaddress(), value))
+            .collect();
+        match manager.build_batch_payment(&monitor_id, &recipients, &logger) {
+            Err(Error::InsufficientFunds) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert!(manager.reserved_utxo_ids().unwrap().is_empty());
+
+        let recipients: Vec<(PublicAddress, u64)> = (0..num_recipients)
+            .map(|_| {
+                (
+                    AccountKey::random(&mut rng).default_subaddress(),
+                    DEFAULT_PER_RECIPIENT_AMOUNT / 4,
+                )
+            })
+            .collect();
+        let tx_proposals = manager
+            .build_batch_payment(&monitor_id, &recipients, &logger)
+            .unwrap();
+        assert_eq!(tx_proposals.len(), 2);
+
+        for tx_proposal in &tx_proposals {
+            assert!(tx_proposal.tx.prefix.outputs.len() <= MAX_OUTPUTS as usize);
+        }
+
+        // Every recipient is paid exactly once.
+        for (receiver, value) in &recipients {
+            let outlays: Vec<&Outlay> = tx_proposals
+                .iter()
+                .flat_map(|tx_proposal| tx_proposal.outlays.iter())
+                .filter(|outlay| outlay.receiver == *receiver)
+                .collect();
+            assert_eq!(outlays.len(), 1);
+            assert_eq!(outlays[0].value, *value);
+        }
+
+        // The inputs of the whole batch are reserved, and no input is spent
+        // twice.
+        let utxo_ids: Vec<UtxoId> = tx_proposals
+            .iter()
+            .flat_map(|tx_proposal| tx_proposal.utxos.iter().map(UtxoId::from))
+            .collect();
+        let reserved_utxo_ids = manager.reserved_utxo_ids().unwrap();
+        assert_eq!(reserved_utxo_ids.len(), utxo_ids.len());
+        assert_eq!(
+            reserved_utxo_ids,
+            utxo_ids.into_iter().collect::<HashSet<_>>()
+        );
+    }
+
+    #[test_with_logger]
+    fn test_consolidation_preview(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([45u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            5,
+            &[sender.default_subaddress()],
+            12,
+            logger.clone(),
+            &mut rng,
+        );
+
+        let monitor_data = MonitorData::new(
+            sender.clone(),
+            DEFAULT_SUBADDRESS_INDEX, // first subaddress
+            1,                        // number of subaddresses
+            0,                        // first block
+            "",                       // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        let utxos = get_utxos_in_blocks(&ledger_db, &sender, 1..12);
+        mobilecoind_db
+            .block_processed(&monitor_id, 0, &utxos, &[])
+            .unwrap();
+
+        let mut manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db,
+            ConnectionManager::<ThickClient<HardcodedCredentialsProvider>>::new(
+                vec![],
+                logger.clone(),
+            ),
+            Arc::new(|_| Ok(MockFogPubkeyResolver::new())),
+            0,
+            logger.clone(),
+        );
+        manager.set_max_inputs(7).unwrap();
+
+        // The input limit of the manager caps the one requested.
+        let preview = manager
+            .consolidation_preview(&monitor_id, MAX_INPUTS as usize)
+            .unwrap();
+        assert_eq!(preview.num_inputs, 7);
+
+        let preview = manager.consolidation_preview(&monitor_id, 4).unwrap();
+        assert_eq!(preview.num_inputs, 4);
+
+        match manager.consolidation_preview(&monitor_id, 0) {
+            Err(Error::InvalidArgument(_, _)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // The preview matches the consolidation that is then built.
+        let tx_proposal = manager
+            .build_consolidation(&monitor_id, DEFAULT_SUBADDRESS_INDEX, 4, &logger)
+            .unwrap();
+        let tx_out = &tx_proposal.tx.prefix.outputs[0];
+        let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
+        let shared_secret = get_tx_out_shared_secret(sender.view_private_key(), &tx_public_key);
+        let (value, _blinding) = tx_out.amount.get_value(&shared_secret).unwrap();
+        assert_eq!(
+            preview,
+            ConsolidationPreview {
+                num_inputs: tx_proposal.utxos.len(),
+                output_value: value,
+                fee: tx_proposal.fee(),
+            }
+        );
+
+        // Unknown monitors.
+        let (_data, unknown_monitor_id) = get_test_monitor_data_and_id(&mut rng);
+        match manager.consolidation_preview(&unknown_monitor_id, 4) {
+            Err(Error::MonitorIdNotFound) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_build_transaction_with_first_input(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([46u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let recipient = AccountKey::random(&mut rng);
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            5,
+            &[sender.default_subaddress()],
+            12,
+            logger.clone(),
+            &mut rng,
+        );
+
+        let monitor_data = MonitorData::new(
+            sender.clone(),
+            DEFAULT_SUBADDRESS_INDEX, // first subaddress
+            1,                        // number of subaddresses
+            0,                        // first block
+            "",                       // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        let utxos = get_utxos_in_blocks(&ledger_db, &sender, 1..12);
+        mobilecoind_db
+            .block_processed(&monitor_id, 0, &utxos, &[])
+            .unwrap();
+
+        let manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db,
+            ConnectionManager::<ThickClient<HardcodedCredentialsProvider>>::new(
+                vec![],
+                logger.clone(),
+            ),
+            Arc::new(|_| Ok(MockFogPubkeyResolver::new())),
+            0,
+            logger.clone(),
+        );
+
+        // Sending more than a single output holds requires a second input.
+        let outlays = vec![Outlay {
+            value: DEFAULT_PER_RECIPIENT_AMOUNT,
+            receiver: recipient.default_subaddress(),
+        }];
+        let first_input = &utxos[7];
+        let tx_proposal = manager
+            .build_transaction_with_first_input(
+                &monitor_id,
+                DEFAULT_SUBADDRESS_INDEX,
+                &first_input.tx_out.public_key,
+                &utxos,
+                &outlays,
+                MINIMUM_FEE,
+                FeePriority::Normal,
+                0,
+                &logger,
+            )
+            .unwrap();
+
+        assert_eq!(tx_proposal.utxos.len(), 2);
+        assert_eq!(tx_proposal.utxos[0], *first_input);
+        assert_ne!(tx_proposal.utxos[1], *first_input);
+        assert!(tx_proposal.tx.key_images().contains(&first_input.key_image));
+
+        // An output the monitor does not own cannot be forced.
+        let foreign_tx_out = ledger_db.get_block_contents(0).unwrap().outputs[0].clone();
+        match manager.build_transaction_with_first_input(
+            &monitor_id,
+            DEFAULT_SUBADDRESS_INDEX,
+            &foreign_tx_out.public_key,
+            &utxos,
+            &outlays,
+            MINIMUM_FEE,
+            FeePriority::Normal,
+            0,
+            &logger,
+        ) {
+            Err(Error::InvalidArgument(_, _)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    // Inputs reserved by pending transactions should not be spent again.
+    #[test_with_logger]
+    fn test_build_transaction_skips_reserved_inputs(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([47u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let recipient = AccountKey::random(&mut rng);
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            5,
+            &[sender.default_subaddress()],
+            4,
+            logger.clone(),
+            &mut rng,
+        );
+
+        let monitor_data = MonitorData::new(
+            sender.clone(),
+            DEFAULT_SUBADDRESS_INDEX, // first subaddress
+            1,                        // number of subaddresses
+            0,                        // first block
+            "",                       // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        let utxos = get_utxos_in_blocks(&ledger_db, &sender, 1..4);
+        mobilecoind_db
+            .block_processed(&monitor_id, 0, &utxos, &[])
+            .unwrap();
+
+        let manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db,
+            ConnectionManager::<ThickClient<HardcodedCredentialsProvider>>::new(
+                vec![],
+                logger.clone(),
+            ),
+            Arc::new(|_| Ok(MockFogPubkeyResolver::new())),
+            0,
+            logger.clone(),
+        );
+
+        // Reserve all but the last output, as a pending transaction would.
+        let mut tx = Tx::default();
+        tx.prefix.tombstone_block = ledger_db.num_blocks().unwrap() + 10;
+        let pending_proposal = TxProposal {
+            utxos: utxos[..2].to_vec(),
+            outlays: vec![],
+            tx,
+            outlay_index_to_tx_out_index: HashMap::default(),
+            outlay_confirmation_numbers: vec![],
+        };
+        manager
+            .reserve_inputs(&pending_proposal, ledger_db.num_blocks().unwrap())
+            .unwrap();
+
+        let build = |value: u64| {
+            manager.build_transaction(
+                &monitor_id,
+                DEFAULT_SUBADDRESS_INDEX,
+                &utxos,
+                &[Outlay {
+                    value,
+                    receiver: recipient.default_subaddress(),
+                }],
+                MINIMUM_FEE,
+                FeePriority::Normal,
+                0,
+                &logger,
+            )
+        };
+
+        // Only the unreserved output may be spent.
+        let tx_proposal = build(1000).unwrap();
+        assert_eq!(tx_proposal.utxos, vec![utxos[2].clone()]);
+
+        // Covering the value would take a reserved output.
+        match build(DEFAULT_PER_RECIPIENT_AMOUNT) {
+            Err(Error::InsufficientFunds) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    // Transactions built against a pruned ledger should only use TxOuts that are
+    // still available as mixins.
+    #[test_with_logger]
+    fn test_build_transaction_with_pruned_ledger(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([47u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let recipient = AccountKey::random(&mut rng);
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            5,
+            &[sender.default_subaddress()],
+            12,
+            logger.clone(),
+            &mut rng,
+        );
+
+        let monitor_data = MonitorData::new(
+            sender.clone(),
+            DEFAULT_SUBADDRESS_INDEX, // first subaddress
+            1,                        // number of subaddresses
+            0,                        // first block
+            "",                       // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+
+        let utxos = get_utxos_in_blocks(&ledger_db, &sender, 0..12);
+        mobilecoind_db
+            .block_processed(&monitor_id, 0, &utxos, &[])
+            .unwrap();
+        let sender_tx_out_indices: HashSet<u64> = utxos
+            .iter()
+            .map(|utxo| {
+                ledger_db
+                    .get_tx_out_index_by_hash(&utxo.tx_out.hash())
+                    .unwrap()
+            })
+            .collect();
+
+        // Every other output of the first 10 blocks is spent and pruned, which
+        // leaves 22 of the 72 outputs.
+        let mut pruning_ledger_db = ledger_db.clone();
+        pruning_ledger_db.set_prune_depth(2);
+        let spent_key_image = ledger_db.get_key_images_by_block(1).unwrap()[0];
+        for tx_out_index in 0..60 {
+            if !sender_tx_out_indices.contains(&tx_out_index) {
+                pruning_ledger_db
+                    .set_tx_out_key_image(tx_out_index, &spent_key_image)
+                    .unwrap();
+            }
+        }
+        assert_eq!(ledger_db.num_pruned_tx_outs().unwrap(), 50);
+
+        let manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db,
+            ConnectionManager::<ThickClient<HardcodedCredentialsProvider>>::new(
+                vec![],
+                logger.clone(),
+            ),
+            Arc::new(|_| Ok(MockFogPubkeyResolver::new())),
+            0,
+            logger.clone(),
+        );
+
+        let outlays = vec![Outlay {
+            value: 1000,
+            receiver: recipient.default_subaddress(),
+        }];
+        let tx_proposal = manager
+            .build_transaction(
+                &monitor_id,
+                DEFAULT_SUBADDRESS_INDEX,
+                &utxos[0..1],
+                &outlays,
+                MINIMUM_FEE,
+                FeePriority::Normal,
+                0,
+                &logger,
+            )
+            .unwrap();
+
+        let ring = &tx_proposal.tx.prefix.inputs[0].ring;
+        assert_eq!(ring.len(), DEFAULT_RING_SIZE);
+        for tx_out in ring {
+            let index = ledger_db.get_tx_out_index_by_hash(&tx_out.hash()).unwrap();
+            assert_eq!(ledger_db.get_tx_out_by_index(index).unwrap(), *tx_out);
+        }
+    }
+
+    #[test_with_logger]
+    fn test_estimate_tx_size(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([43u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let recipient = AccountKey::random(&mut rng).default_subaddress();
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            5,
+            &[sender.default_subaddress()],
+            12,
+            logger.clone(),
+            &mut rng,
+        );
+
+        let manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db,
+            ConnectionManager::<ThickClient<HardcodedCredentialsProvider>>::new(
+                vec![],
+                logger.clone(),
+            ),
+            Arc::new(|_| Ok(MockFogPubkeyResolver::new())),
+            0,
+            logger.clone(),
+        );
+        let fog_resolver_factory: Arc<
+            dyn Fn(&[FogUri]) -> Result<MockFogPubkeyResolver, String> + Send + Sync,
+        > = Arc::new(|_| Ok(MockFogPubkeyResolver::new()));
+
+        // The sender's outputs in blocks 1 to 3.
+        let utxos = get_utxos_in_blocks(&ledger_db, &sender, 1..4);
+        assert_eq!(utxos.len(), 3);
+
+        let num_tx_outs = ledger_db.num_txos().unwrap();
+        let tombstone_block = ledger_db.num_blocks().unwrap() + 10;
+
+        for (num_inputs, num_destinations) in &[(1, 1), (2, 1), (2, 3), (3, 5)] {
+            let material = manager
+                .export_offline_tx_material(&utxos[..*num_inputs], DEFAULT_RING_SIZE)
+                .unwrap();
+            let outlays: Vec<Outlay> = (0..*num_destinations)
+                .map(|_| Outlay {
+                    value: 1,
+                    receiver: recipient.clone(),
+                })
+                .collect();
+
+            let tx_proposal = TransactionsManager::<
+                ThickClient<HardcodedCredentialsProvider>,
+                MockFogPubkeyResolver,
+            >::build_tx_proposal_offline(
+                material,
+                MINIMUM_FEE,
+                &sender,
+                DEFAULT_SUBADDRESS_INDEX,
+                &outlays,
+                tombstone_block,
+                &fog_resolver_factory,
+                &mut rng,
+                &logger,
+            )
+            .unwrap();
+
+            // The destinations plus change.
+            let num_outputs = num_destinations + 1;
+            assert_eq!(tx_proposal.tx.prefix.outputs.len(), num_outputs);
+
+            let estimate = estimate_tx_size_for_ledger(
+                *num_inputs,
+                num_outputs,
+                DEFAULT_RING_SIZE,
+                num_tx_outs,
+            );
+            let actual = mc_util_serial::encode(&tx_proposal.tx).len();
+            assert!(
+                (estimate as f64 - actual as f64).abs()
+                    <= actual as f64 * TX_SIZE_ESTIMATE_TOLERANCE_PERCENT as f64 / 100.0,
+                "estimate {} too far from actual size {} for {} inputs and {} outputs",
+                estimate,
+                actual,
+                num_inputs,
+                num_outputs
+            );
+            assert!(is_tx_size_estimate_accurate(&tx_proposal.tx));
+
+            // A larger ledger means longer membership proofs.
+            assert!(estimate_tx_size(*num_inputs, num_outputs, DEFAULT_RING_SIZE) > estimate);
+        }
+    }
+
+    #[test_with_logger]
+    fn test_build_tx_proposal_offline(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([42u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let recipient = AccountKey::random(&mut rng).default_subaddress();
+        let (mut ledger_db, mobilecoind_db) = get_test_databases(
+            5,
+            &[sender.default_subaddress()],
+            12,
+            logger.clone(),
+            &mut rng,
+        );
+
+        let manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db,
+            ConnectionManager::<ThickClient<HardcodedCredentialsProvider>>::new(
+                vec![],
+                logger.clone(),
+            ),
+            Arc::new(|_| Ok(MockFogPubkeyResolver::new())),
+            0,
+            logger.clone(),
+        );
+
+        // The sender's outputs in blocks 1 and 2.
+        let utxos: Vec<UnspentTxOut> = (1..3)
+            .flat_map(|block_index| {
+                let outputs = ledger_db.get_block_contents(block_index).unwrap().outputs;
+                outputs.into_iter().map(move |tx_out| (block_index, tx_out))
+            })
+            .filter_map(|(block_index, tx_out)| {
+                let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
+                let shared_secret =
+                    get_tx_out_shared_secret(sender.view_private_key(), &tx_public_key);
+                let (value, _blinding) = tx_out.amount.get_value(&shared_secret).ok()?;
+                let onetime_private_key = recover_onetime_private_key(
+                    &tx_public_key,
+                    sender.view_private_key(),
+                    &sender.default_subaddress_spend_private(),
+                );
+                Some(UnspentTxOut {
+                    tx_out,
+                    subaddress_index: DEFAULT_SUBADDRESS_INDEX,
+                    key_image: KeyImage::from(&onetime_private_key),
+                    value,
+                    attempted_spend_height: 0,
+                    attempted_spend_tombstone: 0,
+                    received_block_index: block_index,
+                })
+            })
+            .collect();
+        assert_eq!(utxos.len(), 2);
+
+        // Export the material while the ledger is still at the snapshot.
+        let material = manager
+            .export_offline_tx_material(&utxos, DEFAULT_RING_SIZE)
+            .unwrap();
+        assert_eq!(material.inputs.len(), utxos.len());
+        assert_eq!(material.rings.len(), utxos.len());
+        let live_proof = ledger_db.get_tx_out_proof_of_memberships(&[0]).unwrap();
+        assert_eq!(
+            material.root,
+            compute_implied_merkle_root(&live_proof[0]).unwrap()
+        );
+
+        // The ledger moves on before the transaction is built.
+        add_block_to_ledger_db(&mut ledger_db, &[recipient.clone()], 1, &[], &mut rng);
+
+        let fog_resolver_factory: Arc<
+            dyn Fn(&[FogUri]) -> Result<MockFogPubkeyResolver, String> + Send + Sync,
+        > = Arc::new(|_| Ok(MockFogPubkeyResolver::new()));
+        let total_value: u64 = utxos.iter().map(|utxo| utxo.value).sum();
+        let outlays = vec![Outlay {
+            value: total_value - MINIMUM_FEE,
+            receiver: recipient,
+        }];
+        let tombstone_block = ledger_db.num_blocks().unwrap() + 10;
+
+        // A material whose proofs do not match its root is rejected.
+        let mut tampered = material.clone();
+        tampered.root.hash.0[0] ^= 1;
+        match TransactionsManager::<
+            ThickClient<HardcodedCredentialsProvider>,
+            MockFogPubkeyResolver,
+        >::build_tx_proposal_offline(
+            tampered,
+            MINIMUM_FEE,
+            &sender,
+            DEFAULT_SUBADDRESS_INDEX,
+            &outlays,
+            tombstone_block,
+            &fog_resolver_factory,
+            &mut rng,
+            &logger,
+        ) {
+            Err(Error::TxBuildError(_)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        let tx_proposal = TransactionsManager::<
+            ThickClient<HardcodedCredentialsProvider>,
+            MockFogPubkeyResolver,
+        >::build_tx_proposal_offline(
+            material,
+            MINIMUM_FEE,
+            &sender,
+            DEFAULT_SUBADDRESS_INDEX,
+            &outlays,
+            tombstone_block,
+            &fog_resolver_factory,
+            &mut rng,
+            &logger,
+        )
+        .unwrap();
+        assert_eq!(tx_proposal.utxos, utxos);
+
+        // The transaction is valid against the live ledger.
+        let tx = tx_proposal.tx;
+        let ring_indexes: Vec<u64> = tx
+            .prefix
+            .inputs
+            .iter()
+            .flat_map(|tx_in| tx_in.ring.iter())
+            .map(|tx_out| ledger_db.get_tx_out_index_by_hash(&tx_out.hash()).unwrap())
+            .collect();
+        let root_proofs = ledger_db
+            .get_tx_out_proof_of_memberships(&ring_indexes)
+            .unwrap();
+        mc_transaction_core::validation::validate(
+            &tx,
+            ledger_db.num_blocks().unwrap(),
+            &root_proofs,
+            MINIMUM_FEE,
+            &mut rng,
+        )
+        .unwrap();
+    }
+
+    #[test_with_logger]
+    fn test_build_tx_proposal_to_fog_recipient(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([44u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let (fog_recipient, fog_private_key) = get_fog_enabled_account(&mut rng);
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            3,
+            &[sender.default_subaddress()],
+            5,
+            logger.clone(),
+            &mut rng,
+        );
+
+        let manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db,
+            ConnectionManager::<ThickClient<HardcodedCredentialsProvider>>::new(
+                vec![],
+                logger.clone(),
+            ),
+            Arc::new(|_| Ok(MockFogPubkeyResolver::new())),
+            0,
+            logger.clone(),
+        );
+
+        // A resolver that returns a known fog key for the fog recipient.
+        let fog_pubkey = RistrettoPublic::from(&fog_private_key);
+        let expected_recipient = fog_recipient.default_subaddress();
+        let fog_resolver_factory: Arc<
+            dyn Fn(&[FogUri]) -> Result<MockFogPubkeyResolver, String> + Send + Sync,
+        > = Arc::new(move |_| {
+            let mut fog_resolver = MockFogPubkeyResolver::new();
+            let expected_recipient = expected_recipient.clone();
+            fog_resolver
+                .expect_get_fog_pubkey()
+                .withf(move |recipient| *recipient == expected_recipient)
+                .return_once(move |_| {
+                    Ok(FullyValidatedFogPubkey {
+                        pubkey: fog_pubkey,
+                        pubkey_expiry: 10000,
+                    })
+                });
+            Ok(fog_resolver)
+        });
+
+        let utxos = get_utxos_in_blocks(&ledger_db, &sender, 1..2);
+        let material = manager
+            .export_offline_tx_material(&utxos, DEFAULT_RING_SIZE)
+            .unwrap();
+        let outlays = vec![Outlay {
+            value: 1,
+            receiver: fog_recipient.default_subaddress(),
+        }];
+        let tombstone_block = ledger_db.num_blocks().unwrap() + 10;
+
+        let tx_proposal = TransactionsManager::<
+            ThickClient<HardcodedCredentialsProvider>,
+            MockFogPubkeyResolver,
+        >::build_tx_proposal_offline(
+            material,
+            MINIMUM_FEE,
+            &sender,
+            DEFAULT_SUBADDRESS_INDEX,
+            &outlays,
+            tombstone_block,
+            &fog_resolver_factory,
+            &mut rng,
+            &logger,
+        )
+        .unwrap();
+
+        // The fog recipient's hint is encrypted to the resolved fog key.
+        let outlay_tx_out_index = tx_proposal.outlay_index_to_tx_out_index[&0];
+        let outlay_tx_out = &tx_proposal.tx.prefix.outputs[outlay_tx_out_index];
+        assert_fog_hint_decrypts_to(
+            outlay_tx_out,
+            &fog_private_key,
+            &fog_recipient.default_subaddress(),
+        );
+
+        // The change goes to the sender, who has no fog, so its hint is not.
+        let change_tx_out = tx_proposal
+            .tx
+            .prefix
+            .outputs
+            .iter()
+            .enumerate()
+            .find(|(index, _)| *index != outlay_tx_out_index)
+            .map(|(_, tx_out)| tx_out)
+            .unwrap();
+        assert!(decrypt_fog_hint(change_tx_out, &fog_private_key).is_none());
+    }
 }
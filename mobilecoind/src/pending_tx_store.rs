@@ -0,0 +1,148 @@
+// Copyright (c) 2018-2021 The MobileCoin Foundation
+
+//! Database storage for transactions submitted by the transactions manager.
+//! * Stores a map of tx hash -> PendingTx, which holds the inputs the
+//!   transaction reserved until it lands in the ledger or its tombstone block
+//!   passes.
+
+use crate::{error::Error, monitor_store::MonitorId, utxo_store::UtxoId};
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mc_common::logger::{log, Logger};
+use mc_util_serial::Message;
+use std::sync::Arc;
+
+// LMDB Database Names
+pub const TX_HASH_TO_PENDING_TX_DB_NAME: &str =
+    "mobilecoind_db:pending_tx_store:tx_hash_to_pending_tx";
+
+/// A transaction that was submitted to the network, along with the inputs it
+/// reserved.
+#[derive(Clone, Eq, Hash, PartialEq, Message)]
+pub struct PendingTx {
+    /// Hash of the submitted transaction.
+    #[prost(bytes, tag = "1")]
+    pub tx_hash: Vec<u8>,
+
+    /// The UTXOs spent by the transaction.
+    #[prost(message, repeated, tag = "2")]
+    pub utxo_ids: Vec<UtxoId>,
+
+    /// The block height at which the transaction was submitted.
+    #[prost(uint64, tag = "3")]
+    pub submitted_block_height: u64,
+
+    /// The tombstone block of the transaction. Once the ledger reaches this
+    /// block the transaction can no longer land, and its reservation expires.
+    #[prost(uint64, tag = "4")]
+    pub tombstone_block: u64,
+
+    /// The monitor whose inputs the transaction spends, if they belong to a
+    /// known monitor.
+    #[prost(message, optional, tag = "5")]
+    pub monitor_id: Option<MonitorId>,
+
+    /// The value the transaction returns to the sender as change.
+    #[prost(uint64, tag = "6")]
+    pub change_value: u64,
+}
+
+impl PendingTx {
+    /// Check whether the transaction can no longer land in a ledger that has
+    /// `num_blocks` blocks.
+    pub fn is_expired(&self, num_blocks: u64) -> bool {
+        self.tombstone_block <= num_blocks
+    }
+}
+
+/// The pending transactions database.
+#[derive(Clone)]
+pub struct PendingTxStore {
+    /// LMDB Environment.
+    env: Arc<Environment>,
+
+    /// Mapping of tx hash -> PendingTx.
+    tx_hash_to_pending_tx: Database,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl PendingTxStore {
+    pub fn new(env: Arc<Environment>, logger: Logger) -> Result<Self, Error> {
+        let tx_hash_to_pending_tx =
+            env.create_db(Some(TX_HASH_TO_PENDING_TX_DB_NAME), DatabaseFlags::empty())?;
+
+        Ok(Self {
+            env,
+            tx_hash_to_pending_tx,
+            logger,
+        })
+    }
+
+    /// Store a pending transaction, replacing any previous entry for the same
+    /// tx hash.
+    pub fn insert<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        pending_tx: &PendingTx,
+    ) -> Result<(), Error> {
+        db_txn.put(
+            self.tx_hash_to_pending_tx,
+            &pending_tx.tx_hash,
+            &mc_util_serial::encode(pending_tx),
+            WriteFlags::empty(),
+        )?;
+        Ok(())
+    }
+
+    /// Remove a pending transaction. Removing an unknown transaction is not an
+    /// error.
+    pub fn remove<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        tx_hash: &[u8],
+    ) -> Result<(), Error> {
+        match db_txn.del(self.tx_hash_to_pending_tx, &tx_hash, None) {
+            Ok(_) | Err(lmdb::Error::NotFound) => Ok(()),
+            Err(err) => Err(Error::Lmdb(err)),
+        }
+    }
+
+    /// Get all pending transactions.
+    pub fn get_all(&self, db_txn: &impl Transaction) -> Result<Vec<PendingTx>, Error> {
+        let mut cursor = db_txn.open_ro_cursor(self.tx_hash_to_pending_tx)?;
+        cursor
+            .iter()
+            .map(|result| {
+                result
+                    .map_err(Error::from)
+                    .and_then(|(_key_bytes, value_bytes)| Ok(mc_util_serial::decode(value_bytes)?))
+            })
+            .collect()
+    }
+
+    /// Remove all pending transactions that expired in a ledger with
+    /// `num_blocks` blocks, returning the removed entries.
+    pub fn remove_expired<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        num_blocks: u64,
+    ) -> Result<Vec<PendingTx>, Error> {
+        let expired: Vec<PendingTx> = self
+            .get_all(db_txn)?
+            .into_iter()
+            .filter(|pending_tx| pending_tx.is_expired(num_blocks))
+            .collect();
+
+        for pending_tx in expired.iter() {
+            log::trace!(
+                self.logger,
+                "removing expired pending tx {}",
+                hex_fmt::HexFmt(&pending_tx.tx_hash)
+            );
+            self.remove(db_txn, &pending_tx.tx_hash)?;
+        }
+
+        Ok(expired)
+    }
+}
@@ -3,10 +3,12 @@
 //! Database storage for data obtained by processing blocks.
 //! * Stores a map of (monitor id, block number) -> list of transactions that
 //!   appeared in the given block number and belong to a given monitor id.
+//! * Stores a map of (monitor id, key image, direction) -> block number, to
+//!   find where a given monitor received or spent an output.
 
 use crate::{error::Error, monitor_store::MonitorId, utxo_store::UnspentTxOut};
 use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
-use mc_common::logger::Logger;
+use mc_common::logger::{log, Logger};
 use mc_crypto_keys::CompressedRistrettoPublic;
 use mc_transaction_core::ring_signature::KeyImage;
 use prost::{Enumeration, Message};
@@ -15,6 +17,8 @@ use std::{convert::TryFrom, sync::Arc};
 // LMDB Database Names
 pub const PROCESSED_BLOCK_KEY_TO_PROCESSED_TX_OUTS_DB_NAME: &str =
     "mobilecoind_db:processed_block_store:processed_block_key_to_processed_tx_outs";
+pub const KEY_IMAGE_KEY_TO_BLOCK_INDEX_DB_NAME: &str =
+    "mobilecoind_db:processed_block_store:key_image_key_to_block_index";
 
 /// Type used as the key in the databases managed by the processed block store.
 #[derive(Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -70,6 +74,41 @@ impl TryFrom<&[u8]> for ProcessedBlockKey {
     }
 }
 
+/// Type used as the key in the key_image_key_to_block_index database.
+#[derive(Eq, PartialEq, Hash)]
+pub struct KeyImageKey {
+    /// The monitor_id for which the data belongs to.
+    pub monitor_id: MonitorId,
+
+    /// The key image of the TxOut.
+    pub key_image: KeyImage,
+
+    /// Whether the TxOut was received or spent.
+    pub direction: ProcessedTxOutDirection,
+}
+impl KeyImageKey {
+    pub fn new(
+        monitor_id: &MonitorId,
+        key_image: &KeyImage,
+        direction: ProcessedTxOutDirection,
+    ) -> Self {
+        Self {
+            monitor_id: *monitor_id,
+            key_image: *key_image,
+            direction,
+        }
+    }
+
+    // 65 bytes: 32 for MonitorId, 32 for key image, 1 for direction.
+    pub fn to_bytes(&self) -> [u8; 65] {
+        let mut buf = [0u8; 65];
+        buf[0..32].copy_from_slice(self.monitor_id.as_bytes());
+        buf[32..64].copy_from_slice(self.key_image.as_bytes());
+        buf[64] = self.direction as u8;
+        buf
+    }
+}
+
 /// Direction of a ProcessedTxOut
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Enumeration)]
 pub enum ProcessedTxOutDirection {
@@ -136,6 +175,9 @@ pub struct ProcessedBlockStore {
     /// Mapping of ProcessedBlockKey -> [ProcessedTxOut].
     processed_block_key_to_processed_tx_outs: Database,
 
+    /// Mapping of KeyImageKey -> block index.
+    key_image_key_to_block_index: Database,
+
     /// Logger.
     logger: Logger,
 }
@@ -146,12 +188,112 @@ impl ProcessedBlockStore {
             Some(PROCESSED_BLOCK_KEY_TO_PROCESSED_TX_OUTS_DB_NAME),
             DatabaseFlags::DUP_SORT,
         )?;
+        let key_image_key_to_block_index = env.create_db(
+            Some(KEY_IMAGE_KEY_TO_BLOCK_INDEX_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
 
-        Ok(Self {
+        let store = Self {
             env,
             processed_block_key_to_processed_tx_outs,
+            key_image_key_to_block_index,
             logger,
-        })
+        };
+
+        // Databases created before the key image index was added need it built from
+        // the processed blocks.
+        let mut db_txn = store.env.begin_rw_txn()?;
+        store.index_key_images_if_missing(&mut db_txn)?;
+        db_txn.commit()?;
+
+        Ok(store)
+    }
+
+    /// Fill the key image index from the processed blocks, if it is empty while
+    /// there are processed blocks.
+    fn index_key_images_if_missing<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+    ) -> Result<(), Error> {
+        {
+            let mut cursor = db_txn.open_ro_cursor(self.key_image_key_to_block_index)?;
+            if cursor.iter_start().next().is_some() {
+                return Ok(());
+            }
+        }
+
+        let entries = {
+            let mut cursor =
+                db_txn.open_ro_cursor(self.processed_block_key_to_processed_tx_outs)?;
+            cursor
+                .iter_start()
+                .map(|result| {
+                    result.map_err(Error::from).and_then(|(db_key, db_value)| {
+                        let key = ProcessedBlockKey::try_from(db_key)?;
+                        let processed_tx_out: ProcessedTxOut = mc_util_serial::decode(db_value)?;
+                        Ok((key, processed_tx_out))
+                    })
+                })
+                .collect::<Result<Vec<_>, Error>>()?
+        };
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        log::info!(
+            self.logger,
+            "Indexing the key images of {} processed TxOuts",
+            entries.len()
+        );
+        for (key, processed_tx_out) in entries {
+            self.index_key_image(db_txn, &key.monitor_id, key.block_index, &processed_tx_out)?;
+        }
+
+        Ok(())
+    }
+
+    /// Record the block in which a given monitor received or spent a TxOut.
+    fn index_key_image<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+        block_index: u64,
+        processed_tx_out: &ProcessedTxOut,
+    ) -> Result<(), Error> {
+        let direction = ProcessedTxOutDirection::from_i32(processed_tx_out.direction)
+            .unwrap_or(ProcessedTxOutDirection::Invalid);
+        let key = KeyImageKey::new(monitor_id, &processed_tx_out.key_image, direction);
+        db_txn.put(
+            self.key_image_key_to_block_index,
+            &key.to_bytes(),
+            &block_index.to_be_bytes(),
+            WriteFlags::empty(),
+        )?;
+        Ok(())
+    }
+
+    /// Get the index of the block in which a given monitor received or spent
+    /// the TxOut with a given key image, or None if it did not.
+    pub fn get_block_index_by_key_image(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: &MonitorId,
+        key_image: &KeyImage,
+        direction: ProcessedTxOutDirection,
+    ) -> Result<Option<u64>, Error> {
+        let key = KeyImageKey::new(monitor_id, key_image, direction);
+        match db_txn.get(self.key_image_key_to_block_index, &key.to_bytes()) {
+            Ok(value_bytes) => {
+                let mut index_bytes = [0u8; 8];
+                if value_bytes.len() != index_bytes.len() {
+                    return Err(Error::KeyDeserializationError);
+                }
+                index_bytes.copy_from_slice(value_bytes);
+                Ok(Some(u64::from_be_bytes(index_bytes)))
+            }
+            Err(lmdb::Error::NotFound) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
     }
 
     /// Get processed block information for a given (monitor id, block number).
@@ -179,6 +321,91 @@ impl ProcessedBlockStore {
             .collect::<Result<Vec<_>, Error>>()
     }
 
+    /// Get the index of the earliest block in which a given monitor received a
+    /// TxOut, or None if it never received one.
+    pub fn get_first_received_block_index(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: &MonitorId,
+    ) -> Result<Option<u64>, Error> {
+        let start_key = ProcessedBlockKey::new(monitor_id, 0);
+        let start_key_bytes = start_key.to_vec();
+
+        let mut cursor = db_txn.open_ro_cursor(self.processed_block_key_to_processed_tx_outs)?;
+
+        // Keys are ordered by monitor id and then by big-endian block index, so the
+        // first received TxOut we encounter is the earliest one.
+        for result in cursor.iter_from(&start_key_bytes) {
+            let (db_key, db_value) = result?;
+            let key = ProcessedBlockKey::try_from(db_key)?;
+            if key.monitor_id != *monitor_id {
+                break;
+            }
+
+            let processed_tx_out: ProcessedTxOut = mc_util_serial::decode(db_value)?;
+            if processed_tx_out.direction == ProcessedTxOutDirection::Received as i32 {
+                return Ok(Some(key.block_index));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Get the processed TxOuts of a given monitor in blocks `from_block`
+    /// (inclusive) to `to_block` (exclusive), ordered by block number.
+    pub fn get_processed_tx_outs_in_range(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: &MonitorId,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<ProcessedTxOut>, Error> {
+        let start_key = ProcessedBlockKey::new(monitor_id, from_block);
+        let start_key_bytes = start_key.to_vec();
+
+        let mut cursor = db_txn.open_ro_cursor(self.processed_block_key_to_processed_tx_outs)?;
+
+        let mut processed_tx_outs = Vec::new();
+        for result in cursor.iter_from(&start_key_bytes) {
+            let (db_key, db_value) = result?;
+            let key = ProcessedBlockKey::try_from(db_key)?;
+            if key.monitor_id != *monitor_id || key.block_index >= to_block {
+                break;
+            }
+
+            processed_tx_outs.push(mc_util_serial::decode(db_value)?);
+        }
+
+        Ok(processed_tx_outs)
+    }
+
+    /// Call `f` with every processed TxOut of a given monitor and the index of
+    /// the block it was processed in, ordered by block number. Nothing is
+    /// buffered, and the first error returned by `f` stops the iteration.
+    pub fn for_each_processed_tx_out(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: &MonitorId,
+        mut f: impl FnMut(u64, ProcessedTxOut) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let start_key = ProcessedBlockKey::new(monitor_id, 0);
+        let start_key_bytes = start_key.to_vec();
+
+        let mut cursor = db_txn.open_ro_cursor(self.processed_block_key_to_processed_tx_outs)?;
+
+        for result in cursor.iter_from(&start_key_bytes) {
+            let (db_key, db_value) = result?;
+            let key = ProcessedBlockKey::try_from(db_key)?;
+            if key.monitor_id != *monitor_id {
+                break;
+            }
+
+            f(key.block_index, mc_util_serial::decode(db_value)?)?;
+        }
+
+        Ok(())
+    }
+
     /// Remove the data associated with a given monitor id.
     pub fn remove<'env>(
         &self,
@@ -198,6 +425,20 @@ impl ProcessedBlockStore {
                 break;
             }
         }
+        drop(cursor);
+
+        // Key image index keys start with the monitor id.
+        let mut cursor = db_txn.open_rw_cursor(self.key_image_key_to_block_index)?;
+        for (db_key, _db_value) in cursor
+            .iter_from(monitor_id.as_bytes())
+            .filter_map(|r| r.ok())
+        {
+            if db_key.starts_with(monitor_id.as_bytes()) {
+                cursor.del(WriteFlags::empty())?;
+            } else {
+                break;
+            }
+        }
 
         Ok(())
     }
@@ -210,33 +451,72 @@ impl ProcessedBlockStore {
         block_index: u64,
         discovered_utxos: &[UnspentTxOut],
         spent_utxos: &[UnspentTxOut],
+    ) -> Result<(), Error> {
+        let processed_tx_outs: Vec<ProcessedTxOut> = discovered_utxos
+            .iter()
+            .map(ProcessedTxOut::from_received_utxo)
+            .chain(spent_utxos.iter().map(ProcessedTxOut::from_spent_utxo))
+            .collect();
+
+        self.insert(db_txn, monitor_id, block_index, &processed_tx_outs)
+    }
+
+    /// Store processed TxOuts for a given (monitor id, block number).
+    pub fn insert<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+        block_index: u64,
+        processed_tx_outs: &[ProcessedTxOut],
     ) -> Result<(), Error> {
         let key = ProcessedBlockKey::new(monitor_id, block_index);
         let key_bytes = key.to_vec();
 
-        for utxo in discovered_utxos.iter() {
-            let processed_tx_out = ProcessedTxOut::from_received_utxo(utxo);
-            let processed_tx_out_bytes = mc_util_serial::encode(&processed_tx_out);
+        for processed_tx_out in processed_tx_outs.iter() {
+            let processed_tx_out_bytes = mc_util_serial::encode(processed_tx_out);
             db_txn.put(
                 self.processed_block_key_to_processed_tx_outs,
                 &key_bytes,
                 &processed_tx_out_bytes,
                 WriteFlags::empty(),
             )?;
+            self.index_key_image(db_txn, monitor_id, block_index, processed_tx_out)?;
         }
 
-        for utxo in spent_utxos.iter() {
-            let processed_tx_out = ProcessedTxOut::from_spent_utxo(utxo);
-            let processed_tx_out_bytes = mc_util_serial::encode(&processed_tx_out);
-            db_txn.put(
-                self.processed_block_key_to_processed_tx_outs,
-                &key_bytes,
-                &processed_tx_out_bytes,
-                WriteFlags::empty(),
-            )?;
+        Ok(())
+    }
+
+    /// Get all processed block information stored for a given monitor id, as
+    /// (block number, processed TxOuts) pairs ordered by block number. Blocks
+    /// in which the monitor had no activity are not included.
+    pub fn get_all_processed_blocks(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: &MonitorId,
+    ) -> Result<Vec<(u64, Vec<ProcessedTxOut>)>, Error> {
+        let start_key = ProcessedBlockKey::new(monitor_id, 0);
+        let start_key_bytes = start_key.to_vec();
+
+        let mut cursor = db_txn.open_ro_cursor(self.processed_block_key_to_processed_tx_outs)?;
+
+        let mut processed_blocks: Vec<(u64, Vec<ProcessedTxOut>)> = Vec::new();
+        for result in cursor.iter_from(&start_key_bytes) {
+            let (db_key, db_value) = result?;
+            let key = ProcessedBlockKey::try_from(db_key)?;
+            if key.monitor_id != *monitor_id {
+                break;
+            }
+
+            let processed_tx_out: ProcessedTxOut = mc_util_serial::decode(db_value)?;
+            match processed_blocks.last_mut() {
+                Some((block_index, tx_outs)) if *block_index == key.block_index => {
+                    tx_outs.push(processed_tx_out)
+                }
+                _ => processed_blocks.push((key.block_index, vec![processed_tx_out])),
+            }
         }
 
-        Ok(())
+        Ok(processed_blocks)
     }
 }
 
@@ -245,7 +525,9 @@ mod test {
     use super::*;
     use crate::{
         monitor_store::MonitorData,
-        test_utils::{get_test_databases, DEFAULT_PER_RECIPIENT_AMOUNT},
+        test_utils::{
+            get_block_tx_outs_for_account, get_test_databases, DEFAULT_PER_RECIPIENT_AMOUNT,
+        },
     };
     use mc_account_keys::AccountKey;
     use mc_common::{
@@ -282,17 +564,15 @@ mod test {
         let num_blocks = ledger_db.num_blocks().expect("failed getting num blocks");
         let account_tx_outs: Vec<TxOut> = (0..num_blocks)
             .map(|idx| {
-                let block_contents = ledger_db.get_block_contents(idx as u64).unwrap();
-                // We grab the 4th tx out in each block since the test ledger had 3 random
-                // recipients, followed by our known recipient.
-                // See the call to `get_testing_environment` at the beginning of the test.
-                block_contents.outputs[3].clone()
+                // The test ledger has a single tx out for our known recipient in each block.
+                get_block_tx_outs_for_account(&ledger_db, idx as u64, &account_key)[0].clone()
             })
             .collect();
 
         let account_utxos: Vec<UnspentTxOut> = account_tx_outs
             .iter()
-            .map(|tx_out| {
+            .enumerate()
+            .map(|(block_index, tx_out)| {
                 // Calculate the key image for this tx out.
                 let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
                 let onetime_private_key = recover_onetime_private_key(
@@ -310,6 +590,7 @@ mod test {
                     value: DEFAULT_PER_RECIPIENT_AMOUNT,
                     attempted_spend_height: 0,
                     attempted_spend_tombstone: 0,
+                    received_block_index: block_index as u64,
                 }
             })
             .collect();
@@ -0,0 +1,158 @@
+// Copyright (c) 2018-2021 The MobileCoin Foundation
+
+//! Measures how fast monitors are being synced.
+//! Sync workers record every block they finish processing for a monitor. The
+//! meter keeps per-second totals over a recent window, from which the number
+//! of blocks scanned per second is estimated. Dividing the number of blocks
+//! monitors still have to scan by that rate gives an estimate of the time left
+//! until they are synced.
+
+use crate::counters;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The window over which the scan rate is averaged.
+pub const SCAN_THROUGHPUT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Blocks recorded within this duration of each other are totalled together.
+const BUCKET_DURATION: Duration = Duration::from_secs(1);
+
+/// A rolling measurement of the number of blocks scanned per second. Clones
+/// share the same measurement.
+#[derive(Clone)]
+pub struct ScanThroughputMeter {
+    window: Duration,
+
+    /// The start time and number of blocks of each bucket, oldest first.
+    buckets: Arc<Mutex<VecDeque<(Instant, u64)>>>,
+}
+
+impl Default for ScanThroughputMeter {
+    fn default() -> Self {
+        Self::new(SCAN_THROUGHPUT_WINDOW)
+    }
+}
+
+impl ScanThroughputMeter {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            buckets: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    /// The window over which the scan rate is averaged.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Record that a number of blocks have been scanned.
+    pub fn record(&self, num_blocks: u64) {
+        self.record_at(Instant::now(), num_blocks);
+        counters::BLOCKS_SCANNED.inc_by(num_blocks);
+    }
+
+    /// The average number of blocks scanned per second over the window. The
+    /// average only covers the time since the oldest block still in the
+    /// window was recorded, so that the rate is meaningful right after
+    /// scanning starts.
+    pub fn blocks_per_second(&self) -> f64 {
+        self.blocks_per_second_at(Instant::now())
+    }
+
+    /// Update the scan rate reported to the metrics registry.
+    pub fn update_metrics(&self) {
+        counters::SCAN_BLOCKS_PER_SECOND.set(self.blocks_per_second().round() as i64);
+    }
+
+    fn record_at(&self, now: Instant, num_blocks: u64) {
+        let mut buckets = self.buckets.lock().expect("mutex poisoned");
+        match buckets.back_mut() {
+            Some((start, total)) if now.saturating_duration_since(*start) < BUCKET_DURATION => {
+                *total += num_blocks;
+            }
+            _ => buckets.push_back((now, num_blocks)),
+        }
+        self.prune(&mut buckets, now);
+    }
+
+    fn blocks_per_second_at(&self, now: Instant) -> f64 {
+        let mut buckets = self.buckets.lock().expect("mutex poisoned");
+        self.prune(&mut buckets, now);
+
+        let oldest_start = match buckets.front() {
+            Some((start, _)) => *start,
+            None => return 0.0,
+        };
+        let num_blocks: u64 = buckets.iter().map(|(_, total)| total).sum();
+        let elapsed = now
+            .saturating_duration_since(oldest_start)
+            .max(BUCKET_DURATION);
+        num_blocks as f64 / elapsed.as_secs_f64()
+    }
+
+    /// Drop the buckets that started before the window.
+    fn prune(&self, buckets: &mut VecDeque<(Instant, u64)>, now: Instant) {
+        while let Some((start, _)) = buckets.front() {
+            if now.saturating_duration_since(*start) <= self.window {
+                break;
+            }
+            buckets.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_blocks_per_second_over_window() {
+        let meter = ScanThroughputMeter::new(Duration::from_secs(10));
+        let start = Instant::now();
+        assert_eq!(meter.blocks_per_second_at(start), 0.0);
+
+        // 20 blocks over 4 seconds.
+        for secs in 0..4 {
+            meter.record_at(start + Duration::from_secs(secs), 5);
+        }
+        assert_eq!(
+            meter.blocks_per_second_at(start + Duration::from_secs(4)),
+            5.0
+        );
+
+        // Blocks recorded in quick succession share a bucket.
+        meter.record_at(start + Duration::from_millis(3_500), 10);
+        assert_eq!(meter.buckets.lock().unwrap().len(), 4);
+        assert_eq!(
+            meter.blocks_per_second_at(start + Duration::from_secs(5)),
+            6.0
+        );
+
+        // Old buckets leave the window.
+        assert_eq!(
+            meter.blocks_per_second_at(start + Duration::from_millis(12_500)),
+            15.0 / 9.5
+        );
+        assert_eq!(
+            meter.blocks_per_second_at(start + Duration::from_secs(20)),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_blocks_per_second_right_after_start() {
+        let meter = ScanThroughputMeter::new(Duration::from_secs(10));
+        let start = Instant::now();
+
+        // A burst is averaged over at least a second.
+        meter.record_at(start, 7);
+        assert_eq!(
+            meter.blocks_per_second_at(start + Duration::from_millis(1)),
+            7.0
+        );
+    }
+}
@@ -7,29 +7,37 @@
 //! * writes matching transactions to a local DB, organized by subaddress_id
 
 use crate::{
-    database::Database,
+    database::{AddMonitorOptions, Database},
     error::Error,
     monitor_store::{MonitorData, MonitorId},
-    payments::{Outlay, TransactionsManager, TxProposal},
-    sync::SyncThread,
+    payments::{split_by_confirmations, Outlay, TransactionsManager, TxProposal},
+    scan_throughput::ScanThroughputMeter,
+    sync::{BlockProcessingRetry, SyncThread},
     utxo_store::{UnspentTxOut, UtxoId},
 };
 use bip39::{Language, Mnemonic, MnemonicType};
-use grpcio::{EnvBuilder, RpcContext, RpcStatus, RpcStatusCode, ServerBuilder, UnarySink};
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    prelude::*,
+};
+use grpcio::{
+    ChannelBuilder, EnvBuilder, Metadata, MetadataBuilder, RpcContext, RpcStatus, RpcStatusCode,
+    ServerBuilder, ServerStreamingSink, UnarySink, WriteFlags,
+};
 use mc_account_keys::{AccountKey, PublicAddress, RootIdentity, DEFAULT_SUBADDRESS_INDEX};
 use mc_account_keys_slip10::Slip10KeyGenerator;
 use mc_common::{
-    logger::{log, Logger},
+    logger::{log, o, Logger},
     HashMap,
 };
 use mc_connection::{BlockchainConnection, UserTxConnection};
 use mc_crypto_keys::{CompressedRistrettoPublic, RistrettoPublic};
 use mc_fog_report_validation::FogPubkeyResolver;
-use mc_ledger_db::{Error as LedgerError, Ledger, LedgerDB};
+use mc_ledger_db::{read_only_ledger::ReadOnlyLedger, Error as LedgerError, LedgerDB};
 use mc_ledger_sync::{NetworkState, PollingNetworkState};
 use mc_mobilecoind_api::{
     mobilecoind_api_grpc::{create_mobilecoind_api, MobilecoindApi},
-    MobilecoindUri,
+    ErrorCode, MobilecoindUri, ERROR_CODE_METADATA_KEY,
 };
 use mc_transaction_core::{
     get_tx_out_shared_secret,
@@ -39,16 +47,76 @@ use mc_transaction_core::{
 };
 use mc_util_from_random::FromRandom;
 use mc_util_grpc::{
-    rpc_internal_error, rpc_invalid_arg_error, rpc_logger, send_result, AdminService,
-    BuildInfoService, ConnectionUriGrpcioServer,
+    rpc_internal_error, rpc_invalid_arg_error, rpc_logger, rpc_permissions_error, send_result,
+    AdminService, BuildInfoService, ConnectionUriGrpcioServer,
 };
 use mc_watcher::watcher_db::WatcherDB;
 use protobuf::{ProtobufEnum, RepeatedField};
 use std::{
     convert::TryFrom,
+    fmt::Display,
+    ops::{Deref, DerefMut},
     sync::{Arc, Mutex, RwLock},
+    thread,
+    time::{Duration, Instant},
 };
 
+/// Number of recent blocks over which the average block interval is estimated.
+const BLOCK_INTERVAL_ESTIMATE_WINDOW: u64 = 100;
+
+/// How often the progress of a restored account is checked.
+const RESTORE_ACCOUNT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long the progress of a restored account is reported for before the call
+/// gives up, e.g. because the monitor is not being synced.
+const RESTORE_ACCOUNT_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Metadata key clients may use to supply their own request id, which is
+/// attached to the log lines of the call.
+pub const REQUEST_ID_METADATA_KEY: &str = "x-request-id";
+
+/// Client supplied request ids longer than this are ignored.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// When RPCs reporting the network height re-poll the network first, so that
+/// they don't answer from a stale view of it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NetworkStateRefresh {
+    /// The network state is re-polled if its last poll is older than this.
+    pub max_age: Duration,
+
+    /// How long a re-poll waits for peers. Peers that have not responded by
+    /// then are reported with their previously polled state.
+    pub timeout: Duration,
+}
+
+/// Optional settings of the mobilecoind service.
+#[derive(Clone, Debug, Default)]
+pub struct ServiceConfig {
+    /// How processing a block for a monitor is retried when it fails.
+    pub block_processing_retry: BlockProcessingRetry,
+
+    /// Whether the network state is re-polled when stale. If None, RPCs always
+    /// answer from the state polled by the ledger sync thread.
+    pub network_state_refresh: Option<NetworkStateRefresh>,
+
+    /// Whether the ExportAccountKey API is allowed to return account keys.
+    pub allow_account_key_export: bool,
+
+    /// Maximum size of gRPC messages, in bytes. gRPC defaults apply if None.
+    pub max_message_size: Option<usize>,
+
+    /// The address consensus sends fees to. Outputs received at a monitored
+    /// subaddress matching it are fee outputs, and are not counted towards
+    /// balances.
+    pub fee_recipient: Option<PublicAddress>,
+
+    /// The name of the network this node is connected to. Transaction
+    /// proposals are labeled with it, and proposals labeled for other networks
+    /// are refused.
+    pub network_name: Option<String>,
+}
+
 pub struct Service {
     /// Sync thread.
     _sync_thread: Arc<Mutex<Option<SyncThread>>>,
@@ -69,8 +137,13 @@ impl Service {
         network_state: Arc<RwLock<PollingNetworkState<T>>>,
         listen_uri: &MobilecoindUri,
         num_workers: Option<usize>,
+        config: ServiceConfig,
         logger: Logger,
     ) -> Self {
+        let block_processing_retry = config.block_processing_retry;
+        let max_message_size = config.max_message_size;
+        let scan_throughput = ScanThroughputMeter::default();
+
         let sync_thread = if mobilecoind_db.is_db_encrypted() {
             log::info!(logger, "Db encryption enabled, sync task would start once password is provided via the API.");
             Arc::new(Mutex::new(None))
@@ -80,6 +153,8 @@ impl Service {
                 ledger_db.clone(),
                 mobilecoind_db.clone(),
                 num_workers,
+                block_processing_retry,
+                scan_throughput.clone(),
                 logger.clone(),
             ))))
         };
@@ -89,6 +164,7 @@ impl Service {
             let mobilecoind_db = mobilecoind_db.clone();
             let logger = logger.clone();
             let sync_thread = sync_thread.clone();
+            let scan_throughput = scan_throughput.clone();
             Arc::new(move || {
                 let mut sync_thread = sync_thread.lock().expect("mutex poisoned");
                 assert!(sync_thread.is_none());
@@ -97,6 +173,8 @@ impl Service {
                     ledger_db.clone(),
                     mobilecoind_db.clone(),
                     num_workers,
+                    block_processing_retry,
+                    scan_throughput.clone(),
                     logger.clone(),
                 ));
             })
@@ -104,11 +182,13 @@ impl Service {
 
         let api = ServiceApi::new(
             transactions_manager,
-            ledger_db,
+            ledger_db.read_only(),
             mobilecoind_db,
             watcher_db,
             network_state,
             start_sync_thread,
+            scan_throughput,
+            config,
             logger.clone(),
         );
 
@@ -139,7 +219,19 @@ impl Service {
                 .build(),
         );
 
-        let server_builder = ServerBuilder::new(env)
+        let mut server_builder = ServerBuilder::new(env.clone());
+        if let Some(max_message_size) = max_message_size {
+            // gRPC limits are i32, so larger values are clamped.
+            let max_message_len = max_message_size.min(i32::MAX as usize) as i32;
+            server_builder = server_builder.channel_args(
+                ChannelBuilder::new(env)
+                    .max_receive_message_len(max_message_len)
+                    .max_send_message_len(max_message_len)
+                    .build_args(),
+            );
+        }
+
+        let server_builder = server_builder
             .register_service(admin_service)
             .register_service(build_info_service)
             .register_service(health_service)
@@ -161,11 +253,28 @@ pub struct ServiceApi<
     FPR: FogPubkeyResolver + 'static,
 > {
     transactions_manager: TransactionsManager<T, FPR>,
-    ledger_db: LedgerDB,
+    /// The ledger. The API only reads from it, appending is done by the ledger
+    /// sync service.
+    ledger_db: Arc<dyn ReadOnlyLedger>,
     mobilecoind_db: Database,
     watcher_db: Option<WatcherDB>,
     network_state: Arc<RwLock<PollingNetworkState<T>>>,
+    /// Whether the network state is re-polled when stale. If None, RPCs always
+    /// answer from the state polled by the ledger sync thread.
+    network_state_refresh: Option<NetworkStateRefresh>,
     start_sync_thread: Arc<dyn Fn() + Send + Sync>,
+    /// How fast the sync thread scans blocks.
+    scan_throughput: ScanThroughputMeter,
+    /// Whether the ExportAccountKey API is allowed to return account keys.
+    allow_account_key_export: bool,
+    /// The address consensus sends fees to. Outputs received at a monitored
+    /// subaddress matching it are fee outputs, and are not counted towards
+    /// balances.
+    fee_recipient: Option<PublicAddress>,
+    /// The name of the network this node is connected to. Transaction
+    /// proposals are labeled with it, and proposals labeled for other networks
+    /// are refused.
+    network_name: Option<String>,
     logger: Logger,
 }
 
@@ -179,7 +288,12 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             mobilecoind_db: self.mobilecoind_db.clone(),
             watcher_db: self.watcher_db.clone(),
             network_state: self.network_state.clone(),
+            network_state_refresh: self.network_state_refresh,
             start_sync_thread: self.start_sync_thread.clone(),
+            scan_throughput: self.scan_throughput.clone(),
+            allow_account_key_export: self.allow_account_key_export,
+            fee_recipient: self.fee_recipient.clone(),
+            network_name: self.network_name.clone(),
             logger: self.logger.clone(),
         }
     }
@@ -190,11 +304,13 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
 {
     pub fn new(
         transactions_manager: TransactionsManager<T, FPR>,
-        ledger_db: LedgerDB,
+        ledger_db: Arc<dyn ReadOnlyLedger>,
         mobilecoind_db: Database,
         watcher_db: Option<WatcherDB>,
         network_state: Arc<RwLock<PollingNetworkState<T>>>,
         start_sync_thread: Arc<dyn Fn() + Send + Sync>,
+        scan_throughput: ScanThroughputMeter,
+        config: ServiceConfig,
         logger: Logger,
     ) -> Self {
         Self {
@@ -203,15 +319,93 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             mobilecoind_db,
             watcher_db,
             network_state,
+            network_state_refresh: config.network_state_refresh,
             start_sync_thread,
+            scan_throughput,
+            allow_account_key_export: config.allow_account_key_export,
+            fee_recipient: config.fee_recipient,
+            network_name: config.network_name,
             logger,
         }
     }
 
+    /// Convert a TxProposal to its API representation, labeled with the name
+    /// of the network this node is connected to.
+    fn tx_proposal_to_proto(&self, tx_proposal: &TxProposal) -> mc_mobilecoind_api::TxProposal {
+        let mut proto_tx_proposal = mc_mobilecoind_api::TxProposal::from(tx_proposal);
+        if let Some(network_name) = self.network_name.as_ref() {
+            proto_tx_proposal.set_network_name(network_name.clone());
+        }
+        proto_tx_proposal
+    }
+
+    /// Re-poll the network if the network state is older than allowed by
+    /// `network_state_refresh`. A slow network delays the caller by at most
+    /// the refresh timeout.
+    fn refresh_network_state_if_stale(&self) {
+        let refresh = match self.network_state_refresh {
+            Some(refresh) => refresh,
+            None => return,
+        };
+        let is_stale = |network_state: &PollingNetworkState<T>| {
+            network_state
+                .last_poll_age()
+                .map_or(true, |age| age > refresh.max_age)
+        };
+
+        if !is_stale(&self.network_state.read().expect("lock poisoned")) {
+            return;
+        }
+        let mut network_state = self.network_state.write().expect("lock poisoned");
+        // Another call may have re-polled while we waited for the lock.
+        if is_stale(&network_state) {
+            log::debug!(self.logger, "Network state is stale, re-polling");
+            network_state.poll_with_timeout(refresh.timeout);
+        }
+    }
+
+    /// Whether a monitor's subaddress is the configured fee recipient, in which
+    /// case the outputs it receives are fee outputs. Unknown monitors control
+    /// no subaddresses.
+    fn is_fee_recipient_subaddress(
+        &self,
+        monitor_id: &MonitorId,
+        subaddress_index: u64,
+    ) -> Result<bool, Error> {
+        let fee_recipient = match self.fee_recipient.as_ref() {
+            Some(fee_recipient) => fee_recipient,
+            None => return Ok(false),
+        };
+
+        match self.mobilecoind_db.get_monitor_data(monitor_id) {
+            Ok(data) => Ok(data.account_key.subaddress(subaddress_index) == *fee_recipient),
+            Err(Error::MonitorIdNotFound) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Fill in the block index of UTXOs stored before it was recorded, which
+    /// hold 0, by looking up their TxOut in the ledger. UTXOs received in the
+    /// origin block also hold 0, and the lookup leaves them unchanged.
+    fn backfill_received_block_index(&self, utxos: &mut [UnspentTxOut]) -> Result<(), LedgerError> {
+        for utxo in utxos
+            .iter_mut()
+            .filter(|utxo| utxo.received_block_index == 0)
+        {
+            let tx_out_index = self
+                .ledger_db
+                .get_tx_out_index_by_public_key(&utxo.tx_out.public_key)?;
+            utxo.received_block_index = self
+                .ledger_db
+                .get_block_index_by_tx_out_index(tx_out_index)?;
+        }
+        Ok(())
+    }
+
     fn add_monitor_impl(
         &mut self,
         request: mc_mobilecoind_api::AddMonitorRequest,
-    ) -> Result<mc_mobilecoind_api::AddMonitorResponse, RpcStatus> {
+    ) -> Result<mc_mobilecoind_api::AddMonitorResponse, RpcError> {
         // Get the AccountKey from the GRPC request.
         let proto_account_key = request.account_key.as_ref().ok_or_else(|| {
             RpcStatus::new(
@@ -223,7 +417,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             .map_err(|err| rpc_internal_error("account_key.try_from", err, &self.logger))?;
 
         // Populate a new `MonitorData` instance.
-        let data = MonitorData::new(
+        let mut data = MonitorData::new(
             account_key,
             request.first_subaddress,
             request.num_subaddresses,
@@ -231,14 +425,29 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             &request.name,
         )
         .map_err(|err| rpc_internal_error("monitor_data.new", err, &self.logger))?;
+        data.gap_limit = request.gap_limit;
+        data.confirmations_required = request.confirmations_required;
 
         // Insert into database. Return the id and flag if the monitor already existed.
-        let (id, is_new) = match self.mobilecoind_db.add_monitor(&data) {
+        let options = AddMonitorOptions {
+            require_unique_name: request.require_unique_name,
+        };
+        let (id, is_new) = match self
+            .mobilecoind_db
+            .add_monitor_with_options(&data, &options)
+        {
             Ok(id) => Ok((id, true)),
             Err(Error::MonitorIdExists) => Ok((MonitorId::from(&data), false)),
+            Err(Error::MonitorNameExists(name)) => {
+                return Err(RpcStatus::new(
+                    RpcStatusCode::ALREADY_EXISTS,
+                    Some(format!("A monitor named '{}' already exists", name)),
+                )
+                .into());
+            }
             Err(err) => Err(err),
         }
-        .map_err(|err| rpc_internal_error("mobilecoind_db.add_monitor", err, &self.logger))?;
+        .map_err(|err| rpc_error("mobilecoind_db.add_monitor", err, &self.logger))?;
 
         // Return success response.
         let mut response = mc_mobilecoind_api::AddMonitorResponse::new();
@@ -250,7 +459,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     fn remove_monitor_impl(
         &mut self,
         request: mc_mobilecoind_api::RemoveMonitorRequest,
-    ) -> Result<mc_mobilecoind_api::Empty, RpcStatus> {
+    ) -> Result<mc_mobilecoind_api::Empty, RpcError> {
         // Get MonitorId from from the GRPC request.
         let monitor_id = MonitorId::try_from(&request.monitor_id)
             .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
@@ -258,9 +467,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         // Remove from database.
         self.mobilecoind_db
             .remove_monitor(&monitor_id)
-            .map_err(|err| {
-                rpc_internal_error("mobilecoind_db.remove_monitor", err, &self.logger)
-            })?;
+            .map_err(|err| rpc_error("mobilecoind_db.remove_monitor", err, &self.logger))?;
 
         // Return success response.
         let response = mc_mobilecoind_api::Empty::new();
@@ -270,11 +477,11 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     fn get_monitor_list_impl(
         &mut self,
         _request: mc_mobilecoind_api::Empty,
-    ) -> Result<mc_mobilecoind_api::GetMonitorListResponse, RpcStatus> {
-        let monitor_map: HashMap<MonitorId, MonitorData> =
-            self.mobilecoind_db.get_monitor_map().map_err(|err| {
-                rpc_internal_error("mobilecoind_db.get_monitor_store_map", err, &self.logger)
-            })?;
+    ) -> Result<mc_mobilecoind_api::GetMonitorListResponse, RpcError> {
+        let monitor_map: HashMap<MonitorId, MonitorData> = self
+            .mobilecoind_db
+            .get_monitor_map()
+            .map_err(|err| rpc_error("mobilecoind_db.get_monitor_store_map", err, &self.logger))?;
 
         let mut response = mc_mobilecoind_api::GetMonitorListResponse::new();
         for id in monitor_map.keys() {
@@ -286,16 +493,14 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     fn get_monitor_status_impl(
         &mut self,
         request: mc_mobilecoind_api::GetMonitorStatusRequest,
-    ) -> Result<mc_mobilecoind_api::GetMonitorStatusResponse, RpcStatus> {
+    ) -> Result<mc_mobilecoind_api::GetMonitorStatusResponse, RpcError> {
         let monitor_id = MonitorId::try_from(&request.monitor_id)
             .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
 
         let data = self
             .mobilecoind_db
             .get_monitor_data(&monitor_id)
-            .map_err(|err| {
-                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
-            })?;
+            .map_err(|err| rpc_error("mobilecoind_db.get_monitor_data", err, &self.logger))?;
 
         let mut status = mc_mobilecoind_api::MonitorStatus::new();
         status.set_account_key(mc_api::external::AccountKey::from(&data.account_key));
@@ -303,6 +508,8 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         status.set_num_subaddresses(data.num_subaddresses);
         status.set_first_block(data.first_block);
         status.set_next_block(data.next_block);
+        status.set_gap_limit(data.gap_limit);
+        status.set_confirmations_required(data.confirmations_required);
 
         let mut response = mc_mobilecoind_api::GetMonitorStatusResponse::new();
         response.set_status(status);
@@ -312,22 +519,34 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     fn get_unspent_tx_out_list_impl(
         &mut self,
         request: mc_mobilecoind_api::GetUnspentTxOutListRequest,
-    ) -> Result<mc_mobilecoind_api::GetUnspentTxOutListResponse, RpcStatus> {
+    ) -> Result<mc_mobilecoind_api::GetUnspentTxOutListResponse, RpcError> {
         // Get MonitorId from from the GRPC request.
         let monitor_id = MonitorId::try_from(&request.monitor_id)
             .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
 
         // Get UnspentTxOuts.
-        let utxos = self
+        let mut utxos = self
             .mobilecoind_db
             .get_utxos_for_subaddress(&monitor_id, request.subaddress_index)
             .map_err(|err| {
-                rpc_internal_error("mobilecoind_db.get_utxos_for_subaddress", err, &self.logger)
+                rpc_error("mobilecoind_db.get_utxos_for_subaddress", err, &self.logger)
+            })?;
+        self.backfill_received_block_index(&mut utxos)
+            .map_err(|err| {
+                rpc_internal_error("backfill_received_block_index", err, &self.logger)
             })?;
 
-        // Convert to protos.
-        let proto_utxos: Vec<mc_mobilecoind_api::UnspentTxOut> =
-            utxos.iter().map(|utxo| utxo.into()).collect();
+        // Keep the outputs within the requested value range, and convert them to
+        // protos.
+        let max_value = match request.max_value {
+            0 => u64::MAX,
+            max_value => max_value,
+        };
+        let proto_utxos: Vec<mc_mobilecoind_api::UnspentTxOut> = utxos
+            .iter()
+            .filter(|utxo| request.min_value <= utxo.value && utxo.value <= max_value)
+            .map(|utxo| utxo.into())
+            .collect();
 
         // Returrn response.
         let mut response = mc_mobilecoind_api::GetUnspentTxOutListResponse::new();
@@ -335,6 +554,191 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         Ok(response)
     }
 
+    fn get_next_unused_subaddress_impl(
+        &mut self,
+        request: mc_mobilecoind_api::GetNextUnusedSubaddressRequest,
+    ) -> Result<mc_mobilecoind_api::GetNextUnusedSubaddressResponse, RpcError> {
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        let subaddress_index = self
+            .mobilecoind_db
+            .get_next_unused_subaddress(&monitor_id)
+            .map_err(|err| {
+                rpc_error(
+                    "mobilecoind_db.get_next_unused_subaddress",
+                    err,
+                    &self.logger,
+                )
+            })?;
+
+        let data = self
+            .mobilecoind_db
+            .get_monitor_data(&monitor_id)
+            .map_err(|err| rpc_error("mobilecoind_db.get_monitor_data", err, &self.logger))?;
+        let subaddress = data.account_key.subaddress(subaddress_index);
+
+        let mut wrapper = mc_mobilecoind_api::printable::PrintableWrapper::new();
+        wrapper.set_public_address((&subaddress).into());
+
+        let mut response = mc_mobilecoind_api::GetNextUnusedSubaddressResponse::new();
+        response.set_subaddress_index(subaddress_index);
+        response.set_public_address((&subaddress).into());
+        response.set_b58_code(
+            wrapper
+                .b58_encode()
+                .map_err(|err| rpc_internal_error("b58_encode", err, &self.logger))?,
+        );
+
+        Ok(response)
+    }
+
+    fn cancel_payment_request_impl(
+        &mut self,
+        request: mc_mobilecoind_api::CancelPaymentRequestRequest,
+    ) -> Result<mc_mobilecoind_api::Empty, RpcError> {
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        self.mobilecoind_db
+            .cancel_payment_request(&monitor_id, request.subaddress_index)
+            .map_err(|err| rpc_error("mobilecoind_db.cancel_payment_request", err, &self.logger))?;
+
+        Ok(mc_mobilecoind_api::Empty::new())
+    }
+
+    fn export_account_key_impl(
+        &mut self,
+        request: mc_mobilecoind_api::ExportAccountKeyRequest,
+    ) -> Result<mc_mobilecoind_api::ExportAccountKeyResponse, RpcError> {
+        if !self.allow_account_key_export {
+            return Err(rpc_permissions_error(
+                "export_account_key",
+                "account key export is disabled",
+                &self.logger,
+            )
+            .into());
+        }
+
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        let data = self
+            .mobilecoind_db
+            .get_monitor_data(&monitor_id)
+            .map_err(|err| rpc_error("mobilecoind_db.get_monitor_data", err, &self.logger))?;
+
+        // The response contains the account's private keys, and must never be logged.
+        log::info!(
+            self.logger,
+            "Exporting account key of monitor {}",
+            monitor_id
+        );
+
+        let mut response = mc_mobilecoind_api::ExportAccountKeyResponse::new();
+        response.set_account_key(mc_api::external::AccountKey::from(&data.account_key));
+        Ok(response)
+    }
+
+    fn export_view_key_impl(
+        &mut self,
+        request: mc_mobilecoind_api::ExportViewKeyRequest,
+    ) -> Result<mc_mobilecoind_api::ExportViewKeyResponse, RpcError> {
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        let data = self
+            .mobilecoind_db
+            .get_monitor_data(&monitor_id)
+            .map_err(|err| rpc_error("mobilecoind_db.get_monitor_data", err, &self.logger))?;
+
+        // Only subaddresses the monitor tracks can be exported.
+        let end = request
+            .first_subaddress
+            .checked_add(request.num_subaddresses)
+            .unwrap_or(u64::MAX);
+        if request.num_subaddresses == 0
+            || request.first_subaddress < data.first_subaddress
+            || end > data.first_subaddress + data.num_subaddresses
+        {
+            return Err(RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("first_subaddress/num_subaddresses".to_string()),
+            )
+            .into());
+        }
+
+        // The view private key lets its holder see incoming funds, but the spend
+        // private key needed for key images never leaves mobilecoind.
+        log::info!(
+            self.logger,
+            "Exporting view key of monitor {} for subaddresses {}..{}",
+            monitor_id,
+            request.first_subaddress,
+            end
+        );
+
+        let mut response = mc_mobilecoind_api::ExportViewKeyResponse::new();
+        response.set_view_private_key(data.account_key.view_private_key().into());
+        response.set_subaddress_spend_public_keys(RepeatedField::from_vec(
+            (request.first_subaddress..end)
+                .map(|index| data.account_key.subaddress(index).spend_public_key().into())
+                .collect(),
+        ));
+        Ok(response)
+    }
+
+    fn restore_account_impl(
+        &mut self,
+        request: mc_mobilecoind_api::RestoreAccountRequest,
+    ) -> Result<
+        UnboundedReceiver<Result<mc_mobilecoind_api::RestoreAccountResponse, RpcStatus>>,
+        RpcError,
+    > {
+        // Validate the request before anything is scanned.
+        let mnemonic = Mnemonic::from_phrase(request.get_mnemonic(), Language::English)
+            .map_err(|err| rpc_invalid_arg_error("mnemonic", err, &self.logger))?;
+        let account_key = AccountKey::from(mnemonic.derive_slip10_key(request.account_index));
+
+        let data = MonitorData::new(
+            account_key,
+            DEFAULT_SUBADDRESS_INDEX,
+            request.num_subaddresses,
+            request.scan_from_block,
+            "",
+        )
+        .map_err(|err| rpc_invalid_arg_error("monitor_data.new", err, &self.logger))?;
+
+        // Restoring an account that is already monitored reports on the existing
+        // monitor.
+        let monitor_id = match self.mobilecoind_db.add_monitor(&data) {
+            Ok(monitor_id) => Ok(monitor_id),
+            Err(Error::MonitorIdExists) => Ok(MonitorId::from(&data)),
+            Err(err) => Err(err),
+        }
+        .map_err(|err| rpc_error("mobilecoind_db.add_monitor", err, &self.logger))?;
+
+        let (sender, receiver) = mpsc::unbounded();
+        let ledger_db = self.ledger_db.clone();
+        let mobilecoind_db = self.mobilecoind_db.clone();
+        let logger = self.logger.clone();
+        thread::Builder::new()
+            .name("restore_account".to_string())
+            .spawn(move || {
+                report_restore_progress(
+                    ledger_db,
+                    mobilecoind_db,
+                    monitor_id,
+                    sender,
+                    RESTORE_ACCOUNT_TIMEOUT,
+                    logger,
+                )
+            })
+            .map_err(|err| rpc_internal_error("thread.spawn", err, &self.logger))?;
+
+        Ok(receiver)
+    }
+
     fn generate_root_entropy_impl(
         &mut self,
         _request: mc_mobilecoind_api::Empty,
@@ -400,7 +804,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     fn get_public_address_impl(
         &mut self,
         request: mc_mobilecoind_api::GetPublicAddressRequest,
-    ) -> Result<mc_mobilecoind_api::GetPublicAddressResponse, RpcStatus> {
+    ) -> Result<mc_mobilecoind_api::GetPublicAddressResponse, RpcError> {
         // Get MonitorId from from the GRPC request.
         let monitor_id = MonitorId::try_from(&request.monitor_id)
             .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
@@ -409,9 +813,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         let data = self
             .mobilecoind_db
             .get_monitor_data(&monitor_id)
-            .map_err(|err| {
-                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
-            })?;
+            .map_err(|err| rpc_error("mobilecoind_db.get_monitor_data", err, &self.logger))?;
 
         // Verify subaddress falls in the range we are monitoring.
         if !data
@@ -421,7 +823,8 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             return Err(RpcStatus::new(
                 RpcStatusCode::INVALID_ARGUMENT,
                 Some("subaddress_index".to_string()),
-            ));
+            )
+            .into());
         }
 
         // Get the subaddress.
@@ -536,6 +939,16 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         let tx_out = self.ledger_db.get_tx_out_by_index(index).map_err(|err| {
             rpc_internal_error("ledger_db.get_tx_out_by_index", err, &self.logger)
         })?;
+        let block_index = self
+            .ledger_db
+            .get_block_index_by_tx_out_index(index)
+            .map_err(|err| {
+                rpc_internal_error(
+                    "ledger_db.get_block_index_by_tx_out_index",
+                    err,
+                    &self.logger,
+                )
+            })?;
 
         // Use bip39 or root entropy to construct AccountKey.
         let account_key = if !transfer_payload.get_bip39_entropy().is_empty() {
@@ -582,6 +995,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             value,
             attempted_spend_height: 0,
             attempted_spend_tombstone: 0,
+            received_block_index: block_index,
         };
 
         let mut response = mc_mobilecoind_api::ParseTransferCodeResponse::new();
@@ -706,6 +1120,109 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         Ok(response)
     }
 
+    fn encode_address_impl(
+        &mut self,
+        request: mc_mobilecoind_api::EncodeAddressRequest,
+    ) -> Result<mc_mobilecoind_api::EncodeAddressResponse, RpcStatus> {
+        let public_address = PublicAddress::try_from(request.get_public_address())
+            .map_err(|err| rpc_invalid_arg_error("public_address", err, &self.logger))?;
+
+        let mut wrapper = mc_mobilecoind_api::printable::PrintableWrapper::new();
+        wrapper.set_public_address((&public_address).into());
+
+        let encoded = wrapper
+            .b58_encode()
+            .map_err(|err| rpc_internal_error("b58_encode", err, &self.logger))?;
+
+        let mut response = mc_mobilecoind_api::EncodeAddressResponse::new();
+        response.set_b58_address(encoded);
+        Ok(response)
+    }
+
+    fn decode_address_impl(
+        &mut self,
+        request: mc_mobilecoind_api::DecodeAddressRequest,
+    ) -> Result<mc_mobilecoind_api::DecodeAddressResponse, RpcStatus> {
+        let wrapper = mc_mobilecoind_api::printable::PrintableWrapper::b58_decode(
+            request.get_b58_address().to_string(),
+        )
+        .map_err(|err| rpc_invalid_arg_error("b58_address", err, &self.logger))?;
+
+        if !wrapper.has_public_address() {
+            return Err(RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("b58_address: not a public address".to_string()),
+            ));
+        }
+
+        // Make sure the decoded keys are valid before handing the address back.
+        let public_address = PublicAddress::try_from(wrapper.get_public_address())
+            .map_err(|err| rpc_invalid_arg_error("b58_address", err, &self.logger))?;
+
+        let mut response = mc_mobilecoind_api::DecodeAddressResponse::new();
+        response.set_public_address((&public_address).into());
+        Ok(response)
+    }
+
+    fn encode_payment_request_impl(
+        &mut self,
+        request: mc_mobilecoind_api::EncodePaymentRequestRequest,
+    ) -> Result<mc_mobilecoind_api::EncodePaymentRequestResponse, RpcStatus> {
+        let public_address = PublicAddress::try_from(request.get_public_address())
+            .map_err(|err| rpc_invalid_arg_error("public_address", err, &self.logger))?;
+
+        if request.get_value() == 0 {
+            return Err(RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("value: must be greater than zero".to_string()),
+            ));
+        }
+
+        let mut payment_request = mc_mobilecoind_api::printable::PaymentRequest::new();
+        payment_request.set_public_address((&public_address).into());
+        payment_request.set_value(request.get_value());
+        payment_request.set_memo(request.get_memo().to_string());
+
+        let mut wrapper = mc_mobilecoind_api::printable::PrintableWrapper::new();
+        wrapper.set_payment_request(payment_request);
+
+        let encoded = wrapper
+            .b58_encode()
+            .map_err(|err| rpc_internal_error("b58_encode", err, &self.logger))?;
+
+        let mut response = mc_mobilecoind_api::EncodePaymentRequestResponse::new();
+        response.set_b58_code(encoded);
+        Ok(response)
+    }
+
+    fn decode_payment_request_impl(
+        &mut self,
+        request: mc_mobilecoind_api::DecodePaymentRequestRequest,
+    ) -> Result<mc_mobilecoind_api::DecodePaymentRequestResponse, RpcStatus> {
+        let wrapper = mc_mobilecoind_api::printable::PrintableWrapper::b58_decode(
+            request.get_b58_code().to_string(),
+        )
+        .map_err(|err| rpc_invalid_arg_error("b58_code", err, &self.logger))?;
+
+        if !wrapper.has_payment_request() {
+            return Err(RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("b58_code: not a payment request".to_string()),
+            ));
+        }
+        let payment_request = wrapper.get_payment_request();
+
+        // Make sure the decoded keys are valid before handing the address back.
+        let public_address = PublicAddress::try_from(payment_request.get_public_address())
+            .map_err(|err| rpc_invalid_arg_error("b58_code", err, &self.logger))?;
+
+        let mut response = mc_mobilecoind_api::DecodePaymentRequestResponse::new();
+        response.set_public_address((&public_address).into());
+        response.set_value(payment_request.get_value());
+        response.set_memo(payment_request.get_memo().to_string());
+        Ok(response)
+    }
+
     /// Get mixins
     fn get_mixins_impl(
         &mut self,
@@ -785,7 +1302,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     fn generate_tx_impl(
         &mut self,
         request: mc_mobilecoind_api::GenerateTxRequest,
-    ) -> Result<mc_mobilecoind_api::GenerateTxResponse, RpcStatus> {
+    ) -> Result<mc_mobilecoind_api::GenerateTxResponse, RpcError> {
         // Get sender monitor id from request.
         let sender_monitor_id = MonitorId::try_from(&request.sender_monitor_id)
             .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
@@ -794,9 +1311,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         let sender_monitor_data = self
             .mobilecoind_db
             .get_monitor_data(&sender_monitor_id)
-            .map_err(|err| {
-                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
-            })?;
+            .map_err(|err| rpc_error("mobilecoind_db.get_monitor_data", err, &self.logger))?;
 
         // Check that change_subaddress is covered by this monitor.
         if !sender_monitor_data
@@ -806,7 +1321,8 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             return Err(RpcStatus::new(
                 RpcStatusCode::INVALID_ARGUMENT,
                 Some("change_subaddress".to_string()),
-            ));
+            )
+            .into());
         }
 
         // Get the list of potential inputs passed to.
@@ -825,7 +1341,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                     .mobilecoind_db
                     .get_subaddress_id_by_utxo_id(&UtxoId::from(&utxo))
                     .map_err(|err| {
-                        rpc_internal_error(
+                        rpc_error(
                             "mobilecoind_db.get_subaddress_id_by_utxo_id",
                             err,
                             &self.logger,
@@ -836,13 +1352,14 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                     return Err(RpcStatus::new(
                         RpcStatusCode::INVALID_ARGUMENT,
                         Some(format!("input_list.{}", i)),
-                    ));
+                    )
+                    .into());
                 }
 
                 // Success.
                 Ok(utxo)
             })
-            .collect::<Result<Vec<UnspentTxOut>, RpcStatus>>()?;
+            .collect::<Result<Vec<UnspentTxOut>, RpcError>>()?;
 
         // Get the list of outlays.
         let outlays: Vec<Outlay> = request
@@ -855,40 +1372,99 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             .collect::<Result<Vec<Outlay>, RpcStatus>>()?;
 
         // Attempt to construct a transaction.
-        let tx_proposal = self
-            .transactions_manager
-            .build_transaction(
-                &sender_monitor_id,
-                request.change_subaddress,
-                &input_list,
-                &outlays,
-                request.fee,
-                request.tombstone,
-            )
-            .map_err(|err| {
-                rpc_internal_error("transactions_manager.build_transaction", err, &self.logger)
-            })?;
-
-        // Success.
-        let mut response = mc_mobilecoind_api::GenerateTxResponse::new();
-        response.set_tx_proposal((&tx_proposal).into());
-        Ok(response)
-    }
+        let tx_proposal = if request.has_first_input_receipt() {
+            let first_input =
+                RistrettoPublic::try_from(request.get_first_input_receipt().get_tx_public_key())
+                    .map(|public_key| CompressedRistrettoPublic::from(&public_key))
+                    .map_err(|err| {
+                        rpc_internal_error("RistrettoPublic.try_from", err, &self.logger)
+                    })?;
 
-    fn generate_optimization_tx_impl(
-        &mut self,
-        request: mc_mobilecoind_api::GenerateOptimizationTxRequest,
-    ) -> Result<mc_mobilecoind_api::GenerateOptimizationTxResponse, RpcStatus> {
-        // Get monitor id from request.
-        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            self.transactions_manager
+                .build_transaction_with_first_input(
+                    &sender_monitor_id,
+                    request.change_subaddress,
+                    &first_input,
+                    &input_list,
+                    &outlays,
+                    request.fee,
+                    request.get_fee_priority().into(),
+                    request.tombstone,
+                    &self.logger,
+                )
+                .map_err(|err| {
+                    rpc_error(
+                        "transactions_manager.build_transaction_with_first_input",
+                        err,
+                        &self.logger,
+                    )
+                })?
+        } else {
+            self.transactions_manager
+                .build_transaction(
+                    &sender_monitor_id,
+                    request.change_subaddress,
+                    &input_list,
+                    &outlays,
+                    request.fee,
+                    request.get_fee_priority().into(),
+                    request.tombstone,
+                    &self.logger,
+                )
+                .map_err(|err| {
+                    rpc_error("transactions_manager.build_transaction", err, &self.logger)
+                })?
+        };
+
+        let mut response = mc_mobilecoind_api::GenerateTxResponse::new();
+
+        // The transaction will be submitted elsewhere, so reserve its inputs now.
+        // Nothing has been sent to the network yet, so failing here is safe.
+        if request.for_external_submission {
+            let block_height = self
+                .ledger_db
+                .num_blocks()
+                .map_err(|err| rpc_internal_error("ledger_db.num_blocks", err, &self.logger))?;
+
+            self.transactions_manager
+                .reserve_inputs(&tx_proposal, block_height)
+                .map_err(|err| {
+                    rpc_error("transactions_manager.reserve_inputs", err, &self.logger)
+                })?;
+
+            let utxo_ids: Vec<UtxoId> = tx_proposal.utxos.iter().map(UtxoId::from).collect();
+            self.mobilecoind_db
+                .update_attempted_spend(
+                    &utxo_ids,
+                    block_height,
+                    tx_proposal.tx.prefix.tombstone_block,
+                )
+                .map_err(|err| {
+                    rpc_error("mobilecoind_db.update_attempted_spend", err, &self.logger)
+                })?;
+
+            response.set_serialized_tx(mc_util_serial::encode(&tx_proposal.tx));
+        }
+
+        // Success.
+        response.set_tx_proposal(self.tx_proposal_to_proto(&tx_proposal));
+        Ok(response)
+    }
+
+    fn generate_optimization_tx_impl(
+        &mut self,
+        request: mc_mobilecoind_api::GenerateOptimizationTxRequest,
+    ) -> Result<mc_mobilecoind_api::GenerateOptimizationTxResponse, RpcError> {
+        // Get monitor id from request.
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
             .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
 
         // Generate optimization tx.
         let tx_proposal = self
             .transactions_manager
-            .generate_optimization_tx(&monitor_id, request.subaddress, request.fee)
+            .generate_optimization_tx(&monitor_id, request.subaddress, request.fee, &self.logger)
             .map_err(|err| {
-                rpc_internal_error(
+                rpc_error(
                     "transactions_manager.generate_optimization_tx",
                     err,
                     &self.logger,
@@ -897,14 +1473,79 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
 
         // Success.
         let mut response = mc_mobilecoind_api::GenerateOptimizationTxResponse::new();
-        response.set_tx_proposal((&tx_proposal).into());
+        response.set_tx_proposal(self.tx_proposal_to_proto(&tx_proposal));
+        Ok(response)
+    }
+
+    fn get_consolidation_preview_impl(
+        &mut self,
+        request: mc_mobilecoind_api::GetConsolidationPreviewRequest,
+    ) -> Result<mc_mobilecoind_api::GetConsolidationPreviewResponse, RpcError> {
+        // Get monitor id from request.
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        // A max_inputs of 0 means the configured input limit.
+        let max_inputs = match request.max_inputs {
+            0 => self.transactions_manager.max_inputs(),
+            max_inputs => max_inputs as usize,
+        };
+
+        let preview = self
+            .transactions_manager
+            .consolidation_preview(&monitor_id, max_inputs)
+            .map_err(|err| {
+                rpc_error(
+                    "transactions_manager.consolidation_preview",
+                    err,
+                    &self.logger,
+                )
+            })?;
+
+        // Success.
+        let mut response = mc_mobilecoind_api::GetConsolidationPreviewResponse::new();
+        response.set_num_inputs(preview.num_inputs as u64);
+        response.set_output_value(preview.output_value);
+        response.set_fee(preview.fee);
+        Ok(response)
+    }
+
+    fn generate_consolidation_tx_impl(
+        &mut self,
+        request: mc_mobilecoind_api::GenerateConsolidationTxRequest,
+    ) -> Result<mc_mobilecoind_api::GenerateConsolidationTxResponse, RpcError> {
+        // Get monitor id from request.
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        // A max_inputs of 0 means the configured input limit.
+        let max_inputs = match request.max_inputs {
+            0 => self.transactions_manager.max_inputs(),
+            max_inputs => max_inputs as usize,
+        };
+
+        // Generate consolidation tx.
+        let tx_proposal = self
+            .transactions_manager
+            .build_consolidation(&monitor_id, request.subaddress, max_inputs, &self.logger)
+            .map_err(|err| {
+                rpc_error(
+                    "transactions_manager.build_consolidation",
+                    err,
+                    &self.logger,
+                )
+            })?;
+
+        // Success.
+        let mut response = mc_mobilecoind_api::GenerateConsolidationTxResponse::new();
+        response.set_tx_proposal(self.tx_proposal_to_proto(&tx_proposal));
         Ok(response)
     }
 
     fn generate_tx_from_tx_out_list_impl(
         &mut self,
         request: mc_mobilecoind_api::GenerateTxFromTxOutListRequest,
-    ) -> Result<mc_mobilecoind_api::GenerateTxFromTxOutListResponse, RpcStatus> {
+    ) -> Result<mc_mobilecoind_api::GenerateTxFromTxOutListResponse, RpcError> {
         let proto_account_key = request.account_key.as_ref().ok_or_else(|| {
             RpcStatus::new(
                 RpcStatusCode::INVALID_ARGUMENT,
@@ -930,9 +1571,15 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
 
         let tx_proposal = self
             .transactions_manager
-            .generate_tx_from_tx_list(&account_key, &input_list, &receiver, request.fee)
+            .generate_tx_from_tx_list(
+                &account_key,
+                &input_list,
+                &receiver,
+                request.fee,
+                &self.logger,
+            )
             .map_err(|err| {
-                rpc_internal_error(
+                rpc_error(
                     "transactions_manager.generate_tx_from_tx_list",
                     err,
                     &self.logger,
@@ -940,14 +1587,14 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             })?;
 
         let mut response = mc_mobilecoind_api::GenerateTxFromTxOutListResponse::new();
-        response.set_tx_proposal((&tx_proposal).into());
+        response.set_tx_proposal(self.tx_proposal_to_proto(&tx_proposal));
         Ok(response)
     }
 
     fn generate_transfer_code_tx_impl(
         &mut self,
         request: mc_mobilecoind_api::GenerateTransferCodeTxRequest,
-    ) -> Result<mc_mobilecoind_api::GenerateTransferCodeTxResponse, RpcStatus> {
+    ) -> Result<mc_mobilecoind_api::GenerateTransferCodeTxResponse, RpcError> {
         // Generate entropy.
         let mnemonic_response = self.generate_mnemonic_impl(mc_mobilecoind_api::Empty::new())?;
         let mnemonic_str = mnemonic_response.get_mnemonic().to_string();
@@ -989,7 +1636,8 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                         "outlay_index_to_tx_out_index contains {} elements, was expecting 1",
                         tx_proposal.get_outlay_index_to_tx_out_index().len()
                     )),
-                ));
+                )
+                .into());
             }
 
             // Get the TxOut index of our single outlay.
@@ -1048,7 +1696,24 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     fn submit_tx_impl(
         &mut self,
         request: mc_mobilecoind_api::SubmitTxRequest,
-    ) -> Result<mc_mobilecoind_api::SubmitTxResponse, RpcStatus> {
+    ) -> Result<mc_mobilecoind_api::SubmitTxResponse, RpcError> {
+        // Refuse proposals labeled for a different network, which were most likely
+        // sent to the wrong mobilecoind. The label is not signed, so this is no
+        // protection against replaying the transaction.
+        if let Some(network_name) = self.network_name.as_ref() {
+            let tx_network_name = request.get_tx_proposal().get_network_name();
+            if tx_network_name != network_name {
+                return Err(RpcStatus::new(
+                    RpcStatusCode::INVALID_ARGUMENT,
+                    Some(format!(
+                        "tx_proposal.network_name: expected {:?}, got {:?}",
+                        network_name, tx_network_name
+                    )),
+                )
+                .into());
+            }
+        }
+
         // Get TxProposal from request.
         let tx_proposal = TxProposal::try_from(request.get_tx_proposal())
             .map_err(|err| rpc_internal_error("tx_proposal.try_from", err, &self.logger))?;
@@ -1056,9 +1721,9 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         // Submit to network.
         let block_height = self
             .transactions_manager
-            .submit_tx_proposal(&tx_proposal)
+            .submit_tx_proposal(&tx_proposal, &self.logger)
             .map_err(|err| {
-                rpc_internal_error("transactions_manager.submit_tx_proposal", err, &self.logger)
+                rpc_error("transactions_manager.submit_tx_proposal", err, &self.logger)
             })?;
 
         // Update the attempted spend block height in db. Note that we swallow the error
@@ -1394,20 +2059,22 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     fn get_tx_status_as_receiver_impl(
         &mut self,
         request: mc_mobilecoind_api::GetTxStatusAsReceiverRequest,
-    ) -> Result<mc_mobilecoind_api::GetTxStatusAsReceiverResponse, RpcStatus> {
+    ) -> Result<mc_mobilecoind_api::GetTxStatusAsReceiverResponse, RpcError> {
         // Sanity-test the request.
         if request.get_receipt().get_tx_out_hash().len() != 32 {
             return Err(RpcStatus::new(
                 RpcStatusCode::INVALID_ARGUMENT,
                 Some("receipt.tx_out_hash".to_string()),
-            ));
+            )
+            .into());
         }
 
         if request.get_receipt().tombstone == 0 {
             return Err(RpcStatus::new(
                 RpcStatusCode::INVALID_ARGUMENT,
                 Some("receipt.tombstone".to_string()),
-            ));
+            )
+            .into());
         }
 
         // Check if the hash landed in the ledger.
@@ -1430,11 +2097,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                             .mobilecoind_db
                             .get_monitor_data(&monitor_id)
                             .map_err(|err| {
-                                rpc_internal_error(
-                                    "mobilecoind_db.get_monitor_data",
-                                    err,
-                                    &self.logger,
-                                )
+                                rpc_error("mobilecoind_db.get_monitor_data", err, &self.logger)
                             })?;
                         let tx_public_key =
                             RistrettoPublic::try_from(request.get_receipt().get_tx_public_key())
@@ -1451,7 +2114,8 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                             return Err(RpcStatus::new(
                                 RpcStatusCode::INVALID_ARGUMENT,
                                 Some("receipt.confirmation_number".to_string()),
-                            ));
+                            )
+                            .into());
                         }
 
                         // Test that the confirmation number is valid. Only the party constructing
@@ -1481,7 +2145,8 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                         return Err(RpcStatus::new(
                             RpcStatusCode::INVALID_ARGUMENT,
                             Some("monitor_id".to_string()),
-                        ));
+                        )
+                        .into());
                     }
                 }
 
@@ -1496,7 +2161,8 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                     "ledger_db.get_tx_out_index_by_hash",
                     err,
                     &self.logger,
-                ));
+                )
+                .into());
             }
         };
 
@@ -1521,7 +2187,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     fn get_processed_block_impl(
         &mut self,
         request: mc_mobilecoind_api::GetProcessedBlockRequest,
-    ) -> Result<mc_mobilecoind_api::GetProcessedBlockResponse, RpcStatus> {
+    ) -> Result<mc_mobilecoind_api::GetProcessedBlockResponse, RpcError> {
         // Get MonitorId from from the GRPC request.
         let monitor_id = MonitorId::try_from(&request.monitor_id)
             .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
@@ -1530,18 +2196,14 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         let account_key = self
             .mobilecoind_db
             .get_monitor_data(&monitor_id)
-            .map_err(|err| {
-                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
-            })?
+            .map_err(|err| rpc_error("mobilecoind_db.get_monitor_data", err, &self.logger))?
             .account_key;
 
         // Get all processed block data for the requested block.
         let processed_tx_outs = self
             .mobilecoind_db
             .get_processed_block(&monitor_id, request.block)
-            .map_err(|err| {
-                rpc_internal_error("mobilecoind_db.get_processed_block", err, &self.logger)
-            })?
+            .map_err(|err| rpc_error("mobilecoind_db.get_processed_block", err, &self.logger))?
             .iter()
             .map(|src| {
                 let mut dst = mc_mobilecoind_api::ProcessedTxOut::new();
@@ -1608,46 +2270,313 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         Ok(response)
     }
 
+    fn get_tx_out_index_impl(
+        &mut self,
+        request: mc_mobilecoind_api::GetTxOutIndexRequest,
+    ) -> Result<mc_mobilecoind_api::GetTxOutIndexResponse, RpcStatus> {
+        let tx_out_public_key = RistrettoPublic::try_from(request.get_tx_out_public_key())
+            .map_err(|err| rpc_internal_error("RistrettoPublic.try_from", err, &self.logger))?;
+
+        let index = self
+            .ledger_db
+            .get_tx_out_index_by_public_key(&CompressedRistrettoPublic::from(&tx_out_public_key))
+            .map_err(|err| match err {
+                LedgerError::NotFound => RpcStatus::new(
+                    RpcStatusCode::NOT_FOUND,
+                    Some("tx_out_public_key".to_string()),
+                ),
+                err => rpc_internal_error(
+                    "ledger_db.get_tx_out_index_by_public_key",
+                    err,
+                    &self.logger,
+                ),
+            })?;
+
+        let mut response = mc_mobilecoind_api::GetTxOutIndexResponse::new();
+        response.set_index(index);
+        Ok(response)
+    }
+
+    fn get_tombstone_blocks_remaining_impl(
+        &mut self,
+        request: mc_mobilecoind_api::GetTombstoneBlocksRemainingRequest,
+    ) -> Result<mc_mobilecoind_api::GetTombstoneBlocksRemainingResponse, RpcStatus> {
+        let num_blocks = self
+            .ledger_db
+            .num_blocks()
+            .map_err(|err| rpc_internal_error("ledger_db.num_blocks", err, &self.logger))?;
+
+        // A transaction may be included in any block whose index is below its
+        // tombstone block.
+        let blocks_remaining = request.tombstone_block.saturating_sub(num_blocks);
+
+        let mut response = mc_mobilecoind_api::GetTombstoneBlocksRemainingResponse::new();
+        response.set_blocks_remaining(blocks_remaining);
+        if let Some(block_interval) = self.average_block_interval(num_blocks)? {
+            response.set_has_eta(true);
+            response.set_eta_seconds(blocks_remaining.saturating_mul(block_interval));
+        }
+        Ok(response)
+    }
+
+    fn get_blocks_until_spendable_impl(
+        &mut self,
+        request: mc_mobilecoind_api::GetBlocksUntilSpendableRequest,
+    ) -> Result<mc_mobilecoind_api::GetBlocksUntilSpendableResponse, RpcError> {
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        let data = self
+            .mobilecoind_db
+            .get_monitor_data(&monitor_id)
+            .map_err(|err| rpc_error("mobilecoind_db.get_monitor_data", err, &self.logger))?;
+
+        let tx_out_public_key = RistrettoPublic::try_from(request.get_tx_out_public_key())
+            .map_err(|err| rpc_internal_error("RistrettoPublic.try_from", err, &self.logger))?;
+
+        let tx_out_index = self
+            .ledger_db
+            .get_tx_out_index_by_public_key(&CompressedRistrettoPublic::from(&tx_out_public_key))
+            .map_err(|err| match err {
+                LedgerError::NotFound => RpcStatus::new(
+                    RpcStatusCode::NOT_FOUND,
+                    Some("tx_out_public_key".to_string()),
+                ),
+                err => rpc_internal_error(
+                    "ledger_db.get_tx_out_index_by_public_key",
+                    err,
+                    &self.logger,
+                ),
+            })?;
+
+        let block_index = self
+            .ledger_db
+            .get_block_index_by_tx_out_index(tx_out_index)
+            .map_err(|err| {
+                rpc_internal_error(
+                    "ledger_db.get_block_index_by_tx_out_index",
+                    err,
+                    &self.logger,
+                )
+            })?;
+
+        let num_blocks = self
+            .ledger_db
+            .num_blocks()
+            .map_err(|err| rpc_internal_error("ledger_db.num_blocks", err, &self.logger))?;
+
+        // The TxOut is confirmed once the ledger holds `confirmations_required`
+        // blocks counting from the one it landed in.
+        let blocks_remaining = block_index
+            .saturating_add(data.confirmations_required)
+            .saturating_sub(num_blocks);
+
+        let mut response = mc_mobilecoind_api::GetBlocksUntilSpendableResponse::new();
+        response.set_blocks_remaining(blocks_remaining);
+        if let Some(block_interval) = self.average_block_interval(num_blocks)? {
+            response.set_has_eta(true);
+            response.set_eta_seconds(blocks_remaining.saturating_mul(block_interval));
+        }
+        Ok(response)
+    }
+
+    fn check_key_images_impl(
+        &mut self,
+        request: mc_mobilecoind_api::CheckKeyImagesRequest,
+    ) -> Result<mc_mobilecoind_api::CheckKeyImagesResponse, RpcStatus> {
+        let statuses = request
+            .get_key_images()
+            .iter()
+            .map(|proto_key_image| {
+                let key_image = KeyImage::try_from(proto_key_image).map_err(|err| {
+                    rpc_invalid_arg_error("key_image.try_from", err, &self.logger)
+                })?;
+
+                let spent_block_index =
+                    self.ledger_db.check_key_image(&key_image).map_err(|err| {
+                        rpc_internal_error("ledger_db.check_key_image", err, &self.logger)
+                    })?;
+
+                let mut status = mc_mobilecoind_api::KeyImageStatus::new();
+                status.set_key_image(proto_key_image.clone());
+                if let Some(block_index) = spent_block_index {
+                    status.set_spent(true);
+                    status.set_spent_block_index(block_index);
+                }
+                Ok(status)
+            })
+            .collect::<Result<Vec<_>, RpcStatus>>()?;
+
+        let mut response = mc_mobilecoind_api::CheckKeyImagesResponse::new();
+        response.set_statuses(RepeatedField::from_vec(statuses));
+        Ok(response)
+    }
+
+    /// Estimate the average number of seconds between recent blocks, if a
+    /// watcher is available and has timestamps for them.
+    fn average_block_interval(&self, num_blocks: u64) -> Result<Option<u64>, RpcStatus> {
+        let watcher_db = match self.watcher_db.as_ref() {
+            Some(watcher_db) => watcher_db,
+            None => return Ok(None),
+        };
+
+        // The origin block is not signed, so it has no timestamp.
+        let last_block = num_blocks.saturating_sub(1);
+        let first_block = last_block
+            .saturating_sub(BLOCK_INTERVAL_ESTIMATE_WINDOW)
+            .max(1);
+
+        watcher_db
+            .average_block_interval(first_block, last_block)
+            .map_err(|err| {
+                rpc_internal_error("watcher_db.average_block_interval", err, &self.logger)
+            })
+    }
+
     fn get_balance_impl(
         &mut self,
         request: mc_mobilecoind_api::GetBalanceRequest,
-    ) -> Result<mc_mobilecoind_api::GetBalanceResponse, RpcStatus> {
+    ) -> Result<mc_mobilecoind_api::GetBalanceResponse, RpcError> {
         // Get MonitorId from from the GRPC request.
         let monitor_id = MonitorId::try_from(&request.monitor_id)
             .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
 
         // Get all utxos for this monitor id.
-        let utxos = self
+        let mut utxos = self
             .mobilecoind_db
             .get_utxos_for_subaddress(&monitor_id, request.subaddress_index)
             .map_err(|err| {
-                rpc_internal_error("mobilecoind_db.get_utxos_for_subaddress", err, &self.logger)
+                rpc_error("mobilecoind_db.get_utxos_for_subaddress", err, &self.logger)
             })?;
 
-        // Sum them up.
-        let balance = utxos.iter().map(|utxo| utxo.value as u128).sum::<u128>();
-
-        // It's possible the balance does not fit into a u64.
-        if balance > u64::max_value().into() {
-            return Err(RpcStatus::new(
-                RpcStatusCode::INTERNAL,
-                Some(format!(
-                    "balance of {} won't fit in u64, fetch utxo list instead",
-                    balance
-                )),
-            ));
+        // Fee outputs do not count towards the balance.
+        if self
+            .is_fee_recipient_subaddress(&monitor_id, request.subaddress_index)
+            .map_err(|err| rpc_internal_error("is_fee_recipient_subaddress", err, &self.logger))?
+        {
+            utxos.clear();
         }
 
+        let monitor_data = match self.mobilecoind_db.get_monitor_data(&monitor_id) {
+            Ok(data) => Some(data),
+            Err(Error::MonitorIdNotFound) => None,
+            Err(err) => {
+                return Err(rpc_error(
+                    "mobilecoind_db.get_monitor_data",
+                    err,
+                    &self.logger,
+                ))
+            }
+        };
+
+        // Outputs without the confirmations the monitor requires are not spendable
+        // yet, and are reported separately.
+        let (utxos, unconfirmed_utxos) = match monitor_data.as_ref() {
+            Some(data) => split_by_confirmations(&*self.ledger_db, data, utxos)
+                .map_err(|err| rpc_error("split_by_confirmations", err, &self.logger))?,
+            None => (utxos, Vec::new()),
+        };
+
+        // Sum them up. It's possible a balance does not fit into a u64.
+        let sum_values = |utxos: &[UnspentTxOut]| -> Result<u64, RpcStatus> {
+            let balance = utxos.iter().map(|utxo| utxo.value as u128).sum::<u128>();
+            if balance > u64::max_value().into() {
+                return Err(RpcStatus::new(
+                    RpcStatusCode::INTERNAL,
+                    Some(format!(
+                        "balance of {} won't fit in u64, fetch utxo list instead",
+                        balance
+                    )),
+                ));
+            }
+            Ok(balance as u64)
+        };
+        let balance = sum_values(&utxos)?;
+        let unconfirmed_balance = sum_values(&unconfirmed_utxos)?;
+
+        // The balance only accounts for the blocks the monitor has processed, so
+        // it is partial until the monitor catches up with the ledger.
+        let num_blocks = self
+            .ledger_db
+            .num_blocks()
+            .map_err(|err| rpc_internal_error("ledger_db.num_blocks", err, &self.logger))?;
+        let partial = monitor_data
+            .map(|data| data.next_block < num_blocks)
+            .unwrap_or(false);
+
         // Return response.
         let mut response = mc_mobilecoind_api::GetBalanceResponse::new();
-        response.set_balance(balance as u64);
+        response.set_balance(balance);
+        response.set_unconfirmed_balance(unconfirmed_balance);
+        response.set_partial(partial);
+        Ok(response)
+    }
+
+    fn get_account_state_impl(
+        &mut self,
+        request: mc_mobilecoind_api::GetAccountStateRequest,
+    ) -> Result<mc_mobilecoind_api::GetAccountStateResponse, RpcError> {
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        let state = self
+            .mobilecoind_db
+            .get_account_state(&monitor_id)
+            .map_err(|err| rpc_error("mobilecoind_db.get_account_state", err, &self.logger))?;
+
+        // Fee outputs do not count towards the balance.
+        let utxos: Vec<UnspentTxOut> = match self.fee_recipient.as_ref() {
+            Some(fee_recipient) => {
+                let account_key = &state.data.account_key;
+                let mut is_fee_subaddress: HashMap<u64, bool> = HashMap::default();
+                state
+                    .utxos
+                    .into_iter()
+                    .filter(|utxo| {
+                        !*is_fee_subaddress
+                            .entry(utxo.subaddress_index)
+                            .or_insert_with(|| {
+                                account_key.subaddress(utxo.subaddress_index) == *fee_recipient
+                            })
+                    })
+                    .collect()
+            }
+            None => state.utxos,
+        };
+
+        // Outputs without the confirmations the monitor requires are not spendable
+        // yet, and are reported separately, as GetBalance does.
+        let (confirmed_utxos, unconfirmed_utxos) =
+            split_by_confirmations(&*self.ledger_db, &state.data, utxos.clone())
+                .map_err(|err| rpc_error("split_by_confirmations", err, &self.logger))?;
+
+        let sum_values = |utxos: &[UnspentTxOut]| -> Result<u64, RpcStatus> {
+            let balance = utxos.iter().map(|utxo| utxo.value as u128).sum::<u128>();
+            if balance > u64::max_value().into() {
+                return Err(RpcStatus::new(
+                    RpcStatusCode::INTERNAL,
+                    Some(format!("balance of {} won't fit in u64", balance)),
+                ));
+            }
+            Ok(balance as u64)
+        };
+        let balance = sum_values(&confirmed_utxos)?;
+        let unconfirmed_balance = sum_values(&unconfirmed_utxos)?;
+
+        let mut response = mc_mobilecoind_api::GetAccountStateResponse::new();
+        response.set_balance(balance);
+        response.set_unconfirmed_balance(unconfirmed_balance);
+        response.set_output_list(RepeatedField::from_vec(
+            utxos.iter().map(|utxo| utxo.into()).collect(),
+        ));
+        response.set_next_block(state.data.next_block);
         Ok(response)
     }
 
     fn send_payment_impl(
         &mut self,
         request: mc_mobilecoind_api::SendPaymentRequest,
-    ) -> Result<mc_mobilecoind_api::SendPaymentResponse, RpcStatus> {
+    ) -> Result<mc_mobilecoind_api::SendPaymentResponse, RpcError> {
         // Get sender monitor id from request.
         let sender_monitor_id = MonitorId::try_from(&request.sender_monitor_id)
             .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
@@ -1657,7 +2586,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
             .mobilecoind_db
             .get_utxos_for_subaddress(&sender_monitor_id, request.sender_subaddress)
             .map_err(|err| {
-                rpc_internal_error("mobilecoind_db.get_utxos_for_subaddress", err, &self.logger)
+                rpc_error("mobilecoind_db.get_utxos_for_subaddress", err, &self.logger)
             })?;
 
         // Optionally filter for max value.
@@ -1691,13 +2620,15 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
                 &utxos,
                 &outlays,
                 request.fee,
+                request.get_fee_priority().into(),
                 request.tombstone,
+                &self.logger,
             )
             .map_err(|err| {
-                rpc_internal_error("transactions_manager.build_transaction", err, &self.logger)
+                rpc_error("transactions_manager.build_transaction", err, &self.logger)
             })?;
 
-        let proto_tx_proposal = mc_mobilecoind_api::TxProposal::from(&tx_proposal);
+        let proto_tx_proposal = self.tx_proposal_to_proto(&tx_proposal);
 
         // Submit transaction.
         let mut submit_tx_request = mc_mobilecoind_api::SubmitTxRequest::new();
@@ -1715,13 +2646,14 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     fn pay_address_code_impl(
         &mut self,
         request: mc_mobilecoind_api::PayAddressCodeRequest,
-    ) -> Result<mc_mobilecoind_api::SendPaymentResponse, RpcStatus> {
+    ) -> Result<mc_mobilecoind_api::SendPaymentResponse, RpcError> {
         // Sanity check.
         if request.get_amount() == 0 {
             return Err(RpcStatus::new(
                 RpcStatusCode::INVALID_ARGUMENT,
                 Some("amount".to_string()),
-            ));
+            )
+            .into());
         }
 
         // Try and decode the address code.
@@ -1752,6 +2684,7 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         &mut self,
         _request: mc_mobilecoind_api::Empty,
     ) -> Result<mc_mobilecoind_api::GetNetworkStatusResponse, RpcStatus> {
+        self.refresh_network_state_if_stale();
         let network_state = self.network_state.read().expect("lock poisoned");
         let num_blocks = self
             .ledger_db
@@ -1783,22 +2716,76 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
         Ok(response)
     }
 
+    fn get_node_sync_status_impl(
+        &mut self,
+        _request: mc_mobilecoind_api::Empty,
+    ) -> Result<mc_mobilecoind_api::GetNodeSyncStatusResponse, RpcStatus> {
+        self.refresh_network_state_if_stale();
+        let network_state = self.network_state.read().expect("lock poisoned");
+        let local_block_height = self
+            .ledger_db
+            .num_blocks()
+            .map_err(|err| rpc_internal_error("ledger_db.num_blocks", err, &self.logger))?;
+
+        let mut response = mc_mobilecoind_api::GetNodeSyncStatusResponse::new();
+        response.set_local_block_height(local_block_height);
+
+        // The network height is unknown until a poll of the network succeeds.
+        if let Some(network_highest_block_index) = network_state.highest_block_index_on_network() {
+            let network_block_height = network_highest_block_index + 1;
+            let blocks_behind = network_block_height.saturating_sub(local_block_height);
+            response.set_network_height_known(true);
+            response.set_network_block_height(network_block_height);
+            response.set_is_behind(blocks_behind > 0);
+            response.set_blocks_behind(blocks_behind);
+        }
+
+        Ok(response)
+    }
+
+    fn get_scan_throughput_impl(
+        &mut self,
+        _request: mc_mobilecoind_api::Empty,
+    ) -> Result<mc_mobilecoind_api::GetScanThroughputResponse, RpcError> {
+        let num_blocks = self
+            .ledger_db
+            .num_blocks()
+            .map_err(|err| rpc_internal_error("ledger_db.num_blocks", err, &self.logger))?;
+
+        let monitor_map = self
+            .mobilecoind_db
+            .get_monitor_map()
+            .map_err(|err| rpc_error("mobilecoind_db.get_monitor_map", err, &self.logger))?;
+        let blocks_remaining: u64 = monitor_map
+            .values()
+            .filter(|monitor_data| !monitor_data.paused)
+            .map(|monitor_data| num_blocks.saturating_sub(monitor_data.next_block))
+            .sum();
+
+        let mut response = mc_mobilecoind_api::GetScanThroughputResponse::new();
+        response.set_blocks_per_second(self.scan_throughput.blocks_per_second());
+        response.set_window_seconds(self.scan_throughput.window().as_secs());
+        response.set_blocks_remaining(blocks_remaining);
+        Ok(response)
+    }
+
     fn set_db_password_impl(
         &mut self,
         request: mc_mobilecoind_api::SetDbPasswordRequest,
-    ) -> Result<mc_mobilecoind_api::Empty, RpcStatus> {
+    ) -> Result<mc_mobilecoind_api::Empty, RpcError> {
         // Check if the database is unlocked and allowing this operation.
         if !self.mobilecoind_db.is_unlocked() {
             return Err(RpcStatus::new(
                 RpcStatusCode::INTERNAL,
                 Some("must unlock before changing current password".to_owned()),
-            ));
+            )
+            .into());
         }
 
         // Re-encrypt data using the new password.
         self.mobilecoind_db
             .re_encrypt(&request.get_password())
-            .map_err(|err| rpc_internal_error("mobilecoind_db.re_encrypt", err, &self.logger))?;
+            .map_err(|err| rpc_error("mobilecoind_db.re_encrypt", err, &self.logger))?;
 
         log::info!(self.logger, "DB encryption password updated successfully.");
 
@@ -1808,18 +2795,19 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     fn unlock_db_impl(
         &mut self,
         request: mc_mobilecoind_api::UnlockDbRequest,
-    ) -> Result<mc_mobilecoind_api::Empty, RpcStatus> {
+    ) -> Result<mc_mobilecoind_api::Empty, RpcError> {
         if self.mobilecoind_db.is_unlocked() {
             return Err(RpcStatus::new(
                 RpcStatusCode::INTERNAL,
                 Some("already unlocked".to_owned()),
-            ));
+            )
+            .into());
         }
 
         self.mobilecoind_db
             .check_and_store_password(&request.get_password())
             .map_err(|err| {
-                rpc_internal_error("mobilecoind_db.check_and_store_password", err, &self.logger)
+                rpc_error("mobilecoind_db.check_and_store_password", err, &self.logger)
             })?;
 
         log::info!(self.logger, "Successfully unlocked, starting sync thread.");
@@ -1829,9 +2817,282 @@ impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolve
     }
 }
 
-macro_rules! build_api {
-    ($( $service_function_name:ident $service_request_type:ident $service_response_type:ident $service_function_impl:ident ),+)
-    =>
+/// Poll a restored account's monitor until it has scanned the whole local
+/// ledger, sending its progress to `sender`, followed by the recovered
+/// balance. Stops early if the receiving end goes away.
+fn report_restore_progress(
+    ledger_db: Arc<dyn ReadOnlyLedger>,
+    mobilecoind_db: Database,
+    monitor_id: MonitorId,
+    sender: UnboundedSender<Result<mc_mobilecoind_api::RestoreAccountResponse, RpcStatus>>,
+    timeout: Duration,
+    logger: Logger,
+) {
+    let deadline = Instant::now() + timeout;
+    let mut last_next_block = None;
+    loop {
+        // The client may have gone away while the monitor made no progress.
+        if sender.is_closed() {
+            log::debug!(logger, "Restore of monitor {} cancelled", monitor_id);
+            return;
+        }
+
+        if Instant::now() >= deadline {
+            log::warn!(
+                logger,
+                "Gave up reporting the restore progress of monitor {}",
+                monitor_id
+            );
+            let _ = sender.unbounded_send(Err(RpcStatus::new(
+                RpcStatusCode::DEADLINE_EXCEEDED,
+                Some(format!(
+                    "monitor did not finish syncing within {:?}",
+                    timeout
+                )),
+            )));
+            return;
+        }
+
+        let progress = ledger_db
+            .num_blocks()
+            .map_err(Error::from)
+            .and_then(|num_blocks| Ok((mobilecoind_db.get_monitor_data(&monitor_id)?, num_blocks)));
+        let (data, num_blocks) = match progress {
+            Ok(progress) => progress,
+            Err(err) => {
+                // The response headers went out with the first message, so the error
+                // code cannot be sent.
+                let _ =
+                    sender.unbounded_send(Err(
+                        rpc_error("restore_account.progress", err, &logger).status
+                    ));
+                return;
+            }
+        };
+
+        let mut response = mc_mobilecoind_api::RestoreAccountResponse::new();
+        response.set_monitor_id(monitor_id.to_vec());
+        response.set_next_block(data.next_block);
+        response.set_num_blocks(num_blocks);
+
+        if data.next_block >= num_blocks {
+            let balance = data
+                .subaddress_indexes()
+                .map(|index| mobilecoind_db.get_utxos_for_subaddress(&monitor_id, index))
+                .collect::<Result<Vec<_>, Error>>()
+                .map(|utxos| {
+                    utxos
+                        .iter()
+                        .flatten()
+                        .map(|utxo| utxo.value as u128)
+                        .sum::<u128>()
+                });
+            let result = match balance {
+                Ok(balance) if balance <= u64::max_value().into() => {
+                    response.set_is_complete(true);
+                    response.set_balance(balance as u64);
+                    Ok(response)
+                }
+                Ok(balance) => Err(RpcStatus::new(
+                    RpcStatusCode::INTERNAL,
+                    Some(format!(
+                        "balance of {} won't fit in u64, fetch utxo list instead",
+                        balance
+                    )),
+                )),
+                Err(err) => {
+                    Err(rpc_error("mobilecoind_db.get_utxos_for_subaddress", err, &logger).status)
+                }
+            };
+            let _ = sender.unbounded_send(result);
+            return;
+        }
+
+        if last_next_block != Some(data.next_block) {
+            if sender.unbounded_send(Ok(response)).is_err() {
+                log::debug!(logger, "Restore of monitor {} cancelled", monitor_id);
+                return;
+            }
+            last_next_block = Some(data.next_block);
+        }
+
+        thread::sleep(RESTORE_ACCOUNT_POLL_INTERVAL);
+    }
+}
+
+/// A call failed by an error of mobilecoind, and the `ErrorCode` sent to the
+/// client in the `ERROR_CODE_METADATA_KEY` metadata entry, so that clients can
+/// tell the errors they handle apart without matching on the status details.
+#[derive(Debug)]
+struct RpcError {
+    status: RpcStatus,
+    code: ErrorCode,
+}
+
+impl From<RpcStatus> for RpcError {
+    fn from(status: RpcStatus) -> Self {
+        Self {
+            status,
+            code: ErrorCode::Unspecified,
+        }
+    }
+}
+
+fn rpc_error<S: Display>(context: S, err: Error, logger: &Logger) -> RpcError {
+    let (status_code, code) = err.rpc_codes();
+    let message = format!("{}: {}", context, err);
+    log::error!(logger, "{}", message);
+    RpcError {
+        status: RpcStatus::new(status_code, Some(message)),
+        code,
+    }
+}
+
+/// The response metadata carrying the `ErrorCode` of a failed call.
+fn error_code_metadata(code: ErrorCode) -> Metadata {
+    let mut builder = MetadataBuilder::new();
+    builder
+        .add_str(ERROR_CODE_METADATA_KEY, &code.value().to_string())
+        .expect("error code metadata is valid");
+    builder.build()
+}
+
+/// Gives the service the logger of a call while the call is handled. The
+/// service logger is restored when the guard is dropped, even if handling the
+/// call panics.
+struct CallLogger<'a, T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + 'static,
+{
+    api: &'a mut ServiceApi<T, FPR>,
+    service_logger: Logger,
+}
+
+impl<'a, T, FPR> CallLogger<'a, T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + 'static,
+{
+    fn new(api: &'a mut ServiceApi<T, FPR>, logger: Logger) -> Self {
+        let service_logger = std::mem::replace(&mut api.logger, logger);
+        Self {
+            api,
+            service_logger,
+        }
+    }
+}
+
+impl<'a, T, FPR> Deref for CallLogger<'a, T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + 'static,
+{
+    type Target = ServiceApi<T, FPR>;
+
+    fn deref(&self) -> &Self::Target {
+        self.api
+    }
+}
+
+impl<'a, T, FPR> DerefMut for CallLogger<'a, T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + 'static,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.api
+    }
+}
+
+impl<'a, T, FPR> Drop for CallLogger<'a, T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + 'static,
+{
+    fn drop(&mut self) {
+        std::mem::swap(&mut self.api.logger, &mut self.service_logger);
+    }
+}
+
+/// A logger for a single call. Every line it emits carries the
+/// `rpc_request_id` generated for the call, and the `client_request_id` the
+/// client supplied in the `REQUEST_ID_METADATA_KEY` metadata entry, if any.
+fn request_logger(ctx: &RpcContext, logger: &Logger) -> Logger {
+    let logger = rpc_logger(ctx, logger);
+    let client_request_id = ctx.request_headers().iter().find_map(|(key, value)| {
+        if key.to_lowercase() != REQUEST_ID_METADATA_KEY || value.len() > MAX_REQUEST_ID_LEN {
+            return None;
+        }
+        std::str::from_utf8(value).ok().map(str::to_owned)
+    });
+    match client_request_id {
+        Some(client_request_id) => logger.new(o!("client_request_id" => client_request_id)),
+        None => logger,
+    }
+}
+
+/// Reply to a unary call. A failed call carries its error code in the response
+/// metadata.
+fn send_unary_result<T, E: Into<RpcError>>(
+    ctx: RpcContext,
+    mut sink: UnarySink<T>,
+    resp: Result<T, E>,
+    logger: &Logger,
+) {
+    let resp = resp.map_err(|err| {
+        let err = err.into();
+        sink.set_headers(error_code_metadata(err.code));
+        err.status
+    });
+    send_result(ctx, sink, resp, logger)
+}
+
+/// Forward the responses of a server streaming call to its sink. The call fails
+/// if it could not be started, or once an error is received. Only a call that
+/// could not be started carries its error code in the response metadata.
+fn send_stream<T: Send + 'static, E: Into<RpcError>>(
+    ctx: RpcContext,
+    mut sink: ServerStreamingSink<T>,
+    resp: Result<UnboundedReceiver<Result<T, RpcStatus>>, E>,
+    logger: &Logger,
+) {
+    let logger = logger.clone();
+
+    let mut receiver = match resp {
+        Ok(receiver) => receiver,
+        Err(err) => {
+            let err = err.into();
+            sink.set_headers(error_code_metadata(err.code));
+            ctx.spawn(
+                sink.fail(err.status)
+                    .map_err(move |err| log::error!(logger, "failed to reply: {}", err))
+                    .map(|_| ()),
+            );
+            return;
+        }
+    };
+
+    let forward = async move {
+        while let Some(item) = receiver.next().await {
+            match item {
+                Ok(response) => sink.send((response, WriteFlags::default())).await?,
+                Err(err) => return sink.fail(err).await,
+            }
+        }
+        sink.close().await
+    };
+    ctx.spawn(
+        forward
+            .map_err(move |err| log::error!(logger, "failed to reply: {}", err))
+            .map(|_| ()),
+    );
+}
+
+macro_rules! build_api {
+    ($( $service_function_name:ident $service_request_type:ident $service_response_type:ident $service_function_impl:ident ),+;
+     $( $streaming_function_name:ident $streaming_request_type:ident $streaming_response_type:ident $streaming_function_impl:ident ),*)
+    =>
     (
         impl<T: BlockchainConnection + UserTxConnection + 'static, FPR: FogPubkeyResolver> MobilecoindApi for ServiceApi<T, FPR> {
             $(
@@ -1841,15 +3102,25 @@ macro_rules! build_api {
                     request: mc_mobilecoind_api::$service_request_type,
                     sink: UnarySink<mc_mobilecoind_api::$service_response_type>,
                 ) {
-                    let logger = rpc_logger(&ctx, &self.logger);
-                    send_result(
-                        ctx,
-                        sink,
-                        self.$service_function_impl(request),
-                        &logger,
-                    )
+                    // Lines logged while handling the call carry its request id.
+                    let logger = request_logger(&ctx, &self.logger);
+                    let result = CallLogger::new(self, logger.clone()).$service_function_impl(request);
+                    send_unary_result(ctx, sink, result, &logger)
                 }
             )+
+            $(
+                fn $streaming_function_name(
+                    &mut self,
+                    ctx: RpcContext,
+                    request: mc_mobilecoind_api::$streaming_request_type,
+                    sink: ServerStreamingSink<mc_mobilecoind_api::$streaming_response_type>,
+                ) {
+                    // Lines logged while handling the call carry its request id.
+                    let logger = request_logger(&ctx, &self.logger);
+                    let result = CallLogger::new(self, logger.clone()).$streaming_function_impl(request);
+                    send_stream(ctx, sink, result, &logger)
+                }
+            )*
         }
     );
 }
@@ -1861,6 +3132,10 @@ build_api! {
     get_monitor_list Empty GetMonitorListResponse get_monitor_list_impl,
     get_monitor_status GetMonitorStatusRequest GetMonitorStatusResponse get_monitor_status_impl,
     get_unspent_tx_out_list GetUnspentTxOutListRequest GetUnspentTxOutListResponse get_unspent_tx_out_list_impl,
+    get_next_unused_subaddress GetNextUnusedSubaddressRequest GetNextUnusedSubaddressResponse get_next_unused_subaddress_impl,
+    cancel_payment_request CancelPaymentRequestRequest Empty cancel_payment_request_impl,
+    export_account_key ExportAccountKeyRequest ExportAccountKeyResponse export_account_key_impl,
+    export_view_key ExportViewKeyRequest ExportViewKeyResponse export_view_key_impl,
 
     // Utilities
     generate_root_entropy Empty GenerateRootEntropyResponse generate_root_entropy_impl,
@@ -1876,12 +3151,18 @@ build_api! {
     create_transfer_code CreateTransferCodeRequest CreateTransferCodeResponse create_transfer_code_impl,
     parse_address_code ParseAddressCodeRequest ParseAddressCodeResponse parse_address_code_impl,
     create_address_code CreateAddressCodeRequest CreateAddressCodeResponse create_address_code_impl,
+    encode_address EncodeAddressRequest EncodeAddressResponse encode_address_impl,
+    decode_address DecodeAddressRequest DecodeAddressResponse decode_address_impl,
+    encode_payment_request EncodePaymentRequestRequest EncodePaymentRequestResponse encode_payment_request_impl,
+    decode_payment_request DecodePaymentRequestRequest DecodePaymentRequestResponse decode_payment_request_impl,
 
     // Transactions
     get_mixins GetMixinsRequest GetMixinsResponse get_mixins_impl,
     get_membership_proofs GetMembershipProofsRequest GetMembershipProofsResponse get_membership_proofs_impl,
     generate_tx GenerateTxRequest GenerateTxResponse generate_tx_impl,
     generate_optimization_tx GenerateOptimizationTxRequest GenerateOptimizationTxResponse generate_optimization_tx_impl,
+    get_consolidation_preview GetConsolidationPreviewRequest GetConsolidationPreviewResponse get_consolidation_preview_impl,
+    generate_consolidation_tx GenerateConsolidationTxRequest GenerateConsolidationTxResponse generate_consolidation_tx_impl,
     generate_transfer_code_tx GenerateTransferCodeTxRequest GenerateTransferCodeTxResponse generate_transfer_code_tx_impl,
     generate_tx_from_tx_out_list GenerateTxFromTxOutListRequest GenerateTxFromTxOutListResponse generate_tx_from_tx_out_list_impl,
     submit_tx SubmitTxRequest SubmitTxResponse submit_tx_impl,
@@ -1894,35 +3175,49 @@ build_api! {
     get_tx_status_as_receiver GetTxStatusAsReceiverRequest GetTxStatusAsReceiverResponse get_tx_status_as_receiver_impl,
     get_processed_block GetProcessedBlockRequest GetProcessedBlockResponse get_processed_block_impl,
     get_block_index_by_tx_pub_key GetBlockIndexByTxPubKeyRequest GetBlockIndexByTxPubKeyResponse get_block_index_by_tx_pub_key_impl,
+    get_tx_out_index GetTxOutIndexRequest GetTxOutIndexResponse get_tx_out_index_impl,
+    get_tombstone_blocks_remaining GetTombstoneBlocksRemainingRequest GetTombstoneBlocksRemainingResponse get_tombstone_blocks_remaining_impl,
+    get_blocks_until_spendable GetBlocksUntilSpendableRequest GetBlocksUntilSpendableResponse get_blocks_until_spendable_impl,
+    check_key_images CheckKeyImagesRequest CheckKeyImagesResponse check_key_images_impl,
 
     // Convenience calls
     get_balance GetBalanceRequest GetBalanceResponse get_balance_impl,
+    get_account_state GetAccountStateRequest GetAccountStateResponse get_account_state_impl,
     send_payment SendPaymentRequest SendPaymentResponse send_payment_impl,
     pay_address_code PayAddressCodeRequest SendPaymentResponse pay_address_code_impl,
 
     // Network status
     get_network_status Empty GetNetworkStatusResponse get_network_status_impl,
+    get_node_sync_status Empty GetNodeSyncStatusResponse get_node_sync_status_impl,
+    get_scan_throughput Empty GetScanThroughputResponse get_scan_throughput_impl,
 
     // Database encryption
     set_db_password SetDbPasswordRequest Empty set_db_password_impl,
-    unlock_db UnlockDbRequest Empty unlock_db_impl
+    unlock_db UnlockDbRequest Empty unlock_db_impl;
+
+    // Server streaming calls
+    restore_account RestoreAccountRequest RestoreAccountResponse restore_account_impl
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::{
-        payments::DEFAULT_NEW_TX_BLOCK_ATTEMPTS,
+        payments::{DEFAULT_NEW_TX_BLOCK_ATTEMPTS, MAX_MEMBERSHIP_PROOF_AGE},
         subaddress_store::SubaddressSPKId,
         test_utils::{
-            self, add_block_to_ledger_db, add_txos_to_ledger_db, get_testing_environment,
-            wait_for_monitors, DEFAULT_PER_RECIPIENT_AMOUNT,
+            self, add_block_to_ledger_db, add_txos_to_ledger_db, get_test_monitor_data_and_id,
+            get_testing_environment, wait_for_monitors, DEFAULT_PER_RECIPIENT_AMOUNT,
         },
         utxo_store::UnspentTxOut,
     };
-    use grpcio::{Error as GrpcError, RpcStatus};
+    use grpcio::{CallOption, Error as GrpcError, MetadataBuilder};
     use mc_account_keys::{AccountKey, PublicAddress, DEFAULT_SUBADDRESS_INDEX};
-    use mc_common::{logger::test_with_logger, HashSet};
+    use mc_common::{
+        logger::{slog, test_with_logger},
+        HashSet,
+    };
+    use mc_connection_test_utils::{test_client_uri, MockBlockchainConnection};
     use mc_crypto_keys::RistrettoPrivate;
     use mc_crypto_rand::RngCore;
     use mc_fog_report_validation::{FullyValidatedFogPubkey, MockFogPubkeyResolver};
@@ -1937,7 +3232,7 @@ mod test {
     };
     use mc_transaction_std::TransactionBuilder;
     use mc_util_repr_bytes::{typenum::U32, GenericArray, ReprBytes};
-    use mc_util_uri::FogUri;
+    use mc_util_uri::{ConnectionUri, FogUri};
     use rand::{rngs::StdRng, SeedableRng};
     use std::{
         convert::{TryFrom, TryInto},
@@ -2215,17 +3510,17 @@ mod test {
         let num_blocks = ledger_db.num_blocks().unwrap();
         let account_tx_outs: Vec<TxOut> = (0..num_blocks)
             .map(|idx| {
-                let block_contents = ledger_db.get_block_contents(idx as u64).unwrap();
-                // We grab the 4th tx out in each block since the test ledger had 3 random
-                // recipients, followed by our known recipient.
+                // The test ledger has a single tx out for our known recipient in each block.
                 // See the call to `get_testing_environment` at the beginning of the test.
-                block_contents.outputs[3].clone()
+                test_utils::get_block_tx_outs_for_account(&ledger_db, idx as u64, &account_key)[0]
+                    .clone()
             })
             .collect();
 
         let expected_utxos: Vec<UnspentTxOut> = account_tx_outs
             .iter()
-            .map(|tx_out| {
+            .enumerate()
+            .map(|(block_index, tx_out)| {
                 // Calculate the key image for this tx out.
                 let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
                 let onetime_private_key = recover_onetime_private_key(
@@ -2243,6 +3538,7 @@ mod test {
                     value: test_utils::DEFAULT_PER_RECIPIENT_AMOUNT,
                     attempted_spend_height: 0,
                     attempted_spend_tombstone: 0,
+                    received_block_index: block_index as u64,
                 }
             })
             .collect();
@@ -2255,6 +3551,156 @@ mod test {
         );
     }
 
+    #[test_with_logger]
+    fn test_get_unspent_tx_out_list_by_value_range(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 3 random recipients and no monitors.
+        let (mut ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+
+        // Outputs of several values for the monitored account.
+        for value in &[10, 20, 30, 40] {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &[account_key.default_subaddress()],
+                *value,
+                &[],
+                &mut rng,
+            );
+        }
+
+        let id = mobilecoind_db.add_monitor(&data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let get_values = |min_value: u64, max_value: u64| -> Vec<u64> {
+            let mut request = mc_mobilecoind_api::GetUnspentTxOutListRequest::new();
+            request.set_monitor_id(id.to_vec());
+            request.set_subaddress_index(0);
+            request.set_min_value(min_value);
+            request.set_max_value(max_value);
+
+            let response = client
+                .get_unspent_tx_out_list(&request)
+                .expect("failed to get unspent tx out list");
+            let mut values: Vec<u64> = response
+                .output_list
+                .iter()
+                .map(|proto_utxo| proto_utxo.value)
+                .collect();
+            values.sort_unstable();
+            values
+        };
+
+        // No filter.
+        assert_eq!(get_values(0, 0), vec![10, 20, 30, 40]);
+
+        // Both bounds are inclusive.
+        assert_eq!(get_values(20, 30), vec![20, 30]);
+        assert_eq!(get_values(15, 35), vec![20, 30]);
+        assert_eq!(get_values(30, 30), vec![30]);
+
+        // A single bound.
+        assert_eq!(get_values(25, 0), vec![30, 40]);
+        assert_eq!(get_values(0, 20), vec![10, 20]);
+
+        // Nothing in range.
+        assert_eq!(get_values(21, 29), Vec::<u64>::new());
+        assert_eq!(get_values(50, 0), Vec::<u64>::new());
+        assert_eq!(get_values(30, 20), Vec::<u64>::new());
+    }
+
+    #[test_with_logger]
+    fn test_get_unspent_tx_out_list_reports_received_block(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 3 random recipients and no monitors.
+        let (mut ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+
+        // Fund the account in a known block, followed by another block.
+        let funding_block_index = ledger_db.num_blocks().unwrap();
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &[account_key.default_subaddress()],
+            DEFAULT_PER_RECIPIENT_AMOUNT,
+            &[],
+            &mut rng,
+        );
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &[AccountKey::random(&mut rng).default_subaddress()],
+            DEFAULT_PER_RECIPIENT_AMOUNT,
+            &[],
+            &mut rng,
+        );
+
+        let id = mobilecoind_db.add_monitor(&data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let mut request = mc_mobilecoind_api::GetUnspentTxOutListRequest::new();
+        request.set_monitor_id(id.to_vec());
+        request.set_subaddress_index(0);
+        let response = client.get_unspent_tx_out_list(&request).unwrap();
+
+        assert_eq!(response.output_list.len(), 1);
+        assert_eq!(
+            response.output_list[0].received_block_index,
+            funding_block_index
+        );
+
+        // UTXOs stored before the block index was recorded get it from the ledger.
+        let num_blocks = ledger_db.num_blocks().unwrap();
+        let tx_out = ledger_db
+            .get_block_contents(funding_block_index + 1)
+            .unwrap()
+            .outputs[0]
+            .clone();
+        let legacy_utxo = UnspentTxOut {
+            tx_out: tx_out.clone(),
+            subaddress_index: 0,
+            key_image: KeyImage::from(num_blocks),
+            value: DEFAULT_PER_RECIPIENT_AMOUNT,
+            attempted_spend_height: 0,
+            attempted_spend_tombstone: 0,
+            received_block_index: 0,
+        };
+        mobilecoind_db
+            .block_processed(&id, num_blocks, &[legacy_utxo], &[])
+            .unwrap();
+
+        let response = client.get_unspent_tx_out_list(&request).unwrap();
+        assert_eq!(response.output_list.len(), 2);
+        let legacy_output = response
+            .output_list
+            .iter()
+            .find(|utxo| {
+                utxo.get_tx_out().get_public_key().get_data() == &tx_out.public_key.as_bytes()[..]
+            })
+            .unwrap();
+        assert_eq!(legacy_output.received_block_index, funding_block_index + 1);
+    }
+
     #[test_with_logger]
     fn test_generate_root_entropy_impl(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
@@ -2295,6 +3741,128 @@ mod test {
         assert_eq!(mnemonic.entropy(), response.get_bip39_entropy());
     }
 
+    #[test_with_logger]
+    fn test_restore_account(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let mnemonic_str =
+            "legal winner thank year wave sausage worth useful legal winner thank yellow";
+        let account_key = {
+            let mnemonic =
+                Mnemonic::from_phrase(mnemonic_str, Language::English).expect("from_phrase failed");
+            AccountKey::from(mnemonic.derive_slip10_key(0))
+        };
+
+        // The account received an output in every block.
+        let (_ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        let mut request = mc_mobilecoind_api::RestoreAccountRequest::new();
+        request.set_mnemonic(mnemonic_str.to_string());
+        request.set_account_index(0);
+        request.set_num_subaddresses(10);
+        request.set_scan_from_block(0);
+
+        let responses: Vec<mc_mobilecoind_api::RestoreAccountResponse> =
+            futures::executor::block_on(client.restore_account(&request).unwrap().try_collect())
+                .unwrap();
+
+        // Progress is reported until the monitor caught up, followed by the balance.
+        let (last, progress) = responses.split_last().unwrap();
+        assert!(progress.iter().all(|response| !response.is_complete));
+        assert!(progress
+            .windows(2)
+            .all(|pair| pair[0].next_block < pair[1].next_block));
+
+        let expected_monitor_id = MonitorId::from(
+            &MonitorData::new(account_key, DEFAULT_SUBADDRESS_INDEX, 10, 0, "").unwrap(),
+        );
+        assert_eq!(last.monitor_id, expected_monitor_id.to_vec());
+        assert!(last.is_complete);
+        assert_eq!(
+            last.next_block,
+            test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS as u64
+        );
+        assert_eq!(
+            last.balance,
+            test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS as u64 * DEFAULT_PER_RECIPIENT_AMOUNT
+        );
+
+        // An invalid mnemonic is rejected before anything is scanned.
+        request.set_mnemonic("legal winner thank year".to_string());
+        let result: Result<Vec<mc_mobilecoind_api::RestoreAccountResponse>, GrpcError> =
+            futures::executor::block_on(client.restore_account(&request).unwrap().try_collect());
+        match result {
+            Ok(_) => panic!("Should've returned an error"),
+            Err(GrpcError::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::INVALID_ARGUMENT)
+            }
+            Err(err) => panic!("Unexpected error {:?}", err),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_report_restore_progress_stops(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // No sync thread runs, so the monitor never advances.
+        let (ledger_db, mobilecoind_db) =
+            test_utils::get_test_databases(3, &[], 5, logger.clone(), &mut rng);
+        let data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            DEFAULT_SUBADDRESS_INDEX,
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        let start_reporting = |timeout: Duration| {
+            let (sender, receiver) = mpsc::unbounded();
+            let ledger_db: Arc<dyn ReadOnlyLedger> = Arc::new(ledger_db.clone());
+            let mobilecoind_db = mobilecoind_db.clone();
+            let logger = logger.clone();
+            let handle = thread::spawn(move || {
+                report_restore_progress(
+                    ledger_db,
+                    mobilecoind_db,
+                    monitor_id,
+                    sender,
+                    timeout,
+                    logger,
+                )
+            });
+            (receiver, handle)
+        };
+
+        // Reporting stops once the client goes away, even without progress.
+        let (mut receiver, handle) = start_reporting(Duration::from_secs(600));
+        let first = futures::executor::block_on(receiver.next())
+            .unwrap()
+            .unwrap();
+        assert!(!first.is_complete);
+        drop(receiver);
+        handle.join().unwrap();
+
+        // Reporting gives up at the deadline.
+        let (mut receiver, handle) = start_reporting(Duration::from_millis(300));
+        handle.join().unwrap();
+        let responses: Vec<_> = futures::executor::block_on(receiver.by_ref().collect());
+        let (last, progress) = responses.split_last().unwrap();
+        assert_eq!(progress.len(), 1);
+        match last {
+            Err(status) => assert_eq!(status.status, RpcStatusCode::DEADLINE_EXCEEDED),
+            Ok(response) => panic!("Unexpected response {:?}", response),
+        }
+    }
+
     #[test_with_logger]
     fn test_get_account_key_from_mnemonic_impl(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
@@ -2421,14 +3989,419 @@ mod test {
         request.set_monitor_id(id.to_vec());
         request.set_subaddress_index(1000);
         assert!(client.get_public_address(&request).is_err());
+
+        // The last monitored subaddress is in range, the ones just outside are
+        // rejected as invalid arguments.
+        let mut request = mc_mobilecoind_api::GetPublicAddressRequest::new();
+        request.set_monitor_id(id.to_vec());
+        request.set_subaddress_index(29);
+        let response = client.get_public_address(&request).unwrap();
+        assert_eq!(
+            PublicAddress::try_from(response.get_public_address()).unwrap(),
+            account_key.subaddress(29)
+        );
+
+        for subaddress_index in &[9, 30] {
+            request.set_subaddress_index(*subaddress_index);
+            match client.get_public_address(&request) {
+                Err(GrpcError::RpcFailure(status)) => {
+                    assert_eq!(status.status, RpcStatusCode::INVALID_ARGUMENT)
+                }
+                Err(err) => panic!("Unexpected error {:?}", err),
+                Ok(_) => panic!("Subaddress index should be rejected"),
+            }
+        }
     }
 
     #[test_with_logger]
-    fn test_get_ledger_info_impl(logger: Logger) {
+    fn test_get_next_unused_subaddress_impl(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
-
-        // no known recipient, 3 random recipients and no monitors.
-        let (ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+        let account_key = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            3,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 1 known recipient (the default subaddress), 3 random recipients and one
+        // monitor.
+        let (_ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![data.clone()],
+                logger.clone(),
+                &mut rng,
+            );
+        let id = MonitorId::from(&data);
+
+        // The default subaddress has received funds, so it is skipped. Once the
+        // initial range is exhausted, it gets extended.
+        let mut indexes = Vec::new();
+        for _ in 0..4 {
+            let mut request = mc_mobilecoind_api::GetNextUnusedSubaddressRequest::new();
+            request.set_monitor_id(id.to_vec());
+            let response = client.get_next_unused_subaddress(&request).unwrap();
+
+            assert_eq!(
+                PublicAddress::try_from(response.get_public_address()).unwrap(),
+                account_key.subaddress(response.subaddress_index)
+            );
+            indexes.push(response.subaddress_index);
+        }
+        assert_eq!(indexes, vec![1, 2, 3, 4]);
+
+        let mut request = mc_mobilecoind_api::GetMonitorStatusRequest::new();
+        request.set_monitor_id(id.to_vec());
+        let response = client.get_monitor_status(&request).unwrap();
+        assert_eq!(response.get_status().num_subaddresses, 5);
+
+        // An invalid monitor id should error.
+        let mut request = mc_mobilecoind_api::GetNextUnusedSubaddressRequest::new();
+        request.set_monitor_id(vec![3; 3]);
+        assert!(client.get_next_unused_subaddress(&request).is_err());
+    }
+
+    #[test_with_logger]
+    fn test_rpc_error(logger: Logger) {
+        // The status details are the message alone, and the error code is sent
+        // in the response metadata.
+        let err = rpc_error(
+            "transactions_manager.build_transaction",
+            Error::InsufficientFunds,
+            &logger,
+        );
+        assert_eq!(err.status.status, RpcStatusCode::INTERNAL);
+        assert_eq!(
+            err.status.details,
+            Some("transactions_manager.build_transaction: Insufficient funds".to_owned())
+        );
+        assert_eq!(err.code, mc_mobilecoind_api::ErrorCode::InsufficientFunds);
+
+        let metadata = error_code_metadata(err.code);
+        let entries: Vec<(&str, &[u8])> = metadata.iter().collect();
+        assert_eq!(
+            entries,
+            vec![(
+                ERROR_CODE_METADATA_KEY,
+                mc_mobilecoind_api::ErrorCode::InsufficientFunds
+                    .value()
+                    .to_string()
+                    .as_bytes()
+            )]
+        );
+
+        // Failures that are not errors of mobilecoind carry no error code.
+        let err = RpcError::from(RpcStatus::new(RpcStatusCode::INVALID_ARGUMENT, None));
+        assert_eq!(err.code, mc_mobilecoind_api::ErrorCode::Unspecified);
+
+        let err = rpc_error(
+            "mobilecoind_db.cancel_payment_request",
+            Error::PaymentRequestPartiallyPaid(1),
+            &logger,
+        );
+        assert_eq!(err.status.status, RpcStatusCode::FAILED_PRECONDITION);
+        assert_eq!(
+            err.code,
+            mc_mobilecoind_api::ErrorCode::PaymentRequestPartiallyPaid
+        );
+    }
+
+    #[test_with_logger]
+    fn test_cancel_payment_request_impl(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            3,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 1 known recipient (the default subaddress), 3 random recipients and one
+        // monitor.
+        let (_ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![data.clone()],
+                logger.clone(),
+                &mut rng,
+            );
+        let id = MonitorId::from(&data);
+
+        let mut request = mc_mobilecoind_api::GetNextUnusedSubaddressRequest::new();
+        request.set_monitor_id(id.to_vec());
+        let response = client.get_next_unused_subaddress(&request).unwrap();
+        assert_eq!(response.subaddress_index, 1);
+
+        // The request for subaddress 1 went unpaid, so canceling it hands the
+        // subaddress out again.
+        let mut cancel_request = mc_mobilecoind_api::CancelPaymentRequestRequest::new();
+        cancel_request.set_monitor_id(id.to_vec());
+        cancel_request.set_subaddress_index(1);
+        client.cancel_payment_request(&cancel_request).unwrap();
+
+        let response = client.get_next_unused_subaddress(&request).unwrap();
+        assert_eq!(response.subaddress_index, 1);
+
+        // The default subaddress has received funds, so it cannot be released.
+        cancel_request.set_subaddress_index(0);
+        match client.cancel_payment_request(&cancel_request) {
+            Ok(_) => panic!("Should've returned an error"),
+            Err(GrpcError::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::FAILED_PRECONDITION);
+            }
+            Err(err) => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_export_account_key_impl(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        let (ledger_db, mobilecoind_db) = test_utils::get_test_databases(
+            3,
+            &vec![],
+            test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS,
+            logger.clone(),
+            &mut rng,
+        );
+        let id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        let port = test_utils::get_free_port();
+        let uri = MobilecoindUri::from_str(&format!("insecure-mobilecoind://127.0.0.1:{}/", port))
+            .unwrap();
+        let (_server, _server_conn_manager) = test_utils::setup_server::<MockFogResolver>(
+            logger.clone(),
+            ledger_db,
+            mobilecoind_db,
+            None,
+            None,
+            ServiceConfig {
+                allow_account_key_export: true,
+                ..Default::default()
+            },
+            &uri,
+        );
+        let client = test_utils::setup_client(&uri, None, &logger);
+
+        // The exported account key should match the monitor's.
+        let mut request = mc_mobilecoind_api::ExportAccountKeyRequest::new();
+        request.set_monitor_id(id.to_vec());
+        let response = client.export_account_key(&request).unwrap();
+        assert_eq!(
+            AccountKey::try_from(response.get_account_key()).unwrap(),
+            account_key
+        );
+
+        // An unknown monitor id should error.
+        let mut request = mc_mobilecoind_api::ExportAccountKeyRequest::new();
+        request.set_monitor_id(vec![3; 32]);
+        assert!(client.export_account_key(&request).is_err());
+    }
+
+    #[test_with_logger]
+    fn test_export_account_key_impl_disabled(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            account_key,
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // Account key export is disabled by default.
+        let (_ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![data.clone()], logger.clone(), &mut rng);
+
+        let mut request = mc_mobilecoind_api::ExportAccountKeyRequest::new();
+        request.set_monitor_id(MonitorId::from(&data).to_vec());
+        match client.export_account_key(&request) {
+            Err(GrpcError::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::PERMISSION_DENIED)
+            }
+            Err(err) => panic!("Unexpected error {:?}", err),
+            Ok(_) => panic!("Account key export should be disabled"),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_export_view_key_impl(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            10, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        let (_ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![data.clone()], logger.clone(), &mut rng);
+
+        let mut request = mc_mobilecoind_api::ExportViewKeyRequest::new();
+        request.set_monitor_id(MonitorId::from(&data).to_vec());
+        request.set_first_subaddress(2);
+        request.set_num_subaddresses(4);
+        let response = client.export_view_key(&request).unwrap();
+
+        let view_private_key = RistrettoPrivate::try_from(response.get_view_private_key()).unwrap();
+        assert_eq!(
+            view_private_key.to_bytes(),
+            account_key.view_private_key().to_bytes()
+        );
+        assert_ne!(
+            view_private_key.to_bytes(),
+            account_key.spend_private_key().to_bytes()
+        );
+        let subaddress_spend_public_keys: Vec<CompressedRistrettoPublic> = response
+            .get_subaddress_spend_public_keys()
+            .iter()
+            .map(|key| CompressedRistrettoPublic::try_from(key).unwrap())
+            .collect();
+        assert_eq!(subaddress_spend_public_keys.len(), 4);
+
+        // Returns the position of the exported subaddress an output was sent to, as a
+        // third party holding only the exported keys would compute it.
+        let match_output = |tx_out: &TxOut| -> Option<usize> {
+            let spend_public_key = recover_public_subaddress_spend_key(
+                &view_private_key,
+                &RistrettoPublic::try_from(&tx_out.target_key).unwrap(),
+                &RistrettoPublic::try_from(&tx_out.public_key).unwrap(),
+            );
+            subaddress_spend_public_keys
+                .iter()
+                .position(|key| *key == CompressedRistrettoPublic::from(&spend_public_key))
+        };
+
+        // Outputs to exported subaddresses match, others do not.
+        for (subaddress_index, expected) in &[(2, Some(0)), (5, Some(3)), (1, None), (6, None)] {
+            let tx_private_key = RistrettoPrivate::from_random(&mut rng);
+            let tx_out = TxOut::new(
+                10,
+                &account_key.subaddress(*subaddress_index),
+                &tx_private_key,
+                Default::default(),
+            )
+            .unwrap();
+            assert_eq!(match_output(&tx_out), *expected);
+
+            // Spending requires the subaddress spend private key, which was not exported.
+            // Using the exported view key in its place yields the wrong key image.
+            let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
+            let key_image = KeyImage::from(&recover_onetime_private_key(
+                &tx_public_key,
+                account_key.view_private_key(),
+                &account_key.subaddress_spend_private(*subaddress_index),
+            ));
+            let forged_key_image = KeyImage::from(&recover_onetime_private_key(
+                &tx_public_key,
+                &view_private_key,
+                &view_private_key,
+            ));
+            assert_ne!(key_image, forged_key_image);
+        }
+
+        // Ranges outside the monitor's subaddresses are rejected.
+        for (first_subaddress, num_subaddresses) in &[(8, 3), (0, 0), (u64::MAX, 2)] {
+            request.set_first_subaddress(*first_subaddress);
+            request.set_num_subaddresses(*num_subaddresses);
+            match client.export_view_key(&request) {
+                Err(GrpcError::RpcFailure(status)) => {
+                    assert_eq!(status.status, RpcStatusCode::INVALID_ARGUMENT)
+                }
+                Err(err) => panic!("Unexpected error {:?}", err),
+                Ok(_) => panic!("Range should be rejected"),
+            }
+        }
+    }
+
+    #[test_with_logger]
+    fn test_max_message_size(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // Each block holds one output per recipient, so blocks are large enough to
+        // test limits with.
+        let (ledger_db, mobilecoind_db) =
+            test_utils::get_test_databases(100, &vec![], 2, logger.clone(), &mut rng);
+
+        let start_server = |max_message_size: Option<usize>| {
+            let port = test_utils::get_free_port();
+            let uri =
+                MobilecoindUri::from_str(&format!("insecure-mobilecoind://127.0.0.1:{}/", port))
+                    .unwrap();
+            let (server, _server_conn_manager) = test_utils::setup_server::<MockFogResolver>(
+                logger.clone(),
+                ledger_db.clone(),
+                mobilecoind_db.clone(),
+                None,
+                None,
+                ServiceConfig {
+                    max_message_size,
+                    ..Default::default()
+                },
+                &uri,
+            );
+            let client = test_utils::setup_client(&uri, max_message_size, &logger);
+            (server, client)
+        };
+
+        let mut request = mc_mobilecoind_api::GetBlockRequest::new();
+        request.set_block(1);
+
+        // The encoded size of the response, using the default limits.
+        let response_size = {
+            let (_server, client) = start_server(None);
+            let response = client.get_block(&request).unwrap();
+            protobuf::Message::compute_size(&response) as usize
+        };
+        assert!(response_size > 2048);
+
+        // A response just under the limit goes through.
+        {
+            let (_server, client) = start_server(Some(response_size + 1024));
+            assert!(client.get_block(&request).is_ok());
+        }
+
+        // A response over the limit fails with RESOURCE_EXHAUSTED.
+        {
+            let (_server, client) = start_server(Some(response_size - 1024));
+            match client.get_block(&request) {
+                Err(GrpcError::RpcFailure(status)) => {
+                    assert_eq!(status.status, RpcStatusCode::RESOURCE_EXHAUSTED)
+                }
+                Err(err) => panic!("Unexpected error {:?}", err),
+                Ok(_) => panic!("Response should exceed the max message size"),
+            }
+        }
+    }
+
+    #[test_with_logger]
+    fn test_get_ledger_info_impl(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // no known recipient, 3 random recipients and no monitors.
+        let (ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
             get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
 
         // Call get ledger info.
@@ -2902,17 +4875,17 @@ mod test {
         let num_blocks = ledger_db.num_blocks().expect("failed getting num blocks");
         let account_tx_outs: Vec<TxOut> = (0..num_blocks)
             .map(|idx| {
-                let block_contents = ledger_db.get_block_contents(idx as u64).unwrap();
-                // We grab the 4th tx out in each block since the test ledger had 3 random
-                // recipients, followed by our known recipient.
+                // The test ledger has a single tx out for our known recipient in each block.
                 // See the call to `get_testing_environment` at the beginning of the test.
-                block_contents.outputs[3].clone()
+                test_utils::get_block_tx_outs_for_account(&ledger_db, idx as u64, &account_key)[0]
+                    .clone()
             })
             .collect();
 
         let expected_utxos: Vec<UnspentTxOut> = account_tx_outs
             .iter()
-            .map(|tx_out| {
+            .enumerate()
+            .map(|(block_index, tx_out)| {
                 // Calculate the key image for this tx out.
                 let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
                 let onetime_private_key = recover_onetime_private_key(
@@ -2930,6 +4903,7 @@ mod test {
                     value: test_utils::DEFAULT_PER_RECIPIENT_AMOUNT,
                     attempted_spend_height: 0,
                     attempted_spend_tombstone: 0,
+                    received_block_index: block_index as u64,
                 }
             })
             .collect();
@@ -3536,61 +5510,187 @@ mod test {
     }
 
     #[test_with_logger]
-    fn test_generate_transfer_code_tx(logger: Logger) {
+    fn test_get_tx_out_index(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
 
-        let sender = AccountKey::random(&mut rng);
+        let account_key = AccountKey::random(&mut rng);
         let data = MonitorData::new(
-            sender.clone(),
+            account_key.clone(),
             0,  // first_subaddress
-            20, // num_subaddresses
+            1,  // num_subaddresses
             0,  // first_block
             "", // name
         )
         .unwrap();
 
         // 1 known recipient, 3 random recipients and no monitors.
-        let (mut ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
             get_testing_environment(
                 3,
-                &vec![sender.default_subaddress()],
+                &vec![account_key.default_subaddress()],
                 &vec![],
                 logger.clone(),
                 &mut rng,
             );
 
-        // Insert into database.
         let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
-
-        // Allow the new monitor to process the ledger.
         wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
 
-        // Get list of unspent tx outs
+        // The index of every received output points at it in the ledger.
         let utxos = mobilecoind_db
             .get_utxos_for_subaddress(&monitor_id, 0)
             .unwrap();
-        assert!(!utxos.is_empty());
+        assert_eq!(utxos.len(), test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS);
+        for utxo in utxos {
+            let mut request = mc_mobilecoind_api::GetTxOutIndexRequest::new();
+            request.set_tx_out_public_key(mc_mobilecoind_api::external::CompressedRistretto::from(
+                &utxo.tx_out.public_key,
+            ));
 
-        // Call generate transfer code ctx.
-        let mut request = mc_mobilecoind_api::GenerateTransferCodeTxRequest::new();
-        request.set_sender_monitor_id(monitor_id.to_vec());
-        request.set_change_subaddress(0);
-        request.set_input_list(RepeatedField::from_vec(
-            utxos
+            let response = client.get_tx_out_index(&request).unwrap();
+            assert_eq!(
+                ledger_db.get_tx_out_by_index(response.index).unwrap(),
+                utxo.tx_out
+            );
+        }
+
+        // An output that is not in the ledger is not found.
+        let tx_out = TxOut::new(
+            10,
+            &account_key.default_subaddress(),
+            &RistrettoPrivate::from_random(&mut rng),
+            Default::default(),
+        )
+        .unwrap();
+        let mut request = mc_mobilecoind_api::GetTxOutIndexRequest::new();
+        request.set_tx_out_public_key(mc_mobilecoind_api::external::CompressedRistretto::from(
+            &tx_out.public_key,
+        ));
+        match client.get_tx_out_index(&request) {
+            Ok(_) => panic!("Got response for an output that is not in the ledger"),
+            Err(GrpcError::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::NOT_FOUND)
+            }
+            Err(err) => panic!("Unexpected error {:?}", err),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_check_key_images(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // no known recipient, 3 random recipients and no monitors.
+        let (mut ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+
+        // Spend some outputs in a new block.
+        let spent_key_images = vec![KeyImage::from(1), KeyImage::from(2)];
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &[AccountKey::random(&mut rng).default_subaddress()],
+            DEFAULT_PER_RECIPIENT_AMOUNT,
+            &spent_key_images,
+            &mut rng,
+        );
+        let spent_block_index = ledger_db.num_blocks().unwrap() - 1;
+
+        // A key image spent in an earlier block.
+        let earlier_key_image = ledger_db.get_block_contents(1).unwrap().key_images[0];
+
+        let key_images = vec![
+            KeyImage::from(1),
+            KeyImage::from(3),
+            earlier_key_image,
+            KeyImage::from(2),
+        ];
+        let mut request = mc_mobilecoind_api::CheckKeyImagesRequest::new();
+        request.set_key_images(RepeatedField::from_vec(
+            key_images
                 .iter()
-                .map(mc_mobilecoind_api::UnspentTxOut::from)
+                .map(mc_mobilecoind_api::external::KeyImage::from)
                 .collect(),
         ));
-        request.set_value(1337);
 
-        let response = client.generate_transfer_code_tx(&request).unwrap();
+        let response = client.check_key_images(&request).unwrap();
+        let statuses: Vec<(KeyImage, bool, u64)> = response
+            .get_statuses()
+            .iter()
+            .map(|status| {
+                (
+                    KeyImage::try_from(status.get_key_image()).unwrap(),
+                    status.spent,
+                    status.spent_block_index,
+                )
+            })
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (KeyImage::from(1), true, spent_block_index),
+                (KeyImage::from(3), false, 0),
+                (earlier_key_image, true, 1),
+                (KeyImage::from(2), true, spent_block_index),
+            ]
+        );
+    }
+
+    #[test_with_logger]
+    fn test_generate_transfer_code_tx(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (mut ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![sender.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Insert into database.
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // Get list of unspent tx outs
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        assert!(!utxos.is_empty());
+
+        // Call generate transfer code ctx.
+        let mut request = mc_mobilecoind_api::GenerateTransferCodeTxRequest::new();
+        request.set_sender_monitor_id(monitor_id.to_vec());
+        request.set_change_subaddress(0);
+        request.set_input_list(RepeatedField::from_vec(
+            utxos
+                .iter()
+                .map(mc_mobilecoind_api::UnspentTxOut::from)
+                .collect(),
+        ));
+        request.set_value(1337);
+
+        let response = client.generate_transfer_code_tx(&request).unwrap();
 
         // Test that the generated transaction can be picked up by mobilecoind.
         {
             let tx_proposal = TxProposal::try_from(response.get_tx_proposal()).unwrap();
             let key_images = tx_proposal.tx.key_images();
             let outputs = tx_proposal.tx.prefix.outputs.clone();
-            let block_contents = BlockContents::new(key_images, outputs);
+            let mut block_contents = BlockContents::new(key_images, outputs);
+            block_contents.sort_outputs();
 
             // Append to ledger.
             let num_blocks = ledger_db.num_blocks().unwrap();
@@ -3601,8 +5701,7 @@ mod test {
                 &Default::default(),
                 &block_contents,
             );
-            ledger_db
-                .append_block(&new_block, &block_contents, None)
+            mc_ledger_db::Ledger::append_block(&mut ledger_db, &new_block, &block_contents, None)
                 .unwrap();
 
             // Use bip39 entropy to construct AccountKey.
@@ -3744,6 +5843,276 @@ mod test {
         );
     }
 
+    #[test_with_logger]
+    fn test_get_consolidation_preview(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let sender_default_subaddress = sender.default_subaddress();
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 1 known recipient, and a bunch of random recipients and no monitors.
+        // The random recipients are needed for mixins.
+        let num_random_recipients = MAX_INPUTS as u32 * RING_SIZE as u32
+            / test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS as u32;
+        let (mut ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                num_random_recipients as u32,
+                &vec![sender_default_subaddress.clone()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Add a bunch of blocks/utxos for our recipient.
+        for _ in 0..MAX_INPUTS {
+            let _ = add_block_to_ledger_db(
+                &mut ledger_db,
+                &[sender_default_subaddress.clone()],
+                DEFAULT_PER_RECIPIENT_AMOUNT,
+                &[KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+
+        // Insert into database.
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // A max_inputs of 0 uses the configured input limit.
+        let mut request = mc_mobilecoind_api::GetConsolidationPreviewRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+
+        let response = client.get_consolidation_preview(&request).unwrap();
+        assert_eq!(response.num_inputs, MAX_INPUTS as u64);
+        assert_eq!(response.fee, MINIMUM_FEE);
+        assert_eq!(
+            response.output_value,
+            DEFAULT_PER_RECIPIENT_AMOUNT * MAX_INPUTS as u64 - MINIMUM_FEE
+        );
+
+        // An explicit max_inputs limits the UTXOs spent.
+        request.set_max_inputs(4);
+
+        let response = client.get_consolidation_preview(&request).unwrap();
+        assert_eq!(response.num_inputs, 4);
+        assert_eq!(response.fee, MINIMUM_FEE);
+        assert_eq!(
+            response.output_value,
+            DEFAULT_PER_RECIPIENT_AMOUNT * 4 - MINIMUM_FEE
+        );
+
+        // Unknown monitors are rejected.
+        let (_data, unknown_monitor_id) = get_test_monitor_data_and_id(&mut rng);
+        request.set_monitor_id(unknown_monitor_id.to_vec());
+        assert!(client.get_consolidation_preview(&request).is_err());
+    }
+
+    #[test_with_logger]
+    fn test_generate_consolidation_tx(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let sender_default_subaddress = sender.default_subaddress();
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 1 known recipient, and a bunch of random recipients and no monitors.
+        // The random recipients are needed for mixins.
+        let num_random_recipients = MAX_INPUTS as u32 * RING_SIZE as u32
+            / test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS as u32;
+        let (mut ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                num_random_recipients as u32,
+                &vec![sender_default_subaddress.clone()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Add a bunch of blocks/utxos for our recipient.
+        for _ in 0..MAX_INPUTS {
+            let _ = add_block_to_ledger_db(
+                &mut ledger_db,
+                &[sender_default_subaddress.clone()],
+                DEFAULT_PER_RECIPIENT_AMOUNT,
+                &[KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+
+        // Insert into database.
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // Preview, then generate, a consolidation spending 4 UTXOs into subaddress 1.
+        let mut preview_request = mc_mobilecoind_api::GetConsolidationPreviewRequest::new();
+        preview_request.set_monitor_id(monitor_id.to_vec());
+        preview_request.set_max_inputs(4);
+        let preview = client.get_consolidation_preview(&preview_request).unwrap();
+
+        let mut request = mc_mobilecoind_api::GenerateConsolidationTxRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        request.set_subaddress(1);
+        request.set_max_inputs(4);
+
+        let response = client.generate_consolidation_tx(&request).unwrap();
+
+        // The transaction matches the preview.
+        let tx_proposal = TxProposal::try_from(response.get_tx_proposal()).unwrap();
+        assert_eq!(tx_proposal.utxos.len() as u64, preview.num_inputs);
+        assert_eq!(tx_proposal.tx.prefix.inputs.len(), 4);
+        assert_eq!(tx_proposal.fee(), preview.fee);
+
+        assert_eq!(tx_proposal.outlays.len(), 1);
+        assert_eq!(
+            tx_proposal.outlays[0].receiver,
+            data.account_key.subaddress(1)
+        );
+        assert_eq!(tx_proposal.outlays[0].value, preview.output_value);
+
+        assert_eq!(tx_proposal.tx.prefix.outputs.len(), 1);
+        let tx_out = &tx_proposal.tx.prefix.outputs[0];
+        let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
+        let shared_secret =
+            get_tx_out_shared_secret(data.account_key.view_private_key(), &tx_public_key);
+        let (value, _blinding) = tx_out.amount.get_value(&shared_secret).unwrap();
+        assert_eq!(value, preview.output_value);
+
+        // Unknown monitors are rejected.
+        let (_data, unknown_monitor_id) = get_test_monitor_data_and_id(&mut rng);
+        request.set_monitor_id(unknown_monitor_id.to_vec());
+        assert!(client.generate_consolidation_tx(&request).is_err());
+    }
+
+    #[test_with_logger]
+    fn test_generate_tx_for_external_submission(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![sender.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Insert into database.
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        assert!(!utxos.is_empty());
+
+        let outlay = Outlay {
+            value: 123,
+            receiver: AccountKey::random(&mut rng).default_subaddress(),
+        };
+
+        let mut request = mc_mobilecoind_api::GenerateTxRequest::new();
+        request.set_sender_monitor_id(monitor_id.to_vec());
+        request.set_change_subaddress(0);
+        request.set_input_list(RepeatedField::from_vec(
+            utxos
+                .iter()
+                .map(mc_mobilecoind_api::UnspentTxOut::from)
+                .collect(),
+        ));
+        request.set_outlay_list(RepeatedField::from_vec(vec![
+            mc_mobilecoind_api::Outlay::from(&outlay),
+        ]));
+
+        // Without the flag, no serialized Tx is returned and nothing is reserved.
+        let response = client.generate_tx(&request).unwrap();
+        assert!(response.get_serialized_tx().is_empty());
+        assert!(mobilecoind_db.get_pending_txs().unwrap().is_empty());
+
+        request.set_for_external_submission(true);
+        let response = client.generate_tx(&request).unwrap();
+
+        // The serialized Tx matches the proposal and is valid against the ledger.
+        let tx: Tx = mc_util_serial::decode(response.get_serialized_tx()).unwrap();
+        assert_eq!(
+            tx,
+            Tx::try_from(response.get_tx_proposal().get_tx()).unwrap()
+        );
+
+        let ring_indexes: Vec<u64> = tx
+            .prefix
+            .inputs
+            .iter()
+            .flat_map(|tx_in| tx_in.ring.iter())
+            .map(|tx_out| ledger_db.get_tx_out_index_by_hash(&tx_out.hash()).unwrap())
+            .collect();
+        let root_proofs = ledger_db
+            .get_tx_out_proof_of_memberships(&ring_indexes)
+            .unwrap();
+        mc_transaction_core::validation::validate(
+            &tx,
+            ledger_db.num_blocks().unwrap(),
+            &root_proofs,
+            MINIMUM_FEE,
+            &mut rng,
+        )
+        .unwrap();
+
+        // Nothing was submitted to the network.
+        for conn in server_conn_manager.conns() {
+            assert!(conn.read().proposed_txs.is_empty());
+        }
+
+        // The inputs are reserved.
+        let pending_txs = mobilecoind_db.get_pending_txs().unwrap();
+        assert_eq!(pending_txs.len(), 1);
+        assert_eq!(pending_txs[0].tx_hash, tx.tx_hash().to_vec());
+
+        let spent_key_images: HashSet<KeyImage> = tx.key_images().into_iter().collect();
+        for utxo in mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap()
+        {
+            if spent_key_images.contains(&utxo.key_image) {
+                assert!(utxo.attempted_spend_height > 0);
+                assert_eq!(utxo.attempted_spend_tombstone, tx.prefix.tombstone_block);
+            } else {
+                assert_eq!(utxo.attempted_spend_height, 0);
+            }
+        }
+    }
+
     #[test_with_logger]
     fn test_generate_tx_from_tx_out_list(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
@@ -4068,12 +6437,12 @@ mod test {
     }
 
     #[test_with_logger]
-    fn test_send_payment(logger: Logger) {
+    fn test_get_balance_partial(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
 
-        let sender = AccountKey::random(&mut rng);
+        let account_key = AccountKey::random(&mut rng);
         let data = MonitorData::new(
-            sender.clone(),
+            account_key.clone(),
             0,  // first_subaddress
             20, // num_subaddresses
             0,  // first_block
@@ -4082,18 +6451,610 @@ mod test {
         .unwrap();
 
         // 1 known recipient, 3 random recipients and no monitors.
-        let (ledger_db, mobilecoind_db, client, _server, server_conn_manager) =
+        let (mut ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
             get_testing_environment(
                 3,
-                &vec![sender.default_subaddress()],
+                &vec![account_key.default_subaddress()],
                 &vec![],
                 logger.clone(),
                 &mut rng,
             );
 
-        // Insert into database.
-        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
-
+        let id = mobilecoind_db.add_monitor(&data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let mut request = mc_mobilecoind_api::GetBalanceRequest::new();
+        request.set_monitor_id(id.to_vec());
+        request.set_subaddress_index(0);
+
+        // The monitor is synced, so the balance is complete.
+        let response = client.get_balance(&request).unwrap();
+        assert_eq!(
+            response.balance,
+            DEFAULT_PER_RECIPIENT_AMOUNT * test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS as u64
+        );
+        assert!(!response.partial);
+
+        // Hold the monitor back while a block paying it is appended, so that it is
+        // caught mid-scan.
+        mobilecoind_db.set_monitor_paused(&id, true).unwrap();
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &[account_key.default_subaddress()],
+            DEFAULT_PER_RECIPIENT_AMOUNT,
+            &[KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        let response = client.get_balance(&request).unwrap();
+        assert_eq!(
+            response.balance,
+            DEFAULT_PER_RECIPIENT_AMOUNT * test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS as u64
+        );
+        assert!(response.partial);
+
+        // Once the monitor catches up, the balance is complete again.
+        mobilecoind_db.set_monitor_paused(&id, false).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let response = client.get_balance(&request).unwrap();
+        assert_eq!(
+            response.balance,
+            DEFAULT_PER_RECIPIENT_AMOUNT
+                * (test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS as u64 + 1)
+        );
+        assert!(!response.partial);
+    }
+
+    #[test_with_logger]
+    fn test_get_account_state(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (mut ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        let id = mobilecoind_db.add_monitor(&data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let mut request = mc_mobilecoind_api::GetAccountStateRequest::new();
+        request.set_monitor_id(id.to_vec());
+
+        // The outputs always add up to the balance. No confirmations are required,
+        // so none of them is unconfirmed.
+        let assert_consistent = |response: &mc_mobilecoind_api::GetAccountStateResponse| {
+            let total: u64 = response
+                .get_output_list()
+                .iter()
+                .map(|utxo| utxo.value)
+                .sum();
+            assert_eq!(response.balance, total);
+            assert_eq!(response.unconfirmed_balance, 0);
+        };
+
+        let response = client.get_account_state(&request).unwrap();
+        assert_consistent(&response);
+        assert_eq!(
+            response.balance,
+            DEFAULT_PER_RECIPIENT_AMOUNT * test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS as u64
+        );
+        assert_eq!(response.next_block, ledger_db.num_blocks().unwrap());
+
+        // A block paying two subaddresses is appended while the monitor is held
+        // back, so the ledger is ahead of the state.
+        mobilecoind_db.set_monitor_paused(&id, true).unwrap();
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &[account_key.default_subaddress(), account_key.subaddress(1)],
+            DEFAULT_PER_RECIPIENT_AMOUNT,
+            &[KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        let response = client.get_account_state(&request).unwrap();
+        assert_consistent(&response);
+        assert_eq!(
+            response.balance,
+            DEFAULT_PER_RECIPIENT_AMOUNT * test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS as u64
+        );
+        assert_eq!(response.next_block, ledger_db.num_blocks().unwrap() - 1);
+
+        // Once the monitor catches up, the state covers the new block.
+        mobilecoind_db.set_monitor_paused(&id, false).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let response = client.get_account_state(&request).unwrap();
+        assert_consistent(&response);
+        assert_eq!(
+            response.balance,
+            DEFAULT_PER_RECIPIENT_AMOUNT
+                * (test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS as u64 + 2)
+        );
+        assert_eq!(response.next_block, ledger_db.num_blocks().unwrap());
+        assert!(response
+            .get_output_list()
+            .iter()
+            .any(|utxo| utxo.subaddress_index == 1));
+    }
+
+    #[test_with_logger]
+    fn test_get_account_state_with_confirmations_required(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Outputs need to have been in the ledger for 3 blocks to be spendable.
+        let mut request = mc_mobilecoind_api::AddMonitorRequest::new();
+        request.set_account_key(mc_api::external::AccountKey::from(&account_key));
+        request.set_num_subaddresses(1);
+        request.set_confirmations_required(3);
+        let monitor_id = client.add_monitor(&request).unwrap().monitor_id;
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let mut request = mc_mobilecoind_api::GetAccountStateRequest::new();
+        request.set_monitor_id(monitor_id.clone());
+        let state = client.get_account_state(&request).unwrap();
+
+        let mut request = mc_mobilecoind_api::GetBalanceRequest::new();
+        request.set_monitor_id(monitor_id);
+        request.set_subaddress_index(0);
+        let balance = client.get_balance(&request).unwrap();
+
+        // The outputs of the last two blocks have too few confirmations. They are
+        // listed, but not counted as spendable, as with GetBalance.
+        let num_blocks = test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS as u64;
+        assert_eq!(state.get_output_list().len() as u64, num_blocks);
+        assert_eq!(
+            state.balance,
+            DEFAULT_PER_RECIPIENT_AMOUNT * (num_blocks - 2)
+        );
+        assert_eq!(state.unconfirmed_balance, DEFAULT_PER_RECIPIENT_AMOUNT * 2);
+        assert_eq!(state.balance, balance.balance);
+        assert_eq!(state.unconfirmed_balance, balance.unconfirmed_balance);
+    }
+
+    #[test_with_logger]
+    fn test_submit_tx_for_other_network_is_rejected(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        let (ledger_db, mobilecoind_db) = test_utils::get_test_databases(
+            3,
+            &vec![sender.default_subaddress()],
+            test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS,
+            logger.clone(),
+            &mut rng,
+        );
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        let port = test_utils::get_free_port();
+        let uri = MobilecoindUri::from_str(&format!("insecure-mobilecoind://127.0.0.1:{}/", port))
+            .unwrap();
+        let (_server, server_conn_manager) = test_utils::setup_server::<MockFogResolver>(
+            logger.clone(),
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            None,
+            None,
+            ServiceConfig {
+                network_name: Some("main".to_owned()),
+                ..Default::default()
+            },
+            &uri,
+        );
+        let client = test_utils::setup_client(&uri, None, &logger);
+
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        let outlay = Outlay {
+            value: 123,
+            receiver: AccountKey::random(&mut rng).default_subaddress(),
+        };
+
+        let mut request = mc_mobilecoind_api::GenerateTxRequest::new();
+        request.set_sender_monitor_id(monitor_id.to_vec());
+        request.set_change_subaddress(0);
+        request.set_input_list(RepeatedField::from_vec(
+            utxos
+                .iter()
+                .map(mc_mobilecoind_api::UnspentTxOut::from)
+                .collect(),
+        ));
+        request.set_outlay_list(RepeatedField::from_vec(vec![
+            mc_mobilecoind_api::Outlay::from(&outlay),
+        ]));
+
+        // Generated transactions are labeled with the node's network name.
+        let response = client.generate_tx(&request).unwrap();
+        let tx_proposal = response.get_tx_proposal().clone();
+        assert_eq!(tx_proposal.get_network_name(), "main");
+
+        // Transactions for other networks, or without a network name, are rejected.
+        for network_name in &["test", ""] {
+            let mut other_tx_proposal = tx_proposal.clone();
+            other_tx_proposal.set_network_name(network_name.to_string());
+
+            let mut request = mc_mobilecoind_api::SubmitTxRequest::new();
+            request.set_tx_proposal(other_tx_proposal);
+            match client.submit_tx(&request) {
+                Err(GrpcError::RpcFailure(status)) => {
+                    assert_eq!(status.status, RpcStatusCode::INVALID_ARGUMENT)
+                }
+                Err(err) => panic!("Unexpected error {:?}", err),
+                Ok(_) => panic!("Tx for network {:?} should be rejected", network_name),
+            }
+        }
+        for conn in server_conn_manager.conns() {
+            assert!(conn.read().proposed_txs.is_empty());
+        }
+
+        // The transaction is accepted on its own network.
+        let mut request = mc_mobilecoind_api::SubmitTxRequest::new();
+        request.set_tx_proposal(tx_proposal);
+        client.submit_tx(&request).unwrap();
+    }
+
+    #[test_with_logger]
+    fn test_submit_tx_built_against_outdated_ledger_is_rejected(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (mut ledger_db, mobilecoind_db, client, _server, server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![sender.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // Build two transactions spending different inputs against the current
+        // ledger.
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        let tx_proposals: Vec<_> = utxos[0..2]
+            .iter()
+            .map(|utxo| {
+                let outlay = Outlay {
+                    value: 123,
+                    receiver: AccountKey::random(&mut rng).default_subaddress(),
+                };
+
+                let mut request = mc_mobilecoind_api::GenerateTxRequest::new();
+                request.set_sender_monitor_id(monitor_id.to_vec());
+                request.set_change_subaddress(0);
+                request.set_input_list(RepeatedField::from_vec(vec![
+                    mc_mobilecoind_api::UnspentTxOut::from(utxo),
+                ]));
+                request.set_outlay_list(RepeatedField::from_vec(vec![
+                    mc_mobilecoind_api::Outlay::from(&outlay),
+                ]));
+                client
+                    .generate_tx(&request)
+                    .unwrap()
+                    .get_tx_proposal()
+                    .clone()
+            })
+            .collect();
+
+        let advance_ledger = |ledger_db: &mut LedgerDB, num_blocks: u64, rng: &mut StdRng| {
+            let recipient = AccountKey::random(rng).default_subaddress();
+            for _ in 0..num_blocks {
+                add_block_to_ledger_db(
+                    ledger_db,
+                    &[recipient.clone()],
+                    DEFAULT_PER_RECIPIENT_AMOUNT,
+                    &[KeyImage::from(rng.next_u64())],
+                    rng,
+                );
+            }
+        };
+
+        // Transactions are still accepted while the ledger has not advanced too
+        // far.
+        advance_ledger(&mut ledger_db, MAX_MEMBERSHIP_PROOF_AGE, &mut rng);
+        let mut request = mc_mobilecoind_api::SubmitTxRequest::new();
+        request.set_tx_proposal(tx_proposals[0].clone());
+        client.submit_tx(&request).unwrap();
+
+        // Past that, they need to be rebuilt.
+        advance_ledger(&mut ledger_db, 1, &mut rng);
+        let mut request = mc_mobilecoind_api::SubmitTxRequest::new();
+        request.set_tx_proposal(tx_proposals[1].clone());
+        match client.submit_tx(&request) {
+            Err(GrpcError::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::FAILED_PRECONDITION);
+                assert!(status.details.unwrap().contains("rebuild transaction"));
+            }
+            Err(err) => panic!("Unexpected error {:?}", err),
+            Ok(_) => panic!("Tx built against an outdated ledger should be rejected"),
+        }
+
+        let num_proposed_txs: usize = server_conn_manager
+            .conns()
+            .iter()
+            .map(|conn| conn.read().proposed_txs.len())
+            .sum();
+        assert_eq!(num_proposed_txs, 1);
+    }
+
+    #[test_with_logger]
+    fn test_get_balance_with_confirmations_required(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (mut ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Outputs need to have been in the ledger for 3 blocks to be spendable.
+        let mut request = mc_mobilecoind_api::AddMonitorRequest::new();
+        request.set_account_key(mc_api::external::AccountKey::from(&account_key));
+        request.set_num_subaddresses(1);
+        request.set_confirmations_required(3);
+        let monitor_id = client.add_monitor(&request).unwrap().monitor_id;
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let mut request = mc_mobilecoind_api::GetMonitorStatusRequest::new();
+        request.set_monitor_id(monitor_id.clone());
+        let response = client.get_monitor_status(&request).unwrap();
+        assert_eq!(response.get_status().confirmations_required, 3);
+
+        let mut request = mc_mobilecoind_api::GetBalanceRequest::new();
+        request.set_monitor_id(monitor_id);
+        request.set_subaddress_index(0);
+
+        // The account received an output in every block. Those of the last two
+        // blocks have too few confirmations.
+        let num_blocks = test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS as u64;
+        let response = client.get_balance(&request).unwrap();
+        assert_eq!(
+            response.balance,
+            DEFAULT_PER_RECIPIENT_AMOUNT * (num_blocks - 2)
+        );
+        assert_eq!(
+            response.unconfirmed_balance,
+            DEFAULT_PER_RECIPIENT_AMOUNT * 2
+        );
+
+        // Every block appended confirms one more of them.
+        let other_recipient = AccountKey::random(&mut rng).default_subaddress();
+        for num_unconfirmed in (0..2).rev() {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &[other_recipient.clone()],
+                DEFAULT_PER_RECIPIENT_AMOUNT,
+                &[],
+                &mut rng,
+            );
+            wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+            let response = client.get_balance(&request).unwrap();
+            assert_eq!(
+                response.balance,
+                DEFAULT_PER_RECIPIENT_AMOUNT * (num_blocks - num_unconfirmed)
+            );
+            assert_eq!(
+                response.unconfirmed_balance,
+                DEFAULT_PER_RECIPIENT_AMOUNT * num_unconfirmed
+            );
+        }
+    }
+
+    #[test_with_logger]
+    fn test_get_balance_with_fee_recipient(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // The monitored account controls the fee recipient address, its default
+        // subaddress, and also receives regular payments at subaddress 1.
+        let account_key = AccountKey::random(&mut rng);
+        let fee_recipient = account_key.default_subaddress();
+        let data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            2,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        let (ledger_db, mobilecoind_db) = test_utils::get_test_databases(
+            3,
+            &vec![fee_recipient.clone(), account_key.subaddress(1)],
+            test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS,
+            logger.clone(),
+            &mut rng,
+        );
+        let id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        let port = test_utils::get_free_port();
+        let uri = MobilecoindUri::from_str(&format!("insecure-mobilecoind://127.0.0.1:{}/", port))
+            .unwrap();
+        let (_server, _server_conn_manager) = test_utils::setup_server::<MockFogResolver>(
+            logger.clone(),
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            None,
+            None,
+            ServiceConfig {
+                fee_recipient: Some(fee_recipient),
+                ..Default::default()
+            },
+            &uri,
+        );
+        let client = test_utils::setup_client(&uri, None, &logger);
+
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // The fee outputs are matched and stored like any other output.
+        let num_blocks = ledger_db.num_blocks().unwrap();
+        assert_eq!(
+            mobilecoind_db
+                .get_utxos_for_subaddress(&id, 0)
+                .unwrap()
+                .len() as u64,
+            num_blocks
+        );
+
+        // They are not part of the balance of the fee recipient subaddress.
+        let mut request = mc_mobilecoind_api::GetBalanceRequest::new();
+        request.set_monitor_id(id.to_vec());
+        request.set_subaddress_index(0);
+
+        let response = client.get_balance(&request).unwrap();
+        assert_eq!(response.balance, 0);
+
+        // Outputs received at other subaddresses still count.
+        let mut request = mc_mobilecoind_api::GetBalanceRequest::new();
+        request.set_monitor_id(id.to_vec());
+        request.set_subaddress_index(1);
+
+        let response = client.get_balance(&request).unwrap();
+        assert_eq!(
+            response.balance,
+            test_utils::DEFAULT_PER_RECIPIENT_AMOUNT * num_blocks
+        );
+    }
+
+    #[test_with_logger]
+    fn test_async_client_concurrent_get_balance(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and one monitor.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![data.clone()],
+                logger.clone(),
+                &mut rng,
+            );
+        let id = MonitorId::from(&data);
+        let async_client = test_utils::AsyncMobilecoindClient::new(&client);
+
+        let mut request_0 = mc_mobilecoind_api::GetBalanceRequest::new();
+        request_0.set_monitor_id(id.to_vec());
+        request_0.set_subaddress_index(0);
+
+        let mut request_1 = mc_mobilecoind_api::GetBalanceRequest::new();
+        request_1.set_monitor_id(id.to_vec());
+        request_1.set_subaddress_index(1);
+
+        // Fire both queries before awaiting either of them.
+        let (response_0, response_1) = futures::executor::block_on(futures::future::join(
+            async_client.get_balance(&request_0),
+            async_client.get_balance(&request_1),
+        ));
+
+        assert_eq!(
+            response_0.unwrap().balance,
+            test_utils::DEFAULT_PER_RECIPIENT_AMOUNT * ledger_db.num_blocks().unwrap()
+        );
+        assert_eq!(response_1.unwrap().balance, 0);
+
+        // The synchronous client keeps working over the shared channel.
+        let response = client.get_balance(&request_0).unwrap();
+        assert_eq!(
+            response.balance,
+            test_utils::DEFAULT_PER_RECIPIENT_AMOUNT * ledger_db.num_blocks().unwrap()
+        );
+    }
+
+    #[test_with_logger]
+    fn test_send_payment(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![sender.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Insert into database.
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
         // Allow the new monitor to process the ledger.
         wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
 
@@ -4305,7 +7266,8 @@ mod test {
         ];
 
         // Call send payment without a limit on UTXOs - a single large UTXO should be
-        // selected.
+        // selected. The transaction expires with the next block, releasing its
+        // inputs for the following attempts.
         let mut request = mc_mobilecoind_api::SendPaymentRequest::new();
         request.set_sender_monitor_id(monitor_id.to_vec());
         request.set_sender_subaddress(0);
@@ -4315,6 +7277,7 @@ mod test {
                 .map(mc_mobilecoind_api::Outlay::from)
                 .collect(),
         ));
+        request.set_tombstone(ledger_db.num_blocks().unwrap() + 1);
 
         let response = client.send_payment(&request).unwrap();
 
@@ -4333,6 +7296,16 @@ mod test {
             HashSet::from_iter(utxos.clone())
         );
 
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &[AccountKey::random(&mut rng).default_subaddress()],
+            DEFAULT_PER_RECIPIENT_AMOUNT,
+            &[KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        request.set_tombstone(0);
+
         // Try again, placing a cap at the max UTXO that can be selected. This should
         // cause send payment to fail.
         request.set_max_input_utxo_value(20);
@@ -4418,10 +7391,11 @@ mod test {
             mobilecoind_db.clone(),
             None,
             Some(fog_pubkey_resolver_factory),
+            ServiceConfig::default(),
             &uri,
         );
         log::debug!(logger, "Setting up client {:?}", port);
-        let client = test_utils::setup_client(&uri, &logger);
+        let client = test_utils::setup_client(&uri, None, &logger);
 
         // Insert into database.
         let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
@@ -5082,73 +8056,393 @@ mod test {
     }
 
     #[test_with_logger]
-    fn test_address_code(logger: Logger) {
+    fn test_address_code(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // no known recipient, 3 random recipients and no monitors.
+        let (_ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+
+        {
+            // Random receiver address.
+            let receiver = AccountKey::random(&mut rng).default_subaddress();
+
+            // Generate a request code
+            let mut request = mc_mobilecoind_api::CreateAddressCodeRequest::new();
+            request.set_receiver(mc_api::external::PublicAddress::from(&receiver));
+
+            let response = client.create_address_code(&request).unwrap();
+            let b58_code = response.get_b58_code();
+
+            // Attempt to decode it.
+            let mut request = mc_mobilecoind_api::ParseAddressCodeRequest::new();
+            request.set_b58_code(b58_code.to_string());
+
+            let response = client.parse_address_code(&request).unwrap();
+
+            // Check that input equals output.
+            assert_eq!(
+                PublicAddress::try_from(response.get_receiver()).unwrap(),
+                receiver
+            );
+        }
+
+        // Also accept a payment request code as an address code
+        {
+            // Random receiver address.
+            let receiver = AccountKey::random(&mut rng).default_subaddress();
+
+            // Generate a request code
+            let mut request = mc_mobilecoind_api::CreateRequestCodeRequest::new();
+            request.set_receiver(mc_api::external::PublicAddress::from(&receiver));
+            request.set_value(1234567890);
+            request.set_memo("hello there".to_owned());
+
+            let response = client.create_request_code(&request).unwrap();
+            let b58_code = response.get_b58_code();
+
+            // Attempt to decode it.
+            let mut request = mc_mobilecoind_api::ParseAddressCodeRequest::new();
+            request.set_b58_code(b58_code.to_string());
+
+            let response = client.parse_address_code(&request).unwrap();
+
+            // Check that input equals output.
+            assert_eq!(
+                PublicAddress::try_from(response.get_receiver()).unwrap(),
+                receiver
+            );
+        }
+
+        // Attempting to decode junk data should fail
+        {
+            let mut request = mc_mobilecoind_api::ParseAddressCodeRequest::new();
+            request.set_b58_code("junk".to_owned());
+
+            assert!(client.parse_address_code(&request).is_err());
+        }
+    }
+
+    #[test_with_logger]
+    fn test_encode_decode_address(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // no known recipient, 3 random recipients and no monitors.
+        let (_ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+
+        let expect_invalid_argument = |b58_address: &str| {
+            let mut request = mc_mobilecoind_api::DecodeAddressRequest::new();
+            request.set_b58_address(b58_address.to_owned());
+            match client.decode_address(&request) {
+                Err(GrpcError::RpcFailure(status)) => {
+                    assert_eq!(status.status, RpcStatusCode::INVALID_ARGUMENT)
+                }
+                Err(err) => panic!("Unexpected error {:?}", err),
+                Ok(_) => panic!("{} should not decode", b58_address),
+            }
+        };
+
+        // Addresses with and without fog round-trip, and match the canonical wrapper.
+        let fog_account_key = AccountKey::random_with_fog(&mut rng);
+        let receivers = vec![
+            AccountKey::random(&mut rng).default_subaddress(),
+            fog_account_key.default_subaddress(),
+        ];
+        assert!(receivers[1].fog_report_url().is_some());
+
+        for receiver in receivers.iter() {
+            let mut request = mc_mobilecoind_api::EncodeAddressRequest::new();
+            request.set_public_address(mc_api::external::PublicAddress::from(receiver));
+            let response = client.encode_address(&request).unwrap();
+            let b58_address = response.get_b58_address().to_owned();
+
+            let mut wrapper = mc_mobilecoind_api::printable::PrintableWrapper::new();
+            wrapper.set_public_address(receiver.into());
+            assert_eq!(b58_address, wrapper.b58_encode().unwrap());
+
+            let mut request = mc_mobilecoind_api::DecodeAddressRequest::new();
+            request.set_b58_address(b58_address.clone());
+            let response = client.decode_address(&request).unwrap();
+            assert_eq!(
+                PublicAddress::try_from(response.get_public_address()).unwrap(),
+                *receiver
+            );
+
+            // Altering the payload breaks the checksum.
+            let mut corrupted = b58_address.clone();
+            let last = corrupted.pop().unwrap();
+            corrupted.push(if last == '2' { '3' } else { '2' });
+            expect_invalid_argument(&corrupted);
+        }
+
+        // Junk, and codes that are not plain addresses, are rejected.
+        expect_invalid_argument("junk");
+        expect_invalid_argument("0OIl");
+
+        let mut request = mc_mobilecoind_api::CreateRequestCodeRequest::new();
+        request.set_receiver(mc_api::external::PublicAddress::from(&receivers[0]));
+        request.set_value(10);
+        let response = client.create_request_code(&request).unwrap();
+        expect_invalid_argument(response.get_b58_code());
+    }
+
+    #[test_with_logger]
+    fn test_encode_decode_payment_request(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // no known recipient, 3 random recipients and no monitors.
+        let (_ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+
+        let receiver = AccountKey::random_with_fog(&mut rng).default_subaddress();
+
+        // Encode and decode an address, amount and memo.
+        let mut request = mc_mobilecoind_api::EncodePaymentRequestRequest::new();
+        request.set_public_address(mc_api::external::PublicAddress::from(&receiver));
+        request.set_value(1234567890);
+        request.set_memo("coffee".to_owned());
+        let response = client.encode_payment_request(&request).unwrap();
+        let b58_code = response.get_b58_code().to_owned();
+
+        let mut request = mc_mobilecoind_api::DecodePaymentRequestRequest::new();
+        request.set_b58_code(b58_code.clone());
+        let response = client.decode_payment_request(&request).unwrap();
+        assert_eq!(
+            PublicAddress::try_from(response.get_public_address()).unwrap(),
+            receiver
+        );
+        assert_eq!(response.get_value(), 1234567890);
+        assert_eq!(response.get_memo(), "coffee");
+
+        // The code is also understood by ParseRequestCode.
+        let mut request = mc_mobilecoind_api::ParseRequestCodeRequest::new();
+        request.set_b58_code(b58_code);
+        let response = client.parse_request_code(&request).unwrap();
+        assert_eq!(response.get_value(), 1234567890);
+
+        // A zero value is rejected on encode.
+        let mut request = mc_mobilecoind_api::EncodePaymentRequestRequest::new();
+        request.set_public_address(mc_api::external::PublicAddress::from(&receiver));
+        request.set_memo("coffee".to_owned());
+        match client.encode_payment_request(&request) {
+            Err(GrpcError::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::INVALID_ARGUMENT)
+            }
+            Err(err) => panic!("Unexpected error {:?}", err),
+            Ok(_) => panic!("Zero value should be rejected"),
+        }
+
+        // Malformed codes, and plain addresses, are rejected on decode.
+        let mut request = mc_mobilecoind_api::EncodeAddressRequest::new();
+        request.set_public_address(mc_api::external::PublicAddress::from(&receiver));
+        let address_code = client
+            .encode_address(&request)
+            .unwrap()
+            .get_b58_address()
+            .to_owned();
+
+        for b58_code in &["junk".to_owned(), address_code] {
+            let mut request = mc_mobilecoind_api::DecodePaymentRequestRequest::new();
+            request.set_b58_code(b58_code.clone());
+            match client.decode_payment_request(&request) {
+                Err(GrpcError::RpcFailure(status)) => {
+                    assert_eq!(status.status, RpcStatusCode::INVALID_ARGUMENT)
+                }
+                Err(err) => panic!("Unexpected error {:?}", err),
+                Ok(_) => panic!("{} should not decode", b58_code),
+            }
+        }
+    }
+
+    #[test_with_logger]
+    fn test_get_tombstone_blocks_remaining(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
 
         // no known recipient, 3 random recipients and no monitors.
-        let (_ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+        let (ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
             get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+        let num_blocks = ledger_db.num_blocks().unwrap();
 
-        {
-            // Random receiver address.
-            let receiver = AccountKey::random(&mut rng).default_subaddress();
+        let blocks_remaining = |tombstone_block: u64| {
+            let mut request = mc_mobilecoind_api::GetTombstoneBlocksRemainingRequest::new();
+            request.set_tombstone_block(tombstone_block);
+            let response = client.get_tombstone_blocks_remaining(&request).unwrap();
 
-            // Generate a request code
-            let mut request = mc_mobilecoind_api::CreateAddressCodeRequest::new();
-            request.set_receiver(mc_api::external::PublicAddress::from(&receiver));
+            // There is no watcher to estimate block intervals from.
+            assert!(!response.get_has_eta());
+            assert_eq!(response.get_eta_seconds(), 0);
 
-            let response = client.create_address_code(&request).unwrap();
-            let b58_code = response.get_b58_code();
+            response.get_blocks_remaining()
+        };
 
-            // Attempt to decode it.
-            let mut request = mc_mobilecoind_api::ParseAddressCodeRequest::new();
-            request.set_b58_code(b58_code.to_string());
+        assert_eq!(blocks_remaining(num_blocks + 7), 7);
+        assert_eq!(blocks_remaining(num_blocks + 1), 1);
 
-            let response = client.parse_address_code(&request).unwrap();
+        // Expired tombstones are clamped at zero.
+        assert_eq!(blocks_remaining(num_blocks), 0);
+        assert_eq!(blocks_remaining(1), 0);
+    }
 
-            // Check that input equals output.
-            assert_eq!(
-                PublicAddress::try_from(response.get_receiver()).unwrap(),
-                receiver
+    #[test_with_logger]
+    fn test_get_blocks_until_spendable(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // Outputs need to have been in the ledger for 3 blocks to be spendable.
+        let account_key = AccountKey::random(&mut rng);
+        let mut data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            1,  // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+        data.confirmations_required = 3;
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (mut ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
             );
-        }
 
-        // Also accept a payment request code as an address code
-        {
-            // Random receiver address.
-            let receiver = AccountKey::random(&mut rng).default_subaddress();
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
 
-            // Generate a request code
-            let mut request = mc_mobilecoind_api::CreateRequestCodeRequest::new();
-            request.set_receiver(mc_api::external::PublicAddress::from(&receiver));
-            request.set_value(1234567890);
-            request.set_memo("hello there".to_owned());
+        let blocks_until_spendable = |tx_out_public_key: &CompressedRistrettoPublic| {
+            let mut request = mc_mobilecoind_api::GetBlocksUntilSpendableRequest::new();
+            request.set_monitor_id(monitor_id.to_vec());
+            request.set_tx_out_public_key(mc_mobilecoind_api::external::CompressedRistretto::from(
+                tx_out_public_key,
+            ));
+            let response = client.get_blocks_until_spendable(&request).unwrap();
 
-            let response = client.create_request_code(&request).unwrap();
-            let b58_code = response.get_b58_code();
+            // There is no watcher to estimate block intervals from.
+            assert!(!response.get_has_eta());
+            assert_eq!(response.get_eta_seconds(), 0);
 
-            // Attempt to decode it.
-            let mut request = mc_mobilecoind_api::ParseAddressCodeRequest::new();
-            request.set_b58_code(b58_code.to_string());
+            response.get_blocks_remaining()
+        };
 
-            let response = client.parse_address_code(&request).unwrap();
+        // The account received an output in every block. Those of the last two
+        // blocks have too few confirmations, the others are already spendable.
+        let num_blocks = ledger_db.num_blocks().unwrap();
+        let mut utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        utxos.sort_by_key(|utxo| utxo.received_block_index);
+        assert_eq!(utxos.len() as u64, num_blocks);
+        for utxo in &utxos {
+            let expected = (utxo.received_block_index + 3).saturating_sub(num_blocks);
+            assert_eq!(blocks_until_spendable(&utxo.tx_out.public_key), expected);
+        }
+        let recent_utxo = utxos.last().unwrap();
+        assert_eq!(blocks_until_spendable(&recent_utxo.tx_out.public_key), 2);
 
-            // Check that input equals output.
+        // Every block appended brings the recent output closer to spendable.
+        let other_recipient = AccountKey::random(&mut rng).default_subaddress();
+        for blocks_remaining in (0..2).rev() {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &[other_recipient.clone()],
+                DEFAULT_PER_RECIPIENT_AMOUNT,
+                &[],
+                &mut rng,
+            );
             assert_eq!(
-                PublicAddress::try_from(response.get_receiver()).unwrap(),
-                receiver
+                blocks_until_spendable(&recent_utxo.tx_out.public_key),
+                blocks_remaining
             );
         }
 
-        // Attempting to decode junk data should fail
-        {
-            let mut request = mc_mobilecoind_api::ParseAddressCodeRequest::new();
-            request.set_b58_code("junk".to_owned());
-
-            assert!(client.parse_address_code(&request).is_err());
+        // Unknown outputs are not found.
+        let mut request = mc_mobilecoind_api::GetBlocksUntilSpendableRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        request.set_tx_out_public_key(mc_mobilecoind_api::external::CompressedRistretto::from(
+            &CompressedRistrettoPublic::from(&RistrettoPublic::from_random(&mut rng)),
+        ));
+        match client.get_blocks_until_spendable(&request) {
+            Err(GrpcError::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::NOT_FOUND)
+            }
+            result => panic!("Unexpected result {:?}", result),
         }
     }
 
+    #[test_with_logger]
+    fn test_setup_server_with_connections(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let (ledger_db, mobilecoind_db) = test_utils::get_test_databases(
+            3,
+            &vec![],
+            test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS,
+            logger.clone(),
+            &mut rng,
+        );
+
+        let port = test_utils::get_free_port();
+        let uri = MobilecoindUri::from_str(&format!("insecure-mobilecoind://127.0.0.1:{}/", port))
+            .unwrap();
+        let (_server, server_conn_manager) =
+            test_utils::setup_server_with_connections::<MockFogResolver, _>(
+                logger.clone(),
+                ledger_db.clone(),
+                mobilecoind_db,
+                None,
+                None,
+                ServiceConfig::default(),
+                &uri,
+                |ledger_db| {
+                    (7..10)
+                        .map(|node_id| {
+                            MockBlockchainConnection::new(
+                                test_client_uri(node_id),
+                                ledger_db.clone(),
+                                0,
+                                None,
+                            )
+                        })
+                        .collect()
+                },
+            );
+        let client = test_utils::setup_client(&uri, None, &logger);
+
+        // The manager holds exactly the supplied connections.
+        let expected_responder_ids: HashSet<String> = (7..10)
+            .map(|node_id| test_client_uri(node_id).responder_id().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            server_conn_manager
+                .responder_ids()
+                .iter()
+                .map(|responder_id| responder_id.to_string())
+                .collect::<HashSet<_>>(),
+            expected_responder_ids
+        );
+
+        // The network state was polled through them.
+        let network_status = client
+            .get_network_status(&mc_mobilecoind_api::Empty::new())
+            .unwrap();
+        assert_eq!(
+            network_status
+                .get_peer_block_index_map()
+                .keys()
+                .cloned()
+                .collect::<HashSet<_>>(),
+            expected_responder_ids
+        );
+        assert_eq!(
+            network_status.network_highest_block_index,
+            ledger_db.num_blocks().unwrap() - 1
+        );
+    }
+
     #[test_with_logger]
     fn test_get_network_status(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
@@ -5171,6 +8465,205 @@ mod test {
         );
     }
 
+    #[test_with_logger]
+    fn test_get_scan_throughput(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // Three random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+
+        // Nothing has been scanned yet.
+        let response = client
+            .get_scan_throughput(&mc_mobilecoind_api::Empty::new())
+            .unwrap();
+        assert_eq!(response.blocks_per_second, 0.0);
+        assert_eq!(response.window_seconds, 60);
+        assert_eq!(response.blocks_remaining, 0);
+
+        // A paused monitor is not waited on.
+        let mut paused_data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            DEFAULT_SUBADDRESS_INDEX, // first_subaddress
+            1,                        // num_subaddresses
+            0,                        // first_block
+            "",                       // name
+        )
+        .unwrap();
+        paused_data.paused = true;
+        mobilecoind_db.add_monitor(&paused_data).unwrap();
+
+        let data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            DEFAULT_SUBADDRESS_INDEX, // first_subaddress
+            1,                        // num_subaddresses
+            0,                        // first_block
+            "",                       // name
+        )
+        .unwrap();
+        mobilecoind_db.add_monitor(&data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let response = client
+            .get_scan_throughput(&mc_mobilecoind_api::Empty::new())
+            .unwrap();
+        assert!(response.blocks_per_second > 0.0);
+        assert_eq!(response.blocks_remaining, 0);
+    }
+
+    #[test_with_logger]
+    fn test_get_node_sync_status(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let (ledger_db, mobilecoind_db) = test_utils::get_test_databases(
+            3,
+            &vec![],
+            test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS,
+            logger.clone(),
+            &mut rng,
+        );
+
+        // The network has 5 more blocks than the local ledger.
+        let (network_ledger_db, _network_mobilecoind_db) = test_utils::get_test_databases(
+            3,
+            &vec![],
+            test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS + 5,
+            logger.clone(),
+            &mut rng,
+        );
+
+        let port = test_utils::get_free_port();
+        let uri = MobilecoindUri::from_str(&format!("insecure-mobilecoind://127.0.0.1:{}/", port))
+            .unwrap();
+        let (_server, _server_conn_manager) =
+            test_utils::setup_server_with_connections::<MockFogResolver, _>(
+                logger.clone(),
+                ledger_db.clone(),
+                mobilecoind_db,
+                None,
+                None,
+                ServiceConfig::default(),
+                &uri,
+                |_ledger_db| {
+                    (1..3)
+                        .map(|node_id| {
+                            MockBlockchainConnection::new(
+                                test_client_uri(node_id),
+                                network_ledger_db.clone(),
+                                0,
+                                None,
+                            )
+                        })
+                        .collect()
+                },
+            );
+        let client = test_utils::setup_client(&uri, None, &logger);
+
+        let sync_status = client
+            .get_node_sync_status(&mc_mobilecoind_api::Empty::new())
+            .unwrap();
+
+        assert_eq!(
+            sync_status.local_block_height,
+            test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS as u64
+        );
+        assert!(sync_status.network_height_known);
+        assert_eq!(
+            sync_status.network_block_height,
+            network_ledger_db.num_blocks().unwrap()
+        );
+        assert!(sync_status.is_behind);
+        assert_eq!(sync_status.blocks_behind, 5);
+    }
+
+    #[test_with_logger]
+    fn test_stale_network_state_is_refreshed(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let (ledger_db, mobilecoind_db) = test_utils::get_test_databases(
+            3,
+            &vec![],
+            test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS,
+            logger.clone(),
+            &mut rng,
+        );
+        let (mut network_ledger_db, _network_mobilecoind_db) = test_utils::get_test_databases(
+            3,
+            &vec![],
+            test_utils::GET_TESTING_ENVIRONMENT_NUM_BLOCKS,
+            logger.clone(),
+            &mut rng,
+        );
+
+        // Any polled state is considered stale.
+        let network_state_refresh = NetworkStateRefresh {
+            max_age: Duration::from_secs(0),
+            timeout: Duration::from_secs(5),
+        };
+
+        let port = test_utils::get_free_port();
+        let uri = MobilecoindUri::from_str(&format!("insecure-mobilecoind://127.0.0.1:{}/", port))
+            .unwrap();
+        let (_server, _server_conn_manager) =
+            test_utils::setup_server_with_connections::<MockFogResolver, _>(
+                logger.clone(),
+                ledger_db,
+                mobilecoind_db,
+                None,
+                None,
+                ServiceConfig {
+                    network_state_refresh: Some(network_state_refresh),
+                    ..Default::default()
+                },
+                &uri,
+                |_ledger_db| {
+                    (1..3)
+                        .map(|node_id| {
+                            MockBlockchainConnection::new(
+                                test_client_uri(node_id),
+                                network_ledger_db.clone(),
+                                0,
+                                None,
+                            )
+                        })
+                        .collect()
+                },
+            );
+        let client = test_utils::setup_client(&uri, None, &logger);
+
+        let sync_status = client
+            .get_node_sync_status(&mc_mobilecoind_api::Empty::new())
+            .unwrap();
+        assert!(!sync_status.is_behind);
+
+        // The network moves ahead. Nothing polls it in the background, so only a
+        // re-poll by the RPC can notice.
+        add_block_to_ledger_db(
+            &mut network_ledger_db,
+            &[AccountKey::random(&mut rng).default_subaddress()],
+            DEFAULT_PER_RECIPIENT_AMOUNT,
+            &[],
+            &mut rng,
+        );
+
+        let sync_status = client
+            .get_node_sync_status(&mc_mobilecoind_api::Empty::new())
+            .unwrap();
+        assert_eq!(
+            sync_status.network_block_height,
+            network_ledger_db.num_blocks().unwrap()
+        );
+        assert_eq!(sync_status.blocks_behind, 1);
+
+        let network_status = client
+            .get_network_status(&mc_mobilecoind_api::Empty::new())
+            .unwrap();
+        assert_eq!(
+            network_status.network_highest_block_index,
+            network_ledger_db.num_blocks().unwrap() - 1
+        );
+    }
+
     #[test_with_logger]
     fn test_add_remove_add_monitor_with_spent_key_images(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
@@ -5304,4 +8797,103 @@ mod test {
             .expect("Failed getting processed block");
         assert_eq!(response.get_tx_outs().len(), 1);
     }
+
+    /// A log drain that records the message and key-value pairs of every line.
+    #[derive(Clone, Default)]
+    struct CapturingDrain(Arc<Mutex<Vec<(String, HashMap<String, String>)>>>);
+
+    impl slog::Drain for CapturingDrain {
+        type Ok = ();
+        type Err = slog::Never;
+
+        fn log(
+            &self,
+            record: &slog::Record,
+            values: &slog::OwnedKVList,
+        ) -> Result<Self::Ok, Self::Err> {
+            struct Collector(HashMap<String, String>);
+            impl slog::Serializer for Collector {
+                fn emit_arguments(
+                    &mut self,
+                    key: slog::Key,
+                    val: &std::fmt::Arguments,
+                ) -> slog::Result {
+                    self.0.insert(key.to_string(), val.to_string());
+                    Ok(())
+                }
+            }
+
+            let mut collector = Collector(HashMap::default());
+            slog::KV::serialize(values, record, &mut collector).unwrap();
+            slog::KV::serialize(&record.kv(), record, &mut collector).unwrap();
+            self.0
+                .lock()
+                .unwrap()
+                .push((record.msg().to_string(), collector.0));
+            Ok(())
+        }
+    }
+
+    #[test_with_logger]
+    fn test_request_id_logging(_logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let drain = CapturingDrain::default();
+        let logger = Logger::root(drain.clone(), o!());
+
+        let data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,  // first_subaddress
+            10, // num_subaddresses
+            0,  // first_block
+            "", // name
+        )
+        .unwrap();
+        let (_ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![data.clone()], logger, &mut rng);
+
+        let mut request = mc_mobilecoind_api::ExportViewKeyRequest::new();
+        request.set_monitor_id(MonitorId::from(&data).to_vec());
+        request.set_first_subaddress(0);
+        request.set_num_subaddresses(1);
+
+        // One call supplies its own request id, the other does not.
+        let mut metadata_builder = MetadataBuilder::new();
+        metadata_builder
+            .add_str(REQUEST_ID_METADATA_KEY, "test-request")
+            .unwrap();
+        client
+            .export_view_key_opt(
+                &request,
+                CallOption::default().headers(metadata_builder.build()),
+            )
+            .unwrap();
+        client.export_view_key(&request).unwrap();
+
+        let lines: Vec<HashMap<String, String>> = drain
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(msg, _)| msg.starts_with("Exporting view key"))
+            .map(|(_, values)| values.clone())
+            .collect();
+        assert_eq!(lines.len(), 2);
+
+        // Every line logged while handling the first call carries the same ids.
+        let first_request_id = lines[0].get("rpc_request_id").unwrap().clone();
+        assert_eq!(
+            lines[0].get("client_request_id"),
+            Some(&"test-request".to_string())
+        );
+        for (_, values) in drain.0.lock().unwrap().iter() {
+            if values.get("client_request_id").is_some() {
+                assert_eq!(values.get("rpc_request_id"), Some(&first_request_id));
+            }
+        }
+
+        // The second call gets an id of its own.
+        let second_request_id = lines[1].get("rpc_request_id").unwrap();
+        assert_ne!(second_request_id, &first_request_id);
+        assert_eq!(lines[1].get("client_request_id"), None);
+    }
 }
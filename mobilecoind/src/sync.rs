@@ -19,12 +19,16 @@
 //! available blocks gets processed at once. When that happens, instead of
 //! removing the monitor id from the hashset, it would be placed back into the
 //! queue to be picked up by the next available worker thread.
+//! Processing a block is retried with backoff when it fails. If it keeps
+//! failing, the monitor is left at that block and gets queued again by the
+//! main thread, so that no block is ever skipped.
 
 use crate::{
     database::Database,
     error::Error,
     monitor_store::{MonitorData, MonitorId},
-    subaddress_store::SubaddressSPKId,
+    scan_throughput::ScanThroughputMeter,
+    subaddress_store::{SubaddressId, SubaddressSPKId},
     utxo_store::UnspentTxOut,
 };
 use mc_common::{
@@ -46,11 +50,116 @@ use std::{
         Arc, Mutex,
     },
     thread,
+    time::Duration,
 };
 
 ///  The maximal number of blocks a worker thread would process at once.
 const MAX_BLOCKS_PROCESSING_CHUNK_SIZE: usize = 5;
 
+/// How processing a block is retried when it fails, for example because the
+/// database is momentarily unavailable.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BlockProcessingRetry {
+    /// Number of times a failed block is retried before the worker gives up on
+    /// it until the next sync cycle.
+    pub max_retries: usize,
+
+    /// Delay before the first retry. It doubles with every further retry.
+    pub initial_backoff: Duration,
+}
+
+impl Default for BlockProcessingRetry {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+        }
+    }
+}
+
+/// The database operations needed to sync a monitor.
+trait SyncStore {
+    fn get_monitor_data(&self, monitor_id: &MonitorId) -> Result<MonitorData, Error>;
+
+    fn get_subaddress_id_by_spk(
+        &self,
+        subaddress_spk: &SubaddressSPKId,
+    ) -> Result<SubaddressId, Error>;
+
+    fn block_processed(
+        &self,
+        monitor_id: &MonitorId,
+        block_num: u64,
+        discovered_utxos: &[UnspentTxOut],
+        spent_key_images: &[KeyImage],
+    ) -> Result<(), Error>;
+}
+
+impl SyncStore for Database {
+    fn get_monitor_data(&self, monitor_id: &MonitorId) -> Result<MonitorData, Error> {
+        Database::get_monitor_data(self, monitor_id)
+    }
+
+    fn get_subaddress_id_by_spk(
+        &self,
+        subaddress_spk: &SubaddressSPKId,
+    ) -> Result<SubaddressId, Error> {
+        Database::get_subaddress_id_by_spk(self, subaddress_spk)
+    }
+
+    fn block_processed(
+        &self,
+        monitor_id: &MonitorId,
+        block_num: u64,
+        discovered_utxos: &[UnspentTxOut],
+        spent_key_images: &[KeyImage],
+    ) -> Result<(), Error> {
+        Database::block_processed(
+            self,
+            monitor_id,
+            block_num,
+            discovered_utxos,
+            spent_key_images,
+        )
+    }
+}
+
+/// A store that records every block it processes in a throughput meter.
+struct MeteredSyncStore<'a> {
+    mobilecoind_db: &'a Database,
+    scan_throughput: &'a ScanThroughputMeter,
+}
+
+impl<'a> SyncStore for MeteredSyncStore<'a> {
+    fn get_monitor_data(&self, monitor_id: &MonitorId) -> Result<MonitorData, Error> {
+        self.mobilecoind_db.get_monitor_data(monitor_id)
+    }
+
+    fn get_subaddress_id_by_spk(
+        &self,
+        subaddress_spk: &SubaddressSPKId,
+    ) -> Result<SubaddressId, Error> {
+        self.mobilecoind_db.get_subaddress_id_by_spk(subaddress_spk)
+    }
+
+    fn block_processed(
+        &self,
+        monitor_id: &MonitorId,
+        block_num: u64,
+        discovered_utxos: &[UnspentTxOut],
+        spent_key_images: &[KeyImage],
+    ) -> Result<(), Error> {
+        self.mobilecoind_db.block_processed(
+            monitor_id,
+            block_num,
+            discovered_utxos,
+            spent_key_images,
+        )?;
+        self.scan_throughput.record(1);
+        Ok(())
+    }
+}
+
 /// Message type the our crossbeam channel used to communicate with the worker
 /// thread pull.
 enum SyncMsg {
@@ -82,6 +191,8 @@ impl SyncThread {
         ledger_db: LedgerDB,
         mobilecoind_db: Database,
         num_workers: Option<usize>,
+        block_processing_retry: BlockProcessingRetry,
+        scan_throughput: ScanThroughputMeter,
         logger: Logger,
     ) -> Self {
         // Queue for sending jobs to our worker threads.
@@ -91,15 +202,17 @@ impl SyncThread {
         // preventing them from being sent again until they are processed.
         let queued_monitor_ids = Arc::new(Mutex::new(HashSet::<MonitorId>::default()));
 
-        // Create worker threads.
+        // Create worker threads. At least one worker is needed for monitors to get
+        // synced at all; a single worker syncs them one at a time.
         let mut worker_join_handles = Vec::new();
 
-        for idx in 0..num_workers.unwrap_or_else(num_cpus::get) {
+        for idx in 0..num_workers.unwrap_or_else(num_cpus::get).max(1) {
             let thread_ledger_db = ledger_db.clone();
             let thread_mobilecoind_db = mobilecoind_db.clone();
             let thread_sender = sender.clone();
             let thread_receiver = receiver.clone();
             let thread_queued_monitor_ids = queued_monitor_ids.clone();
+            let thread_scan_throughput = scan_throughput.clone();
             let thread_logger = logger.clone();
             let join_handle = thread::Builder::new()
                 .name(format!("sync_worker_{}", idx))
@@ -110,6 +223,8 @@ impl SyncThread {
                         thread_sender,
                         thread_receiver,
                         thread_queued_monitor_ids,
+                        block_processing_retry,
+                        thread_scan_throughput,
                         thread_logger,
                     );
                 })
@@ -136,6 +251,8 @@ impl SyncThread {
                             break;
                         }
 
+                        scan_throughput.update_metrics();
+
                         // Get the current number of blocks in ledger.
                         let num_blocks = ledger_db
                             .num_blocks()
@@ -154,8 +271,9 @@ impl SyncThread {
                             .get_monitor_map()
                             .expect("failed getting monitor map")
                         {
-                            // If there are no new blocks for this monitor, don't do anything.
-                            if monitor_data.next_block >= num_blocks {
+                            // If there are no new blocks for this monitor, or it is paused, don't
+                            // do anything.
+                            if monitor_data.paused || monitor_data.next_block >= num_blocks {
                                 continue;
                             }
 
@@ -238,12 +356,25 @@ fn sync_thread_entry_point(
     sender: crossbeam_channel::Sender<SyncMsg>,
     receiver: crossbeam_channel::Receiver<SyncMsg>,
     queued_monitor_ids: Arc<Mutex<HashSet<MonitorId>>>,
+    block_processing_retry: BlockProcessingRetry,
+    scan_throughput: ScanThroughputMeter,
     logger: Logger,
 ) {
+    let store = MeteredSyncStore {
+        mobilecoind_db: &mobilecoind_db,
+        scan_throughput: &scan_throughput,
+    };
+
     for msg in receiver.iter() {
         match msg {
             SyncMsg::SyncMonitor(monitor_id) => {
-                match sync_monitor(&ledger_db, &mobilecoind_db, &monitor_id, &logger) {
+                match sync_monitor(
+                    &ledger_db,
+                    &store,
+                    &monitor_id,
+                    &block_processing_retry,
+                    &logger,
+                ) {
                     // Success - No more blocks are currently available.
                     Ok(SyncMonitorOk::NoMoreBlocks) => {
                         // Remove the monitor id from the list of queued ones so that the main
@@ -272,9 +403,14 @@ fn sync_thread_entry_point(
                     // Errors that are acceptable - nothing to do.
                     Err(Error::MonitorIdNotFound) => {}
 
-                    // Other errors - log.
+                    // Other errors - log, and let the main thread queue the monitor again so
+                    // that the block that failed is retried on the next cycle.
                     Err(err) => {
                         log::error!(logger, "error syncing monitor {}: {:?}", monitor_id, err);
+
+                        let mut queued_monitor_ids =
+                            queued_monitor_ids.lock().expect("mutex poisoned");
+                        queued_monitor_ids.remove(&monitor_id);
                     }
                 };
             }
@@ -289,14 +425,21 @@ fn sync_thread_entry_point(
 /// Sync a single monitor.
 fn sync_monitor(
     ledger_db: &LedgerDB,
-    mobilecoind_db: &Database,
+    mobilecoind_db: &impl SyncStore,
     monitor_id: &MonitorId,
+    block_processing_retry: &BlockProcessingRetry,
     logger: &Logger,
 ) -> Result<SyncMonitorOk, Error> {
     for _ in 0..MAX_BLOCKS_PROCESSING_CHUNK_SIZE {
         // Get the monitor data. If it is no longer available, the monitor has been
         // removed and we can simply return.
         let monitor_data = mobilecoind_db.get_monitor_data(monitor_id)?;
+
+        // The monitor may have been paused after it was queued.
+        if monitor_data.paused {
+            return Ok(SyncMonitorOk::NoMoreBlocks);
+        }
+
         let block_contents = match ledger_db.get_block_contents(monitor_data.next_block) {
             Ok(block_contents) => block_contents,
             Err(mc_ledger_db::Error::NotFound) => {
@@ -316,30 +459,64 @@ fn sync_monitor(
             monitor_id,
         );
 
-        // Match tx outs into UTXOs.
-        let utxos = match_tx_outs_into_utxos(
-            &mobilecoind_db,
-            &block_contents.outputs,
-            monitor_id,
-            &monitor_data,
-            logger,
-        )?;
-
-        // Update database.
-        mobilecoind_db.block_processed(
-            monitor_id,
-            monitor_data.next_block,
-            &utxos,
-            &block_contents.key_images,
-        )?;
+        with_retry(block_processing_retry, logger, || {
+            // Match tx outs into UTXOs.
+            let utxos = match_tx_outs_into_utxos(
+                mobilecoind_db,
+                &block_contents.outputs,
+                monitor_id,
+                &monitor_data,
+                logger,
+            )?;
+
+            // Update database.
+            mobilecoind_db.block_processed(
+                monitor_id,
+                monitor_data.next_block,
+                &utxos,
+                &block_contents.key_images,
+            )
+        })?;
     }
 
     Ok(SyncMonitorOk::MoreBlocksPotentiallyAvailable)
 }
 
-/// Helper function for matching a list of TxOuts to a given monitor.
+/// Run a block processing step, retrying it with exponential backoff if it
+/// fails. A removed monitor is not retried.
+fn with_retry(
+    block_processing_retry: &BlockProcessingRetry,
+    logger: &Logger,
+    mut f: impl FnMut() -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut backoff = block_processing_retry.initial_backoff;
+    let mut num_retries = 0;
+    loop {
+        match f() {
+            Ok(()) => return Ok(()),
+            Err(Error::MonitorIdNotFound) => return Err(Error::MonitorIdNotFound),
+            Err(err) if num_retries < block_processing_retry.max_retries => {
+                num_retries += 1;
+                log::warn!(
+                    logger,
+                    "processing block failed ({:?}), retry {}/{} in {:?}",
+                    err,
+                    num_retries,
+                    block_processing_retry.max_retries,
+                    backoff,
+                );
+                thread::sleep(backoff);
+                backoff *= 2;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Helper function for matching a list of TxOuts to a given monitor. The TxOuts
+/// are those of the block the monitor processes next.
 fn match_tx_outs_into_utxos(
-    mobilecoind_db: &Database,
+    mobilecoind_db: &impl SyncStore,
     outputs: &[TxOut],
     monitor_id: &MonitorId,
     monitor_data: &MonitorData,
@@ -404,6 +581,7 @@ fn match_tx_outs_into_utxos(
             value,
             attempted_spend_height: 0,
             attempted_spend_tombstone: 0,
+            received_block_index: monitor_data.next_block,
         });
     }
 
@@ -423,9 +601,110 @@ mod test {
 
     use mc_common::logger::{test_with_logger, Logger};
     use mc_transaction_core::tx::TxOut;
-    use rand::{rngs::StdRng, SeedableRng};
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
     use std::iter::FromIterator;
 
+    /// A store that fails processing a given block a number of times before
+    /// letting it through.
+    struct FaultyStore {
+        db: Database,
+        failing_block: u64,
+        failures_left: Mutex<usize>,
+    }
+
+    impl SyncStore for FaultyStore {
+        fn get_monitor_data(&self, monitor_id: &MonitorId) -> Result<MonitorData, Error> {
+            self.db.get_monitor_data(monitor_id)
+        }
+
+        fn get_subaddress_id_by_spk(
+            &self,
+            subaddress_spk: &SubaddressSPKId,
+        ) -> Result<SubaddressId, Error> {
+            self.db.get_subaddress_id_by_spk(subaddress_spk)
+        }
+
+        fn block_processed(
+            &self,
+            monitor_id: &MonitorId,
+            block_num: u64,
+            discovered_utxos: &[UnspentTxOut],
+            spent_key_images: &[KeyImage],
+        ) -> Result<(), Error> {
+            if block_num == self.failing_block {
+                let mut failures_left = self.failures_left.lock().unwrap();
+                if *failures_left > 0 {
+                    *failures_left -= 1;
+                    return Err(Error::Lmdb(lmdb::Error::BadTxn));
+                }
+            }
+            self.db
+                .block_processed(monitor_id, block_num, discovered_utxos, spent_key_images)
+        }
+    }
+
+    #[test_with_logger]
+    // A monitor added from the tip should only pick up outputs from later blocks.
+    fn test_sync_monitor_added_from_tip(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([98u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let recipients = vec![account_key.default_subaddress()];
+
+        // The account received an output in each of the existing blocks.
+        let num_blocks = 5;
+        let (mut ledger_db, mobilecoind_db) =
+            get_test_databases(0, &recipients, num_blocks, logger.clone(), &mut rng);
+
+        let monitor_id = mobilecoind_db
+            .add_monitor_from_tip(&ledger_db, &account_key, 0..5)
+            .unwrap();
+
+        let monitor_data = mobilecoind_db.get_monitor_data(&monitor_id).unwrap();
+        assert_eq!(monitor_data.first_block, num_blocks as u64);
+        assert_eq!(monitor_data.next_block, num_blocks as u64);
+        assert_eq!(monitor_data.subaddress_indexes(), 0..5);
+
+        // Nothing to sync, and the earlier outputs are ignored.
+        let result = sync_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &monitor_id,
+            &BlockProcessingRetry::default(),
+            &logger,
+        )
+        .unwrap();
+        assert_eq!(result, SyncMonitorOk::NoMoreBlocks);
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, DEFAULT_SUBADDRESS_INDEX)
+            .unwrap();
+        assert_eq!(utxos.len(), 0);
+
+        // Outputs in a new block are discovered.
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &recipients,
+            DEFAULT_PER_RECIPIENT_AMOUNT,
+            &[KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        let result = sync_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &monitor_id,
+            &BlockProcessingRetry::default(),
+            &logger,
+        )
+        .unwrap();
+        assert_eq!(result, SyncMonitorOk::NoMoreBlocks);
+
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, DEFAULT_SUBADDRESS_INDEX)
+            .unwrap();
+        assert_eq!(utxos.len(), 1);
+        assert_eq!(utxos[0].value, DEFAULT_PER_RECIPIENT_AMOUNT);
+    }
+
     #[test_with_logger]
     fn test_sync_monitor(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([98u8; 32]);
@@ -460,8 +739,9 @@ mod test {
         // the TxOuts relevant to our particular recipient.
         let account0_tx_outs: Vec<TxOut> = (0..num_blocks)
             .map(|idx| {
-                let block_contents = ledger_db.get_block_contents(idx as u64).unwrap();
-                block_contents.outputs[0].clone()
+                test_utils::get_block_tx_outs_for_account(&ledger_db, idx as u64, &account_keys[0])
+                    [0]
+                .clone()
             })
             .collect();
 
@@ -484,7 +764,14 @@ mod test {
         assert_eq!(monitor_data.next_block, 0);
 
         // Process the first MAX_BLOCKS_PROCESSING_CHUNK_SIZE blocks.
-        let result = sync_monitor(&ledger_db, &mobilecoind_db, &monitor_id, &logger).unwrap();
+        let result = sync_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &monitor_id,
+            &BlockProcessingRetry::default(),
+            &logger,
+        )
+        .unwrap();
         assert_eq!(result, SyncMonitorOk::MoreBlocksPotentiallyAvailable);
 
         // We should now discover some outputs. Each block has 1 output per recipient,
@@ -509,7 +796,14 @@ mod test {
         }
 
         // Process the second MAX_BLOCKS_PROCESSING_CHUNK_SIZE blocks.
-        let result = sync_monitor(&ledger_db, &mobilecoind_db, &monitor_id, &logger).unwrap();
+        let result = sync_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &monitor_id,
+            &BlockProcessingRetry::default(),
+            &logger,
+        )
+        .unwrap();
         assert_eq!(result, SyncMonitorOk::MoreBlocksPotentiallyAvailable);
 
         let monitor_data = mobilecoind_db.get_monitor_data(&monitor_id).unwrap();
@@ -532,7 +826,14 @@ mod test {
         }
 
         // Process the last remaining block.
-        let result = sync_monitor(&ledger_db, &mobilecoind_db, &monitor_id, &logger).unwrap();
+        let result = sync_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &monitor_id,
+            &BlockProcessingRetry::default(),
+            &logger,
+        )
+        .unwrap();
         assert_eq!(result, SyncMonitorOk::NoMoreBlocks);
 
         let monitor_data = mobilecoind_db.get_monitor_data(&monitor_id).unwrap();
@@ -552,7 +853,14 @@ mod test {
         }
 
         // Calling sync_monitor again should not change the results.
-        let result = sync_monitor(&ledger_db, &mobilecoind_db, &monitor_id, &logger).unwrap();
+        let result = sync_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &monitor_id,
+            &BlockProcessingRetry::default(),
+            &logger,
+        )
+        .unwrap();
         assert_eq!(result, SyncMonitorOk::NoMoreBlocks);
 
         let monitor_data = mobilecoind_db.get_monitor_data(&monitor_id).unwrap();
@@ -585,7 +893,14 @@ mod test {
             &mut rng,
         );
 
-        let result = sync_monitor(&ledger_db, &mobilecoind_db, &monitor_id, &logger).unwrap();
+        let result = sync_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &monitor_id,
+            &BlockProcessingRetry::default(),
+            &logger,
+        )
+        .unwrap();
         assert_eq!(result, SyncMonitorOk::NoMoreBlocks);
 
         let utxos = mobilecoind_db
@@ -626,7 +941,14 @@ mod test {
         assert_eq!(mobilecoind_db.add_monitor(&data).unwrap(), monitor_id);
 
         // Sync.
-        let result = sync_monitor(&ledger_db, &mobilecoind_db, &monitor_id, &logger).unwrap();
+        let result = sync_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &monitor_id,
+            &BlockProcessingRetry::default(),
+            &logger,
+        )
+        .unwrap();
         assert_eq!(result, SyncMonitorOk::NoMoreBlocks);
 
         // Should have a single non-zero utxo for our monitor.
@@ -646,7 +968,14 @@ mod test {
             &mut rng,
         );
 
-        let result = sync_monitor(&ledger_db, &mobilecoind_db, &monitor_id, &logger).unwrap();
+        let result = sync_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &monitor_id,
+            &BlockProcessingRetry::default(),
+            &logger,
+        )
+        .unwrap();
         assert_eq!(result, SyncMonitorOk::NoMoreBlocks);
 
         // We should now have only a zero utxo.
@@ -657,4 +986,384 @@ mod test {
         assert_eq!(utxos.len(), 1);
         assert_eq!(utxos[0].value, 0);
     }
+
+    #[test_with_logger]
+    // A single worker thread should still sync every monitor.
+    fn test_sync_thread_with_single_worker(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([98u8; 32]);
+
+        let account_keys: Vec<_> = (0..4).map(|_i| AccountKey::random(&mut rng)).collect();
+        let recipients: Vec<PublicAddress> = account_keys
+            .iter()
+            .map(AccountKey::default_subaddress)
+            .collect();
+
+        // More blocks than a worker processes at once, so monitors need to be
+        // requeued.
+        let num_blocks = (MAX_BLOCKS_PROCESSING_CHUNK_SIZE * 2) + 1;
+        let (ledger_db, mobilecoind_db) =
+            get_test_databases(0, &recipients, num_blocks, logger.clone(), &mut rng);
+
+        let monitor_ids: Vec<MonitorId> = account_keys
+            .iter()
+            .map(|account_key| {
+                let data = MonitorData::new(
+                    account_key.clone(),
+                    DEFAULT_SUBADDRESS_INDEX, // first subaddress
+                    1,                        // number of subaddresses
+                    0,                        // first block
+                    "",                       // name
+                )
+                .unwrap();
+                mobilecoind_db.add_monitor(&data).unwrap()
+            })
+            .collect();
+
+        let mut sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            Some(1),
+            BlockProcessingRetry::default(),
+            ScanThroughputMeter::default(),
+            logger.clone(),
+        );
+        test_utils::wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        sync_thread.stop();
+
+        for monitor_id in monitor_ids.iter() {
+            let monitor_data = mobilecoind_db.get_monitor_data(monitor_id).unwrap();
+            assert_eq!(monitor_data.next_block, num_blocks as u64);
+
+            let utxos = mobilecoind_db
+                .get_utxos_for_subaddress(monitor_id, DEFAULT_SUBADDRESS_INDEX)
+                .unwrap();
+            assert_eq!(utxos.len(), num_blocks);
+        }
+    }
+
+    #[test_with_logger]
+    // Blocks scanned by the sync thread should be reflected in the scan
+    // throughput meter.
+    fn test_sync_thread_measures_scan_throughput(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([98u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let num_blocks = 12;
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            0,
+            &[account_key.default_subaddress()],
+            num_blocks,
+            logger.clone(),
+            &mut rng,
+        );
+
+        let data = MonitorData::new(
+            account_key,
+            DEFAULT_SUBADDRESS_INDEX, // first subaddress
+            1,                        // number of subaddresses
+            0,                        // first block
+            "",                       // name
+        )
+        .unwrap();
+        mobilecoind_db.add_monitor(&data).unwrap();
+
+        let scan_throughput = ScanThroughputMeter::default();
+        assert_eq!(scan_throughput.blocks_per_second(), 0.0);
+
+        let mut sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            None,
+            BlockProcessingRetry::default(),
+            scan_throughput.clone(),
+            logger.clone(),
+        );
+        test_utils::wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        sync_thread.stop();
+
+        // The rate is averaged over at least a second, so scanning the blocks
+        // cannot appear faster than that.
+        let blocks_per_second = scan_throughput.blocks_per_second();
+        assert!(blocks_per_second > 0.0);
+        assert!(blocks_per_second <= num_blocks as f64);
+    }
+
+    #[test_with_logger]
+    // A paused monitor should not advance until it is resumed, and then catch up
+    // from where it left off.
+    fn test_sync_thread_skips_paused_monitor(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([98u8; 32]);
+
+        let account_keys: Vec<_> = (0..2).map(|_i| AccountKey::random(&mut rng)).collect();
+        let recipients: Vec<PublicAddress> = account_keys
+            .iter()
+            .map(AccountKey::default_subaddress)
+            .collect();
+
+        let num_blocks = 5;
+        let (mut ledger_db, mobilecoind_db) =
+            get_test_databases(0, &recipients, num_blocks, logger.clone(), &mut rng);
+
+        let monitor_ids: Vec<MonitorId> = account_keys
+            .iter()
+            .map(|account_key| {
+                let data = MonitorData::new(
+                    account_key.clone(),
+                    DEFAULT_SUBADDRESS_INDEX, // first subaddress
+                    1,                        // number of subaddresses
+                    0,                        // first block
+                    "",                       // name
+                )
+                .unwrap();
+                mobilecoind_db.add_monitor(&data).unwrap()
+            })
+            .collect();
+        let paused_monitor_id = &monitor_ids[0];
+        let active_monitor_id = &monitor_ids[1];
+
+        mobilecoind_db
+            .set_monitor_paused(paused_monitor_id, true)
+            .unwrap();
+
+        let mut sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            None,
+            BlockProcessingRetry::default(),
+            ScanThroughputMeter::default(),
+            logger.clone(),
+        );
+
+        // Only the active monitor syncs.
+        test_utils::wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        let next_block = |monitor_id| {
+            mobilecoind_db
+                .get_monitor_data(monitor_id)
+                .unwrap()
+                .next_block
+        };
+        assert_eq!(next_block(active_monitor_id), num_blocks as u64);
+        assert_eq!(next_block(paused_monitor_id), 0);
+
+        // New blocks are not picked up by the paused monitor either.
+        for _ in 0..3 {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &recipients,
+                DEFAULT_PER_RECIPIENT_AMOUNT,
+                &[KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+        test_utils::wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        assert_eq!(next_block(active_monitor_id), num_blocks as u64 + 3);
+        assert_eq!(next_block(paused_monitor_id), 0);
+
+        // Once resumed, the monitor catches up.
+        mobilecoind_db
+            .set_monitor_paused(paused_monitor_id, false)
+            .unwrap();
+        test_utils::wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        sync_thread.stop();
+
+        assert_eq!(next_block(paused_monitor_id), num_blocks as u64 + 3);
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(paused_monitor_id, DEFAULT_SUBADDRESS_INDEX)
+            .unwrap();
+        assert_eq!(utxos.len(), num_blocks + 3);
+    }
+
+    #[test_with_logger]
+    // Matching goes through the spend public key lookup table, so outputs to any
+    // subaddress of a large range are found without testing each subaddress.
+    fn test_sync_monitor_with_large_subaddress_range(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([98u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let subaddress_indexes = vec![0, 5000, 9999];
+        let recipients: Vec<PublicAddress> = subaddress_indexes
+            .iter()
+            .map(|index| account_key.subaddress(*index))
+            .collect();
+
+        let num_blocks = 3;
+        let (ledger_db, mobilecoind_db) =
+            get_test_databases(2, &recipients, num_blocks, logger.clone(), &mut rng);
+
+        let data = MonitorData::new(
+            account_key,
+            0,      // first subaddress
+            10_000, // number of subaddresses
+            0,      // first block
+            "",     // name
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        let result = sync_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &monitor_id,
+            &BlockProcessingRetry::default(),
+            &logger,
+        )
+        .unwrap();
+        assert_eq!(result, SyncMonitorOk::NoMoreBlocks);
+
+        for index in subaddress_indexes {
+            let utxos = mobilecoind_db
+                .get_utxos_for_subaddress(&monitor_id, index)
+                .unwrap();
+            assert_eq!(utxos.len(), num_blocks);
+            assert!(utxos.iter().all(|utxo| utxo.subaddress_index == index));
+        }
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 1)
+            .unwrap();
+        assert!(utxos.is_empty());
+    }
+
+    #[test_with_logger]
+    // A monitor with a gap limit should grow its range as subaddresses near its end
+    // receive funds, but miss outputs sent further than the gap limit.
+    fn test_sync_monitor_with_gap_limit(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([98u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let other_recipient = AccountKey::random(&mut rng).default_subaddress();
+        let (mut ledger_db, mobilecoind_db) =
+            get_test_databases(2, &[other_recipient], 1, logger.clone(), &mut rng);
+
+        let mut data = MonitorData::new(
+            account_key.clone(),
+            0,  // first subaddress
+            5,  // number of subaddresses
+            0,  // first block
+            "", // name
+        )
+        .unwrap();
+        data.gap_limit = 5;
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        let fund_and_sync = |ledger_db: &mut LedgerDB, index: u64, rng: &mut StdRng| {
+            add_block_to_ledger_db(
+                ledger_db,
+                &[account_key.subaddress(index)],
+                DEFAULT_PER_RECIPIENT_AMOUNT,
+                &[KeyImage::from(rng.next_u64())],
+                rng,
+            );
+            let result = sync_monitor(
+                ledger_db,
+                &mobilecoind_db,
+                &monitor_id,
+                &BlockProcessingRetry::default(),
+                &logger,
+            )
+            .unwrap();
+            assert_eq!(result, SyncMonitorOk::NoMoreBlocks);
+            mobilecoind_db
+                .get_utxos_for_subaddress(&monitor_id, index)
+                .unwrap()
+        };
+
+        // Funds within the gap of the end of the range are found, and the range grows
+        // to keep the gap past them.
+        assert_eq!(fund_and_sync(&mut ledger_db, 3, &mut rng).len(), 1);
+        let data = mobilecoind_db.get_monitor_data(&monitor_id).unwrap();
+        assert_eq!(data.subaddress_indexes(), 0..9);
+
+        // Subaddresses the range grew to cover are watched.
+        assert_eq!(fund_and_sync(&mut ledger_db, 8, &mut rng).len(), 1);
+        let data = mobilecoind_db.get_monitor_data(&monitor_id).unwrap();
+        assert_eq!(data.subaddress_indexes(), 0..14);
+
+        // Funds sent further than the gap limit are missed, and do not grow the range.
+        assert!(fund_and_sync(&mut ledger_db, 30, &mut rng).is_empty());
+        let data = mobilecoind_db.get_monitor_data(&monitor_id).unwrap();
+        assert_eq!(data.subaddress_indexes(), 0..14);
+
+        // Growing the range does not change the monitor id.
+        assert_eq!(MonitorId::from(&data), monitor_id);
+    }
+
+    #[test_with_logger]
+    // A block that fails to be processed should be retried, and never skipped.
+    fn test_sync_monitor_retries_failed_block(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([98u8; 32]);
+
+        let account_keys: Vec<_> = (0..2).map(|_i| AccountKey::random(&mut rng)).collect();
+        let recipients: Vec<PublicAddress> = account_keys
+            .iter()
+            .map(AccountKey::default_subaddress)
+            .collect();
+
+        let num_blocks = 4;
+        let (ledger_db, mobilecoind_db) =
+            get_test_databases(0, &recipients, num_blocks, logger.clone(), &mut rng);
+
+        let monitor_ids: Vec<MonitorId> = account_keys
+            .iter()
+            .map(|account_key| {
+                let data = MonitorData::new(
+                    account_key.clone(),
+                    DEFAULT_SUBADDRESS_INDEX, // first subaddress
+                    1,                        // number of subaddresses
+                    0,                        // first block
+                    "",                       // name
+                )
+                .unwrap();
+                mobilecoind_db.add_monitor(&data).unwrap()
+            })
+            .collect();
+
+        let num_utxos = |monitor_id: &MonitorId| {
+            mobilecoind_db
+                .get_utxos_for_subaddress(monitor_id, DEFAULT_SUBADDRESS_INDEX)
+                .unwrap()
+                .len()
+        };
+        let next_block = |monitor_id: &MonitorId| {
+            mobilecoind_db
+                .get_monitor_data(monitor_id)
+                .unwrap()
+                .next_block
+        };
+
+        // Failures within the retry budget are absorbed.
+        let store = FaultyStore {
+            db: mobilecoind_db.clone(),
+            failing_block: 2,
+            failures_left: Mutex::new(2),
+        };
+        let retry = BlockProcessingRetry {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(1),
+        };
+        let result = sync_monitor(&ledger_db, &store, &monitor_ids[0], &retry, &logger).unwrap();
+        assert_eq!(result, SyncMonitorOk::NoMoreBlocks);
+        assert_eq!(*store.failures_left.lock().unwrap(), 0);
+        assert_eq!(next_block(&monitor_ids[0]), num_blocks as u64);
+        assert_eq!(num_utxos(&monitor_ids[0]), num_blocks);
+
+        // Once retries are exhausted, the monitor stays at the failing block.
+        let store = FaultyStore {
+            db: mobilecoind_db.clone(),
+            failing_block: 2,
+            failures_left: Mutex::new(3),
+        };
+        let retry = BlockProcessingRetry {
+            max_retries: 1,
+            initial_backoff: Duration::from_millis(1),
+        };
+        assert!(sync_monitor(&ledger_db, &store, &monitor_ids[1], &retry, &logger).is_err());
+        assert_eq!(next_block(&monitor_ids[1]), 2);
+        assert_eq!(num_utxos(&monitor_ids[1]), 2);
+
+        // The next cycle picks it up from there.
+        let result = sync_monitor(&ledger_db, &store, &monitor_ids[1], &retry, &logger).unwrap();
+        assert_eq!(result, SyncMonitorOk::NoMoreBlocks);
+        assert_eq!(next_block(&monitor_ids[1]), num_blocks as u64);
+        assert_eq!(num_utxos(&monitor_ids[1]), num_blocks);
+    }
 }
@@ -9,28 +9,41 @@ use crate::{
     database::Database,
     monitor_store::{MonitorData, MonitorId},
     payments::TransactionsManager,
-    service::Service,
+    service::{Service, ServiceConfig},
 };
+use futures::Future;
 use grpcio::{ChannelBuilder, EnvBuilder};
 use mc_account_keys::{AccountKey, PublicAddress, DEFAULT_SUBADDRESS_INDEX};
-use mc_common::logger::{log, Logger};
-use mc_connection::{Connection, ConnectionManager};
+use mc_common::{
+    logger::{log, Logger},
+    HashMap,
+};
+use mc_connection::{BlockchainConnection, Connection, ConnectionManager, UserTxConnection};
 use mc_connection_test_utils::{test_client_uri, MockBlockchainConnection};
 use mc_consensus_scp::QuorumSet;
-use mc_crypto_keys::RistrettoPrivate;
+use mc_crypto_keys::{CompressedRistrettoPublic, RistrettoPrivate, RistrettoPublic};
 use mc_crypto_rand::{CryptoRng, RngCore};
 use mc_fog_report_validation_test_utils::{FogPubkeyResolver, MockFogResolver};
 use mc_ledger_db::{Ledger, LedgerDB};
 use mc_ledger_sync::PollingNetworkState;
-use mc_mobilecoind_api::{mobilecoind_api_grpc::MobilecoindApiClient, MobilecoindUri};
+use mc_mobilecoind_api::{
+    empty::Empty, mobilecoind_api_grpc::MobilecoindApiClient, GetBalanceRequest,
+    GetBalanceResponse, GetLedgerInfoResponse, GetMonitorStatusRequest, GetMonitorStatusResponse,
+    GetUnspentTxOutListRequest, GetUnspentTxOutListResponse, MobilecoindUri, SendPaymentRequest,
+    SendPaymentResponse, SubmitTxRequest, SubmitTxResponse,
+};
 use mc_transaction_core::{
-    ring_signature::KeyImage, tx::TxOut, Block, BlockContents, BLOCK_VERSION,
+    encrypted_fog_hint::EncryptedFogHint, fog_hint::FogHint, get_tx_out_shared_secret,
+    onetime_keys::recover_onetime_private_key, ring_signature::KeyImage, tx::TxOut, Block,
+    BlockContents, BlockID, BLOCK_VERSION,
 };
 use mc_util_from_random::FromRandom;
 use mc_util_grpc::ConnectionUriGrpcioChannel;
 use mc_util_uri::{ConnectionUri, FogUri};
 use mc_watcher::watcher_db::WatcherDB;
 use std::{
+    cell::RefCell,
+    convert::TryFrom,
     path::PathBuf,
     str::FromStr,
     sync::{
@@ -46,8 +59,41 @@ pub const DEFAULT_PER_RECIPIENT_AMOUNT: u64 = 5_000 * 1_000_000_000_000;
 /// Number of initial blocks generated by `get_testing_environment`;
 pub const GET_TESTING_ENVIRONMENT_NUM_BLOCKS: usize = 10;
 
+thread_local! {
+    /// Index of the blocks appended with a tag, by tag. This is only kept in
+    /// memory, and per thread since each test runs on its own thread.
+    static BLOCKS_BY_TAG: RefCell<HashMap<String, u64>> = RefCell::new(HashMap::default());
+}
+
+/// Find the index of the last block appended with the given tag by the
+/// current thread.
+pub fn find_block_by_tag(tag: &str) -> Option<u64> {
+    BLOCKS_BY_TAG.with(|blocks_by_tag| blocks_by_tag.borrow().get(tag).cloned())
+}
+
+/// Record the tag of a block that was just appended, if any.
+fn tag_block(tag: Option<&str>, block_index: u64) {
+    if let Some(tag) = tag {
+        BLOCKS_BY_TAG.with(|blocks_by_tag| {
+            blocks_by_tag
+                .borrow_mut()
+                .insert(tag.to_owned(), block_index)
+        });
+    }
+}
+
+/// Get the id of the origin block of a test ledger, e.g. to configure the
+/// expected origin block id.
+pub fn get_origin_block_id(ledger_db: &LedgerDB) -> BlockID {
+    ledger_db
+        .get_block(0)
+        .expect("Test ledger has no origin block")
+        .id
+}
+
 /// Sets up ledger_db and mobilecoind_db. Each block will contains one txo per
-/// recipient.
+/// recipient. The txos of a block are in canonical order, not in the order of
+/// the recipients.
 ///
 /// # Arguments
 /// *
@@ -72,21 +118,7 @@ pub fn get_test_databases(
 
     public_addresses.extend(known_recipients.iter().cloned());
 
-    // Note that TempDir manages uniqueness by constructing paths
-    // like: /tmp/ledger_db.tvF0XHTKsilx
-    let ledger_db_tmp = TempDir::new("ledger_db").expect("Could not make tempdir for ledger db");
-    let ledger_db_path = ledger_db_tmp
-        .path()
-        .to_str()
-        .expect("Could not get path as string");
-    let mobilecoind_db_tmp =
-        TempDir::new("mobilecoind_db").expect("Could not make tempdir for mobilecoind db");
-    let mobilecoind_db_path = mobilecoind_db_tmp
-        .path()
-        .to_str()
-        .expect("Could not get path as string");
-
-    let mut ledger_db = generate_ledger_db(&ledger_db_path);
+    let (mut ledger_db, mobilecoind_db) = create_test_databases(logger);
 
     for block_index in 0..num_blocks {
         let key_images = if block_index == 0 {
@@ -103,6 +135,129 @@ pub fn get_test_databases(
         );
     }
 
+    (ledger_db, mobilecoind_db)
+}
+
+/// An output of a spender that was spent by `get_test_databases_with_spends`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SpentTestOutput {
+    /// The spent output.
+    pub tx_out: TxOut,
+
+    /// The key image of the output, as derived from the spender's keys.
+    pub key_image: KeyImage,
+
+    /// Index of the block the output was received in.
+    pub received_block: u64,
+
+    /// Index of the block whose key images include the output's.
+    pub spent_block: u64,
+}
+
+/// Like `get_test_databases`, except that the outputs received by `spenders`
+/// are spent in the ledger, using their real key images. Of all the outputs
+/// the spenders receive, `spend_fraction` are picked evenly and each is spent
+/// in the block after the one it was received in. Outputs picked in the last
+/// block remain unspent.
+///
+/// Returns the spent outputs, ordered by block.
+///
+/// # Arguments
+/// * `num_random_recipients` - Number of random recipients to create.
+/// * `spenders` - Accounts receiving outputs on their default subaddress.
+/// * `num_blocks` - Number of blocks to create in the ledger_db.
+/// * `spend_fraction` - Fraction of the spenders' outputs to spend, from 0 to
+///   1.
+/// * `logger`
+/// * `rng`
+pub fn get_test_databases_with_spends(
+    num_random_recipients: u32,
+    spenders: &[AccountKey],
+    num_blocks: usize,
+    spend_fraction: f64,
+    logger: Logger,
+    mut rng: &mut (impl CryptoRng + RngCore),
+) -> (LedgerDB, Database, Vec<SpentTestOutput>) {
+    let mut public_addresses: Vec<PublicAddress> = (0..num_random_recipients)
+        .map(|_i| mc_account_keys::AccountKey::random(&mut rng).default_subaddress())
+        .collect();
+
+    public_addresses.extend(spenders.iter().map(|spender| spender.default_subaddress()));
+
+    let (mut ledger_db, mobilecoind_db) = create_test_databases(logger);
+
+    let mut spent_outputs = Vec::new();
+    // Outputs picked in the previous block, to be spent in the next one.
+    let mut picked_outputs: Vec<(TxOut, KeyImage, u64)> = Vec::new();
+    let mut num_spender_outputs = 0u64;
+    for block_index in 0..num_blocks as u64 {
+        let mut key_images: Vec<KeyImage> = picked_outputs
+            .iter()
+            .map(|(_tx_out, key_image, _received_block)| *key_image)
+            .collect();
+        if block_index > 0 && key_images.is_empty() {
+            key_images.push(KeyImage::from(rng.next_u64()));
+        }
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &public_addresses,
+            DEFAULT_PER_RECIPIENT_AMOUNT,
+            &key_images,
+            rng,
+        );
+        spent_outputs.extend(picked_outputs.drain(..).map(
+            |(tx_out, key_image, received_block)| SpentTestOutput {
+                tx_out,
+                key_image,
+                received_block,
+                spent_block: block_index,
+            },
+        ));
+
+        for spender in spenders {
+            for tx_out in get_block_tx_outs_for_account(&ledger_db, block_index, spender) {
+                // Pick an output whenever the running count of outputs to spend
+                // reaches a whole number.
+                num_spender_outputs += 1;
+                if (num_spender_outputs as f64 * spend_fraction).floor()
+                    <= ((num_spender_outputs - 1) as f64 * spend_fraction).floor()
+                {
+                    continue;
+                }
+
+                let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
+                let onetime_private_key = recover_onetime_private_key(
+                    &tx_public_key,
+                    spender.view_private_key(),
+                    &spender.default_subaddress_spend_private(),
+                );
+                picked_outputs.push((tx_out, KeyImage::from(&onetime_private_key), block_index));
+            }
+        }
+    }
+
+    (ledger_db, mobilecoind_db, spent_outputs)
+}
+
+/// Creates an empty LedgerDB and mobilecoind Database, in temporary
+/// directories.
+fn create_test_databases(logger: Logger) -> (LedgerDB, Database) {
+    // Note that TempDir manages uniqueness by constructing paths
+    // like: /tmp/ledger_db.tvF0XHTKsilx
+    let ledger_db_tmp = TempDir::new("ledger_db").expect("Could not make tempdir for ledger db");
+    let ledger_db_path = ledger_db_tmp
+        .path()
+        .to_str()
+        .expect("Could not get path as string");
+    let mobilecoind_db_tmp =
+        TempDir::new("mobilecoind_db").expect("Could not make tempdir for mobilecoind db");
+    let mobilecoind_db_path = mobilecoind_db_tmp
+        .path()
+        .to_str()
+        .expect("Could not get path as string");
+
+    let ledger_db = generate_ledger_db(&ledger_db_path);
+
     let mobilecoind_db = Database::new(mobilecoind_db_path.to_string(), logger)
         .expect("failed creating new mobilecoind db");
 
@@ -141,7 +296,7 @@ fn generate_ledger_db(path: &str) -> LedgerDB {
 }
 
 /// Adds a block containing one txo for each provided recipient and returns new
-/// block height.
+/// block height. The txos are sorted into canonical order.
 ///
 /// # Arguments
 /// * `ledger_db` - Ledger database instance.
@@ -155,6 +310,27 @@ pub fn add_block_to_ledger_db(
     output_value: u64,
     key_images: &[KeyImage],
     rng: &mut (impl CryptoRng + RngCore),
+) -> u64 {
+    add_tagged_block_to_ledger_db(ledger_db, recipients, output_value, key_images, None, rng)
+}
+
+/// Like `add_block_to_ledger_db`, but the block can be tagged so that it can
+/// later be found with `find_block_by_tag`.
+///
+/// # Arguments
+/// * `ledger_db` - Ledger database instance.
+/// * `recipients` - Recipients of outputs.
+/// * `output_value` - The amount each recipient will get.
+/// * `key_images` - Key images to include in the block.
+/// * `tag` - Optional tag identifying the block.
+/// * `rng` - Random number generator.
+pub fn add_tagged_block_to_ledger_db(
+    ledger_db: &mut LedgerDB,
+    recipients: &[PublicAddress],
+    output_value: u64,
+    key_images: &[KeyImage],
+    tag: Option<&str>,
+    rng: &mut (impl CryptoRng + RngCore),
 ) -> u64 {
     let outputs: Vec<_> = recipients
         .iter()
@@ -170,7 +346,8 @@ pub fn add_block_to_ledger_db(
         })
         .collect();
 
-    let block_contents = BlockContents::new(key_images.to_vec(), outputs.clone());
+    let mut block_contents = BlockContents::new(key_images.to_vec(), outputs);
+    block_contents.sort_outputs();
 
     let num_blocks = ledger_db.num_blocks().expect("failed to get block height");
 
@@ -182,16 +359,39 @@ pub fn add_block_to_ledger_db(
         new_block =
             Block::new_with_parent(BLOCK_VERSION, &parent, &Default::default(), &block_contents);
     } else {
-        new_block = Block::new_origin_block(&outputs);
+        new_block = Block::new_origin_block(&block_contents.outputs);
     }
 
     ledger_db
         .append_block(&new_block, &block_contents, None)
         .expect("failed writing initial transactions");
+    tag_block(tag, new_block.index);
 
     ledger_db.num_blocks().expect("failed to get block height")
 }
 
+/// Get the txos of a block that were sent to any subaddress of the given
+/// account. Since the txos of a block are in canonical order, this is how tests
+/// find the txos of a recipient.
+pub fn get_block_tx_outs_for_account(
+    ledger_db: &LedgerDB,
+    block_index: u64,
+    account_key: &AccountKey,
+) -> Vec<TxOut> {
+    ledger_db
+        .get_block_contents(block_index)
+        .expect("failed to get block contents")
+        .outputs
+        .into_iter()
+        .filter(|tx_out| {
+            let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
+            let shared_secret =
+                get_tx_out_shared_secret(account_key.view_private_key(), &tx_public_key);
+            tx_out.amount.get_value(&shared_secret).is_ok()
+        })
+        .collect()
+}
+
 /// Adds a block containing the given TXOs.
 ///
 /// # Arguments
@@ -202,7 +402,25 @@ pub fn add_txos_to_ledger_db(
     outputs: &Vec<TxOut>,
     rng: &mut (impl CryptoRng + RngCore),
 ) -> u64 {
-    let block_contents = BlockContents::new(vec![KeyImage::from(rng.next_u64())], outputs.clone());
+    add_tagged_txos_to_ledger_db(ledger_db, outputs, None, rng)
+}
+
+/// Like `add_txos_to_ledger_db`, but the block can be tagged so that it can
+/// later be found with `find_block_by_tag`.
+///
+/// # Arguments
+/// * `ledger_db`
+/// * `outputs` - TXOs to add to ledger.
+/// * `tag` - Optional tag identifying the block.
+pub fn add_tagged_txos_to_ledger_db(
+    ledger_db: &mut LedgerDB,
+    outputs: &[TxOut],
+    tag: Option<&str>,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> u64 {
+    let mut block_contents =
+        BlockContents::new(vec![KeyImage::from(rng.next_u64())], outputs.to_vec());
+    block_contents.sort_outputs();
 
     let num_blocks = ledger_db.num_blocks().expect("failed to get block height");
 
@@ -214,16 +432,75 @@ pub fn add_txos_to_ledger_db(
         new_block =
             Block::new_with_parent(BLOCK_VERSION, &parent, &Default::default(), &block_contents);
     } else {
-        new_block = Block::new_origin_block(&outputs);
+        new_block = Block::new_origin_block(&block_contents.outputs);
     }
 
     ledger_db
         .append_block(&new_block, &block_contents, None)
         .expect("failed writing initial transactions");
+    tag_block(tag, new_block.index);
 
     ledger_db.num_blocks().expect("failed to get block height")
 }
 
+/// Creates a fog-enabled account, along with the private key of the fog ingest
+/// server that hints for it are encrypted to.
+pub fn get_fog_enabled_account(
+    rng: &mut (impl CryptoRng + RngCore),
+) -> (AccountKey, RistrettoPrivate) {
+    let account_key = AccountKey::random_with_fog(rng);
+    let fog_private_key = RistrettoPrivate::from_random(rng);
+    (account_key, fog_private_key)
+}
+
+/// Creates a TxOut for `recipient`. If `fog_pubkey` is provided the TxOut
+/// carries a fog hint for the recipient encrypted to it, the way the
+/// transaction builder does for fog recipients. Otherwise it carries a fake
+/// hint, as for recipients without fog.
+pub fn create_tx_out_with_fog_hint(
+    value: u64,
+    recipient: &PublicAddress,
+    fog_pubkey: Option<&RistrettoPublic>,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> TxOut {
+    let hint = match fog_pubkey {
+        Some(fog_pubkey) => FogHint::from(recipient).encrypt(fog_pubkey, rng),
+        None => EncryptedFogHint::fake_onetime_hint(rng),
+    };
+    TxOut::new(value, recipient, &RistrettoPrivate::from_random(rng), hint).unwrap()
+}
+
+/// Tries to decrypt the fog hint of a TxOut with a fog ingest private key.
+/// Returns None if the hint was not encrypted to that key.
+pub fn decrypt_fog_hint(tx_out: &TxOut, fog_private_key: &RistrettoPrivate) -> Option<FogHint> {
+    // The placeholder value is only overwritten if decryption succeeds.
+    let mut fog_hint = FogHint::new(RistrettoPublic::from(fog_private_key));
+    if bool::from(FogHint::ct_decrypt(
+        fog_private_key,
+        &tx_out.e_fog_hint,
+        &mut fog_hint,
+    )) {
+        Some(fog_hint)
+    } else {
+        None
+    }
+}
+
+/// Asserts that the fog hint of a TxOut decrypts with `fog_private_key` and
+/// identifies `expected_recipient`.
+pub fn assert_fog_hint_decrypts_to(
+    tx_out: &TxOut,
+    fog_private_key: &RistrettoPrivate,
+    expected_recipient: &PublicAddress,
+) {
+    let fog_hint =
+        decrypt_fog_hint(tx_out, fog_private_key).expect("fog hint could not be decrypted");
+    assert_eq!(
+        fog_hint.get_view_pubkey(),
+        &CompressedRistrettoPublic::from(expected_recipient.view_public_key())
+    );
+}
+
 pub fn get_free_port() -> u16 {
     static PORT_NR: AtomicUsize = AtomicUsize::new(0);
     PORT_NR.fetch_add(1, SeqCst) as u16 + 30100
@@ -235,23 +512,57 @@ pub fn setup_server<FPR: FogPubkeyResolver + Default + Send + Sync + 'static>(
     mobilecoind_db: Database,
     watcher_db: Option<WatcherDB>,
     fog_resolver_factory: Option<Arc<dyn Fn(&[FogUri]) -> Result<FPR, String> + Send + Sync>>,
+    config: ServiceConfig,
     uri: &MobilecoindUri,
 ) -> (
     Service,
     ConnectionManager<MockBlockchainConnection<LedgerDB>>,
 ) {
-    let peer1 = MockBlockchainConnection::new(test_client_uri(1), ledger_db.clone(), 0);
-    let peer2 = MockBlockchainConnection::new(test_client_uri(2), ledger_db.clone(), 0);
+    setup_server_with_connections(
+        logger,
+        ledger_db,
+        mobilecoind_db,
+        watcher_db,
+        fog_resolver_factory,
+        config,
+        uri,
+        |ledger_db| {
+            vec![
+                MockBlockchainConnection::new(test_client_uri(1), ledger_db.clone(), 0, None),
+                MockBlockchainConnection::new(test_client_uri(2), ledger_db.clone(), 0, None),
+            ]
+        },
+    )
+}
+
+/// Same as `setup_server`, except that the connections to consensus peers are
+/// created by `connection_factory`. This allows tests to substitute their own
+/// connections, for example ones wrapped in TLS. All peers are part of the
+/// quorum set.
+pub fn setup_server_with_connections<
+    FPR: FogPubkeyResolver + Default + Send + Sync + 'static,
+    C: BlockchainConnection + UserTxConnection + 'static,
+>(
+    logger: Logger,
+    ledger_db: LedgerDB,
+    mobilecoind_db: Database,
+    watcher_db: Option<WatcherDB>,
+    fog_resolver_factory: Option<Arc<dyn Fn(&[FogUri]) -> Result<FPR, String> + Send + Sync>>,
+    config: ServiceConfig,
+    uri: &MobilecoindUri,
+    connection_factory: impl FnOnce(&LedgerDB) -> Vec<C>,
+) -> (Service, ConnectionManager<C>) {
+    let peers = connection_factory(&ledger_db);
 
     let quorum_set = QuorumSet::new_with_node_ids(
-        2,
-        vec![
-            peer1.uri().responder_id().unwrap(),
-            peer2.uri().responder_id().unwrap(),
-        ],
+        peers.len() as u32,
+        peers
+            .iter()
+            .map(|peer| peer.uri().responder_id().unwrap())
+            .collect(),
     );
 
-    let conn_manager = ConnectionManager::new(vec![peer1, peer2], logger.clone());
+    let conn_manager = ConnectionManager::new(peers, logger.clone());
 
     let network_state = Arc::new(RwLock::new(PollingNetworkState::new(
         quorum_set,
@@ -269,6 +580,7 @@ pub fn setup_server<FPR: FogPubkeyResolver + Default + Send + Sync + 'static>(
         mobilecoind_db.clone(),
         conn_manager.clone(),
         fog_resolver_factory.unwrap_or(Arc::new(|_| Ok(FPR::default()))),
+        0,
         logger.clone(),
     );
 
@@ -280,22 +592,99 @@ pub fn setup_server<FPR: FogPubkeyResolver + Default + Send + Sync + 'static>(
         network_state,
         uri,
         None,
+        config,
         logger,
     );
 
     (service, conn_manager)
 }
 
-pub fn setup_client(uri: &MobilecoindUri, logger: &Logger) -> MobilecoindApiClient {
+/// Connects a client to a server started by `setup_server`. Pass the same
+/// `max_message_size` that the server uses, so that the client accepts every
+/// response the server is willing to send.
+pub fn setup_client(
+    uri: &MobilecoindUri,
+    max_message_size: Option<usize>,
+    logger: &Logger,
+) -> MobilecoindApiClient {
     let env = Arc::new(
         EnvBuilder::new()
             .name_prefix("gRPC-mobilecoind-tests")
             .build(),
     );
-    let ch = ChannelBuilder::new(env).connect_to_uri(uri, logger);
+    let mut ch = ChannelBuilder::new(env);
+    if let Some(max_message_size) = max_message_size {
+        let max_message_len = max_message_size.min(i32::MAX as usize) as i32;
+        ch = ch
+            .max_receive_message_len(max_message_len)
+            .max_send_message_len(max_message_len);
+    }
+    let ch = ch.connect_to_uri(uri, logger);
     MobilecoindApiClient::new(ch)
 }
 
+/// An asynchronous wrapper around `MobilecoindApiClient`, exposing futures for
+/// the commonly used RPCs so that tests can drive several calls concurrently.
+/// All calls go through the gRPC channel of the wrapped client.
+#[derive(Clone)]
+pub struct AsyncMobilecoindClient {
+    client: MobilecoindApiClient,
+}
+
+impl AsyncMobilecoindClient {
+    /// Wrap an existing client. The wrapper shares the client's channel.
+    pub fn new(client: &MobilecoindApiClient) -> Self {
+        Self {
+            client: client.clone(),
+        }
+    }
+
+    pub fn get_balance(
+        &self,
+        request: &GetBalanceRequest,
+    ) -> impl Future<Output = grpcio::Result<GetBalanceResponse>> {
+        let receiver = self.client.get_balance_async(request);
+        async move { receiver?.await }
+    }
+
+    pub fn get_monitor_status(
+        &self,
+        request: &GetMonitorStatusRequest,
+    ) -> impl Future<Output = grpcio::Result<GetMonitorStatusResponse>> {
+        let receiver = self.client.get_monitor_status_async(request);
+        async move { receiver?.await }
+    }
+
+    pub fn get_unspent_tx_out_list(
+        &self,
+        request: &GetUnspentTxOutListRequest,
+    ) -> impl Future<Output = grpcio::Result<GetUnspentTxOutListResponse>> {
+        let receiver = self.client.get_unspent_tx_out_list_async(request);
+        async move { receiver?.await }
+    }
+
+    pub fn get_ledger_info(&self) -> impl Future<Output = grpcio::Result<GetLedgerInfoResponse>> {
+        let receiver = self.client.get_ledger_info_async(&Empty::new());
+        async move { receiver?.await }
+    }
+
+    pub fn submit_tx(
+        &self,
+        request: &SubmitTxRequest,
+    ) -> impl Future<Output = grpcio::Result<SubmitTxResponse>> {
+        let receiver = self.client.submit_tx_async(request);
+        async move { receiver?.await }
+    }
+
+    pub fn send_payment(
+        &self,
+        request: &SendPaymentRequest,
+    ) -> impl Future<Output = grpcio::Result<SendPaymentResponse>> {
+        let receiver = self.client.send_payment_async(request);
+        async move { receiver?.await }
+    }
+}
+
 /// Create a ready test environment.
 /// Recipients can be randomly gernerated or passed in.
 /// The ledger has GET_TESTING_ENVIRONMENT_NUM_BLOCKS blocks. Each block has one
@@ -342,10 +731,11 @@ pub fn get_testing_environment(
         mobilecoind_db.clone(),
         None,
         None,
+        ServiceConfig::default(),
         &uri,
     );
     log::debug!(logger, "Setting up client {:?}", port);
-    let client = setup_client(&uri, &logger);
+    let client = setup_client(&uri, None, &logger);
 
     for data in monitors {
         mobilecoind_db
@@ -364,7 +754,8 @@ pub fn get_testing_environment(
     )
 }
 
-/// Waits until all monitors are current with the last block of the ledger DB
+/// Waits until all monitors that are not paused are current with the last block
+/// of the ledger DB
 ///
 /// # Arguments
 /// * `mobilecoind_db` - Database instance
@@ -384,7 +775,7 @@ pub fn wait_for_monitors(mobilecoind_db: &Database, ledger_db: &LedgerDB, logger
         monitor_map_len = monitor_map.len();
 
         for (i, (_monitor_id, data)) in monitor_map.iter().enumerate() {
-            if data.next_block < num_blocks {
+            if !data.paused && data.next_block < num_blocks {
                 log::info!(
                     logger,
                     "waiting for monitor {}/{}: {} of {} blocks processed",
@@ -411,3 +802,154 @@ pub fn wait_for_monitors(mobilecoind_db: &Database, ledger_db: &LedgerDB, logger
         );
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        scan_throughput::ScanThroughputMeter,
+        sync::{BlockProcessingRetry, SyncThread},
+    };
+    use mc_common::{logger::test_with_logger, HashSet};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    // Outputs spent by get_test_databases_with_spends should have their key
+    // images in the ledger, and monitors should see them as spent.
+    #[test_with_logger]
+    fn test_get_test_databases_with_spends(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let spenders = vec![AccountKey::random(&mut rng), AccountKey::random(&mut rng)];
+
+        let (ledger_db, mobilecoind_db, spent_outputs) =
+            get_test_databases_with_spends(3, &spenders, 6, 0.5, logger.clone(), &mut rng);
+        assert_eq!(ledger_db.num_blocks().unwrap(), 6);
+
+        // Half of the 10 outputs received before the last block are spent.
+        assert_eq!(spent_outputs.len(), 5);
+        for spent_output in spent_outputs.iter() {
+            assert_eq!(spent_output.spent_block, spent_output.received_block + 1);
+            assert_eq!(
+                ledger_db.check_key_image(&spent_output.key_image).unwrap(),
+                Some(spent_output.spent_block)
+            );
+        }
+
+        let mut sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            None,
+            BlockProcessingRetry::default(),
+            ScanThroughputMeter::default(),
+            logger.clone(),
+        );
+        let monitor_ids: Vec<MonitorId> = spenders
+            .iter()
+            .map(|spender| {
+                let data = MonitorData::new(
+                    spender.clone(),
+                    DEFAULT_SUBADDRESS_INDEX, // first_subaddress
+                    1,                        // num_subaddresses
+                    0,                        // first_block
+                    "",                       // name
+                )
+                .unwrap();
+                mobilecoind_db.add_monitor(&data).unwrap()
+            })
+            .collect();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        sync_thread.stop();
+
+        // Each spender received 6 outputs, and the spent ones are gone.
+        let spent_key_images: HashSet<KeyImage> = spent_outputs
+            .iter()
+            .map(|spent_output| spent_output.key_image)
+            .collect();
+        let mut num_utxos = 0;
+        for monitor_id in monitor_ids.iter() {
+            let utxos = mobilecoind_db
+                .get_utxos_for_subaddress(monitor_id, DEFAULT_SUBADDRESS_INDEX)
+                .unwrap();
+            assert!(utxos
+                .iter()
+                .all(|utxo| !spent_key_images.contains(&utxo.key_image)));
+            num_utxos += utxos.len();
+        }
+        assert_eq!(num_utxos, 2 * 6 - spent_outputs.len());
+    }
+
+    // A fog hint encrypted to a fog recipient's ingest key should decrypt to that
+    // recipient.
+    #[test]
+    fn test_fog_hint_round_trip() {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let (account_key, fog_private_key) = get_fog_enabled_account(&mut rng);
+        let recipient = account_key.default_subaddress();
+        assert!(recipient.fog_report_url().is_some());
+
+        let tx_out = create_tx_out_with_fog_hint(
+            10,
+            &recipient,
+            Some(&RistrettoPublic::from(&fog_private_key)),
+            &mut rng,
+        );
+        assert_fog_hint_decrypts_to(&tx_out, &fog_private_key, &recipient);
+
+        // A different ingest key cannot decrypt the hint.
+        let other_fog_private_key = RistrettoPrivate::from_random(&mut rng);
+        assert!(decrypt_fog_hint(&tx_out, &other_fog_private_key).is_none());
+    }
+
+    // The fake hint given to a recipient without fog should not decrypt.
+    #[test]
+    fn test_fog_hint_for_non_fog_recipient() {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let (_account_key, fog_private_key) = get_fog_enabled_account(&mut rng);
+        let recipient = AccountKey::random(&mut rng).default_subaddress();
+
+        let tx_out = create_tx_out_with_fog_hint(10, &recipient, None, &mut rng);
+        assert!(decrypt_fog_hint(&tx_out, &fog_private_key).is_none());
+    }
+
+    // Tagged blocks should be found by their tag, while untagged ones are not
+    // recorded.
+    #[test]
+    fn test_find_block_by_tag() {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let recipient = AccountKey::random(&mut rng).default_subaddress();
+        let ledger_db_tmp = TempDir::new("ledger_db").unwrap();
+        let mut ledger_db = generate_ledger_db(ledger_db_tmp.path().to_str().unwrap());
+
+        add_tagged_block_to_ledger_db(
+            &mut ledger_db,
+            &[recipient.clone()],
+            10,
+            &[],
+            Some("origin"),
+            &mut rng,
+        );
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &[recipient.clone()],
+            10,
+            &[KeyImage::from(1)],
+            &mut rng,
+        );
+        let tx_out = create_tx_out_with_fog_hint(10, &recipient, None, &mut rng);
+        add_tagged_txos_to_ledger_db(&mut ledger_db, &[tx_out], Some("payment"), &mut rng);
+
+        assert_eq!(find_block_by_tag("origin"), Some(0));
+        assert_eq!(find_block_by_tag("payment"), Some(2));
+        assert_eq!(find_block_by_tag("refund"), None);
+
+        // Reusing a tag points it to the latest block.
+        add_tagged_block_to_ledger_db(
+            &mut ledger_db,
+            &[recipient],
+            10,
+            &[KeyImage::from(2)],
+            Some("payment"),
+            &mut rng,
+        );
+        assert_eq!(find_block_by_tag("payment"), Some(3));
+    }
+}
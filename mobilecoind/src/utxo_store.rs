@@ -53,6 +53,11 @@ pub struct UnspentTxOut {
     /// The tombstone block used when we attempted to spend the UTXO.
     #[prost(uint64, tag = "6")]
     pub attempted_spend_tombstone: u64,
+
+    /// Index of the block this TxOut was created in. Outputs stored before this
+    /// was recorded hold 0, and the API looks their block up in the ledger.
+    #[prost(uint64, tag = "7")]
+    pub received_block_index: u64,
 }
 
 /// Type used as the key in the utxo_id_to_utxo  database.
@@ -445,6 +450,7 @@ mod test {
                     value: idx,
                     attempted_spend_height: 0,
                     attempted_spend_tombstone: 0,
+                    received_block_index: ledger_db.get_block_index_by_tx_out_index(idx).unwrap(),
                 }
             })
             .collect();
@@ -5,6 +5,8 @@ use crate::{
     BlockContents, BlockContentsHash, BlockID,
 };
 use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+use displaydoc::Display;
 use mc_crypto_digestible::{DigestTranscript, Digestible, MerlinTranscript};
 use prost::Message;
 use serde::{Deserialize, Serialize};
@@ -12,6 +14,16 @@ use serde::{Deserialize, Serialize};
 /// The current block format version.
 pub const BLOCK_VERSION: u32 = 0;
 
+/// The block format versions this code supports.
+pub const SUPPORTED_BLOCK_VERSIONS: RangeInclusive<u32> = 0..=BLOCK_VERSION;
+
+#[derive(Clone, Debug, Display, Eq, PartialEq)]
+/// Block construction errors.
+pub enum BlockError {
+    /// Unsupported block version `{0}`, the highest supported version is `{1}`
+    UnsupportedVersion(u32, u32),
+}
+
 /// The index of a block in the blockchain.
 pub type BlockIndex = u64;
 
@@ -109,6 +121,60 @@ impl Block {
         )
     }
 
+    /// Like `new_with_parent`, but fails with `BlockError::UnsupportedVersion`
+    /// instead of creating a block whose version is not in
+    /// `SUPPORTED_BLOCK_VERSIONS`.
+    ///
+    /// # Arguments
+    /// * `version` - The block format version
+    /// * `parent` - The parent block
+    /// * `root_element` - The root element for membership proofs
+    /// * `block_contents - The Contents of the block.
+    pub fn try_new_with_parent(
+        version: u32,
+        parent: &Block,
+        root_element: &TxOutMembershipElement,
+        block_contents: &BlockContents,
+    ) -> Result<Self, BlockError> {
+        check_block_version(version)?;
+        Ok(Block::new_with_parent(
+            version,
+            parent,
+            root_element,
+            block_contents,
+        ))
+    }
+
+    /// Like `new`, but fails with `BlockError::UnsupportedVersion` instead of
+    /// creating a block whose version is not in `SUPPORTED_BLOCK_VERSIONS`.
+    ///
+    /// # Arguments
+    /// * `version` - The block format version.
+    /// * `parent_id` - `BlockID` of previous block in the blockchain.
+    /// * `index` - The index of this block in the blockchain.
+    /// * `cumulative_txo_count` - The cumulative txo count *including this
+    ///   block*
+    /// * `root_element` - The root element for membership proofs
+    /// * `block_contents` - Contents of the block.
+    pub fn try_new(
+        version: u32,
+        parent_id: &BlockID,
+        index: BlockIndex,
+        cumulative_txo_count: u64,
+        root_element: &TxOutMembershipElement,
+        block_contents: &BlockContents,
+    ) -> Result<Self, BlockError> {
+        check_block_version(version)?;
+        Ok(Block::new(
+            version,
+            parent_id,
+            index,
+            cumulative_txo_count,
+            root_element,
+            block_contents,
+        ))
+    }
+
     /// Creates a new `Block`.
     /// This low-level version doesn't require having the parent block in hand,
     /// and takes all needed metadata for the block header as input.
@@ -169,6 +235,18 @@ impl Block {
     }
 }
 
+/// Checks that a block version is in `SUPPORTED_BLOCK_VERSIONS`.
+fn check_block_version(version: u32) -> Result<(), BlockError> {
+    if SUPPORTED_BLOCK_VERSIONS.contains(&version) {
+        Ok(())
+    } else {
+        Err(BlockError::UnsupportedVersion(
+            version,
+            *SUPPORTED_BLOCK_VERSIONS.end(),
+        ))
+    }
+}
+
 /// Computes the BlockID by hashing the contents of a block.
 ///
 /// The identifier of a block is the result of hashing everything inside a block
@@ -201,7 +279,8 @@ mod block_tests {
     use crate::{
         membership_proofs::Range,
         tx::{TxOut, TxOutMembershipElement, TxOutMembershipHash},
-        Block, BlockContents, BlockContentsHash, BlockID, BLOCK_VERSION,
+        Block, BlockContents, BlockContentsHash, BlockError, BlockID, BLOCK_VERSION,
+        SUPPORTED_BLOCK_VERSIONS,
     };
     use alloc::vec::Vec;
     use core::convert::TryFrom;
@@ -253,6 +332,51 @@ mod block_tests {
         assert!(block.is_block_id_valid());
     }
 
+    #[test]
+    /// Blocks can be built with any supported version, but not with a version
+    /// beyond the supported range.
+    fn test_try_new_with_parent_rejects_unsupported_version() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let parent = get_block(&mut rng);
+        let block_contents = BlockContents::new(Vec::new(), Vec::new());
+
+        for version in SUPPORTED_BLOCK_VERSIONS {
+            let block =
+                Block::try_new_with_parent(version, &parent, &Default::default(), &block_contents)
+                    .unwrap();
+            assert_eq!(block.version, version);
+            assert!(block.is_block_id_valid());
+        }
+
+        let unsupported_version = *SUPPORTED_BLOCK_VERSIONS.end() + 1;
+        assert_eq!(
+            Block::try_new_with_parent(
+                unsupported_version,
+                &parent,
+                &Default::default(),
+                &block_contents,
+            ),
+            Err(BlockError::UnsupportedVersion(
+                unsupported_version,
+                BLOCK_VERSION
+            ))
+        );
+        assert_eq!(
+            Block::try_new(
+                unsupported_version,
+                &parent.id,
+                parent.index + 1,
+                parent.cumulative_txo_count,
+                &Default::default(),
+                &block_contents,
+            ),
+            Err(BlockError::UnsupportedVersion(
+                unsupported_version,
+                BLOCK_VERSION
+            ))
+        );
+    }
+
     #[test]
     /// The block ID should depend on the block version.
     fn test_block_id_includes_version() {
@@ -31,6 +31,21 @@ impl BlockContents {
         }
     }
 
+    /// Sort the outputs into their canonical order, by public key. Blocks list
+    /// their outputs in this order, so that building a block from the same
+    /// transactions always yields the same contents hash. This must be done
+    /// before the contents hash is computed.
+    pub fn sort_outputs(&mut self) {
+        self.outputs.sort_by(|a, b| a.public_key.cmp(&b.public_key));
+    }
+
+    /// Whether the outputs are in their canonical order, by public key.
+    pub fn has_sorted_outputs(&self) -> bool {
+        self.outputs
+            .windows(2)
+            .all(|pair| pair[0].public_key <= pair[1].public_key)
+    }
+
     /// The Merlin digest of `self`.
     pub fn hash(&self) -> BlockContentsHash {
         BlockContentsHash(self.digest32::<MerlinTranscript>(b"block_contents"))
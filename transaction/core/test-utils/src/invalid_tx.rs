@@ -0,0 +1,118 @@
+// Copyright (c) 2018-2021 The MobileCoin Foundation
+
+//! Transactions with deliberate flaws, for testing that they are rejected.
+//!
+//! Each function builds a transaction that is valid except for a single flaw,
+//! so that a test can check the specific rejection the flaw causes. None of
+//! these transactions should ever be accepted by consensus; they are meant for
+//! negative tests only.
+
+use crate::{create_transaction, AccountKey, KeyImage, PublicAddress, DEFAULT_SUBADDRESS_INDEX};
+use core::convert::TryFrom;
+use mc_crypto_keys::RistrettoPublic;
+use mc_crypto_rand::{CryptoRng, RngCore};
+use mc_ledger_db::Ledger;
+use mc_transaction_core::{
+    onetime_keys::recover_onetime_private_key,
+    ring_signature::CurveScalar,
+    tx::{Tx, TxOut},
+    Amount, BlockIndex,
+};
+use mc_util_from_random::FromRandom;
+
+/// Creates a transaction whose first ring signature has been tampered with.
+/// Consensus rejects it with `InvalidTransactionSignature(InvalidSignature)`.
+///
+/// # Arguments:
+/// * `ledger` - A ledger containing `tx_out`.
+/// * `tx_out` - The TxOut that will be spent.
+/// * `sender` - The owner of `tx_out`.
+/// * `recipient` - The recipient of the new transaction.
+/// * `tombstone_block` - The tombstone block for the new transaction.
+/// * `rng` - The randomness used by this function
+pub fn create_transaction_with_tampered_signature<L: Ledger, R: RngCore + CryptoRng>(
+    ledger: &mut L,
+    tx_out: &TxOut,
+    sender: &AccountKey,
+    recipient: &PublicAddress,
+    tombstone_block: BlockIndex,
+    rng: &mut R,
+) -> Tx {
+    let mut tx = create_transaction(ledger, tx_out, sender, recipient, tombstone_block, rng);
+    tx.signature.ring_signatures[0].c_zero = CurveScalar::from_random(rng);
+    tx
+}
+
+/// Creates a transaction whose first output carries an amount commitment to a
+/// different value than the one that was signed. Consensus rejects it with
+/// `InvalidTransactionSignature(RangeProofError)`, since the range proof no
+/// longer covers the output's commitment.
+///
+/// # Arguments:
+/// * `ledger` - A ledger containing `tx_out`.
+/// * `tx_out` - The TxOut that will be spent.
+/// * `sender` - The owner of `tx_out`.
+/// * `recipient` - The recipient of the new transaction.
+/// * `tombstone_block` - The tombstone block for the new transaction.
+/// * `rng` - The randomness used by this function
+pub fn create_transaction_with_mismatched_commitment<L: Ledger, R: RngCore + CryptoRng>(
+    ledger: &mut L,
+    tx_out: &TxOut,
+    sender: &AccountKey,
+    recipient: &PublicAddress,
+    tombstone_block: BlockIndex,
+    rng: &mut R,
+) -> Tx {
+    let mut tx = create_transaction(ledger, tx_out, sender, recipient, tombstone_block, rng);
+    tx.prefix.outputs[0].amount =
+        Amount::new(rng.next_u64(), &RistrettoPublic::from_random(rng)).unwrap();
+    tx
+}
+
+/// Creates a transaction spending a TxOut of `sender` whose key image is
+/// already in the ledger. Consensus rejects it with `ContainsSpentKeyImage`.
+///
+/// Panics if `sender` has no spent TxOuts in the ledger.
+///
+/// # Arguments:
+/// * `ledger` - A ledger containing a TxOut spent by `sender`.
+/// * `sender` - The owner of the spent TxOut.
+/// * `recipient` - The recipient of the new transaction.
+/// * `tombstone_block` - The tombstone block for the new transaction.
+/// * `rng` - The randomness used by this function
+pub fn create_transaction_reusing_key_image<L: Ledger, R: RngCore + CryptoRng>(
+    ledger: &mut L,
+    sender: &AccountKey,
+    recipient: &PublicAddress,
+    tombstone_block: BlockIndex,
+    rng: &mut R,
+) -> Tx {
+    let spent_tx_out = find_spent_tx_out(ledger, sender).expect("sender has no spent TxOuts");
+    create_transaction(
+        ledger,
+        &spent_tx_out,
+        sender,
+        recipient,
+        tombstone_block,
+        rng,
+    )
+}
+
+/// Finds a TxOut owned by `account_key` whose key image is in the ledger.
+fn find_spent_tx_out<L: Ledger>(ledger: &L, account_key: &AccountKey) -> Option<TxOut> {
+    let spend_private_key = account_key.subaddress_spend_private(DEFAULT_SUBADDRESS_INDEX);
+    let num_blocks = ledger.num_blocks().unwrap();
+    (0..num_blocks)
+        .flat_map(|block_index| ledger.get_block_contents(block_index).unwrap().outputs)
+        .find(|tx_out| {
+            let tx_out_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
+            let onetime_private_key = recover_onetime_private_key(
+                &tx_out_public_key,
+                account_key.view_private_key(),
+                &spend_private_key,
+            );
+            ledger
+                .contains_key_image(&KeyImage::from(&onetime_private_key))
+                .unwrap()
+        })
+}
@@ -1,5 +1,12 @@
 // Copyright (c) 2018-2021 The MobileCoin Foundation
 
+mod invalid_tx;
+
+pub use invalid_tx::{
+    create_transaction_reusing_key_image, create_transaction_with_mismatched_commitment,
+    create_transaction_with_tampered_signature,
+};
+
 use core::convert::TryFrom;
 pub use mc_account_keys::{AccountKey, PublicAddress, ViewKey, DEFAULT_SUBADDRESS_INDEX};
 use mc_crypto_keys::{RistrettoPrivate, RistrettoPublic};
@@ -30,6 +30,9 @@ pub enum TxBuilderError {
     #[fail(display = "No inputs")]
     NoInputs,
 
+    #[fail(display = "Outputs must have a non-zero value")]
+    ZeroValueOutput,
+
     #[fail(display = "Fog public key error: {}", _0)]
     FogPublicKey(FogPubkeyError),
 
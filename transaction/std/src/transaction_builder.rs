@@ -102,6 +102,10 @@ impl<FPR: FogPubkeyResolver> TransactionBuilder<FPR> {
     /// * `recipient` - The recipient's public address
     /// * `fog_hint_address` - The public address used to create the fog hint
     /// * `rng` - RNG used to generate blinding for commitment
+    ///
+    /// Outputs with a value of zero are rejected, since they have no use and
+    /// only take up space in the ledger. This does not apply to the fee
+    /// output, which is minted by consensus rather than by the builder.
     pub fn add_output_with_fog_hint_address<RNG: CryptoRng + RngCore>(
         &mut self,
         value: u64,
@@ -109,6 +113,10 @@ impl<FPR: FogPubkeyResolver> TransactionBuilder<FPR> {
         fog_hint_address: &PublicAddress,
         rng: &mut RNG,
     ) -> Result<(TxOut, TxOutConfirmationNumber), TxBuilderError> {
+        if value == 0 {
+            return Err(TxBuilderError::ZeroValueOutput);
+        }
+
         let (hint, pubkey_expiry) = create_fog_hint(fog_hint_address, &self.fog_resolver, rng)?;
         let (tx_out, shared_secret) = create_output_with_fog_hint(value, recipient, hint, rng)?;
 
@@ -662,6 +670,109 @@ pub mod transaction_builder_tests {
         }
     }
 
+    #[test]
+    // Each output should get a fog hint for its own recipient: an encrypted hint
+    // for a fog recipient, and a fake hint for a recipient without fog.
+    fn test_fog_hints_for_fog_and_non_fog_recipients() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let fog_recipient = AccountKey::random_with_fog(&mut rng);
+        let non_fog_recipient = AccountKey::random(&mut rng);
+        let ingest_private_key = RistrettoPrivate::from_random(&mut rng);
+        let value = 1475 * MILLIMOB_TO_PICOMOB;
+        let fog_value = 1000 * MILLIMOB_TO_PICOMOB;
+
+        let fog_resolver = MockFogResolver(btreemap! {
+                            fog_recipient
+                    .default_subaddress()
+                    .fog_report_url()
+                    .unwrap()
+                    .to_string()
+            =>
+                FullyValidatedFogPubkey {
+                    pubkey: RistrettoPublic::from(&ingest_private_key),
+                    pubkey_expiry: 1000,
+                },
+        });
+
+        let mut transaction_builder = TransactionBuilder::new(fog_resolver);
+
+        let input_credentials = get_input_credentials(&sender, value, &mut rng);
+        transaction_builder.add_input(input_credentials);
+
+        transaction_builder
+            .add_output(fog_value, &fog_recipient.default_subaddress(), &mut rng)
+            .unwrap();
+        transaction_builder
+            .add_output(
+                value - fog_value - MINIMUM_FEE,
+                &non_fog_recipient.default_subaddress(),
+                &mut rng,
+            )
+            .unwrap();
+
+        let tx = transaction_builder.build(&mut rng).unwrap();
+
+        // The transaction should have two outputs.
+        assert_eq!(tx.prefix.outputs.len(), 2);
+
+        let find_output = |account_key: &AccountKey| -> &TxOut {
+            tx.prefix
+                .outputs
+                .iter()
+                .find(|output| {
+                    view_key_matches_output(
+                        &account_key.view_key(),
+                        &RistrettoPublic::try_from(&output.target_key).unwrap(),
+                        &RistrettoPublic::try_from(&output.public_key).unwrap(),
+                    )
+                })
+                .expect("output not found")
+        };
+        let fog_output = find_output(&fog_recipient);
+        let non_fog_output = find_output(&non_fog_recipient);
+
+        // The fog recipient's output should carry a hint that decrypts to the
+        // recipient's view public key.
+        {
+            assert_ne!(fog_output.e_fog_hint, EncryptedFogHint::default());
+
+            let mut output_fog_hint = FogHint::new(RistrettoPublic::from_random(&mut rng));
+            assert!(bool::from(FogHint::ct_decrypt(
+                &ingest_private_key,
+                &fog_output.e_fog_hint,
+                &mut output_fog_hint
+            )));
+            assert_eq!(
+                output_fog_hint.get_view_pubkey(),
+                &CompressedRistrettoPublic::from(
+                    fog_recipient.default_subaddress().view_public_key()
+                )
+            );
+        }
+
+        // The non-fog recipient's output should carry a well-formed fake hint, that
+        // the fog ingest key cannot decrypt.
+        {
+            assert_ne!(non_fog_output.e_fog_hint, EncryptedFogHint::default());
+            assert_ne!(non_fog_output.e_fog_hint, fog_output.e_fog_hint);
+
+            let hint_bytes = mc_util_serial::encode(&non_fog_output.e_fog_hint);
+            let decoded: EncryptedFogHint = mc_util_serial::decode(&hint_bytes).unwrap();
+            assert_eq!(decoded, non_fog_output.e_fog_hint);
+
+            let mut output_fog_hint = FogHint::new(RistrettoPublic::from_random(&mut rng));
+            assert!(!bool::from(FogHint::ct_decrypt(
+                &ingest_private_key,
+                &non_fog_output.e_fog_hint,
+                &mut output_fog_hint
+            )));
+        }
+
+        // The transaction should have a valid signature.
+        assert!(validate_signature(&tx, &mut rng).is_ok());
+    }
+
     #[test]
     #[ignore]
     // `build` should return an error if the inputs contain rings of different
@@ -723,6 +834,34 @@ pub mod transaction_builder_tests {
         }
     }
 
+    #[test]
+    // `add_output` should reject an output with a value of zero.
+    fn test_add_output_rejects_zero_value() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let recipient = AccountKey::random(&mut rng);
+        let value = 1475 * MILLIMOB_TO_PICOMOB;
+
+        let mut transaction_builder = TransactionBuilder::new(MockFogResolver::default());
+        transaction_builder.add_input(get_input_credentials(&sender, value, &mut rng));
+
+        match transaction_builder.add_output(0, &recipient.default_subaddress(), &mut rng) {
+            Err(TxBuilderError::ZeroValueOutput) => {} // Expected.
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // The rejected output should not be part of the transaction.
+        transaction_builder
+            .add_output(
+                value - MINIMUM_FEE,
+                &recipient.default_subaddress(),
+                &mut rng,
+            )
+            .unwrap();
+        let tx = transaction_builder.build(&mut rng).unwrap();
+        assert_eq!(tx.prefix.outputs.len(), 1);
+    }
+
     #[test]
     // `build` should succeed with MAX_INPUTS and MAX_OUTPUTS.
     fn test_max_transaction_size() {
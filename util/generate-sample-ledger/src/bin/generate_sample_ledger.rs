@@ -25,6 +25,12 @@ struct Config {
 
     #[structopt(long = "hint-text")]
     pub hint_text: Option<String>,
+
+    /// Distribution file listing the outputs of the origin block, one
+    /// `<b58 address>,<picoMOB>` entry per line. When set, only the origin
+    /// block is written, and the keys directory is not read.
+    #[structopt(long = "distribution", parse(from_os_str))]
+    pub distribution: Option<PathBuf>,
 }
 
 fn main() {
@@ -33,6 +39,22 @@ fn main() {
     mc_common::setup_panic_handler();
     let logger = create_root_logger();
 
+    if let Some(distribution_path) = config.distribution {
+        let distribution =
+            mc_util_generate_sample_ledger::load_distribution_file(&distribution_path)
+                .unwrap_or_else(|err| panic!("Could not load {:?}: {}", distribution_path, err));
+
+        mc_util_generate_sample_ledger::bootstrap_ledger_from_distribution(
+            &PathBuf::from("ledger"),
+            &distribution,
+            config.seed,
+            config.hint_text.as_deref(),
+            logger,
+        )
+        .expect("Could not create the origin block");
+        return;
+    }
+
     // Read user public keys from disk
     let pub_addrs = mc_util_keyfile::keygen::read_default_pubfiles("keys")
         .expect("Could not read default pubfiles from ./keys");
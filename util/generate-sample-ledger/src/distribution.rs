@@ -0,0 +1,265 @@
+// Copyright (c) 2018-2021 The MobileCoin Foundation
+
+//! Origin blocks built from a distribution file.
+//! A distribution file lists the outputs of the origin block, one per line, as
+//! a b58-encoded public address and an amount in picoMOB separated by a comma:
+//!
+//! ```text
+//! # Comments and blank lines are ignored.
+//! <b58 address>,1000000000000
+//! ```
+
+use crate::{create_output, new_block};
+use displaydoc::Display;
+use mc_account_keys::PublicAddress;
+use mc_api::printable::PrintableWrapper;
+use mc_common::{
+    logger::{log, Logger},
+    HashMap,
+};
+use mc_ledger_db::{Error as LedgerError, Ledger, LedgerDB};
+use mc_transaction_core::tx::TxOut;
+use rand::SeedableRng;
+use rand_hc::Hc128Rng as FixedRng;
+use std::{convert::TryFrom, path::Path};
+
+/// An error loading a distribution or writing its origin block.
+#[derive(Debug, Display, Eq, PartialEq)]
+pub enum DistributionError {
+    /// IO error: {0}
+    Io(String),
+
+    /// Line {0}: expected `<b58 address>,<amount>`
+    InvalidLine(usize),
+
+    /// Line {0}: invalid address: {1}
+    InvalidAddress(usize, String),
+
+    /// Line {0}: invalid amount: {1}
+    InvalidAmount(usize, String),
+
+    /// Line {0}: the amount must be greater than zero
+    ZeroAmount(usize),
+
+    /// Line {0}: the address already appears on line {1}
+    DuplicateAddress(usize, usize),
+
+    /// The distribution has no entries
+    Empty,
+
+    /// The amounts add up to more than a u64 can hold
+    TotalOverflow,
+
+    /// Ledger error: {0}
+    Ledger(LedgerError),
+}
+
+impl From<std::io::Error> for DistributionError {
+    fn from(src: std::io::Error) -> Self {
+        Self::Io(src.to_string())
+    }
+}
+
+impl From<LedgerError> for DistributionError {
+    fn from(src: LedgerError) -> Self {
+        Self::Ledger(src)
+    }
+}
+
+/// Read a distribution file.
+pub fn load_distribution_file(path: &Path) -> Result<Vec<(PublicAddress, u64)>, DistributionError> {
+    parse_distribution(&std::fs::read_to_string(path)?)
+}
+
+/// Parse the contents of a distribution file. Every address must appear once,
+/// with a non-zero amount.
+pub fn parse_distribution(contents: &str) -> Result<Vec<(PublicAddress, u64)>, DistributionError> {
+    let mut distribution = Vec::new();
+    let mut address_lines = HashMap::<PublicAddress, usize>::default();
+    let mut total: u64 = 0;
+
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = line.split(',').map(str::trim);
+        let (b58_address, amount) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(b58_address), Some(amount), None) => (b58_address, amount),
+            _ => return Err(DistributionError::InvalidLine(line_number)),
+        };
+
+        let address = decode_address(b58_address)
+            .map_err(|err| DistributionError::InvalidAddress(line_number, err))?;
+        let amount: u64 = amount
+            .parse()
+            .map_err(|_| DistributionError::InvalidAmount(line_number, amount.to_owned()))?;
+        if amount == 0 {
+            return Err(DistributionError::ZeroAmount(line_number));
+        }
+        if let Some(first_line) = address_lines.insert(address.clone(), line_number) {
+            return Err(DistributionError::DuplicateAddress(line_number, first_line));
+        }
+        total = total
+            .checked_add(amount)
+            .ok_or(DistributionError::TotalOverflow)?;
+
+        distribution.push((address, amount));
+    }
+
+    if distribution.is_empty() {
+        return Err(DistributionError::Empty);
+    }
+    Ok(distribution)
+}
+
+fn decode_address(b58_address: &str) -> Result<PublicAddress, String> {
+    let wrapper =
+        PrintableWrapper::b58_decode(b58_address.to_owned()).map_err(|err| err.to_string())?;
+    if !wrapper.has_public_address() {
+        return Err("not a public address".to_owned());
+    }
+    PublicAddress::try_from(wrapper.get_public_address()).map_err(|err| err.to_string())
+}
+
+/// Create a ledger in `path` whose origin block pays each address of the
+/// distribution its amount, in a single output.
+///
+/// # Arguments
+/// * `path` - Creates a LedgerDB instance at the given path.
+/// * `distribution` - Recipients of the origin block and their amounts.
+/// * `seed` - Seed for the randomness of the outputs.
+/// * `hint_text` - A string to be hashed into the hints for the outputs
+pub fn bootstrap_ledger_from_distribution(
+    path: &Path,
+    distribution: &[(PublicAddress, u64)],
+    seed: Option<[u8; 32]>,
+    hint_text: Option<&str>,
+    logger: Logger,
+) -> Result<(), DistributionError> {
+    std::fs::create_dir_all(path)?;
+    LedgerDB::create(path)?;
+    let mut db = LedgerDB::open(path)?;
+
+    let mut rng: FixedRng = SeedableRng::from_seed(seed.unwrap_or([33u8; 32]));
+
+    log::info!(
+        logger,
+        "Creating an origin block with {} outputs.",
+        distribution.len()
+    );
+    let outputs: Vec<TxOut> = distribution
+        .iter()
+        .map(|(recipient, amount)| create_output(recipient, *amount, &mut rng, hint_text, &logger))
+        .collect();
+
+    let (block, block_contents) = new_block(None, vec![], outputs);
+    db.append_block(&block, &block_contents, None)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_account_keys::AccountKey;
+    use mc_api::external;
+    use mc_common::logger::test_with_logger;
+    use mc_crypto_keys::RistrettoPublic;
+    use mc_transaction_core::get_tx_out_shared_secret;
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+
+    fn encode_address(address: &PublicAddress) -> String {
+        let mut wrapper = PrintableWrapper::new();
+        wrapper.set_public_address(external::PublicAddress::from(address));
+        wrapper.b58_encode().unwrap()
+    }
+
+    #[test_with_logger]
+    fn test_bootstrap_ledger_from_distribution_file(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let account_keys: Vec<AccountKey> = (0..3).map(|_| AccountKey::random(&mut rng)).collect();
+        let amounts = [1_000, 25_000_000_000_000, 7];
+
+        let temp_dir = TempDir::new("distribution").unwrap();
+        let distribution_path = temp_dir.path().join("distribution.csv");
+        let mut contents = "# Test network\n\n".to_owned();
+        for (account_key, amount) in account_keys.iter().zip(amounts.iter()) {
+            contents.push_str(&format!(
+                "{},{}\n",
+                encode_address(&account_key.default_subaddress()),
+                amount
+            ));
+        }
+        std::fs::write(&distribution_path, contents).unwrap();
+
+        let distribution = load_distribution_file(&distribution_path).unwrap();
+        let ledger_path = temp_dir.path().join("ledger");
+        bootstrap_ledger_from_distribution(&ledger_path, &distribution, None, None, logger)
+            .unwrap();
+
+        let ledger_db = LedgerDB::open(&ledger_path).unwrap();
+        assert_eq!(ledger_db.num_blocks().unwrap(), 1);
+        let block_contents = ledger_db.get_block_contents(0).unwrap();
+        assert_eq!(block_contents.outputs.len(), 3);
+        assert!(block_contents.key_images.is_empty());
+
+        // Each recipient can decode exactly one output, holding its amount.
+        for (account_key, amount) in account_keys.iter().zip(amounts.iter()) {
+            let values: Vec<u64> = block_contents
+                .outputs
+                .iter()
+                .filter_map(|tx_out| {
+                    let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
+                    let shared_secret =
+                        get_tx_out_shared_secret(account_key.view_private_key(), &tx_public_key);
+                    tx_out
+                        .amount
+                        .get_value(&shared_secret)
+                        .ok()
+                        .map(|(value, _blinding)| value)
+                })
+                .collect();
+            assert_eq!(values, vec![*amount]);
+        }
+    }
+
+    #[test]
+    fn test_parse_distribution_rejects_invalid_entries() {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let address = encode_address(&AccountKey::random(&mut rng).default_subaddress());
+        let other_address = encode_address(&AccountKey::random(&mut rng).default_subaddress());
+
+        assert_eq!(
+            parse_distribution(&format!("{},10\n{},0\n", address, other_address)),
+            Err(DistributionError::ZeroAmount(2))
+        );
+        assert_eq!(
+            parse_distribution(&format!(
+                "{},10\n{},20\n{},30\n",
+                address, other_address, address
+            )),
+            Err(DistributionError::DuplicateAddress(3, 1))
+        );
+        assert_eq!(
+            parse_distribution(&format!("{},ten\n", address)),
+            Err(DistributionError::InvalidAmount(1, "ten".to_owned()))
+        );
+        assert_eq!(
+            parse_distribution(&format!("{}\n", address)),
+            Err(DistributionError::InvalidLine(1))
+        );
+        assert_eq!(
+            parse_distribution("# Nothing\n"),
+            Err(DistributionError::Empty)
+        );
+        assert_eq!(
+            parse_distribution(&format!("{},10\n{},20\n", address, other_address))
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+}
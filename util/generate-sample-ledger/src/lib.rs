@@ -1,5 +1,12 @@
 // Copyright (c) 2018-2021 The MobileCoin Foundation
 
+mod distribution;
+
+pub use distribution::{
+    bootstrap_ledger_from_distribution, load_distribution_file, parse_distribution,
+    DistributionError,
+};
+
 use mc_account_keys::PublicAddress;
 use mc_common::logger::{log, Logger};
 use mc_crypto_keys::RistrettoPrivate;
@@ -85,14 +92,7 @@ pub fn bootstrap_ledger(
             .map(|_i| KeyImage::from(rng.next_u64()))
             .collect();
 
-        let block_contents = BlockContents::new(key_images, outputs.clone());
-
-        let block = match previous_block {
-            Some(parent) => {
-                Block::new_with_parent(BLOCK_VERSION, &parent, &Default::default(), &block_contents)
-            }
-            None => Block::new_origin_block(&outputs),
-        };
+        let (block, block_contents) = new_block(previous_block.as_ref(), key_images, outputs);
         previous_block = Some(block.clone());
         blocks_and_contents.push((block, block_contents));
     }
@@ -114,6 +114,25 @@ pub fn bootstrap_ledger(
     ).expect("File I/O");
 }
 
+/// Builds the block holding the given key images and outputs, following
+/// `parent`, or the origin block if there is no parent.
+fn new_block(
+    parent: Option<&Block>,
+    key_images: Vec<KeyImage>,
+    outputs: Vec<TxOut>,
+) -> (Block, BlockContents) {
+    let mut block_contents = BlockContents::new(key_images, outputs);
+    block_contents.sort_outputs();
+
+    let block = match parent {
+        Some(parent) => {
+            Block::new_with_parent(BLOCK_VERSION, parent, &Default::default(), &block_contents)
+        }
+        None => Block::new_origin_block(&block_contents.outputs),
+    };
+    (block, block_contents)
+}
+
 fn create_output(
     recipient: &PublicAddress,
     value: u64,
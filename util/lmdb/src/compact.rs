@@ -0,0 +1,121 @@
+// Copyright (c) 2018-2021 The MobileCoin Foundation
+
+//! Compaction of LMDB environments.
+//! LMDB never shrinks its data file: pages freed by deletions are reused, but
+//! the file keeps its size. Compacting copies every database into a fresh
+//! file that only holds live data, and swaps it in place of the original one.
+
+use displaydoc::Display;
+use lmdb::{Cursor, Environment, Error as LmdbError, Transaction, WriteFlags};
+use std::{fs, path::Path};
+
+/// Name of the LMDB data file inside an environment directory.
+const DATA_FILE_NAME: &str = "data.mdb";
+
+/// Name of the directory the compacted copy is written to, inside the
+/// environment directory.
+const COMPACT_DIR_NAME: &str = "compact.tmp";
+
+/// An error type.
+#[derive(Debug, Display, Eq, PartialEq, Clone)]
+pub enum CompactError {
+    /// LMDB Error: {0}
+    Lmdb(LmdbError),
+
+    /// IO Error: {0}
+    Io(String),
+
+    /// Invalid database name: {0:?}
+    InvalidDatabaseName(Vec<u8>),
+}
+
+impl From<LmdbError> for CompactError {
+    fn from(src: LmdbError) -> Self {
+        Self::Lmdb(src)
+    }
+}
+
+impl From<std::io::Error> for CompactError {
+    fn from(src: std::io::Error) -> Self {
+        Self::Io(src.to_string())
+    }
+}
+
+/// Compact the LMDB environment in the directory `path`, with the given
+/// environment settings. Returns the size of the data file before and after
+/// compaction.
+///
+/// The environment must not be open anywhere else, since processes that have
+/// it open would keep using the original file. The write lock of the
+/// environment is held for the whole compaction, so that writers are at least
+/// kept out until the compacted file is in place.
+pub fn compact_environment(
+    path: &Path,
+    max_dbs: u32,
+    map_size: usize,
+) -> Result<(u64, u64), CompactError> {
+    let data_file = path.join(DATA_FILE_NAME);
+    let compact_dir = path.join(COMPACT_DIR_NAME);
+    let size_before = fs::metadata(&data_file)?.len();
+
+    let env = Environment::new()
+        .set_max_dbs(max_dbs)
+        .set_map_size(map_size)
+        .open(path)?;
+
+    // Named databases are listed in the unnamed database. Handles cannot be
+    // opened while this thread has a transaction open, so all are opened
+    // upfront.
+    let main_db = env.open_db(None)?;
+    let db_names = {
+        let db_txn = env.begin_ro_txn()?;
+        let mut cursor = db_txn.open_ro_cursor(main_db)?;
+        cursor
+            .iter_start()
+            .map(|result| {
+                let (name, _) = result?;
+                String::from_utf8(name.to_vec())
+                    .map_err(|_| CompactError::InvalidDatabaseName(name.to_vec()))
+            })
+            .collect::<Result<Vec<String>, CompactError>>()?
+    };
+    let dbs = db_names
+        .iter()
+        .map(|name| env.open_db(Some(name.as_str())))
+        .collect::<Result<Vec<_>, LmdbError>>()?;
+
+    let _ = fs::remove_dir_all(&compact_dir);
+    fs::create_dir_all(&compact_dir)?;
+
+    // Hold the write lock until the compacted file is swapped in.
+    let write_txn = env.begin_rw_txn()?;
+    {
+        let compact_env = Environment::new()
+            .set_max_dbs(max_dbs)
+            .set_map_size(map_size)
+            .open(&compact_dir)?;
+
+        for (name, db) in db_names.iter().zip(dbs.into_iter()) {
+            let flags = write_txn.db_flags(db)?;
+            let compact_db = compact_env.create_db(Some(name.as_str()), flags)?;
+
+            let mut compact_txn = compact_env.begin_rw_txn()?;
+            let mut cursor = write_txn.open_ro_cursor(db)?;
+            for result in cursor.iter_start() {
+                let (key, value) = result?;
+                compact_txn.put(compact_db, &key, &value, WriteFlags::empty())?;
+            }
+            compact_txn.commit()?;
+        }
+
+        compact_env.sync(true)?;
+    }
+    fs::rename(compact_dir.join(DATA_FILE_NAME), &data_file)?;
+    write_txn.abort();
+    drop(env);
+
+    fs::remove_dir_all(&compact_dir)?;
+
+    let size_after = fs::metadata(&data_file)?.len();
+    Ok((size_before, size_after))
+}
@@ -2,8 +2,10 @@
 
 //! LMDB utilities / common features.
 
+mod compact;
 mod metadata_store;
 
+pub use compact::{compact_environment, CompactError};
 pub use metadata_store::{
     MetadataStore, MetadataStoreError, MetadataStoreSettings, MetadataVersion,
 };
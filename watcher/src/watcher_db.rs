@@ -426,6 +426,49 @@ impl WatcherDB {
         }
     }
 
+    /// Get the earliest and latest timestamps signers recorded for a given
+    /// block, as a (min, max) pair. Signers' clocks may be skewed, so the
+    /// spread reflects how uncertain the block's timestamp is. Returns None if
+    /// there are no signatures for the block.
+    pub fn block_timestamp_range(
+        &self,
+        block_index: u64,
+    ) -> Result<Option<(u64, u64)>, WatcherDBError> {
+        let sigs = self.get_block_signatures(block_index)?;
+        let timestamps = sigs.iter().map(|s| s.block_signature.signed_at());
+        match (timestamps.clone().min(), timestamps.max()) {
+            (Some(earliest), Some(latest)) => Ok(Some((earliest, latest))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Estimate the average number of seconds between blocks from the
+    /// timestamps of `first_block` and `last_block`. Returns None if either
+    /// block has no timestamp, or if the timestamps do not increase over the
+    /// range.
+    pub fn average_block_interval(
+        &self,
+        first_block: u64,
+        last_block: u64,
+    ) -> Result<Option<u64>, WatcherDBError> {
+        if last_block <= first_block {
+            return Ok(None);
+        }
+
+        let (first_timestamp, first_result) = self.get_block_timestamp(first_block)?;
+        let (last_timestamp, last_result) = self.get_block_timestamp(last_block)?;
+        if first_result != TimestampResultCode::TimestampFound
+            || last_result != TimestampResultCode::TimestampFound
+            || last_timestamp <= first_timestamp
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            (last_timestamp - first_timestamp) / (last_block - first_block),
+        ))
+    }
+
     /// Get the last synced block per configured url.
     pub fn last_synced_blocks(&self) -> Result<HashMap<Url, Option<u64>>, WatcherDBError> {
         let db_txn = self.env.begin_ro_txn()?;
@@ -1064,6 +1107,31 @@ pub mod tests {
         get_blocks(&recipient_pub_keys, 10, 1, 10, &origin, &mut rng)
     }
 
+    /// Add a signature by `signing_key` for each of `blocks`, with signed_at
+    /// set to the given timestamp. Signatures are added in the order given,
+    /// so blocks may be listed out of index order to model out-of-order
+    /// arrival.
+    pub fn add_block_signatures_with_timestamps(
+        watcher_db: &WatcherDB,
+        src_url: &Url,
+        signing_key: &Ed25519Pair,
+        blocks: &[(&Block, u64)],
+    ) {
+        for (block, timestamp) in blocks {
+            let mut signed_block =
+                BlockSignature::from_block_and_keypair(block, signing_key).unwrap();
+            signed_block.set_signed_at(*timestamp);
+            watcher_db
+                .add_block_signature(
+                    src_url,
+                    block.index,
+                    signed_block,
+                    format!("00/{:02}", block.index),
+                )
+                .unwrap();
+        }
+    }
+
     // SignatureStore should insert and get multiple signatures.
     #[test_with_logger]
     fn test_insert_and_get(logger: Logger) {
@@ -1260,6 +1328,134 @@ pub mod tests {
         });
     }
 
+    // Timestamps should be returned per block as recorded, regardless of the
+    // order in which blocks arrived or whether timestamps increase with the block
+    // index.
+    #[test_with_logger]
+    fn test_timestamps_out_of_order(logger: Logger) {
+        run_with_one_seed(|mut rng| {
+            let url1 = Url::parse("http://www.my_url1.com").unwrap();
+            let urls = vec![url1];
+            let watcher_db = setup_watcher_db(&urls, logger.clone());
+
+            // setup_blocks() does not include the origin block, so blocks[i] has
+            // index i + 1.
+            let blocks = setup_blocks();
+
+            let signing_key_a = Ed25519Pair::from_random(&mut rng);
+
+            // Block 3 is seen first, and block 2 carries a later timestamp than
+            // block 3.
+            add_block_signatures_with_timestamps(
+                &watcher_db,
+                &urls[0],
+                &signing_key_a,
+                &[
+                    (&blocks[2].0, 1594679730),
+                    (&blocks[0].0, 1594679718),
+                    (&blocks[3].0, 1594679725),
+                    (&blocks[1].0, 1594679740),
+                ],
+            );
+
+            assert_eq!(
+                watcher_db.get_block_timestamp(1).unwrap(),
+                (1594679718, TimestampResultCode::TimestampFound)
+            );
+            assert_eq!(
+                watcher_db.get_block_timestamp(2).unwrap(),
+                (1594679740, TimestampResultCode::TimestampFound)
+            );
+            assert_eq!(
+                watcher_db.get_block_timestamp(3).unwrap(),
+                (1594679730, TimestampResultCode::TimestampFound)
+            );
+            assert_eq!(
+                watcher_db.get_block_timestamp(4).unwrap(),
+                (1594679725, TimestampResultCode::TimestampFound)
+            );
+        });
+    }
+
+    // The timestamp range of a block should span the timestamps of all of its
+    // signers.
+    #[test_with_logger]
+    fn test_block_timestamp_range(logger: Logger) {
+        run_with_one_seed(|mut rng| {
+            let url1 = Url::parse("http://www.my_url1.com").unwrap();
+            let url2 = Url::parse("http://www.my_url2.com").unwrap();
+            let url3 = Url::parse("http://www.my_url3.com").unwrap();
+            let urls = vec![url1, url2, url3];
+            let watcher_db = setup_watcher_db(&urls, logger.clone());
+
+            let blocks = setup_blocks();
+
+            // No signatures yet.
+            assert_eq!(watcher_db.block_timestamp_range(1).unwrap(), None);
+
+            // Three signers with skewed clocks sign block 1, and one signs block 2.
+            for (url, timestamp) in urls.iter().zip(&[1594679727, 1594679718, 1594679760]) {
+                let signing_key = Ed25519Pair::from_random(&mut rng);
+                add_block_signatures_with_timestamps(
+                    &watcher_db,
+                    url,
+                    &signing_key,
+                    &[(&blocks[0].0, *timestamp)],
+                );
+            }
+            let signing_key = Ed25519Pair::from_random(&mut rng);
+            add_block_signatures_with_timestamps(
+                &watcher_db,
+                &urls[0],
+                &signing_key,
+                &[(&blocks[1].0, 1594679800)],
+            );
+
+            assert_eq!(
+                watcher_db.block_timestamp_range(1).unwrap(),
+                Some((1594679718, 1594679760))
+            );
+            assert_eq!(
+                watcher_db.block_timestamp_range(2).unwrap(),
+                Some((1594679800, 1594679800))
+            );
+            assert_eq!(watcher_db.block_timestamp_range(3).unwrap(), None);
+        });
+    }
+
+    // The average block interval should be estimated from the timestamps at
+    // either end of the range.
+    #[test_with_logger]
+    fn test_average_block_interval(logger: Logger) {
+        run_with_one_seed(|mut rng| {
+            let url1 = Url::parse("http://www.my_url1.com").unwrap();
+            let urls = vec![url1];
+            let watcher_db = setup_watcher_db(&urls, logger.clone());
+
+            // blocks[i] has index i + 1.
+            let blocks = setup_blocks();
+            let signing_key = Ed25519Pair::from_random(&mut rng);
+            add_block_signatures_with_timestamps(
+                &watcher_db,
+                &urls[0],
+                &signing_key,
+                &[
+                    (&blocks[0].0, 1594679700),
+                    (&blocks[1].0, 1594679705),
+                    (&blocks[3].0, 1594679730),
+                ],
+            );
+
+            assert_eq!(watcher_db.average_block_interval(1, 2).unwrap(), Some(5));
+            assert_eq!(watcher_db.average_block_interval(1, 4).unwrap(), Some(10));
+
+            // Block 3 has no timestamp, and ranges must span at least one block.
+            assert_eq!(watcher_db.average_block_interval(1, 3).unwrap(), None);
+            assert_eq!(watcher_db.average_block_interval(2, 2).unwrap(), None);
+            assert_eq!(watcher_db.average_block_interval(4, 1).unwrap(), None);
+        });
+    }
+
     // Storing and fetching of verification reports should work.
     #[test_with_logger]
     fn test_verification_report_insert_and_get(logger: Logger) {